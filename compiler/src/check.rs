@@ -0,0 +1,427 @@
+use std::{collections::HashMap, fmt::Display};
+
+use crate::{
+    diagnostic::{Diagnostic, Span},
+    ir::{self, Program, Type},
+    parser::{self, Ast, BinaryOperator, ExpressionKind, Statement, TypeName},
+};
+
+/// Resolves names and checks types, producing the typed IR. Every error is
+/// collected before giving up, so they can all be reported at once.
+pub fn check(ast: &Ast) -> Result<Program, Vec<Error>> {
+    let mut errors = Vec::new();
+    let mut indices = HashMap::new();
+    let mut signatures = Vec::new();
+
+    for (index, function) in ast.functions.iter().enumerate() {
+        if indices.contains_key(&function.name) {
+            errors.push(Error::DuplicateFunction {
+                name: function.name.clone(),
+                span: function.span.clone(),
+            });
+        } else {
+            indices.insert(function.name.clone(), index);
+        }
+
+        let parameters: Vec<_> = function
+            .parameters
+            .iter()
+            .map(|parameter| ir::Variable {
+                name: parameter.name.clone(),
+                ty: resolve_type(&parameter.ty, &mut errors),
+            })
+            .collect();
+        let return_type = function
+            .return_type
+            .as_ref()
+            .map_or(Type::W, |ty| resolve_type(ty, &mut errors));
+
+        signatures.push((parameters, return_type));
+    }
+
+    let functions: Vec<_> = ast
+        .functions
+        .iter()
+        .zip(&signatures)
+        .map(|(function, (parameters, return_type))| {
+            let mut checker = Checker {
+                indices: &indices,
+                signatures: &signatures,
+                parameters,
+                locals: Vec::new(),
+                errors: &mut errors,
+            };
+
+            let body: Vec<_> = function
+                .body
+                .statements
+                .iter()
+                .map(|statement| checker.statement(statement, *return_type))
+                .collect();
+            let trailing = function
+                .body
+                .trailing
+                .as_ref()
+                .map(|trailing| checker.check(trailing, *return_type));
+
+            let returns = body
+                .iter()
+                .any(|statement| matches!(statement, ir::Statement::Return(_)));
+            if trailing.is_none() && !returns {
+                checker.errors.push(Error::MissingReturn {
+                    name: function.name.clone(),
+                    span: function.span.clone(),
+                });
+            }
+
+            ir::Function {
+                name: function.name.clone(),
+                exported: function.name == "main",
+                parameters: parameters.clone(),
+                locals: checker.locals,
+                return_type: *return_type,
+                body,
+                trailing,
+            }
+        })
+        .collect();
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    Ok(Program { functions })
+}
+
+/// Falls back to `w` for unknown types, so checking can carry on.
+fn resolve_type(name: &TypeName, errors: &mut Vec<Error>) -> Type {
+    Type::named(&name.name).unwrap_or_else(|| {
+        errors.push(Error::UnknownType {
+            name: name.name.clone(),
+            span: name.span.clone(),
+        });
+        Type::W
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    UnknownFunction {
+        name: String,
+        span: Span,
+    },
+    UnknownVariable {
+        name: String,
+        span: Span,
+    },
+    UnknownType {
+        name: String,
+        span: Span,
+    },
+    DuplicateFunction {
+        name: String,
+        span: Span,
+    },
+    MissingReturn {
+        name: String,
+        span: Span,
+    },
+    WrongArity {
+        name: String,
+        expected: usize,
+        found: usize,
+        span: Span,
+    },
+    TypeMismatch {
+        expected: Type,
+        found: Type,
+        span: Span,
+    },
+    IntegerTooLarge {
+        ty: Type,
+        span: Span,
+    },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownFunction { name, .. } => write!(f, "unknown function `{name}`"),
+            Self::UnknownVariable { name, .. } => write!(f, "unknown variable `{name}`"),
+            Self::UnknownType { name, .. } => write!(f, "unknown type `{name}`"),
+            Self::DuplicateFunction { name, .. } => {
+                write!(f, "function `{name}` is defined twice")
+            }
+            Self::MissingReturn { name, .. } => {
+                write!(f, "function `{name}` doesn't return a value")
+            }
+            Self::WrongArity {
+                name,
+                expected,
+                found,
+                ..
+            } => write!(
+                f,
+                "function `{name}` takes {expected} argument(s) but {found} were given"
+            ),
+            Self::TypeMismatch { .. } => write!(f, "mismatched types"),
+            Self::IntegerTooLarge { ty, .. } => write!(f, "integer is too large for `{ty}`"),
+        }
+    }
+}
+
+impl From<Error> for Diagnostic {
+    fn from(error: Error) -> Self {
+        let (span, label) = match &error {
+            Error::UnknownFunction { span, .. } => (span, "not found".to_owned()),
+            Error::UnknownVariable { span, .. } => (span, "not in scope".to_owned()),
+            Error::UnknownType { span, .. } => {
+                (span, "expected `Int`, `Long` or `Float`".to_owned())
+            }
+            Error::DuplicateFunction { span, .. } => (span, "redefined here".to_owned()),
+            Error::MissingReturn { span, .. } => (
+                span,
+                "expected a trailing expression or `return`".to_owned(),
+            ),
+            Error::WrongArity { span, expected, .. } => {
+                (span, format!("expected {expected} argument(s)"))
+            }
+            Error::TypeMismatch {
+                expected,
+                found,
+                span,
+            } => (span, format!("expected `{expected}`, found `{found}`")),
+            Error::IntegerTooLarge { span, .. } => (span, "doesn't fit".to_owned()),
+        };
+
+        Self {
+            message: error.to_string(),
+            span: span.clone(),
+            label,
+        }
+    }
+}
+
+type Signature = (Vec<ir::Variable>, Type);
+
+struct Checker<'a> {
+    indices: &'a HashMap<String, usize>,
+    signatures: &'a [Signature],
+    parameters: &'a [ir::Variable],
+    /// Locals defined so far. Later ones shadow earlier ones and parameters.
+    locals: Vec<ir::Variable>,
+    errors: &'a mut Vec<Error>,
+}
+
+impl Checker<'_> {
+    fn statement(&mut self, statement: &Statement, return_type: Type) -> ir::Statement {
+        match statement {
+            Statement::Let {
+                name, ty, value, ..
+            } => {
+                // The declared type wins, even if the value doesn't match it
+                let (value, ty) = match ty {
+                    Some(ty) => {
+                        let ty = resolve_type(ty, self.errors);
+                        (self.check(value, ty), ty)
+                    }
+                    None => {
+                        let value = self.infer(value);
+                        let ty = value.ty;
+                        (value, ty)
+                    }
+                };
+
+                // Added after checking the value, which can't see its own name
+                self.locals.push(ir::Variable {
+                    name: name.clone(),
+                    ty,
+                });
+
+                ir::Statement::Let {
+                    local: self.locals.len() - 1,
+                    value,
+                }
+            }
+            Statement::Return { value, .. } => {
+                ir::Statement::Return(self.check(value, return_type))
+            }
+            Statement::Expression(expression) => ir::Statement::Expression(self.infer(expression)),
+        }
+    }
+
+    /// Checks `expression` has the `expected` type. Integer literals take on
+    /// whichever integer type is expected.
+    fn check(&mut self, expression: &parser::Expression, expected: Type) -> ir::Expression {
+        let errors = self.errors.len();
+        let checked = match &expression.kind {
+            ExpressionKind::Number(number) if expected.is_integer() => {
+                self.integer(*number, expected, &expression.span)
+            }
+            ExpressionKind::Binary {
+                operator,
+                left,
+                right,
+            } => {
+                let left = self.check(left, expected);
+                let right = self.check(right, expected);
+                binary(*operator, left, right)
+            }
+            _ => self.infer(expression),
+        };
+
+        // An expression that already failed has a placeholder type
+        if checked.ty != expected && self.errors.len() == errors {
+            self.errors.push(Error::TypeMismatch {
+                expected,
+                found: checked.ty,
+                span: expression.span.clone(),
+            });
+        }
+
+        checked
+    }
+
+    fn infer(&mut self, expression: &parser::Expression) -> ir::Expression {
+        let span = &expression.span;
+
+        match &expression.kind {
+            ExpressionKind::Number(number) => self.integer(*number, Type::W, span),
+            ExpressionKind::Float(float) => ir::Expression {
+                kind: ir::ExpressionKind::Float(*float),
+                ty: Type::D,
+            },
+            ExpressionKind::Variable(name) => self.variable(name).unwrap_or_else(|| {
+                self.errors.push(Error::UnknownVariable {
+                    name: name.clone(),
+                    span: span.clone(),
+                });
+                placeholder()
+            }),
+            ExpressionKind::Call { name, arguments } => self.call(name, arguments, span),
+            ExpressionKind::Binary {
+                operator,
+                left,
+                right,
+            } => {
+                // An untyped literal on the left takes its type from the right
+                let (left, right) = if let ExpressionKind::Number(_) = left.kind {
+                    let right = self.infer(right);
+                    (self.check(left, right.ty), right)
+                } else {
+                    let left = self.infer(left);
+                    let right = self.check(right, left.ty);
+                    (left, right)
+                };
+
+                binary(*operator, left, right)
+            }
+        }
+    }
+
+    fn variable(&self, name: &str) -> Option<ir::Expression> {
+        if let Some(index) = self.locals.iter().rposition(|local| local.name == name) {
+            return Some(ir::Expression {
+                kind: ir::ExpressionKind::Local(index),
+                ty: self.locals[index].ty,
+            });
+        }
+
+        let index = self
+            .parameters
+            .iter()
+            .position(|parameter| parameter.name == name)?;
+        Some(ir::Expression {
+            kind: ir::ExpressionKind::Parameter(index),
+            ty: self.parameters[index].ty,
+        })
+    }
+
+    fn integer(&mut self, number: u64, ty: Type, span: &Span) -> ir::Expression {
+        if ty == Type::W && number > u32::MAX.into() {
+            self.errors.push(Error::IntegerTooLarge {
+                ty,
+                span: span.clone(),
+            });
+        }
+
+        ir::Expression {
+            kind: ir::ExpressionKind::Integer(number),
+            ty,
+        }
+    }
+
+    fn call(
+        &mut self,
+        name: &str,
+        arguments: &[parser::Expression],
+        span: &Span,
+    ) -> ir::Expression {
+        let function = self.indices.get(name).copied();
+        let signature = function.map(|index| &self.signatures[index]);
+
+        let error = match signature {
+            None => Some(Error::UnknownFunction {
+                name: name.to_owned(),
+                span: span.clone(),
+            }),
+            Some((parameters, _)) if parameters.len() != arguments.len() => {
+                Some(Error::WrongArity {
+                    name: name.to_owned(),
+                    expected: parameters.len(),
+                    found: arguments.len(),
+                    span: span.clone(),
+                })
+            }
+            Some(_) => None,
+        };
+
+        // Arguments are still checked when the call is wrong, to report their
+        // errors too
+        let arguments: Vec<_> = match (signature, &error) {
+            (Some((parameters, _)), None) => arguments
+                .iter()
+                .zip(parameters)
+                .map(|(argument, parameter)| self.check(argument, parameter.ty))
+                .collect(),
+            _ => arguments
+                .iter()
+                .map(|argument| self.infer(argument))
+                .collect(),
+        };
+
+        match (function, signature, error) {
+            (Some(function), Some((_, return_type)), None) => ir::Expression {
+                kind: ir::ExpressionKind::Call {
+                    function,
+                    arguments,
+                },
+                ty: *return_type,
+            },
+            (.., error) => {
+                self.errors.extend(error);
+                placeholder()
+            }
+        }
+    }
+}
+
+fn binary(operator: BinaryOperator, left: ir::Expression, right: ir::Expression) -> ir::Expression {
+    ir::Expression {
+        ty: left.ty,
+        kind: ir::ExpressionKind::Binary {
+            operator,
+            left: Box::new(left),
+            right: Box::new(right),
+        },
+    }
+}
+
+/// Stands in for an expression that failed to check. It is never seen, since
+/// checking fails as a whole.
+fn placeholder() -> ir::Expression {
+    ir::Expression {
+        kind: ir::ExpressionKind::Integer(0),
+        ty: Type::W,
+    }
+}
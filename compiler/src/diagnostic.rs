@@ -0,0 +1,161 @@
+use std::{
+    fmt::{Display, Write},
+    hash::Hash,
+    ops::Range,
+};
+
+use chumsky::error::{Simple, SimpleReason};
+
+use crate::lexer::Token;
+
+/// Character offsets into the source, as chumsky reports them.
+pub type Span = Range<usize>;
+
+pub type Spanned<T> = (T, Span);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub span: Span,
+    pub label: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Diagnostics(pub Vec<Diagnostic>);
+
+impl Diagnostic {
+    /// Renders the diagnostic with an excerpt of the line it points at.
+    ///
+    /// ```text
+    /// error: unexpected `;`
+    ///  --> 2:9
+    ///   |
+    /// 2 |     foo(;
+    ///   |         ^ expected expression
+    /// ```
+    pub fn render(&self, source: &str) -> String {
+        let (line_number, line_start, line) = locate(source, self.span.start);
+        let column = self.span.start - line_start;
+        let line_length = line.chars().count();
+        let width = self
+            .span
+            .end
+            .min(line_start + line_length)
+            .saturating_sub(self.span.start)
+            .max(1);
+
+        let gutter = " ".repeat(line_number.to_string().len());
+        let mut output = String::new();
+
+        // Writing to a `String` can't fail
+        let _ = writeln!(output, "error: {}", self.message);
+        let _ = writeln!(output, "{gutter}--> {line_number}:{}", column + 1);
+        let _ = writeln!(output, "{gutter} |");
+        let _ = writeln!(output, "{line_number} | {line}");
+        let _ = writeln!(
+            output,
+            "{gutter} | {}{} {}",
+            " ".repeat(column),
+            "^".repeat(width),
+            self.label
+        );
+
+        output
+    }
+}
+
+/// Finds the 1-based line number, the offset of its first character and the
+/// text of the line containing `offset`. Offsets past the end of the source
+/// point just after the last line.
+fn locate(source: &str, offset: usize) -> (usize, usize, &str) {
+    let mut line_start = 0;
+    let mut lines = source.lines().enumerate().peekable();
+
+    while let Some((index, line)) = lines.next() {
+        let length = line.chars().count();
+        if offset <= line_start + length || lines.peek().is_none() {
+            return (index + 1, line_start, line);
+        }
+
+        line_start += length + 1;
+    }
+
+    (1, 0, "")
+}
+
+impl Diagnostics {
+    pub fn render(&self, source: &str) -> String {
+        self.0
+            .iter()
+            .map(|diagnostic| diagnostic.render(source))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl<T: Into<Diagnostic>> FromIterator<T> for Diagnostics {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self(iter.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Describes a token chumsky found or expected, with `None` being the end of
+/// the input.
+fn describe<T: Display>(token: Option<&T>) -> String {
+    match token {
+        Some(token) => format!("`{token}`"),
+        None => "end of input".to_owned(),
+    }
+}
+
+impl From<Simple<Token>> for Diagnostic {
+    fn from(error: Simple<Token>) -> Self {
+        from_simple(error, true)
+    }
+}
+
+impl From<Simple<char>> for Diagnostic {
+    /// The characters the lexer could have accepted aren't worth listing.
+    fn from(error: Simple<char>) -> Self {
+        from_simple(error, false)
+    }
+}
+
+fn from_simple<T: Display + Hash + Eq>(error: Simple<T>, list_expected: bool) -> Diagnostic {
+    let span = error.span();
+
+    if let SimpleReason::Custom(message) = error.reason() {
+        return Diagnostic {
+            message: message.clone(),
+            label: "here".to_owned(),
+            span,
+        };
+    }
+
+    let message = match error.reason() {
+        SimpleReason::Unclosed { delimiter, .. } => {
+            format!("unclosed delimiter `{delimiter}`")
+        }
+        _ => format!("unexpected {}", describe(error.found())),
+    };
+
+    let mut expected: Vec<_> = error
+        .expected()
+        .filter(|_| list_expected)
+        .map(|token| describe(token.as_ref()))
+        .collect();
+    expected.sort();
+    expected.dedup();
+
+    let label = match expected.as_slice() {
+        [] => "unexpected here".to_owned(),
+        [only] => format!("expected {only}"),
+        [rest @ .., last] => format!("expected {} or {last}", rest.join(", ")),
+    };
+
+    Diagnostic {
+        message,
+        span,
+        label,
+    }
+}
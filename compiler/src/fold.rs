@@ -0,0 +1,85 @@
+use crate::ir::{BinaryOperator, Expression, ExpressionKind, Program, Statement, Type};
+
+/// Evaluates arithmetic on constants at compile time, with the same wrapping
+/// and signed division QBE would use at run time. Divisions that would trap
+/// are left for run time.
+pub fn fold(program: &mut Program) {
+    for function in &mut program.functions {
+        for statement in &mut function.body {
+            match statement {
+                Statement::Let { value, .. } => fold_expression(value),
+                Statement::Return(value) | Statement::Expression(value) => fold_expression(value),
+            }
+        }
+        function.trailing.iter_mut().for_each(fold_expression);
+    }
+}
+
+fn fold_expression(expression: &mut Expression) {
+    let (operator, left, right) = match &mut expression.kind {
+        ExpressionKind::Call { arguments, .. } => {
+            arguments.iter_mut().for_each(fold_expression);
+            return;
+        }
+        ExpressionKind::Binary {
+            operator,
+            left,
+            right,
+        } => {
+            fold_expression(left);
+            fold_expression(right);
+            (*operator, &left.kind, &right.kind)
+        }
+        _ => return,
+    };
+
+    let folded = match (left, right) {
+        (ExpressionKind::Integer(left), ExpressionKind::Integer(right)) => {
+            integer(operator, expression.ty, *left, *right).map(ExpressionKind::Integer)
+        }
+        (ExpressionKind::Float(left), ExpressionKind::Float(right)) => {
+            Some(ExpressionKind::Float(float(operator, *left, *right)))
+        }
+        _ => None,
+    };
+
+    if let Some(folded) = folded {
+        expression.kind = folded;
+    }
+}
+
+fn integer(operator: BinaryOperator, ty: Type, left: u64, right: u64) -> Option<u64> {
+    // Truncating to the type's width and back gives the wrapped result
+    match ty {
+        Type::W => {
+            let (left, right) = (left as i32, right as i32);
+            let result = match operator {
+                BinaryOperator::Add => left.wrapping_add(right),
+                BinaryOperator::Subtract => left.wrapping_sub(right),
+                BinaryOperator::Multiply => left.wrapping_mul(right),
+                BinaryOperator::Divide => left.checked_div(right)?,
+            };
+            Some(result as u32 as u64)
+        }
+        Type::L => {
+            let (left, right) = (left as i64, right as i64);
+            let result = match operator {
+                BinaryOperator::Add => left.wrapping_add(right),
+                BinaryOperator::Subtract => left.wrapping_sub(right),
+                BinaryOperator::Multiply => left.wrapping_mul(right),
+                BinaryOperator::Divide => left.checked_div(right)?,
+            };
+            Some(result as u64)
+        }
+        Type::D => unreachable!("Integers are never typed as floats"),
+    }
+}
+
+fn float(operator: BinaryOperator, left: f64, right: f64) -> f64 {
+    match operator {
+        BinaryOperator::Add => left + right,
+        BinaryOperator::Subtract => left - right,
+        BinaryOperator::Multiply => left * right,
+        BinaryOperator::Divide => left / right,
+    }
+}
@@ -1,32 +1,207 @@
 use std::fmt::Display;
 
-use crate::parser::Ast;
+use crate::ir::{self, BinaryOperator, ExpressionKind, Program, Statement, Type};
 
-pub fn generate(ast: Ast) -> String {
-    Function {
-        name: ast.name,
-        body: Instruction::Ret(ast.body.trailing),
+/// Lowers the checked program to an IR that maps one to one onto QBE, and
+/// whose `Display` impls print QBE IR. Both backends start from here.
+pub fn generate(program: &Program) -> Vec<Function> {
+    program
+        .functions
+        .iter()
+        .map(|function| {
+            Generator {
+                program,
+                function,
+                instructions: Vec::new(),
+                locals: Vec::new(),
+                next_temporary: 0,
+            }
+            .function()
+        })
+        .collect()
+}
+
+struct Generator<'a> {
+    program: &'a Program,
+    function: &'a ir::Function,
+    instructions: Vec<Instruction>,
+    /// The value each local was bound to. Locals are never reassigned, so a
+    /// `let` just names a value rather than copying it.
+    locals: Vec<Value>,
+    next_temporary: usize,
+}
+
+impl Generator<'_> {
+    fn function(mut self) -> Function {
+        let mut returned = false;
+        for statement in &self.function.body {
+            match statement {
+                Statement::Let { value, .. } => {
+                    let value = self.expression(value);
+                    self.locals.push(value);
+                }
+                Statement::Return(value) => {
+                    let value = self.expression(value);
+                    self.instructions.push(Instruction::Ret(value));
+                    returned = true;
+                    // Anything after a `return` can't be reached
+                    break;
+                }
+                Statement::Expression(expression) => self.statement(expression),
+            }
+        }
+
+        if !returned {
+            // The checker makes sure there is a trailing value if nothing returned
+            let trailing = self.function.trailing.as_ref().unwrap();
+            let value = self.expression(trailing);
+            self.instructions.push(Instruction::Ret(value));
+        }
+
+        Function {
+            exported: self.function.exported,
+            name: self.function.name.clone(),
+            parameters: self
+                .function
+                .parameters
+                .iter()
+                .map(|parameter| (parameter.name.clone(), parameter.ty))
+                .collect(),
+            return_type: self.function.return_type,
+            body: self.instructions,
+        }
+    }
+
+    /// Evaluates `expression` for its side effects only.
+    fn statement(&mut self, expression: &ir::Expression) {
+        match &expression.kind {
+            ExpressionKind::Call {
+                function,
+                arguments,
+            } => {
+                let instruction = self.call(None, *function, arguments);
+                self.instructions.push(instruction);
+            }
+            ExpressionKind::Binary { left, right, .. } => {
+                self.statement(left);
+                self.statement(right);
+            }
+            _ => {}
+        }
+    }
+
+    fn expression(&mut self, expression: &ir::Expression) -> Value {
+        match &expression.kind {
+            ExpressionKind::Integer(number) => Value::integer(*number, expression.ty),
+            ExpressionKind::Float(float) => Value::Float(*float),
+            ExpressionKind::Parameter(index) => {
+                Value::Parameter(self.function.parameters[*index].name.clone())
+            }
+            ExpressionKind::Local(index) => self.locals[*index].clone(),
+            ExpressionKind::Call {
+                function,
+                arguments,
+            } => {
+                let instruction = self.call(Some(expression.ty), *function, arguments);
+                self.instructions.push(instruction);
+                Value::Temporary(self.next_temporary - 1)
+            }
+            ExpressionKind::Binary {
+                operator,
+                left,
+                right,
+            } => {
+                let left = self.expression(left);
+                let right = self.expression(right);
+                let result = self.temporary();
+
+                self.instructions.push(Instruction::Binary {
+                    result,
+                    ty: expression.ty,
+                    operator: *operator,
+                    left,
+                    right,
+                });
+                Value::Temporary(result)
+            }
+        }
+    }
+
+    /// Builds a call, numbering its result after its arguments.
+    fn call(
+        &mut self,
+        result: Option<Type>,
+        function: usize,
+        arguments: &[ir::Expression],
+    ) -> Instruction {
+        let arguments = arguments
+            .iter()
+            .map(|argument| (self.expression(argument), argument.ty))
+            .collect();
+
+        Instruction::Call {
+            result: result.map(|ty| (self.temporary(), ty)),
+            name: self.program.functions[function].name.clone(),
+            arguments,
+        }
+    }
+
+    fn temporary(&mut self) -> usize {
+        self.next_temporary += 1;
+        self.next_temporary - 1
     }
-    .to_string()
 }
 
 #[derive(Debug, Clone)]
-struct Function {
-    name: String,
-    body: Instruction,
+pub struct Function {
+    pub exported: bool,
+    pub name: String,
+    pub parameters: Vec<(String, Type)>,
+    pub return_type: Type,
+    pub body: Vec<Instruction>,
 }
 
 impl Display for Function {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "export function w ${}() {{\n@start", self.name)?;
-        writeln!(f, "{}", self.body)?;
+        if self.exported {
+            write!(f, "export ")?;
+        }
+
+        let parameters: Vec<_> = self
+            .parameters
+            .iter()
+            .map(|(name, ty)| format!("{ty} {}", Value::Parameter(name.clone())))
+            .collect();
+
+        writeln!(
+            f,
+            "function {} ${}({}) {{\n@start",
+            self.return_type,
+            self.name,
+            parameters.join(", ")
+        )?;
+        for instruction in &self.body {
+            writeln!(f, "{}", instruction)?;
+        }
         writeln!(f, "}}")
     }
 }
 
 #[derive(Debug, Clone)]
-enum Instruction {
-    Ret(u32),
+pub enum Instruction {
+    Ret(Value),
+    Call {
+        result: Option<(usize, Type)>,
+        name: String,
+        arguments: Vec<(Value, Type)>,
+    },
+    Binary {
+        result: usize,
+        ty: Type,
+        operator: BinaryOperator,
+        left: Value,
+        right: Value,
+    },
 }
 
 impl Display for Instruction {
@@ -34,7 +209,72 @@ impl Display for Instruction {
         write!(f, "    ")?;
 
         match self {
-            Self::Ret(number) => write!(f, "ret {number}"),
+            Self::Ret(value) => write!(f, "ret {value}"),
+            Self::Call {
+                result,
+                name,
+                arguments,
+            } => {
+                if let Some((result, ty)) = result {
+                    write!(f, "{} ={ty} ", Value::Temporary(*result))?;
+                }
+
+                let arguments: Vec<_> = arguments
+                    .iter()
+                    .map(|(value, ty)| format!("{ty} {value}"))
+                    .collect();
+                write!(f, "call ${name}({})", arguments.join(", "))
+            }
+            Self::Binary {
+                result,
+                ty,
+                operator,
+                left,
+                right,
+            } => {
+                let operator = match operator {
+                    BinaryOperator::Add => "add",
+                    BinaryOperator::Subtract => "sub",
+                    BinaryOperator::Multiply => "mul",
+                    BinaryOperator::Divide => "div",
+                };
+
+                write!(
+                    f,
+                    "{} ={ty} {operator} {left}, {right}",
+                    Value::Temporary(*result)
+                )
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// A signed integer, already truncated to the width of its type.
+    Integer(i64),
+    Float(f64),
+    Parameter(String),
+    /// Temporaries use a `.` so they can never clash with a parameter name.
+    Temporary(usize),
+}
+
+impl Value {
+    fn integer(number: u64, ty: Type) -> Self {
+        match ty {
+            Type::W => Self::Integer((number as u32 as i32).into()),
+            _ => Self::Integer(number as i64),
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Integer(number) => write!(f, "{number}"),
+            Self::Float(float) => write!(f, "d_{float}"),
+            Self::Parameter(name) => write!(f, "%{name}"),
+            Self::Temporary(id) => write!(f, "%.{id}"),
         }
     }
 }
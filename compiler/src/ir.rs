@@ -0,0 +1,110 @@
+//! The resolved, typed program that sits between the parser and code
+//! generation. Names are resolved to indices and every expression knows its
+//! type.
+
+use std::fmt::Display;
+
+use serde::{Deserialize, Serialize};
+
+pub use crate::parser::BinaryOperator;
+
+/// QBE's base types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Type {
+    /// A 32 bit integer.
+    W,
+    /// A 64 bit integer.
+    L,
+    /// A 64 bit float.
+    D,
+}
+
+impl Type {
+    /// The type a source type name refers to.
+    pub fn named(name: &str) -> Option<Self> {
+        match name {
+            "Int" => Some(Self::W),
+            "Long" => Some(Self::L),
+            "Float" => Some(Self::D),
+            _ => None,
+        }
+    }
+
+    pub fn is_integer(self) -> bool {
+        matches!(self, Self::W | Self::L)
+    }
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::W => write!(f, "w"),
+            Self::L => write!(f, "l"),
+            Self::D => write!(f, "d"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Program {
+    pub functions: Vec<Function>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Function {
+    pub name: String,
+    /// Whether this is the `main` entry point.
+    pub exported: bool,
+    pub parameters: Vec<Variable>,
+    /// Every `let` in the function, in order.
+    pub locals: Vec<Variable>,
+    pub return_type: Type,
+    pub body: Vec<Statement>,
+    /// Missing when the body always returns.
+    pub trailing: Option<Expression>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Variable {
+    pub name: String,
+    pub ty: Type,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Statement {
+    /// Binds the value to the local with this index.
+    Let {
+        local: usize,
+        value: Expression,
+    },
+    Return(Expression),
+    Expression(Expression),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Expression {
+    pub kind: ExpressionKind,
+    pub ty: Type,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ExpressionKind {
+    /// An integer, truncated to the width of its type.
+    Integer(u64),
+    Float(f64),
+    /// The index of a parameter of the enclosing function.
+    Parameter(usize),
+    /// The index of a local of the enclosing function.
+    Local(usize),
+    Call {
+        /// The index of the function in the program.
+        function: usize,
+        arguments: Vec<Expression>,
+    },
+    Binary {
+        operator: BinaryOperator,
+        left: Box<Expression>,
+        right: Box<Expression>,
+    },
+}
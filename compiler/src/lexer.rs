@@ -1,42 +1,107 @@
+use std::fmt::Display;
+
 use chumsky::{prelude::*, text};
 use serde::{Deserialize, Serialize};
 
+use crate::diagnostic::Spanned;
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Token {
     FuncKeyword,
+    ReturnKeyword,
+    LetKeyword,
+    MutKeyword,
     Identifier(String),
     OpenParen,
     CloseParen,
     OpenBrace,
-    Number(u32),
+    Number(u64),
+    /// Kept as written, since `f64` can't be hashed.
+    Float(String),
     CloseBrace,
     Semicolon,
+    Colon,
+    Comma,
+    Equals,
+    Arrow,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::FuncKeyword => write!(f, "func"),
+            Self::ReturnKeyword => write!(f, "return"),
+            Self::LetKeyword => write!(f, "let"),
+            Self::MutKeyword => write!(f, "mut"),
+            Self::Identifier(name) => write!(f, "{name}"),
+            Self::OpenParen => write!(f, "("),
+            Self::CloseParen => write!(f, ")"),
+            Self::OpenBrace => write!(f, "{{"),
+            Self::Number(number) => write!(f, "{number}"),
+            Self::Float(float) => write!(f, "{float}"),
+            Self::CloseBrace => write!(f, "}}"),
+            Self::Semicolon => write!(f, ";"),
+            Self::Colon => write!(f, ":"),
+            Self::Comma => write!(f, ","),
+            Self::Equals => write!(f, "="),
+            Self::Arrow => write!(f, "->"),
+            Self::Plus => write!(f, "+"),
+            Self::Minus => write!(f, "-"),
+            Self::Star => write!(f, "*"),
+            Self::Slash => write!(f, "/"),
+        }
+    }
 }
 
-pub fn lexer() -> impl Parser<char, Vec<Token>, Error = Simple<char>> {
+pub fn lexer() -> impl Parser<char, Vec<Spanned<Token>>, Error = Simple<char>> {
     use Token::*;
 
+    // One parser for both, so a too large integer isn't reported as a float
+    // missing its `.`
     let num = text::int(10)
-        .chain::<char, _, _>(just('.').chain(text::digits(10)).or_not().flatten())
-        .collect::<String>()
-        .map(|num| Number(num.parse().unwrap()));
+        .then(just('.').chain(text::digits(10)).or_not())
+        .try_map(
+            |(int, fraction): (String, Option<Vec<char>>), span| match fraction {
+                Some(fraction) => Ok(Float(int + &fraction.into_iter().collect::<String>())),
+                None => int
+                    .parse()
+                    .map(Number)
+                    .map_err(|_| Simple::custom(span, "number is too large")),
+            },
+        );
 
-    let ctrl = just('(')
-        .to(OpenParen)
+    let ctrl = just("->")
+        .to(Arrow)
+        .or(just('(').to(OpenParen))
         .or(just(')').to(CloseParen))
         .or(just('{').to(OpenBrace))
         .or(just('}').to(CloseBrace))
-        .or(just(';').to(Semicolon));
+        .or(just(';').to(Semicolon))
+        .or(just(':').to(Colon))
+        .or(just(',').to(Comma))
+        .or(just('=').to(Equals))
+        .or(just('+').to(Plus))
+        .or(just('-').to(Minus))
+        .or(just('*').to(Star))
+        .or(just('/').to(Slash));
 
     let ident = text::ident().map(|ident: String| match ident.as_str() {
         "func" => FuncKeyword,
+        "return" => ReturnKeyword,
+        "let" => LetKeyword,
+        "mut" => MutKeyword,
         _ => Identifier(ident),
     });
 
     let token = num
         .or(ctrl)
         .or(ident)
+        .map_with_span(|token, span| (token, span))
         .recover_with(skip_then_retry_until([]));
 
-    token.padded().repeated()
+    token.padded().repeated().then_ignore(end())
 }
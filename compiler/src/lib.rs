@@ -0,0 +1,70 @@
+pub mod check;
+pub mod diagnostic;
+pub mod fold;
+pub mod generate;
+pub mod ir;
+pub mod lexer;
+pub mod parser;
+#[cfg(test)]
+mod test;
+pub mod tool;
+pub mod x86;
+
+use chumsky::{Parser, Stream};
+
+use diagnostic::{Diagnostics, Spanned};
+use generate::Function;
+use ir::Program;
+use lexer::Token;
+use parser::Ast;
+
+/// Lexes `input`, collecting every lexer error.
+pub fn lex(input: &str) -> Result<Vec<Spanned<Token>>, Diagnostics> {
+    let (tokens, errors) = lexer::lexer().parse_recovery(input);
+
+    match tokens {
+        Some(tokens) if errors.is_empty() => Ok(tokens),
+        _ => Err(errors.into_iter().collect()),
+    }
+}
+
+/// Lexes and parses `input`. The parser only runs on a clean token stream,
+/// since tokens the lexer skipped would otherwise show up again as confusing
+/// parse errors.
+pub fn parse(input: &str) -> Result<Ast, Diagnostics> {
+    let tokens = lex(input)?;
+
+    let end = input.chars().count();
+    let (ast, errors) =
+        parser::parser().parse_recovery(Stream::from_iter(end..end + 1, tokens.into_iter()));
+
+    match ast {
+        Some(ast) if errors.is_empty() => Ok(ast),
+        _ => Err(errors.into_iter().collect()),
+    }
+}
+
+/// Parses and checks `input`, then folds its constants.
+pub fn analyze(input: &str) -> Result<Program, Diagnostics> {
+    let ast = parse(input)?;
+    let mut program = check::check(&ast).map_err(|errors| errors.into_iter().collect())?;
+    fold::fold(&mut program);
+    Ok(program)
+}
+
+/// Lowers `input` to the IR shared by both backends, collecting diagnostics
+/// from the first stage that fails.
+pub fn lower(input: &str) -> Result<Vec<Function>, Diagnostics> {
+    Ok(generate::generate(&analyze(input)?))
+}
+
+/// Compiles `input` to QBE IR.
+pub fn compile(input: &str) -> Result<String, Diagnostics> {
+    let functions: Vec<_> = lower(input)?.iter().map(ToString::to_string).collect();
+    Ok(functions.join("\n"))
+}
+
+/// Compiles `input` to x86-64 assembly with the built-in backend.
+pub fn assemble(input: &str) -> Result<String, Diagnostics> {
+    Ok(x86::emit(&lower(input)?))
+}
@@ -1,62 +1,110 @@
-mod generate;
-mod lexer;
-mod parser;
-#[cfg(test)]
-mod test;
+use std::{
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+    process::Command,
+};
 
-use std::{fs, path::PathBuf, process::Command};
+use anyhow::{bail, Context, Result};
+use clap::{ArgEnum, Parser};
 
-use anyhow::Result;
-use chumsky::Parser;
-use clap::Parser as Clap;
+use compiler::{analyze, assemble, compile, diagnostic::Diagnostics, lex, parse, tool};
 
-use generate::generate;
-use lexer::lexer;
-use parser::parser;
-
-#[derive(Debug, Clap)]
+#[derive(Debug, Parser)]
 struct Args {
     input: PathBuf,
+
+    /// The stage to stop at and write out
+    #[clap(long, arg_enum, default_value = "exe")]
+    emit: Emit,
+
+    /// Where to write the output. Defaults to standard output, or next to the
+    /// input for executables
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+
+    /// Runs the executable once it is built
+    #[clap(long)]
+    run: bool,
+
+    /// How to produce assembly
+    #[clap(long, arg_enum, default_value = "native")]
+    backend: Backend,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ArgEnum)]
+enum Emit {
+    Tokens,
+    Ast,
+    /// The checked and folded program
+    Ir,
+    Ssa,
+    Asm,
+    Exe,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ArgEnum)]
+enum Backend {
+    /// The built-in x86-64 backend
+    Native,
+    /// Shell out to `qbe`
+    Qbe,
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
 
-    let input = fs::read_to_string(&args.input)?;
+    if args.run && args.emit != Emit::Exe {
+        bail!("`--run` needs `--emit exe`");
+    }
 
-    let tokens = lexer().parse(input).unwrap();
-    fs::write(
-        args.input.with_extension("token.json"),
-        serde_json::to_string_pretty(&tokens)?,
-    )?;
+    let input = fs::read_to_string(&args.input)
+        .with_context(|| format!("could not read `{}`", args.input.display()))?;
 
-    let ast = parser().parse(tokens).unwrap();
-    fs::write(
-        args.input.with_extension("ast.json"),
-        serde_json::to_string_pretty(&ast)?,
-    )?;
+    let output = match args.emit {
+        Emit::Tokens => serde_json::to_vec_pretty(&diagnose(&input, lex(&input)))?,
+        Emit::Ast => serde_json::to_vec_pretty(&diagnose(&input, parse(&input)))?,
+        Emit::Ir => serde_json::to_vec_pretty(&diagnose(&input, analyze(&input)))?,
+        Emit::Ssa => diagnose(&input, compile(&input)).into_bytes(),
+        Emit::Asm => assembly(&input, args.backend)?,
+        Emit::Exe => {
+            let ast = diagnose(&input, parse(&input));
+            if !ast.functions.iter().any(|function| function.name == "main") {
+                bail!("no `main` function found");
+            }
 
-    let output = generate(ast);
-    fs::write(args.input.with_extension("ssa"), output)?;
+            let path = args.output.unwrap_or_else(|| args.input.with_extension(""));
+            tool::cc(&assembly(&input, args.backend)?, &path)?;
 
-    Command::new("qbe")
-        .arg(args.input.with_extension("ssa"))
-        .arg("-o")
-        .arg(args.input.with_extension("s"))
-        .output()?;
+            if args.run {
+                let exit_code = Command::new(&path).status()?.code();
+                println!("exit_code: {exit_code:?}");
+            }
 
-    Command::new("cc")
-        .arg(args.input.with_extension("s"))
-        .arg("-o")
-        .arg(args.input.with_extension(""))
-        .output()?;
+            return Ok(());
+        }
+    };
 
-    let exit_code = Command::new(args.input.with_extension(""))
-        .output()?
-        .status
-        .code();
-
-    println!("exit_code: {exit_code:?}");
+    match args.output {
+        Some(path) => fs::write(path, output)?,
+        None => io::stdout().write_all(&output)?,
+    }
 
     Ok(())
 }
+
+/// Unwraps a stage's result, or prints its diagnostics and exits with a
+/// failure status.
+fn diagnose<T>(input: &str, result: Result<T, Diagnostics>) -> T {
+    result.unwrap_or_else(|diagnostics| {
+        eprint!("{}", diagnostics.render(input));
+        std::process::exit(1);
+    })
+}
+
+fn assembly(input: &str, backend: Backend) -> Result<Vec<u8>> {
+    Ok(match backend {
+        Backend::Native => diagnose(input, assemble(input)).into_bytes(),
+        Backend::Qbe => tool::qbe(&diagnose(input, compile(input)))?,
+    })
+}
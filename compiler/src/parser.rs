@@ -1,41 +1,211 @@
 use chumsky::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use crate::lexer::Token;
+use crate::{diagnostic::Span, lexer::Token};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Ast {
+    pub functions: Vec<Function>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Function {
     pub name: String,
+    pub span: Span,
+    pub parameters: Vec<Parameter>,
+    pub return_type: Option<TypeName>,
     pub body: Block,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Parameter {
+    pub name: String,
+    pub ty: TypeName,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TypeName {
+    pub name: String,
+    pub span: Span,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Block {
-    pub body: Vec<u32>,
-    pub trailing: u32,
+    pub statements: Vec<Statement>,
+    /// The block's value, if it doesn't `return` instead.
+    pub trailing: Option<Expression>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Statement {
+    /// `let mut name: Type = value;`, where `mut` and the type are optional.
+    Let {
+        name: String,
+        span: Span,
+        mutable: bool,
+        ty: Option<TypeName>,
+        value: Expression,
+    },
+    /// `return value;`
+    Return {
+        value: Expression,
+        span: Span,
+    },
+    Expression(Expression),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Expression {
+    pub kind: ExpressionKind,
+    pub span: Span,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ExpressionKind {
+    Number(u64),
+    Float(f64),
+    Variable(String),
+    Call {
+        name: String,
+        arguments: Vec<Expression>,
+    },
+    Binary {
+        operator: BinaryOperator,
+        left: Box<Expression>,
+        right: Box<Expression>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+// `select!` returns chumsky's large `Simple` error by value
+#[allow(clippy::result_large_err)]
 pub fn parser() -> impl Parser<Token, Ast, Error = Simple<Token>> {
     use Token::*;
 
-    let number = select! { Number(number) => number };
+    let ident = select! { Identifier(name) => name };
 
-    let block = just(OpenBrace)
-        .ignore_then(
-            number
-                .then_ignore(just(Semicolon))
-                .repeated()
-                .collect::<Vec<_>>(),
+    let expression = recursive(|expression| {
+        let call = ident
+            .then(
+                expression
+                    .clone()
+                    .separated_by(just(Comma))
+                    .allow_trailing()
+                    .delimited_by(OpenParen, CloseParen),
+            )
+            .map(|(name, arguments)| ExpressionKind::Call { name, arguments });
+
+        let atom = select! {
+            Number(number) => ExpressionKind::Number(number),
+            // The lexer only produces valid floats
+            Float(float) => ExpressionKind::Float(float.parse().unwrap()),
+        }
+        .or(call)
+        .or(ident.map(ExpressionKind::Variable))
+        .map_with_span(|kind, span| Expression { kind, span })
+        .or(expression.delimited_by(OpenParen, CloseParen));
+
+        let product = binary(
+            atom.boxed(),
+            select! {
+                Star => BinaryOperator::Multiply,
+                Slash => BinaryOperator::Divide,
+            },
+        );
+
+        binary(
+            product,
+            select! {
+                Plus => BinaryOperator::Add,
+                Minus => BinaryOperator::Subtract,
+            },
         )
-        .then(number)
-        .then_ignore(just(CloseBrace))
-        .map(|(body, trailing)| Block { body, trailing });
-
-    just(FuncKeyword)
-        .ignore_then(select! { Identifier(name) => name })
-        .then_ignore(just(OpenParen))
-        .then_ignore(just(CloseParen))
+    });
+
+    let type_name = ident.map_with_span(|name, span| TypeName { name, span });
+
+    let statement = just(LetKeyword)
+        .ignore_then(just(MutKeyword).or_not().map(|mutable| mutable.is_some()))
+        .then(ident.map_with_span(|name, span| (name, span)))
+        .then(just(Colon).ignore_then(type_name).or_not())
+        .then_ignore(just(Equals))
+        .then(expression.clone())
+        .map(|(((mutable, (name, span)), ty), value)| Statement::Let {
+            name,
+            span,
+            mutable,
+            ty,
+            value,
+        })
+        .or(just(ReturnKeyword)
+            .ignore_then(expression.clone())
+            .map_with_span(|value, span| Statement::Return { value, span }))
+        .or(expression.clone().map(Statement::Expression))
+        .then_ignore(just(Semicolon));
+
+    let block = statement
+        .repeated()
+        .then(expression.or_not())
+        .delimited_by(OpenBrace, CloseBrace)
+        .map(|(statements, trailing)| Block {
+            statements,
+            trailing,
+        });
+
+    let parameter = ident
+        .then_ignore(just(Colon))
+        .then(type_name)
+        .map(|(name, ty)| Parameter { name, ty });
+
+    let function = just(FuncKeyword)
+        .ignore_then(ident.map_with_span(|name, span| (name, span)))
+        .then(
+            parameter
+                .separated_by(just(Comma))
+                .allow_trailing()
+                .delimited_by(OpenParen, CloseParen),
+        )
+        .then(just(Arrow).ignore_then(type_name).or_not())
         .then(block)
         .then_ignore(just(Semicolon))
-        .map(|(name, body)| Ast { name, body })
+        .map(
+            |((((name, span), parameters), return_type), body)| Function {
+                name,
+                span,
+                parameters,
+                return_type,
+                body,
+            },
+        );
+
+    function
+        .repeated()
+        .then_ignore(end())
+        .map(|functions| Ast { functions })
+}
+
+/// Parses a left associative chain of `operand`s joined by `operator`.
+fn binary<'a>(
+    operand: BoxedParser<'a, Token, Expression, Simple<Token>>,
+    operator: impl Parser<Token, BinaryOperator, Error = Simple<Token>> + 'a,
+) -> BoxedParser<'a, Token, Expression, Simple<Token>> {
+    operand
+        .clone()
+        .then(operator.then(operand).repeated())
+        .foldl(|left, (operator, right)| Expression {
+            span: left.span.start..right.span.end,
+            kind: ExpressionKind::Binary {
+                operator,
+                left: Box::new(left),
+                right: Box::new(right),
+            },
+        })
+        .boxed()
 }
@@ -1,6 +1,44 @@
+use std::{
+    process::{self, Command},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
 use indoc::indoc;
 
-use crate::compile;
+use crate::{analyze, assemble, compile, ir::Program, tool};
+
+/// Renders the diagnostics from compiling `input`.
+fn errors(input: &str) -> String {
+    compile(input)
+        .expect_err("input should fail to compile")
+        .render(input)
+}
+
+/// Builds `input` with the native backend and runs it, returning its exit
+/// code. When `qbe` is installed, the QBE build has to exit the same way.
+fn run(input: &str) -> i32 {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+    let exit_code = |assembly: &[u8]| {
+        let id = NEXT.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("compiler-test-{}-{id}", process::id()));
+
+        tool::cc(assembly, &path).unwrap();
+        let status = Command::new(&path).status().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        status.code().unwrap()
+    };
+
+    let native = exit_code(assemble(input).unwrap().as_bytes());
+
+    match tool::qbe(&compile(input).unwrap()) {
+        Ok(assembly) => assert_eq!(native, exit_code(&assembly), "QBE disagrees"),
+        Err(tool::Error::Missing { .. }) => {}
+        Err(error) => panic!("{error}"),
+    }
+
+    native
+}
 
 #[test]
 fn compile_number() {
@@ -14,14 +52,14 @@ fn compile_number() {
 
     let expected = indoc!(
         "
-        export function w $test() {
+        function w $test() {
         @start
             ret 1234
         }
         "
     );
 
-    assert_eq!(expected, compile(input));
+    assert_eq!(expected, compile(input).unwrap());
 }
 
 #[test]
@@ -38,12 +76,504 @@ fn multiple_expressions() {
 
     let expected = indoc!(
         "
-        export function w $test() {
+        function w $test() {
         @start
             ret 789
         }
         "
     );
 
-    assert_eq!(expected, compile(input));
+    assert_eq!(expected, compile(input).unwrap());
+}
+
+#[test]
+fn calls() {
+    let input = indoc!(
+        "
+        func add(a: Int, b: Int) {
+            b
+        };
+
+        func main() {
+            log(1);
+            add(add(1, 2), 3)
+        };
+
+        func log(value: Int) {
+            value
+        };
+        "
+    );
+
+    let expected = indoc!(
+        "
+        function w $add(w %a, w %b) {
+        @start
+            ret %b
+        }
+
+        export function w $main() {
+        @start
+            call $log(w 1)
+            %.0 =w call $add(w 1, w 2)
+            %.1 =w call $add(w %.0, w 3)
+            ret %.1
+        }
+
+        function w $log(w %value) {
+        @start
+            ret %value
+        }
+        "
+    );
+
+    assert_eq!(expected, compile(input).unwrap());
+}
+
+#[test]
+fn call_errors() {
+    let input = indoc!(
+        "
+        func one(a: Int) {
+            a
+        };
+
+        func main() {
+            missing(one());
+            one(b, 2)
+        };
+
+        func one() {
+            1
+        };
+        "
+    );
+
+    let expected = indoc!(
+        "
+        error: function `one` is defined twice
+          --> 10:6
+           |
+        10 | func one() {
+           |      ^^^ redefined here
+
+        error: function `one` takes 1 argument(s) but 0 were given
+         --> 6:13
+          |
+        6 |     missing(one());
+          |             ^^^^^ expected 1 argument(s)
+
+        error: unknown function `missing`
+         --> 6:5
+          |
+        6 |     missing(one());
+          |     ^^^^^^^^^^^^^^ not found
+
+        error: unknown variable `b`
+         --> 7:9
+          |
+        7 |     one(b, 2)
+          |         ^ not in scope
+
+        error: function `one` takes 1 argument(s) but 2 were given
+         --> 7:5
+          |
+        7 |     one(b, 2)
+          |     ^^^^^^^^^ expected 1 argument(s)
+        "
+    );
+
+    assert_eq!(expected, errors(input));
+}
+
+#[test]
+fn lexer_errors() {
+    let input = indoc!(
+        "
+        func main() {
+            1 @;
+            add(999999999999999999999999)
+        };
+        "
+    );
+
+    let expected = indoc!(
+        "
+        error: unexpected `@`
+         --> 2:7
+          |
+        2 |     1 @;
+          |       ^ unexpected here
+
+        error: number is too large
+         --> 3:9
+          |
+        3 |     add(999999999999999999999999)
+          |         ^^^^^^^^^^^^^^^^^^^^^^^^ here
+        "
+    );
+
+    assert_eq!(expected, errors(input));
+}
+
+#[test]
+fn parser_errors() {
+    let input = indoc!(
+        "
+        func main(a Int) {
+            1
+        };
+        "
+    );
+
+    let expected = indoc!(
+        "
+        error: unexpected `Int`
+         --> 1:13
+          |
+        1 | func main(a Int) {
+          |             ^^^ expected `:`
+        "
+    );
+
+    assert_eq!(expected, errors(input));
+
+    let expected = indoc!(
+        "
+        error: unexpected end of input
+         --> 2:6
+          |
+        2 |     1
+          |      ^ expected `;` or `}`
+        "
+    );
+
+    assert_eq!(expected, errors("func main() {\n    1"));
+}
+
+#[test]
+fn returns_and_lets() {
+    let input = indoc!(
+        "
+        func square(x: Long) -> Long {
+            let mut y = x * x;
+            let y: Long = y + 1;
+            return y;
+        };
+
+        func main() {
+            let a = 3;
+            square(2);
+            return a + 39;
+            7
+        };
+        "
+    );
+
+    let expected = indoc!(
+        "
+        function l $square(l %x) {
+        @start
+            %.0 =l mul %x, %x
+            %.1 =l add %.0, 1
+            ret %.1
+        }
+
+        export function w $main() {
+        @start
+            call $square(l 2)
+            %.0 =w add 3, 39
+            ret %.0
+        }
+        "
+    );
+
+    assert_eq!(expected, compile(input).unwrap());
+    assert_eq!(run(input), 42);
+}
+
+#[test]
+fn statement_errors() {
+    let input = indoc!(
+        "
+        func main() {
+            let a: Float = 1;
+            let b = b;
+            return a;
+        };
+
+        func nothing() {
+            let c = 1;
+        };
+        "
+    );
+
+    let expected = indoc!(
+        "
+        error: mismatched types
+         --> 2:20
+          |
+        2 |     let a: Float = 1;
+          |                    ^ expected `d`, found `w`
+
+        error: unknown variable `b`
+         --> 3:13
+          |
+        3 |     let b = b;
+          |             ^ not in scope
+
+        error: mismatched types
+         --> 4:12
+          |
+        4 |     return a;
+          |            ^ expected `w`, found `d`
+
+        error: function `nothing` doesn't return a value
+         --> 7:6
+          |
+        7 | func nothing() {
+          |      ^^^^^^^ expected a trailing expression or `return`
+        "
+    );
+
+    assert_eq!(expected, errors(input));
+}
+
+#[test]
+fn typed_functions() {
+    let input = indoc!(
+        "
+        func scale(x: Float, by: Long) -> Float {
+            x * 2.5
+        };
+
+        func main() {
+            scale(1.5, 4);
+            8 - 3
+        };
+        "
+    );
+
+    let expected = indoc!(
+        "
+        function d $scale(d %x, l %by) {
+        @start
+            %.0 =d mul %x, d_2.5
+            ret %.0
+        }
+
+        export function w $main() {
+        @start
+            call $scale(d d_1.5, l 4)
+            ret 5
+        }
+        "
+    );
+
+    assert_eq!(expected, compile(input).unwrap());
+}
+
+#[test]
+fn constant_folding() {
+    let input = indoc!(
+        "
+        func wrap() {
+            2147483647 + 1
+        };
+
+        func long() -> Long {
+            (0 - 5000000000) / 2
+        };
+
+        func trap(a: Int) {
+            a + 1 * 2 / 0
+        };
+        "
+    );
+
+    let expected = indoc!(
+        "
+        function w $wrap() {
+        @start
+            ret -2147483648
+        }
+
+        function l $long() {
+        @start
+            ret -2500000000
+        }
+
+        function w $trap(w %a) {
+        @start
+            %.0 =w div 2, 0
+            %.1 =w add %a, %.0
+            ret %.1
+        }
+        "
+    );
+
+    assert_eq!(expected, compile(input).unwrap());
+}
+
+#[test]
+fn type_errors() {
+    let input = indoc!(
+        "
+        func half(x: Float) -> Float {
+            x / 2
+        };
+
+        func main(a: Text) {
+            half(1);
+            5000000000
+        };
+        "
+    );
+
+    let expected = indoc!(
+        "
+        error: unknown type `Text`
+         --> 5:14
+          |
+        5 | func main(a: Text) {
+          |              ^^^^ expected `Int`, `Long` or `Float`
+
+        error: mismatched types
+         --> 2:9
+          |
+        2 |     x / 2
+          |         ^ expected `d`, found `w`
+
+        error: mismatched types
+         --> 6:10
+          |
+        6 |     half(1);
+          |          ^ expected `d`, found `w`
+
+        error: integer is too large for `w`
+         --> 7:5
+          |
+        7 |     5000000000
+          |     ^^^^^^^^^^ doesn't fit
+        "
+    );
+
+    assert_eq!(expected, errors(input));
+}
+
+#[test]
+fn serialize_ir() {
+    let input = indoc!(
+        "
+        func main() {
+            add(1, 2.5);
+            3
+        };
+
+        func add(a: Long, b: Float) -> Long {
+            a / 2
+        };
+        "
+    );
+
+    let program = analyze(input).unwrap();
+    let json = serde_json::to_string(&program).unwrap();
+    assert_eq!(program, serde_json::from_str::<Program>(&json).unwrap());
+}
+
+#[test]
+fn native_calls() {
+    let input = indoc!(
+        "
+        func second(a: Int, b: Int) {
+            b
+        };
+
+        func main() {
+            second(1, second(2, 42))
+        };
+        "
+    );
+
+    assert_eq!(run(input), 42);
+}
+
+#[test]
+fn native_sonance() {
+    assert_eq!(run(include_str!("../../c-test/test.son")), 0);
+}
+
+#[test]
+fn native_stack_arguments() {
+    let input = indoc!(
+        "
+        func seventh(a: Int, b: Int, c: Int, d: Int, e: Int, f: Int, g: Int, h: Int) {
+            g
+        };
+
+        func eighth(a: Int, b: Int, c: Int, d: Int, e: Int, f: Int, g: Int, h: Int) {
+            h
+        };
+
+        func main() {
+            seventh(1, 2, 3, 4, 5, 6, eighth(1, 2, 3, 4, 5, 6, 7, 8), 9)
+        };
+        "
+    );
+
+    assert_eq!(run(input), 8);
+}
+
+#[test]
+fn native_spills() {
+    // Eight temporaries are live at once, but only five registers are free
+    let input = indoc!(
+        "
+        func id(a: Int) {
+            a
+        };
+
+        func first(a: Int, b: Int, c: Int, d: Int, e: Int, f: Int, g: Int, h: Int) {
+            a
+        };
+
+        func last(a: Int, b: Int, c: Int, d: Int, e: Int, f: Int, g: Int, h: Int) {
+            h
+        };
+
+        func main() {
+            first(id(11), id(2), id(3), id(4), id(5), id(6), id(7), id(8));
+            last(id(1), id(2), id(3), id(4), id(5), id(6), id(7), first(id(23), 2, 3, 4, 5, 6, 7, 8))
+        };
+        "
+    );
+
+    assert_eq!(run(input), 23);
+}
+
+#[test]
+fn native_types() {
+    // Floats and integers are passed in separate registers, so `h` and `i` go
+    // on the stack while the floats don't
+    let input = indoc!(
+        "
+        func big(a: Long) -> Long {
+            a * 1000000000 / 1000000000 - 4999999990
+        };
+
+        func scale(x: Float, y: Float) -> Float {
+            x * y + 0.5
+        };
+
+        func pick(x: Float, a: Int, y: Float, b: Long, c: Int, d: Int, e: Int, f: Int, h: Int, i: Int) {
+            i - h
+        };
+
+        func main() {
+            pick(scale(1.5, 2.0), 1, 2.5, big(5000000000), 3, 4, 5, 6, 10, 52) + (7 - 7) * 3
+        };
+        "
+    );
+
+    assert_eq!(run(input), 42);
 }
@@ -0,0 +1,94 @@
+use std::{
+    ffi::OsStr,
+    fmt::Display,
+    io::{self, Write},
+    path::Path,
+    process::{Command, Stdio},
+};
+
+/// A failure running one of the external tools the compiler shells out to.
+#[derive(Debug)]
+pub enum Error {
+    /// The tool isn't installed, or isn't on the `PATH`.
+    Missing {
+        name: String,
+    },
+    Failed {
+        name: String,
+        stderr: String,
+    },
+    Io {
+        name: String,
+        error: io::Error,
+    },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Missing { name } => write!(
+                f,
+                "could not find `{name}`, make sure it is installed and on your PATH"
+            ),
+            Self::Failed { name, stderr } => write!(f, "`{name}` failed:\n{stderr}"),
+            Self::Io { name, error } => write!(f, "could not run `{name}`: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Compiles QBE IR to assembly.
+pub fn qbe(ssa: &str) -> Result<Vec<u8>, Error> {
+    run("qbe", [] as [&OsStr; 0], ssa.as_bytes())
+}
+
+/// Assembles and links `assembly` into an executable at `output`.
+pub fn cc(assembly: &[u8], output: &Path) -> Result<(), Error> {
+    let args = ["-x", "assembler", "-", "-o"].map(OsStr::new);
+    run("cc", args.into_iter().chain([output.as_os_str()]), assembly).map(drop)
+}
+
+/// Runs an external tool with `input` on standard input, returning what it
+/// wrote to standard output.
+pub fn run<I, S>(name: &str, args: I, input: &[u8]) -> Result<Vec<u8>, Error>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let io_error = |error: io::Error| Error::Io {
+        name: name.to_owned(),
+        error,
+    };
+
+    let mut child = Command::new(name)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| match error.kind() {
+            io::ErrorKind::NotFound => Error::Missing {
+                name: name.to_owned(),
+            },
+            _ => io_error(error),
+        })?;
+
+    // Write on another thread so a tool filling its stdout pipe can't
+    // deadlock with us. Dropping stdin closes it, ending the tool's input.
+    let mut stdin = child.stdin.take().expect("stdin should be piped");
+    let output = std::thread::scope(|scope| {
+        scope.spawn(move || stdin.write_all(input));
+        child.wait_with_output()
+    })
+    .map_err(io_error)?;
+
+    if !output.status.success() {
+        return Err(Error::Failed {
+            name: name.to_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        });
+    }
+
+    Ok(output.stdout)
+}
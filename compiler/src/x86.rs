@@ -0,0 +1,553 @@
+//! Lowers the IR from [`generate`](crate::generate) straight to x86-64
+//! System V assembly in AT&T syntax, so no external `qbe` is needed.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::Write,
+};
+
+use crate::{
+    generate::{Function, Instruction, Value},
+    ir::{BinaryOperator, Type},
+};
+
+const ARGUMENT_REGISTERS: [Register; 6] = [
+    Register::new("%rdi", "%edi"),
+    Register::new("%rsi", "%esi"),
+    Register::new("%rdx", "%edx"),
+    Register::new("%rcx", "%ecx"),
+    Register::new("%r8", "%r8d"),
+    Register::new("%r9", "%r9d"),
+];
+
+const FLOAT_ARGUMENT_REGISTERS: usize = 8;
+
+/// Only callee-saved registers are allocated, so integers survive calls
+/// without the caller saving anything around them. Every `%xmm` register is
+/// caller-saved, so floats always live in spill slots instead.
+const REGISTERS: [Register; 5] = [
+    Register::new("%rbx", "%ebx"),
+    Register::new("%r12", "%r12d"),
+    Register::new("%r13", "%r13d"),
+    Register::new("%r14", "%r14d"),
+    Register::new("%r15", "%r15d"),
+];
+
+/// Scratch registers for moving values around. Neither is ever allocated.
+const RAX: Register = Register::new("%rax", "%eax");
+const RCX: Register = Register::new("%rcx", "%ecx");
+
+pub fn emit(functions: &[Function]) -> String {
+    let mut output = String::from("    .text\n");
+
+    for function in functions {
+        Emitter::new(function).function(&mut output);
+    }
+
+    output.push_str("    .section .note.GNU-stack,\"\",@progbits\n");
+    output
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Register {
+    quad: &'static str,
+    long: &'static str,
+}
+
+impl Register {
+    const fn new(quad: &'static str, long: &'static str) -> Self {
+        Self { quad, long }
+    }
+
+    fn name(self, ty: Type) -> &'static str {
+        match ty {
+            Type::W => self.long,
+            Type::L => self.quad,
+            Type::D => unreachable!("Floats aren't kept in general purpose registers"),
+        }
+    }
+}
+
+/// The `mov` and arithmetic suffix for an integer type.
+fn suffix(ty: Type) -> &'static str {
+    match ty {
+        Type::W => "l",
+        Type::L => "q",
+        Type::D => unreachable!("Floats use their own instructions"),
+    }
+}
+
+/// A value the allocator has to find a home for.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum Virtual {
+    Parameter(String),
+    Temporary(usize),
+}
+
+impl Virtual {
+    fn of(value: &Value) -> Option<Self> {
+        match value {
+            Value::Integer(_) | Value::Float(_) => None,
+            Value::Parameter(name) => Some(Self::Parameter(name.clone())),
+            Value::Temporary(id) => Some(Self::Temporary(*id)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Location {
+    Register(Register),
+    /// A spill slot in this function's frame.
+    Slot(usize),
+    /// A parameter the caller passed on the stack.
+    Incoming(usize),
+}
+
+/// Where the System V calling convention puts an argument.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Passing {
+    Integer(Register),
+    Float(usize),
+    Stack(usize),
+}
+
+fn classify(types: impl IntoIterator<Item = Type>) -> Vec<Passing> {
+    let (mut integers, mut floats, mut stack) = (0, 0, 0);
+    let next = |count: &mut usize| {
+        *count += 1;
+        *count - 1
+    };
+
+    types
+        .into_iter()
+        .map(|ty| match ty {
+            Type::D if floats < FLOAT_ARGUMENT_REGISTERS => Passing::Float(next(&mut floats)),
+            Type::W | Type::L if integers < ARGUMENT_REGISTERS.len() => {
+                Passing::Integer(ARGUMENT_REGISTERS[next(&mut integers)])
+            }
+            _ => Passing::Stack(next(&mut stack)),
+        })
+        .collect()
+}
+
+/// The positions a value is live between. Parameters are defined at 0 and
+/// the `n`th instruction is at `n + 1`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct Interval {
+    start: usize,
+    end: usize,
+    value: Virtual,
+}
+
+fn intervals(function: &Function, passing: &[Passing]) -> Vec<Interval> {
+    let mut ranges = HashMap::new();
+
+    for ((name, _), passing) in function.parameters.iter().zip(passing) {
+        if !matches!(passing, Passing::Stack(_)) {
+            ranges.insert(Virtual::Parameter(name.clone()), (0, 0));
+        }
+    }
+
+    for (index, instruction) in function.body.iter().enumerate() {
+        let position = index + 1;
+        let (uses, result) = match instruction {
+            Instruction::Ret(value) => (vec![value], None),
+            Instruction::Call {
+                arguments, result, ..
+            } => (
+                arguments.iter().map(|(value, _)| value).collect(),
+                result.map(|(id, _)| id),
+            ),
+            Instruction::Binary {
+                left,
+                right,
+                result,
+                ..
+            } => (vec![left, right], Some(*result)),
+        };
+
+        for value in uses.into_iter().filter_map(Virtual::of) {
+            if let Some((_, end)) = ranges.get_mut(&value) {
+                *end = position;
+            }
+        }
+
+        if let Some(id) = result {
+            ranges.insert(Virtual::Temporary(id), (position, position));
+        }
+    }
+
+    // Parameters that are never used don't need a home. Every other interval
+    // ends after position 0, so the moves out of the argument registers never
+    // clobber each other.
+    let mut intervals: Vec<_> = ranges
+        .into_iter()
+        .filter(|(_, (_, end))| *end > 0)
+        .map(|(value, (start, end))| Interval { start, end, value })
+        .collect();
+    intervals.sort();
+    intervals
+}
+
+/// Linear scan register allocation for integers. When every register is
+/// taken, whichever live value ends last is spilled to the stack for its
+/// whole lifetime.
+fn allocate(
+    function: &Function,
+    types: &HashMap<Virtual, Type>,
+    passing: &[Passing],
+) -> (HashMap<Virtual, Location>, usize) {
+    let mut locations = HashMap::new();
+    let mut free: Vec<_> = REGISTERS.iter().rev().copied().collect();
+    let mut active: Vec<(usize, Virtual, Register)> = Vec::new();
+    let mut slots = 0;
+
+    for ((name, _), passing) in function.parameters.iter().zip(passing) {
+        if let Passing::Stack(index) = passing {
+            locations.insert(Virtual::Parameter(name.clone()), Location::Incoming(*index));
+        }
+    }
+
+    for interval in intervals(function, passing) {
+        if types[&interval.value] == Type::D {
+            locations.insert(interval.value, Location::Slot(slots));
+            slots += 1;
+            continue;
+        }
+
+        // An instruction reads its operands before writing its result, so a
+        // value's register is free again at the position it dies.
+        active.retain(|(end, _, register)| {
+            let live = *end > interval.start;
+            if !live {
+                free.push(*register);
+            }
+            live
+        });
+
+        let register = match free.pop() {
+            Some(register) => register,
+            None => {
+                let (index, (end, ..)) = active
+                    .iter()
+                    .enumerate()
+                    .max_by_key(|(_, (end, ..))| *end)
+                    .expect("there are no free registers, so some must be active");
+
+                if *end <= interval.end {
+                    locations.insert(interval.value, Location::Slot(slots));
+                    slots += 1;
+                    continue;
+                }
+
+                let (_, spilled, register) = active.remove(index);
+                locations.insert(spilled, Location::Slot(slots));
+                slots += 1;
+                register
+            }
+        };
+
+        locations.insert(interval.value.clone(), Location::Register(register));
+        active.push((interval.end, interval.value, register));
+    }
+
+    (locations, slots)
+}
+
+struct Emitter<'a> {
+    function: &'a Function,
+    passing: Vec<Passing>,
+    locations: HashMap<Virtual, Location>,
+    /// The callee-saved registers this function uses, in push order.
+    saved: Vec<Register>,
+    /// Bytes reserved below the saved registers for spill slots.
+    frame: usize,
+}
+
+impl<'a> Emitter<'a> {
+    fn new(function: &'a Function) -> Self {
+        let passing = classify(function.parameters.iter().map(|(_, ty)| *ty));
+
+        let mut types: HashMap<_, _> = function
+            .parameters
+            .iter()
+            .map(|(name, ty)| (Virtual::Parameter(name.clone()), *ty))
+            .collect();
+        for instruction in &function.body {
+            match instruction {
+                Instruction::Call {
+                    result: Some((id, ty)),
+                    ..
+                }
+                | Instruction::Binary { result: id, ty, .. } => {
+                    types.insert(Virtual::Temporary(*id), *ty);
+                }
+                _ => {}
+            }
+        }
+
+        let (locations, slots) = allocate(function, &types, &passing);
+
+        let saved: BTreeSet<_> = locations
+            .values()
+            .filter_map(|location| match location {
+                Location::Register(register) => Some(*register),
+                _ => None,
+            })
+            .collect();
+        let saved: Vec<_> = saved.into_iter().collect();
+
+        // Keep the stack 16 byte aligned at calls, after `%rbp` is pushed
+        let mut frame = slots * 8;
+        if (saved.len() * 8 + frame) % 16 != 0 {
+            frame += 8;
+        }
+
+        Self {
+            function,
+            passing,
+            locations,
+            saved,
+            frame,
+        }
+    }
+
+    // Writing to a `String` can't fail, so results are ignored throughout.
+    fn function(&self, output: &mut String) {
+        let name = &self.function.name;
+        if self.function.exported {
+            let _ = writeln!(output, "    .globl {name}");
+        }
+        let _ = writeln!(output, "{name}:");
+
+        let _ = writeln!(output, "    pushq %rbp");
+        let _ = writeln!(output, "    movq %rsp, %rbp");
+        for register in &self.saved {
+            let _ = writeln!(output, "    pushq {}", register.quad);
+        }
+        if self.frame > 0 {
+            let _ = writeln!(output, "    subq ${}, %rsp", self.frame);
+        }
+
+        for ((name, ty), passing) in self.function.parameters.iter().zip(&self.passing) {
+            let value = Value::Parameter(name.clone());
+            let destination = match self.location(&value) {
+                Some(location) => self.operand(location, *ty),
+                None => continue,
+            };
+
+            let _ = match passing {
+                Passing::Integer(register) => writeln!(
+                    output,
+                    "    mov{} {}, {destination}",
+                    suffix(*ty),
+                    register.name(*ty)
+                ),
+                Passing::Float(index) => writeln!(output, "    movsd %xmm{index}, {destination}"),
+                Passing::Stack(_) => Ok(()),
+            };
+        }
+
+        for instruction in &self.function.body {
+            self.instruction(output, instruction);
+        }
+    }
+
+    fn instruction(&self, output: &mut String, instruction: &Instruction) {
+        match instruction {
+            Instruction::Ret(value) => {
+                match self.function.return_type {
+                    Type::D => self.load_float(output, value, 0),
+                    ty => self.load(output, value, ty, RAX),
+                }
+
+                let _ = writeln!(output, "    leaq -{}(%rbp), %rsp", self.saved.len() * 8);
+                for register in self.saved.iter().rev() {
+                    let _ = writeln!(output, "    popq {}", register.quad);
+                }
+                let _ = writeln!(output, "    popq %rbp");
+                let _ = writeln!(output, "    ret");
+            }
+            Instruction::Call {
+                result,
+                name,
+                arguments,
+            } => self.call(output, *result, name, arguments),
+            Instruction::Binary {
+                result,
+                ty: Type::D,
+                operator,
+                left,
+                right,
+            } => {
+                self.load_float(output, left, 0);
+                self.load_float(output, right, 1);
+
+                let instruction = match operator {
+                    BinaryOperator::Add => "addsd",
+                    BinaryOperator::Subtract => "subsd",
+                    BinaryOperator::Multiply => "mulsd",
+                    BinaryOperator::Divide => "divsd",
+                };
+                let result = self.value(&Value::Temporary(*result), Type::D);
+                let _ = writeln!(output, "    {instruction} %xmm1, %xmm0");
+                let _ = writeln!(output, "    movsd %xmm0, {result}");
+            }
+            Instruction::Binary {
+                result,
+                ty,
+                operator,
+                left,
+                right,
+            } => {
+                self.load(output, left, *ty, RAX);
+                self.load(output, right, *ty, RCX);
+
+                let s = suffix(*ty);
+                let (rax, rcx) = (RAX.name(*ty), RCX.name(*ty));
+                let _ = match operator {
+                    BinaryOperator::Add => writeln!(output, "    add{s} {rcx}, {rax}"),
+                    BinaryOperator::Subtract => writeln!(output, "    sub{s} {rcx}, {rax}"),
+                    BinaryOperator::Multiply => writeln!(output, "    imul{s} {rcx}, {rax}"),
+                    BinaryOperator::Divide => {
+                        // Sign extend into `%rdx`, which is never allocated
+                        let extend = if *ty == Type::W { "cltd" } else { "cqto" };
+                        writeln!(output, "    {extend}\n    idiv{s} {rcx}")
+                    }
+                };
+
+                let result = self.value(&Value::Temporary(*result), *ty);
+                let _ = writeln!(output, "    mov{s} {rax}, {result}");
+            }
+        }
+    }
+
+    fn call(
+        &self,
+        output: &mut String,
+        result: Option<(usize, Type)>,
+        name: &str,
+        arguments: &[(Value, Type)],
+    ) {
+        let passing = classify(arguments.iter().map(|(_, ty)| *ty));
+        let stack: Vec<_> = arguments
+            .iter()
+            .zip(&passing)
+            .filter(|(_, passing)| matches!(passing, Passing::Stack(_)))
+            .map(|(argument, _)| argument)
+            .collect();
+
+        let padding = stack.len() % 2 * 8;
+        if padding > 0 {
+            let _ = writeln!(output, "    subq ${padding}, %rsp");
+        }
+        for (value, ty) in stack.iter().rev() {
+            self.push(output, value, *ty);
+        }
+
+        // Allocated values are never in argument registers, so these moves
+        // can't clobber each other
+        for ((value, ty), passing) in arguments.iter().zip(&passing) {
+            match passing {
+                Passing::Integer(register) => self.load(output, value, *ty, *register),
+                Passing::Float(index) => self.load_float(output, value, *index),
+                Passing::Stack(_) => {}
+            }
+        }
+
+        let _ = writeln!(output, "    call {name}");
+        if !stack.is_empty() {
+            let _ = writeln!(output, "    addq ${}, %rsp", stack.len() * 8 + padding);
+        }
+
+        let _ = match result {
+            Some((id, Type::D)) => {
+                let result = self.value(&Value::Temporary(id), Type::D);
+                writeln!(output, "    movsd %xmm0, {result}")
+            }
+            Some((id, ty)) => {
+                let result = self.value(&Value::Temporary(id), ty);
+                writeln!(output, "    mov{} {}, {result}", suffix(ty), RAX.name(ty))
+            }
+            None => Ok(()),
+        };
+    }
+
+    /// Moves an integer `value` into `register`.
+    fn load(&self, output: &mut String, value: &Value, ty: Type, register: Register) {
+        let _ = match value {
+            // `movq` only takes a sign extended 32 bit immediate
+            Value::Integer(number) if i32::try_from(*number).is_err() => {
+                writeln!(output, "    movabsq ${number}, {}", register.quad)
+            }
+            _ => writeln!(
+                output,
+                "    mov{} {}, {}",
+                suffix(ty),
+                self.value(value, ty),
+                register.name(ty)
+            ),
+        };
+    }
+
+    /// Moves a float `value` into `%xmm{index}`.
+    fn load_float(&self, output: &mut String, value: &Value, index: usize) {
+        let _ = match value {
+            Value::Float(float) => writeln!(
+                output,
+                "    movabsq ${}, %rax\n    movq %rax, %xmm{index}",
+                float.to_bits() as i64
+            ),
+            _ => writeln!(
+                output,
+                "    movsd {}, %xmm{index}",
+                self.value(value, Type::D)
+            ),
+        };
+    }
+
+    /// Pushes a stack argument. Every argument takes 8 bytes, though only as
+    /// many as its type needs are read.
+    fn push(&self, output: &mut String, value: &Value, ty: Type) {
+        let _ = match (value, self.location(value)) {
+            (Value::Integer(number), _) if i32::try_from(*number).is_ok() => {
+                writeln!(output, "    pushq ${number}")
+            }
+            (Value::Integer(number), _) => {
+                writeln!(output, "    movabsq ${number}, %rax\n    pushq %rax")
+            }
+            (Value::Float(float), _) => writeln!(
+                output,
+                "    movabsq ${}, %rax\n    pushq %rax",
+                float.to_bits() as i64
+            ),
+            (_, Some(Location::Register(register))) => {
+                writeln!(output, "    pushq {}", register.quad)
+            }
+            (_, Some(location)) => writeln!(output, "    pushq {}", self.operand(location, ty)),
+            (_, None) => unreachable!("Only constants and unused values have no location"),
+        };
+    }
+
+    /// Where `value` lives, if it is used at all.
+    fn location(&self, value: &Value) -> Option<Location> {
+        self.locations.get(&Virtual::of(value)?).copied()
+    }
+
+    fn operand(&self, location: Location, ty: Type) -> String {
+        match location {
+            Location::Register(register) => register.name(ty).to_owned(),
+            Location::Slot(slot) => format!("-{}(%rbp)", (self.saved.len() + slot + 1) * 8),
+            Location::Incoming(index) => format!("{}(%rbp)", 16 + index * 8),
+        }
+    }
+
+    /// An operand for `value`, which is an immediate for integers.
+    fn value(&self, value: &Value, ty: Type) -> String {
+        match (value, self.location(value)) {
+            (Value::Integer(number), _) => format!("${number}"),
+            (_, Some(location)) => self.operand(location, ty),
+            _ => unreachable!("Only constants and unused values have no location"),
+        }
+    }
+}
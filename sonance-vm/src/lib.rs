@@ -0,0 +1,132 @@
+mod memory;
+
+pub use memory::Memory;
+
+use std::{
+    fmt::{self, Display, Formatter},
+    io::{self, Write},
+};
+
+#[derive(Debug, Eq, PartialEq, num_enum::FromPrimitive)]
+#[repr(u8)]
+pub enum Opcode {
+    #[default]
+    Null = 0x00,
+    Exit = 0x01,  //
+    Print = 0x02, // a
+    Move = 0x03,  // src, dst
+    Const = 0x04, // data, dst
+    Add = 0x10,   // a, b, dst
+    Sub = 0x11,   // a, b, dst
+    Mul = 0x12,   // a, b, dst
+    Div = 0x13,   // a, b, dst
+    And = 0x14,   // a, b, dst
+    Or = 0x15,    // a, b, dst
+    Not = 0x16,   // a, dst
+}
+
+pub const PROGRAM_COUNTER: u8 = 0x01;
+
+/// Why the VM stopped before the program exited.
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// A `Div` by zero, with the address of the instruction.
+    DivideByZero {
+        address: u8,
+    },
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{}", error),
+            Self::DivideByZero { address } => write!(f, "division by zero at {:#04x}", address),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+pub struct VM {
+    memory: Memory,
+}
+
+impl VM {
+    pub fn new(memory: Memory) -> Self {
+        Self { memory }
+    }
+
+    /// Steps until the program exits, writing what it prints to `out`.
+    pub fn run(&mut self, out: &mut impl Write) -> Result<(), Error> {
+        while self.step(out)? {}
+        Ok(())
+    }
+
+    fn next(&mut self) -> u8 {
+        let value = self.memory[self.memory[PROGRAM_COUNTER]];
+        self.memory[PROGRAM_COUNTER] += 1;
+        value
+    }
+
+    /// Runs one instruction, returning whether the program goes on.
+    /// Arithmetic wraps around, since every value is a byte.
+    pub fn step(&mut self, out: &mut impl Write) -> Result<bool, Error> {
+        let address = self.memory[PROGRAM_COUNTER];
+        let opcode = self.next();
+
+        match opcode.into() {
+            Opcode::Null => panic!("Unknown opcode"),
+            Opcode::Exit => return Ok(false),
+            Opcode::Print => {
+                let a = self.next();
+                writeln!(out, "{}", self.memory[a])?;
+            }
+            Opcode::Move => self.unary_op(|a| a),
+            Opcode::Const => {
+                let data = self.next();
+                let dst = self.next();
+
+                self.memory[dst] = data;
+            }
+            Opcode::Add => self.binary_op(u8::wrapping_add),
+            Opcode::Sub => self.binary_op(u8::wrapping_sub),
+            Opcode::Mul => self.binary_op(u8::wrapping_mul),
+            Opcode::Div => {
+                let a = self.next();
+                let b = self.next();
+                let dst = self.next();
+
+                self.memory[dst] = self.memory[a]
+                    .checked_div(self.memory[b])
+                    .ok_or(Error::DivideByZero { address })?;
+            }
+            Opcode::And => self.binary_op(|a, b| a & b),
+            Opcode::Or => self.binary_op(|a, b| a | b),
+            Opcode::Not => self.unary_op(|a| !a),
+        }
+
+        Ok(true)
+    }
+
+    fn unary_op(&mut self, op: impl Fn(u8) -> u8) {
+        let a = self.next();
+        let dst = self.next();
+
+        self.memory[dst] = op(self.memory[a]);
+    }
+
+    fn binary_op(&mut self, op: impl Fn(u8, u8) -> u8) {
+        let a = self.next();
+        let b = self.next();
+        let dst = self.next();
+
+        self.memory[dst] = op(self.memory[a], self.memory[b]);
+    }
+}
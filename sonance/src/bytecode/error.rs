@@ -0,0 +1,71 @@
+use crate::error::{Citation, Span};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuildError<'src> {
+    /// None of the files have a `main` function.
+    NoMain,
+    /// Something the VM has no way to run yet.
+    Unsupported { what: &'static str, span: Span },
+    /// An integer that doesn't fit in a byte, which is all the VM has.
+    OutOfRange { value: i64, span: Span },
+    /// A name that isn't a local of `main`.
+    Unbound { name: &'src str, span: Span },
+    /// A call of a builtin with the wrong arguments.
+    Arguments {
+        name: &'src str,
+        message: String,
+        span: Span,
+    },
+    /// An assignment to a name that wasn't bound with `let mut`.
+    Immutable {
+        name: &'src str,
+        binding: Span,
+        span: Span,
+    },
+    /// The code and the values it needs don't fit in memory together.
+    TooLarge { span: Span },
+}
+
+impl<'src> From<BuildError<'src>> for Citation {
+    fn from(error: BuildError<'src>) -> Self {
+        match error {
+            BuildError::NoMain => Citation::error("No `main` function to build".to_owned())
+                .help("add `func main() { ... };` to the file".to_owned()),
+            BuildError::Unsupported { what, span } => {
+                Citation::error(format!("Can't build {} yet", what))
+                    .span(span, None)
+                    .note(
+                        "only integers, `let`, arithmetic and printing in `main` can be built"
+                            .to_owned(),
+                    )
+            }
+            BuildError::OutOfRange { value, span } => {
+                Citation::error(format!("`{}` doesn't fit in a byte", value)).span(
+                    span,
+                    Some("the VM only has values from 0 to 255".to_owned()),
+                )
+            }
+            BuildError::Unbound { name, span } => {
+                Citation::error(format!("Unbound name `{}`", name))
+                    .span(span, Some("not a local".to_owned()))
+            }
+            BuildError::Arguments {
+                name,
+                message,
+                span,
+            } => {
+                Citation::error(format!("Wrong arguments for `{}`", name)).span(span, Some(message))
+            }
+            BuildError::Immutable {
+                name,
+                binding,
+                span,
+            } => Citation::error(format!("Cannot assign twice to `{}`", name))
+                .span(span, Some("assigned here".to_owned()))
+                .secondary(binding, Some("bound here".to_owned()))
+                .help(format!("make it mutable with `let mut {}`", name)),
+            BuildError::TooLarge { span } => Citation::error("Program too large".to_owned())
+                .span(span, Some("doesn't fit in 256 bytes of memory".to_owned())),
+        }
+    }
+}
@@ -0,0 +1,315 @@
+//! Lowering programs to bytecode for `sonance-vm`.
+//!
+//! The VM has 256 bytes of memory, holding the program and every value it
+//! works with, and every value is a byte. The second byte is the program
+//! counter, so the code starts right after it. Locals and temporaries each
+//! get a byte counting down from the top of memory, and temporaries are
+//! freed once the statement they're made in is done.
+//!
+//! Only `main` is built, and only what the VM has opcodes for: integers,
+//! `let` bindings and assignments, arithmetic builtins and printing. Both
+//! `print` and `print_line` end the line, since the VM's `Print` does.
+//!
+//! Arithmetic differs from the interpreter's, which uses 64-bit integers and
+//! reports overflow. On the VM it wraps around within a byte, so
+//! `3.subtract(5)` prints `254` rather than `-2`, and dividing by zero stops
+//! the VM with an error.
+
+mod error;
+#[cfg(test)]
+mod test;
+
+pub use error::BuildError;
+
+use crate::{error::Span, parser::ast::*, resolve::Unit};
+use sonance_vm::{Opcode, PROGRAM_COUNTER};
+use std::convert::TryFrom;
+
+/// Where the code is loaded, right after the program counter.
+const CODE_START: usize = PROGRAM_COUNTER as usize + 1;
+
+/// Lowers the `main` function of a program made of `units` to a memory
+/// image the VM can load.
+pub fn build<'src>(units: &[Unit<'src>]) -> Result<Vec<u8>, BuildError<'src>> {
+    let main = units
+        .iter()
+        .flat_map(|unit| &unit.file.items)
+        .find_map(|item| match item {
+            Item::Function(function) if function.name.name == "main" => Some(function),
+            _ => None,
+        })
+        .ok_or(BuildError::NoMain)?;
+
+    let mut builder = Builder {
+        code: Vec::new(),
+        locals: Vec::new(),
+        next: u8::MAX as usize,
+        lowest: u8::MAX as usize + 1,
+        span: main.span,
+    };
+    builder.block(&main.body)?;
+    builder.emit(&[Opcode::Exit as u8]);
+
+    // The program counter starts at the code
+    let mut image = vec![0; CODE_START];
+    image[PROGRAM_COUNTER as usize] = CODE_START as u8;
+    image.extend(&builder.code);
+
+    if image.len() > builder.lowest {
+        return Err(BuildError::TooLarge { span: main.span });
+    }
+    Ok(image)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Local<'src> {
+    name: Ident<'src>,
+    mutable: bool,
+    register: u8,
+}
+
+struct Builder<'src> {
+    code: Vec<u8>,
+    locals: Vec<Local<'src>>,
+    /// The next free register. Registers are taken from the top of memory
+    /// down.
+    next: usize,
+    /// The lowest register taken so far, which the code has to end before.
+    lowest: usize,
+    /// The function being built, where running out of memory is reported.
+    span: Span,
+}
+
+impl<'src> Builder<'src> {
+    fn emit(&mut self, bytes: &[u8]) {
+        self.code.extend_from_slice(bytes);
+    }
+
+    fn register(&mut self) -> Result<u8, BuildError<'src>> {
+        if self.next < CODE_START {
+            return Err(BuildError::TooLarge { span: self.span });
+        }
+
+        let register = self.next;
+        self.next -= 1;
+        self.lowest = self.lowest.min(register);
+        Ok(register as u8)
+    }
+
+    fn block(&mut self, block: &Block<'src>) -> Result<(), BuildError<'src>> {
+        for statement in &block.statements {
+            self.statement(statement)?;
+        }
+        if let Some(tail) = &block.tail {
+            let temporaries = self.next;
+            self.effect(tail)?;
+            self.next = temporaries;
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, statement: &Statement<'src>) -> Result<(), BuildError<'src>> {
+        match statement {
+            Statement::Let(statement) => {
+                let (mutable, name) = match statement.pattern {
+                    Pattern::Binding { mutable, name } => (mutable, name),
+                    Pattern::Variant { span, .. } => {
+                        return Err(BuildError::Unsupported {
+                            what: "patterns",
+                            span,
+                        })
+                    }
+                };
+
+                let register = self.register()?;
+                let temporaries = self.next;
+                self.expression(&statement.value, register)?;
+                self.next = temporaries;
+
+                self.locals.push(Local {
+                    name,
+                    mutable,
+                    register,
+                });
+            }
+            Statement::Assign(assignment) => {
+                let local = self.local(assignment.name)?;
+                if !local.mutable {
+                    return Err(BuildError::Immutable {
+                        name: assignment.name.name,
+                        binding: local.name.span,
+                        span: assignment.name.span,
+                    });
+                }
+
+                let temporaries = self.next;
+                self.expression(&assignment.value, local.register)?;
+                self.next = temporaries;
+            }
+            Statement::Expression(expression) => {
+                let temporaries = self.next;
+                self.effect(expression)?;
+                self.next = temporaries;
+            }
+        }
+        Ok(())
+    }
+
+    fn local(&self, name: Ident<'src>) -> Result<Local<'src>, BuildError<'src>> {
+        self.locals
+            .iter()
+            .rev()
+            .find(|local| local.name.name == name.name)
+            .copied()
+            .ok_or(BuildError::Unbound {
+                name: name.name,
+                span: name.span,
+            })
+    }
+
+    /// Lowers an expression whose value isn't used.
+    fn effect(&mut self, expression: &Expression<'src>) -> Result<(), BuildError<'src>> {
+        match call(expression)? {
+            Some((name, arguments)) => self.call(name, arguments, None, expression.span),
+            None => self.operand(expression).map(|_| ()),
+        }
+    }
+
+    /// The register holding an expression's value, which is a new temporary
+    /// unless the expression is a local.
+    fn operand(&mut self, expression: &Expression<'src>) -> Result<u8, BuildError<'src>> {
+        if let ExpressionKind::Name(name) = expression.kind {
+            return Ok(self.local(name)?.register);
+        }
+
+        let register = self.register()?;
+        self.expression(expression, register)?;
+        Ok(register)
+    }
+
+    /// Lowers an expression, leaving its value in `dst`.
+    fn expression(
+        &mut self,
+        expression: &Expression<'src>,
+        dst: u8,
+    ) -> Result<(), BuildError<'src>> {
+        if let Some((name, arguments)) = call(expression)? {
+            return self.call(name, arguments, Some(dst), expression.span);
+        }
+
+        let what = match &expression.kind {
+            ExpressionKind::Literal(Literal::Integer(value)) => {
+                let byte = u8::try_from(*value).map_err(|_| BuildError::OutOfRange {
+                    value: *value,
+                    span: expression.span,
+                })?;
+                self.emit(&[Opcode::Const as u8, byte, dst]);
+                return Ok(());
+            }
+            ExpressionKind::Name(name) => {
+                let src = self.local(*name)?.register;
+                self.emit(&[Opcode::Move as u8, src, dst]);
+                return Ok(());
+            }
+            ExpressionKind::Literal(Literal::Float(_)) => "floats",
+            ExpressionKind::Literal(Literal::Character(_)) => "characters",
+            ExpressionKind::Literal(Literal::String(_)) => "strings",
+            ExpressionKind::Match { .. } => "`match`",
+            ExpressionKind::Loop(_) => "loops",
+            ExpressionKind::Block(_) => "blocks",
+            ExpressionKind::Return { .. } => "`return`",
+            ExpressionKind::Call(_)
+            | ExpressionKind::Property { .. }
+            | ExpressionKind::Chain { .. } => unreachable!("calls are lowered above"),
+        };
+        Err(BuildError::Unsupported {
+            what,
+            span: expression.span,
+        })
+    }
+
+    /// Lowers a call of a builtin, leaving its value in `dst` if it's used.
+    fn call(
+        &mut self,
+        name: Ident<'src>,
+        arguments: Vec<&Expression<'src>>,
+        dst: Option<u8>,
+        span: Span,
+    ) -> Result<(), BuildError<'src>> {
+        let (opcode, arity) = match name.name {
+            "add" => (Opcode::Add, 2),
+            "subtract" => (Opcode::Sub, 2),
+            "multiply" => (Opcode::Mul, 2),
+            "divide" => (Opcode::Div, 2),
+            "print" | "print_line" => (Opcode::Print, 1),
+            _ => {
+                return Err(BuildError::Unsupported {
+                    what: "calls of functions",
+                    span: name.span,
+                })
+            }
+        };
+        if arguments.len() != arity {
+            return Err(BuildError::Arguments {
+                name: name.name,
+                message: format!("takes {} arguments, found {}", arity, arguments.len()),
+                span,
+            });
+        }
+
+        let mut operands = Vec::new();
+        for argument in arguments {
+            operands.push(self.operand(argument)?);
+        }
+
+        if opcode == Opcode::Print {
+            if dst.is_some() {
+                return Err(BuildError::Unsupported {
+                    what: "using what printing returns",
+                    span,
+                });
+            }
+            self.emit(&[opcode as u8, operands[0]]);
+            return Ok(());
+        }
+
+        let dst = match dst {
+            Some(dst) => dst,
+            None => self.register()?,
+        };
+        self.emit(&[opcode as u8, operands[0], operands[1], dst]);
+        Ok(())
+    }
+}
+
+/// The function an expression calls and its arguments, with the receiver
+/// of a chain first. `x.f` is a chain call without arguments.
+fn call<'e, 'src>(
+    expression: &'e Expression<'src>,
+) -> Result<Option<(Ident<'src>, Vec<&'e Expression<'src>>)>, BuildError<'src>> {
+    let (receiver, call) = match &expression.kind {
+        ExpressionKind::Call(call) => (None, call),
+        ExpressionKind::Chain { receiver, call } => (Some(&**receiver), call),
+        ExpressionKind::Property { receiver, name } => return Ok(Some((*name, vec![receiver]))),
+        _ => return Ok(None),
+    };
+
+    if let Some(label) = call.arguments.iter().find_map(|it| it.label) {
+        return Err(BuildError::Unsupported {
+            what: "labelled arguments",
+            span: label.span,
+        });
+    }
+    if let Some(argument) = call.block_arguments.first() {
+        return Err(BuildError::Unsupported {
+            what: "block arguments",
+            span: argument.block.span,
+        });
+    }
+
+    let arguments = receiver
+        .into_iter()
+        .chain(call.arguments.iter().map(|it| &it.value))
+        .collect();
+    Ok(Some((call.name, arguments)))
+}
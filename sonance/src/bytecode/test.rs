@@ -0,0 +1,134 @@
+use super::*;
+use crate::fixture::{map, parse, render};
+use sonance_vm::{Memory, VM};
+
+/// Builds `main` and runs it on the VM, returning what it printed or the
+/// error from building or running it.
+fn run_main(main: &str) -> Result<String, String> {
+    let map = map(&[("main.son", main)]);
+    let units = parse(&map);
+
+    let image = build(&units).map_err(|error| render(&map, [error]))?;
+    let mut out = Vec::new();
+    VM::new(Memory::from_slice(&image))
+        .run(&mut out)
+        .map_err(|error| error.to_string())?;
+    Ok(String::from_utf8(out).unwrap())
+}
+
+#[test]
+fn arithmetic() {
+    let output = run_main(
+        "func main() {
+    let x = 2;
+    let mut y = x.add(3).multiply(4);
+    print_line(y);
+    y = y.subtract(x);
+    print(divide(y, 2));
+    let x = x.multiply(x);
+    print_line(x.add(y.subtract(x)));
+};",
+    );
+
+    assert_eq!(output.as_deref(), Ok("20\n9\n18\n"));
+}
+
+#[test]
+fn wrapping() {
+    let output = run_main(
+        "func main() {
+    print_line(3.subtract(5));
+    print_line(200.add(100));
+    print_line(16.multiply(16));
+};",
+    );
+
+    assert_eq!(output.as_deref(), Ok("254\n44\n0\n"));
+}
+
+#[test]
+fn divide_by_zero() {
+    let output = run_main("func main() { print_line(divide(1, 0)) };");
+
+    assert_eq!(output, Err("division by zero at 0x08".to_owned()));
+}
+
+#[test]
+fn image() {
+    let map = map(&[("main.son", "func main() { print_line(7) };")]);
+    let units = parse(&map);
+
+    assert_eq!(
+        build(&units),
+        Ok(vec![
+            0x00,
+            0x02,
+            Opcode::Const as u8,
+            7,
+            0xFF,
+            Opcode::Print as u8,
+            0xFF,
+            Opcode::Exit as u8,
+        ])
+    );
+}
+
+#[test]
+fn errors() {
+    assert_eq!(
+        run_main("func main() { print_line(256) };"),
+        Err("error: `256` doesn't fit in a byte
+ --> main.son:1:26
+  |
+1 | func main() { print_line(256) };
+  |                          ^^^ the VM only has values from 0 to 255
+"
+        .to_owned())
+    );
+
+    assert_eq!(
+        run_main("func main() { print_line(\"hi\") };"),
+        Err("error: Can't build strings yet
+ --> main.son:1:26
+  |
+1 | func main() { print_line(\"hi\") };
+  |                          ^^^^
+  |
+  = note: only integers, `let`, arithmetic and printing in `main` can be built
+"
+        .to_owned())
+    );
+
+    assert_eq!(
+        run_main("func main() {\n    let x = 1;\n    x = 2;\n};"),
+        Err("error: Cannot assign twice to `x`
+ --> main.son:3:5
+  |
+2 |     let x = 1;
+  |         - bound here
+3 |     x = 2;
+  |     ^ assigned here
+  |
+  = help: make it mutable with `let mut x`
+"
+        .to_owned())
+    );
+}
+
+#[test]
+fn too_large() {
+    // Every local takes a register, and each `let` takes 3 bytes of code
+    let lets: String = (0..64)
+        .map(|index| format!("let x{} = 1;", index))
+        .collect();
+    let output = run_main(&format!("func main() {{ {} }};", lets));
+
+    assert!(
+        output
+            .as_ref()
+            .unwrap_err()
+            .starts_with("error: Program too large"),
+        "{:?}",
+        output
+    );
+}
@@ -0,0 +1,137 @@
+//! Citations as JSON, shaped like the Language Server Protocol's `Diagnostic`
+//! so editors can consume them directly.
+
+use super::*;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnostic {
+    pub range: Range,
+    /// 1 is an error, 2 a warning and 3 information.
+    pub severity: u8,
+    pub source: &'static str,
+    /// The citation's message, followed by its notes and help.
+    pub message: String,
+    /// Every labelled span, including the primary one.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub related_information: Vec<RelatedInformation>,
+}
+
+/// A diagnostic and the document it's in, which is what each line of JSON
+/// output holds, like the Language Server Protocol's
+/// `PublishDiagnosticsParams` with one diagnostic.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Report<'a> {
+    pub uri: &'a str,
+    pub diagnostic: Diagnostic,
+}
+
+/// Zero indexed, with columns counted in UTF-16 code units.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Position {
+    pub line: usize,
+    pub character: usize,
+}
+
+/// The end is exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RelatedInformation {
+    pub location: Location,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Location {
+    pub uri: String,
+    pub range: Range,
+}
+
+impl Reporter<'_> {
+    /// Writes each citation as a JSON object on its own line, with the uri
+    /// of the document.
+    pub fn report_json(
+        &self,
+        out: &mut impl Write,
+        uri: &str,
+        citations: &[Citation],
+    ) -> io::Result<()> {
+        for citation in citations {
+            let report = Report {
+                uri,
+                diagnostic: self.diagnostic(uri, citation),
+            };
+            serde_json::to_writer(&mut *out, &report)?;
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+
+    /// Converts a citation in a document at `uri`.
+    pub fn diagnostic(&self, uri: &str, citation: &Citation) -> Diagnostic {
+        let primary = citation
+            .spans
+            .iter()
+            .find(|label| label.primary)
+            .or_else(|| citation.spans.first());
+
+        let mut message = citation.message.clone();
+        for footer in &citation.footers {
+            let kind = match footer.kind {
+                FooterKind::Note => "note",
+                FooterKind::Help => "help",
+            };
+            message += &format!("\n{}: {}", kind, footer.message);
+        }
+
+        Diagnostic {
+            range: primary
+                .map(|label| self.range(label.span))
+                .unwrap_or_default(),
+            severity: match citation.level {
+                Level::Error => 1,
+                Level::Warning => 2,
+                Level::Info => 3,
+            },
+            source: "sonance",
+            message,
+            related_information: citation
+                .spans
+                .iter()
+                .filter_map(|label| {
+                    Some(RelatedInformation {
+                        location: Location {
+                            uri: uri.to_owned(),
+                            range: self.range(label.span),
+                        },
+                        message: label.message.clone()?,
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    /// The byte index of a position, clamped to the end of its line.
+    pub fn offset(&self, position: Position) -> usize {
+        self.utf16_offset(position.line, position.character)
+    }
+
+    pub fn range(&self, span: Span) -> Range {
+        let position = |index| {
+            let (line, character) = self.utf16_position(index);
+            Position { line, character }
+        };
+
+        Range {
+            start: position(span.start),
+            end: position(self.exclusive_end(span)),
+        }
+    }
+}
@@ -1,8 +1,13 @@
+pub mod json;
 mod reporter;
+mod source_map;
+#[cfg(test)]
+mod test;
 mod util;
 
 use colored::Colorize;
 pub use reporter::Reporter;
+pub use source_map::{SourceFile, SourceMap};
 use std::fmt::{Display, Formatter, Result as FmtResult};
 
 /// A Span in some source code.
@@ -16,6 +21,11 @@ impl Span {
     pub fn new(start: usize, end: usize) -> Self {
         Self { start, end }
     }
+
+    /// A span covering both this span and `end`.
+    pub fn to(self, end: Span) -> Self {
+        Self::new(self.start.min(end.start), self.end.max(end.end))
+    }
 }
 
 impl Display for Span {
@@ -24,7 +34,7 @@ impl Display for Span {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy)]
 enum Level {
     Error,
     Warning,
@@ -35,6 +45,21 @@ enum Level {
 struct SpanLabel {
     span: Span,
     message: Option<String>,
+    /// Primary spans point at the problem, secondary spans give context.
+    primary: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum FooterKind {
+    Note,
+    Help,
+}
+
+/// A line shown after all of a citation's spans.
+#[derive(Debug, Clone)]
+struct Footer {
+    kind: FooterKind,
+    message: String,
 }
 
 #[derive(Debug, Clone)]
@@ -42,40 +67,70 @@ pub struct Citation {
     message: String,
     level: Level,
     spans: Vec<SpanLabel>,
+    footers: Vec<Footer>,
 }
 
 impl Citation {
     pub fn error(message: String) -> Self {
-        Self {
-            message,
-            level: Level::Error,
-            spans: Vec::new(),
-        }
+        Self::new(message, Level::Error)
     }
 
     pub fn warning(message: String) -> Self {
-        Self {
-            message,
-            level: Level::Warning,
-            spans: Vec::new(),
-        }
+        Self::new(message, Level::Warning)
     }
 
     pub fn info(message: String) -> Self {
+        Self::new(message, Level::Info)
+    }
+
+    fn new(message: String, level: Level) -> Self {
         Self {
             message,
-            level: Level::Info,
+            level,
             spans: Vec::new(),
+            footers: Vec::new(),
         }
     }
 
     pub fn span(mut self, span: Span, message: Option<String>) -> Self {
-        self.spans.push(SpanLabel { span, message });
+        self.push_span(span, message);
+        self
+    }
+
+    /// Adds a span that gives context to the primary ones.
+    pub fn secondary(mut self, span: Span, message: Option<String>) -> Self {
+        self.spans.push(SpanLabel {
+            span,
+            message,
+            primary: false,
+        });
+        self
+    }
+
+    /// Extra information about the problem.
+    pub fn note(mut self, message: String) -> Self {
+        self.footers.push(Footer {
+            kind: FooterKind::Note,
+            message,
+        });
+        self
+    }
+
+    /// A suggestion for fixing the problem.
+    pub fn help(mut self, message: String) -> Self {
+        self.footers.push(Footer {
+            kind: FooterKind::Help,
+            message,
+        });
         self
     }
 
     pub fn push_span(&mut self, span: Span, message: Option<String>) -> &mut Self {
-        self.spans.push(SpanLabel { span, message });
+        self.spans.push(SpanLabel {
+            span,
+            message,
+            primary: true,
+        });
         self
     }
 }
@@ -1,209 +1,492 @@
 use super::{util::LinesWithEndings, *};
+use colored::Color;
+use std::{
+    collections::BTreeSet,
+    io::{self, Write},
+};
+use unicode_width::UnicodeWidthChar;
 
-/// A Span that has more line information resolved. Used in error reporting.
+/// Tabs are expanded to this many columns, so carets line up under them.
+const TAB_WIDTH: usize = 4;
+
+/// A line and display column, both zero indexed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Position {
+    line: usize,
+    column: usize,
+}
+
+/// A SpanLabel with its span resolved to positions. Used in error reporting.
 #[derive(Debug, Clone)]
-struct ResolvedSpan {
-    source_start: usize,
-    source_end: usize,
-    line_number_start: usize,
-    line_number_end: usize,
-    local_start: usize,
-    local_end: usize,
+struct ResolvedLabel<'a> {
+    start: Position,
+    /// Exclusive, so a single character spans one column.
+    end: Position,
+    message: Option<&'a str>,
+    primary: bool,
+}
+
+impl ResolvedLabel<'_> {
+    fn is_multi_line(&self) -> bool {
+        self.start.line != self.end.line
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Reporter<'src> {
     source: &'src str,
+    /// Shown before the line and column, when the source came from a file.
+    name: Option<&'src str>,
+    /// The byte index each line starts at.
+    line_starts: Vec<usize>,
+    color: bool,
 }
 
 impl<'src> Reporter<'src> {
     pub fn new(source: &'src str) -> Self {
-        Reporter { source }
+        let line_starts = LinesWithEndings::from(source)
+            .scan(0, |start, line| {
+                let line_start = *start;
+                *start += line.len();
+                Some(line_start)
+            })
+            .collect();
+
+        Reporter {
+            source,
+            name: None,
+            line_starts,
+            color: true,
+        }
     }
 
-    pub fn report(&self, citations: &[Citation]) {
-        for citation in citations {
-            self.citation(citation);
-            for span in &citation.spans {
-                self.span(span);
-            }
+    /// Names the file the source came from.
+    pub fn name(mut self, name: &'src str) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// Whether to style the output with colours, on by default.
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn report(&self, out: &mut impl Write, citations: &[Citation]) -> io::Result<()> {
+        let rendered: Vec<_> = citations
+            .iter()
+            .map(|citation| self.render(citation))
+            .collect();
+        write!(out, "{}", rendered.join("\n"))
+    }
+
+    /// Renders a citation with all of its spans grouped into one snippet,
+    /// followed by its notes and help.
+    pub fn render(&self, citation: &Citation) -> String {
+        let labels: Vec<_> = citation
+            .spans
+            .iter()
+            .map(|label| self.resolve(label))
+            .collect();
+        let last_line = labels.iter().map(|label| label.end.line).max();
+
+        let snippet = Snippet {
+            reporter: self,
+            level: citation.level,
+            labels,
+            side_width: last_line.map_or(0, |line| (line + 1).to_string().len()),
+        };
+
+        let mut output = String::new();
+        snippet.render(&mut output, citation);
+        output
+    }
+
+    fn resolve<'a>(&self, label: &'a SpanLabel) -> ResolvedLabel<'a> {
+        let start = self.position(label.span.start);
+        let mut end = self.position(label.span.end);
+
+        // Spans are inclusive, so the end covers its last character too
+        let last = self.source[self.clamp(label.span.end)..].chars().next();
+        end.column += last.map_or(1, |it| char_width(it).max(1));
+
+        ResolvedLabel {
+            start,
+            end,
+            message: label.message.as_deref(),
+            primary: label.primary,
+        }
+    }
+
+    fn position(&self, index: usize) -> Position {
+        let (line, before) = self.line_prefix(index);
+        Position {
+            line,
+            column: text_width(before),
         }
     }
 
-    fn citation(&self, citation: &Citation) {
-        eprintln!(
-            "{} {}",
-            match citation.level {
-                Level::Error => "error:".red().bold(),
-                Level::Warning => "warning:".yellow().bold(),
-                Level::Info => "info:".blue().bold(),
-            },
-            citation.message,
-        );
+    /// The one indexed line and column of an index, as shown after `-->`.
+    pub(super) fn line_column(&self, index: usize) -> (usize, usize) {
+        let position = self.position(index);
+        (position.line + 1, position.column + 1)
     }
 
-    fn span(&self, label: &SpanLabel) {
-        let span = &self.resolve(&label.span);
-        if span.line_number_start == span.line_number_end {
-            self.single_line(label, span);
-        } else {
-            self.multi_line(label, span);
+    /// The line an index is on, and the text of that line before it.
+    fn line_prefix(&self, index: usize) -> (usize, &'src str) {
+        let index = self.clamp(index);
+        let line = self
+            .line_starts
+            .partition_point(|start| *start <= index)
+            .saturating_sub(1);
+        let line_start = self.line_starts.get(line).copied().unwrap_or(0);
+        (line, &self.source[line_start..index])
+    }
+
+    /// Moves an index past the end of the source, or inside a character, back
+    /// to the nearest character start.
+    fn clamp(&self, index: usize) -> usize {
+        let mut index = index.min(self.source.len());
+        while !self.source.is_char_boundary(index) {
+            index -= 1;
         }
+        index
     }
 
-    fn single_line(&self, label: &SpanLabel, span: &ResolvedSpan) {
-        let line = self.source.lines().nth(span.line_number_start - 1).unwrap();
+    /// The line and UTF-16 column of a byte index, as editors count them.
+    pub(super) fn utf16_position(&self, index: usize) -> (usize, usize) {
+        let (line, before) = self.line_prefix(index);
+        (line, before.encode_utf16().count())
+    }
 
-        // Needed to pad all numbers to same length
-        let side_width = span.line_number_end.to_string().len();
+    /// The inverse of `utf16_position`.
+    pub(super) fn utf16_offset(&self, line: usize, column: usize) -> usize {
+        let start = match self.line_starts.get(line) {
+            Some(start) => *start,
+            None => return self.source.len(),
+        };
 
-        // Start padding
-        self.blank_side(side_width);
-        self.new_line();
+        let mut units = 0;
+        for (index, c) in self.line(line).char_indices() {
+            if units >= column {
+                return start + index;
+            }
+            units += c.len_utf16();
+        }
+        start + self.line(line).len()
+    }
 
-        // Source line
-        self.line_number_side(span.line_number_start, side_width);
-        self.line(line);
-        self.new_line();
+    /// The index just after the character a span ends on.
+    pub(super) fn exclusive_end(&self, span: Span) -> usize {
+        let end = self.clamp(span.end);
+        end + self.source[end..].chars().next().map_or(0, char::len_utf8)
+    }
 
-        // Label line
-        self.blank_side(side_width);
-        self.repeat(" ", span.local_start + 1);
-        // Columns are zero indexed, must +1 for correct alignment
-        self.repeat("^".red().bold(), span.local_end - span.local_start + 1);
-        self.message(&label.message);
-        self.new_line();
+    /// The line's text without its line ending.
+    fn line(&self, line: usize) -> &'src str {
+        let start = self.line_starts.get(line).copied().unwrap_or(0);
+        let end = self
+            .line_starts
+            .get(line + 1)
+            .copied()
+            .unwrap_or(self.source.len());
 
-        // End padding
-        self.blank_side(side_width);
-        self.new_line();
+        self.source[start..end].trim_end_matches(&['\n', '\r'][..])
     }
+}
 
-    fn multi_line(&self, label: &SpanLabel, span: &ResolvedSpan) {
-        let lines = self
-            .source
-            .lines()
-            // Take all lines contained the in the span
-            .skip(span.line_number_start - 1)
-            .take(span.line_number_end - (span.line_number_start - 1))
-            // Pair them with their line numbers
-            .enumerate()
-            .map(|(i, line)| (i + span.line_number_start, line))
-            .peekable();
-
-        // Needed to pad all numbers to same length
-        let side_width = span.line_number_end.to_string().len();
-
-        for (line_number, line) in lines {
-            if line_number == span.line_number_start {
-                // Start padding
-                self.blank_side(side_width);
-                self.new_line();
-
-                // Source line
-                self.line_number_side(line_number, side_width);
-                self.error_bar_align();
-                self.line(line);
-                self.new_line();
-
-                // Start label line
-                self.blank_side(side_width);
-                self.error_bar_align();
-                self.repeat("_".bold().red(), span.local_start + 1);
-                eprint!("{}", "^".bold().red());
-                self.new_line();
-            } else if line_number == span.line_number_end {
-                // Source line
-                self.line_number_side(line_number, side_width);
-                self.error_bar();
-                self.line(line);
-                self.new_line();
-
-                // End label line
-                self.blank_side(side_width);
-                self.error_bar();
-                self.repeat("_".bold().red(), span.local_end + 1);
-                eprint!("{}", "^".bold().red());
-                self.message(&label.message);
-                self.new_line();
-
-                // End padding
-                self.blank_side(side_width);
-                self.new_line();
-            } else {
-                // Source line
-                self.line_number_side(line_number, side_width);
-                self.error_bar();
-                self.line(line);
-                self.new_line();
+/// Builds up the output for one citation.
+struct Snippet<'a> {
+    reporter: &'a Reporter<'a>,
+    level: Level,
+    labels: Vec<ResolvedLabel<'a>>,
+    /// Needed to pad all line numbers to the same length.
+    side_width: usize,
+}
+
+impl Snippet<'_> {
+    fn render(&self, out: &mut String, citation: &Citation) {
+        let (name, color) = match self.level {
+            Level::Error => ("error", Color::Red),
+            Level::Warning => ("warning", Color::Yellow),
+            Level::Info => ("info", Color::Blue),
+        };
+        out.push_str(&format!(
+            "{} {}\n",
+            self.paint(&format!("{}:", name), color),
+            citation.message
+        ));
+
+        // Every line a label touches is shown
+        let lines: BTreeSet<_> = self
+            .labels
+            .iter()
+            .flat_map(|label| label.start.line..=label.end.line)
+            .collect();
+
+        if !lines.is_empty() {
+            let location = self
+                .labels
+                .iter()
+                .find(|label| label.primary)
+                .unwrap_or(&self.labels[0])
+                .start;
+            let name = match self.reporter.name {
+                Some(name) => format!("{}:", name),
+                None => String::new(),
+            };
+            out.push_str(&format!(
+                "{}{} {}{}:{}\n",
+                " ".repeat(self.side_width),
+                self.paint("-->", Color::Cyan),
+                name,
+                location.line + 1,
+                location.column + 1
+            ));
+
+            self.blank_side(out);
+            let mut previous = None;
+            for line in lines {
+                if previous.is_some_and(|previous| line > previous + 1) {
+                    out.push_str(&format!("{}\n", self.paint("...", Color::Cyan)));
+                }
+                self.source_line(out, line);
+                previous = Some(line);
             }
         }
-    }
 
-    fn new_line(&self) {
-        eprintln!();
+        if !citation.footers.is_empty() && !self.labels.is_empty() {
+            self.blank_side(out);
+        }
+        for footer in &citation.footers {
+            let kind = match footer.kind {
+                FooterKind::Note => "note",
+                FooterKind::Help => "help",
+            };
+            out.push_str(&format!(
+                "{} {} {}: {}\n",
+                " ".repeat(self.side_width),
+                self.paint("=", Color::Cyan),
+                self.paint(kind, Color::White),
+                footer.message
+            ));
+        }
     }
 
-    fn line_number_side(&self, line_number: usize, side_width: usize) {
-        eprint!(
-            "{}",
-            format!("{:1$} |", line_number, side_width).cyan().bold()
-        );
+    fn source_line(&self, out: &mut String, line: usize) {
+        let text = expand_tabs(self.reporter.line(line));
+        let side = self.paint(&format!("{:1$} |", line + 1, self.side_width), Color::Cyan);
+        let mut row = self.text_gutter(line);
+        row.push(text);
+        out.push_str(&format!("{} {}\n", side, join(row)));
+
+        self.single_line_labels(out, line);
+
+        let multi_line: Vec<_> = self.multi_line_labels().collect();
+        for (depth, label) in multi_line.iter().copied().enumerate() {
+            if label.start.line == line {
+                // ` ____^` from the gutter to the start
+                let mut row = self.gutter(line, |other| self.fill(other, depth, label));
+                row[depth] = " ".to_owned();
+                row.push(self.paint_label("_", label));
+                row.extend((0..label.start.column).map(|_| self.paint_label("_", label)));
+                row.push(self.marker(label));
+                self.annotation(out, row);
+            }
+        }
+        for (depth, label) in multi_line.iter().copied().enumerate() {
+            if label.end.line == line {
+                // `|___^ message` from the gutter to the end
+                let mut row = self.gutter(line, |other| self.fill(other, depth, label));
+                row[depth] = self.paint_label("|", label);
+                row.push(self.paint_label("_", label));
+                row.extend((1..label.end.column).map(|_| self.paint_label("_", label)));
+                row.push(self.marker(label));
+                self.push_message(&mut row, label);
+                self.annotation(out, row);
+            }
+        }
     }
 
-    fn blank_side(&self, side_width: usize) {
-        eprint!("{}", format!("{:1$} |", "", side_width).cyan().bold());
+    /// Underlines every label on this line, then hangs their messages below
+    /// from right to left, so two labels can share a line.
+    fn single_line_labels(&self, out: &mut String, line: usize) {
+        let mut labels: Vec<_> = self
+            .labels
+            .iter()
+            .filter(|label| !label.is_multi_line() && label.start.line == line)
+            .collect();
+        if labels.is_empty() {
+            return;
+        }
+        labels.sort_by_key(|label| label.start.column);
+
+        let mut row = self.text_gutter(line);
+        // Primary labels are drawn last, so they win where spans overlap
+        for label in labels.iter().filter(|label| !label.primary) {
+            self.underline(&mut row, label);
+        }
+        for label in labels.iter().filter(|label| label.primary) {
+            self.underline(&mut row, label);
+        }
+
+        let (last, rest) = labels.split_last().unwrap();
+        self.push_message(&mut row, last);
+        self.annotation(out, row);
+
+        let text_start = self.text_start();
+        let hanging: Vec<_> = rest
+            .iter()
+            .filter(|label| label.message.is_some())
+            .collect();
+        for (index, label) in hanging.iter().enumerate().rev() {
+            // A row of bars down from every label still to come, then a row
+            // with this label's message at the end of its bar
+            let mut connectors = self.text_gutter(line);
+            let mut message = self.text_gutter(line);
+            for above in &hanging[..index] {
+                pad(&mut connectors, text_start + above.start.column);
+                connectors.push(self.paint_label("|", above));
+                pad(&mut message, text_start + above.start.column);
+                message.push(self.paint_label("|", above));
+            }
+            pad(&mut connectors, text_start + label.start.column);
+            connectors.push(self.paint_label("|", label));
+            pad(&mut message, text_start + label.start.column);
+            message.push(self.paint_label(label.message.unwrap_or_default(), label));
+
+            self.annotation(out, connectors);
+            self.annotation(out, message);
+        }
     }
 
-    fn line(&self, line: &str) {
-        eprint!(" {}", line);
+    fn underline(&self, row: &mut Vec<String>, label: &ResolvedLabel) {
+        let start = self.text_start() + label.start.column;
+        let end = self.text_start() + label.end.column.max(label.start.column + 1);
+        pad(row, end);
+        for cell in &mut row[start..end] {
+            *cell = self.marker(label);
+        }
     }
 
-    fn error_bar_align(&self) {
-        eprint!("  ");
+    fn push_message(&self, row: &mut Vec<String>, label: &ResolvedLabel) {
+        if let Some(message) = label.message {
+            row.push(" ".to_owned());
+            row.push(self.paint_label(message, label));
+        }
     }
 
-    fn error_bar(&self) {
-        eprint!("{}", " |".red().bold());
+    /// The gutter on an annotation row below `line`, with bars for multi-line
+    /// labels that carry on past it, then the space before the text.
+    fn text_gutter(&self, line: usize) -> Vec<String> {
+        let mut row = self.gutter(line, |_| " ".to_owned());
+        pad(&mut row, self.text_start());
+        row
     }
 
-    fn repeat(&self, text: impl std::fmt::Display, times: usize) {
-        for _ in 0..times {
-            eprint!("{}", text);
+    /// Where column zero of the source text is in a row, after the gutter and
+    /// a space if there is a gutter at all.
+    fn text_start(&self) -> usize {
+        match self.gutter_width() {
+            0 => 0,
+            width => width + 1,
         }
     }
 
-    fn message(&self, message: &Option<String>) {
-        if let Some(msg) = message {
-            eprint!("{}", msg.red().bold())
+    /// Gutter cells to the right of `depth` are part of the line drawn across
+    /// to `label`.
+    fn fill(&self, other: usize, depth: usize, label: &ResolvedLabel) -> String {
+        if other > depth {
+            self.paint_label("_", label)
+        } else {
+            " ".to_owned()
         }
     }
 
-    fn resolve(&self, span: &Span) -> ResolvedSpan {
-        // Count lines before the start/end
-        let line_number_start = self.source[..=span.start].lines().count();
-        let line_number_end = self.source[..=span.end].lines().count();
+    /// One cell per multi-line label, a bar if the label is open on this line
+    /// and `fill` otherwise.
+    fn gutter(&self, line: usize, fill: impl Fn(usize) -> String) -> Vec<String> {
+        self.multi_line_labels()
+            .enumerate()
+            .map(|(depth, label)| {
+                if label.start.line < line && line <= label.end.line {
+                    self.paint_label("|", label)
+                } else {
+                    fill(depth)
+                }
+            })
+            .collect()
+    }
 
-        // Find the index of the start of the lines by counting all characters up to it
-        let line_index_start: usize = LinesWithEndings::from(self.source)
-            .take(line_number_start - 1)
-            .map(|l| l.len())
-            .sum();
-        let line_index_end: usize = LinesWithEndings::from(self.source)
-            .take(line_number_end - 1)
-            .map(|l| l.len())
-            .sum();
+    fn gutter_width(&self) -> usize {
+        self.multi_line_labels().count()
+    }
 
-        ResolvedSpan {
-            // Same as the normal span
-            source_start: span.start,
-            source_end: span.end,
-            // Computed above
-            line_number_start,
-            line_number_end,
-            // Find the local ends by subtracting the line offset
-            local_start: span.start - line_index_start,
-            local_end: span.end - line_index_end,
+    fn multi_line_labels(&self) -> impl Iterator<Item = &ResolvedLabel<'_>> {
+        self.labels.iter().filter(|label| label.is_multi_line())
+    }
+
+    fn annotation(&self, out: &mut String, row: Vec<String>) {
+        let side = self.paint(&format!("{:1$} |", "", self.side_width), Color::Cyan);
+        out.push_str(&format!("{} {}\n", side, join(row)));
+    }
+
+    fn blank_side(&self, out: &mut String) {
+        let side = self.paint(&format!("{:1$} |", "", self.side_width), Color::Cyan);
+        out.push_str(&format!("{}\n", side));
+    }
+
+    fn paint(&self, text: &str, color: Color) -> String {
+        if self.reporter.color {
+            text.color(color).bold().to_string()
+        } else {
+            text.to_owned()
         }
     }
+
+    fn marker(&self, label: &ResolvedLabel) -> String {
+        self.paint_label(if label.primary { "^" } else { "-" }, label)
+    }
+
+    /// Primary labels take the colour of the citation's level.
+    fn paint_label(&self, text: &str, label: &ResolvedLabel) -> String {
+        let color = match (label.primary, self.level) {
+            (false, _) => Color::Blue,
+            (true, Level::Error) => Color::Red,
+            (true, Level::Warning) => Color::Yellow,
+            (true, Level::Info) => Color::Green,
+        };
+        self.paint(text, color)
+    }
+}
+
+/// Pads `row` with spaces up to `width` cells.
+fn pad(row: &mut Vec<String>, width: usize) {
+    while row.len() < width {
+        row.push(" ".to_owned());
+    }
+}
+
+/// Joins a row of cells, without any trailing whitespace.
+fn join(mut row: Vec<String>) -> String {
+    while row.last().is_some_and(|cell| cell.trim().is_empty()) {
+        row.pop();
+    }
+    row.concat()
+}
+
+fn expand_tabs(text: &str) -> String {
+    text.replace('\t', &" ".repeat(TAB_WIDTH))
+}
+
+fn char_width(char: char) -> usize {
+    match char {
+        '\t' => TAB_WIDTH,
+        _ => char.width().unwrap_or(0),
+    }
+}
+
+/// How many columns `text` takes up once printed.
+fn text_width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
 }
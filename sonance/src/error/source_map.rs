@@ -0,0 +1,163 @@
+//! Several files laid out one after another in a single span space, so a
+//! `Span` alone says which file it points into.
+
+use super::*;
+use std::io::{self, Write};
+
+#[derive(Debug, Clone)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+    color: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub name: String,
+    pub source: String,
+    /// Where the file starts in the map's span space.
+    pub start: usize,
+}
+
+impl SourceFile {
+    /// Converts a span within this file's own source into the map's space.
+    pub fn span(&self, span: Span) -> Span {
+        Span::new(span.start + self.start, span.end + self.start)
+    }
+
+    /// Converts a citation about this file's own source into the map's space.
+    pub fn citation(&self, mut citation: Citation) -> Citation {
+        for label in &mut citation.spans {
+            label.span = self.span(label.span);
+        }
+        citation
+    }
+
+    fn contains(&self, index: usize) -> bool {
+        // A span may point just past the end, at end of file
+        self.start <= index && index <= self.start + self.source.len()
+    }
+
+    fn reporter(&self, color: bool) -> Reporter<'_> {
+        Reporter::new(&self.source).name(&self.name).color(color)
+    }
+}
+
+impl Default for SourceMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self {
+            files: Vec::new(),
+            color: true,
+        }
+    }
+
+    /// Whether to style the output with colours, on by default.
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Adds a file after the others, returning it.
+    pub fn add(&mut self, name: String, source: String) -> &SourceFile {
+        // The gap keeps an end of file span from landing in the next file
+        let start = self
+            .files
+            .last()
+            .map_or(0, |file| file.start + file.source.len() + 1);
+
+        self.files.push(SourceFile {
+            name,
+            source,
+            start,
+        });
+        &self.files[self.files.len() - 1]
+    }
+
+    pub fn files(&self) -> &[SourceFile] {
+        &self.files
+    }
+
+    /// The file an index in the map's span space is in.
+    pub fn file(&self, index: usize) -> Option<&SourceFile> {
+        self.files.iter().find(|file| file.contains(index))
+    }
+
+    pub fn report(&self, out: &mut impl Write, citations: &[Citation]) -> io::Result<()> {
+        let rendered: Vec<_> = citations
+            .iter()
+            .map(|citation| match self.localize(citation) {
+                (Some(file), citation) => file.reporter(self.color).render(&citation),
+                (None, citation) => Reporter::new("").color(self.color).render(&citation),
+            })
+            .collect();
+        write!(out, "{}", rendered.join("\n"))
+    }
+
+    /// Writes each citation as a JSON object on its own line, using file
+    /// names as uris.
+    pub fn report_json(&self, out: &mut impl Write, citations: &[Citation]) -> io::Result<()> {
+        for citation in citations {
+            let (file, citation) = self.localize(citation);
+            let (reporter, uri) = match file {
+                Some(file) => (file.reporter(false), file.name.as_str()),
+                None => (Reporter::new(""), ""),
+            };
+            reporter.report_json(out, uri, &[citation])?;
+        }
+        Ok(())
+    }
+
+    /// Moves a citation's spans into the file its primary span is in. Spans
+    /// in other files can't share its snippet, so they become notes.
+    fn localize(&self, citation: &Citation) -> (Option<&SourceFile>, Citation) {
+        let primary = citation
+            .spans
+            .iter()
+            .find(|label| label.primary)
+            .or_else(|| citation.spans.first());
+        let file = match primary.and_then(|label| self.file(label.span.start)) {
+            Some(file) => file,
+            None => return (None, citation.clone()),
+        };
+
+        let mut localized = Citation {
+            spans: Vec::new(),
+            footers: Vec::new(),
+            ..citation.clone()
+        };
+
+        for label in &citation.spans {
+            if file.contains(label.span.start) {
+                localized.spans.push(SpanLabel {
+                    span: Span::new(label.span.start - file.start, label.span.end - file.start),
+                    ..label.clone()
+                });
+                continue;
+            }
+
+            let other = match self.file(label.span.start) {
+                Some(other) => other,
+                None => continue,
+            };
+            let (line, column) = other
+                .reporter(false)
+                .line_column(label.span.start - other.start);
+            let location = format!("{}:{}:{}", other.name, line, column);
+            localized.footers.push(Footer {
+                kind: FooterKind::Note,
+                message: match &label.message {
+                    Some(message) => format!("{} at {}", message, location),
+                    None => format!("see {}", location),
+                },
+            });
+        }
+
+        localized.footers.extend(citation.footers.iter().cloned());
+        (Some(file), localized)
+    }
+}
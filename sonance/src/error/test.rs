@@ -0,0 +1,209 @@
+use super::*;
+
+/// Renders with colours disabled, so the output can be compared as text.
+fn render(source: &str, citation: Citation) -> String {
+    Reporter::new(source).color(false).render(&citation)
+}
+
+/// The span of the first `text` in `source`.
+fn find(source: &str, text: &str) -> Span {
+    let start = source.find(text).expect("text should be in source");
+    Span::new(start, start + text.len() - 1)
+}
+
+#[test]
+fn grouped_labels() {
+    let source = "func main() {\n    let x: Int = \"hello\";\n};\n";
+    let citation = Citation::error("Mismatched types".to_owned())
+        .span(
+            find(source, "\"hello\""),
+            Some("expected `Int`, found `String`".to_owned()),
+        )
+        .secondary(find(source, "Int"), Some("expected due to this".to_owned()))
+        .secondary(find(source, "x"), Some("declared here".to_owned()))
+        .note("integers and strings don't convert".to_owned())
+        .help("remove the quotes".to_owned());
+
+    assert_eq!(
+        render(source, citation),
+        r#"error: Mismatched types
+ --> 2:18
+  |
+2 |     let x: Int = "hello";
+  |         -  ---   ^^^^^^^ expected `Int`, found `String`
+  |         |  |
+  |         |  expected due to this
+  |         |
+  |         declared here
+  |
+  = note: integers and strings don't convert
+  = help: remove the quotes
+"#
+    );
+}
+
+#[test]
+fn multi_line_labels() {
+    let source = "func main() {\n    loop {\n        1\n    };\n};\n";
+    let body = source.find("{\n        1").unwrap();
+    let citation = Citation::info("Loops never end".to_owned())
+        .span(
+            Span::new(body, source.find("};\n};").unwrap()),
+            Some("nothing returns from this".to_owned()),
+        )
+        .secondary(find(source, "loop"), None);
+
+    assert_eq!(
+        render(source, citation),
+        r#"info: Loops never end
+ --> 2:10
+  |
+2 |       loop {
+  |       ----
+  |  __________^
+3 | |         1
+4 | |     };
+  | |_____^ nothing returns from this
+"#
+    );
+}
+
+#[test]
+fn wide_characters() {
+    let source = "func main() {\n\tlet 世界 = y;\n};\n";
+    let citation = Citation::error("Unknown name".to_owned())
+        .span(find(source, "y"), Some("not found".to_owned()));
+
+    assert_eq!(
+        render(source, citation),
+        r#"error: Unknown name
+ --> 2:16
+  |
+2 |     let 世界 = y;
+  |                ^ not found
+"#
+    );
+}
+
+#[test]
+fn distant_lines() {
+    let source = "func one() {};\n\nfunc two() {};\n\n\n\n\n\n\n\nfunc one() {};\n";
+    let second = source.rfind("one").unwrap();
+    let citation = Citation::warning("Function defined twice".to_owned())
+        .span(
+            Span::new(second, second + 2),
+            Some("redefined here".to_owned()),
+        )
+        .secondary(find(source, "one"), Some("first defined here".to_owned()));
+
+    assert_eq!(
+        render(source, citation),
+        r#"warning: Function defined twice
+  --> 11:6
+   |
+ 1 | func one() {};
+   |      --- first defined here
+...
+11 | func one() {};
+   |      ^^^ redefined here
+"#
+    );
+}
+
+#[test]
+fn scan_errors() {
+    let source = "let a = \"open\nlet b = 1;\n";
+    let citation = crate::token::scan(source)
+        .find_map(Result::err)
+        .expect("source should fail to scan")
+        .into();
+
+    assert_eq!(
+        render(source, citation),
+        r#"error: Unterminated string literal
+ --> 1:9
+  |
+1 | let a = "open
+  |         ^^^^^ string starts here
+  |
+  = help: add a closing `"` at the end of the string
+"#
+    );
+}
+
+#[test]
+fn json_positions() {
+    // `𝄞` is two UTF-16 code units, so `x` starts at character 5.
+    let source = "// 𝄞\n\"𝄞\" x\n";
+    let citation = Citation::error("Unknown variable".to_owned()).span(find(source, "x"), None);
+
+    let mut out = Vec::new();
+    Reporter::new(source)
+        .report_json(&mut out, "file:///main.son", &[citation])
+        .unwrap();
+
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        r#"{"uri":"file:///main.son","diagnostic":{"range":{"start":{"line":1,"character":5},"end":{"line":1,"character":6}},"severity":1,"source":"sonance","message":"Unknown variable"}}
+"#
+    );
+}
+
+#[test]
+fn json_files() {
+    let mut map = SourceMap::new();
+    map.add("a.son".to_owned(), "one\n".to_owned());
+    let b = map
+        .add("b.son".to_owned(), "two\n".to_owned())
+        .span(Span::new(0, 2));
+    let citations = [
+        Citation::error("In b".to_owned()).span(b, None),
+        Citation::error("In a".to_owned()).span(Span::new(0, 2), None),
+    ];
+
+    let mut out = Vec::new();
+    map.report_json(&mut out, &citations).unwrap();
+
+    let uris: Vec<_> = String::from_utf8(out)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["uri"].clone())
+        .collect();
+    assert_eq!(uris, ["b.son", "a.son"]);
+}
+
+#[test]
+fn json_related_information() {
+    let source = "let x: Int = \"hello\";";
+    let citation = Citation::warning("Mismatched types".to_owned())
+        .span(find(source, "\"hello\""), Some("found `String`".to_owned()))
+        .secondary(find(source, "Int"), Some("expected due to this".to_owned()))
+        .help("remove the quotes".to_owned());
+
+    let diagnostic = Reporter::new(source).diagnostic("main.son", &citation);
+
+    assert_eq!(diagnostic.severity, 2);
+    assert_eq!(
+        diagnostic.message,
+        "Mismatched types\nhelp: remove the quotes"
+    );
+    assert_eq!(
+        diagnostic.range,
+        json::Range {
+            start: json::Position {
+                line: 0,
+                character: 13,
+            },
+            end: json::Position {
+                line: 0,
+                character: 20,
+            },
+        }
+    );
+    let messages: Vec<_> = diagnostic
+        .related_information
+        .iter()
+        .map(|info| info.message.as_str())
+        .collect();
+    assert_eq!(messages, ["found `String`", "expected due to this"]);
+}
@@ -0,0 +1,88 @@
+//! Sources and helpers shared by the tests of several modules.
+
+use crate::{
+    error::{Citation, SourceMap},
+    resolve::{self, Unit},
+};
+
+/// Every file of the core library, named by its path from `core`.
+pub(crate) const DREAMS: &[(&str, &str)] = &[
+    (
+        "scope.son",
+        include_str!("../../sonance-dreams/core/scope.son"),
+    ),
+    (
+        "data/boolean.son",
+        include_str!("../../sonance-dreams/core/data/boolean.son"),
+    ),
+    (
+        "data/never.son",
+        include_str!("../../sonance-dreams/core/data/never.son"),
+    ),
+    (
+        "data/optional.son",
+        include_str!("../../sonance-dreams/core/data/optional.son"),
+    ),
+    (
+        "data/result.son",
+        include_str!("../../sonance-dreams/core/data/result.son"),
+    ),
+    (
+        "data/unit.son",
+        include_str!("../../sonance-dreams/core/data/unit.son"),
+    ),
+    (
+        "traits/default.son",
+        include_str!("../../sonance-dreams/core/traits/default.son"),
+    ),
+    (
+        "traits/from.son",
+        include_str!("../../sonance-dreams/core/traits/from.son"),
+    ),
+];
+
+/// The dreams the AST parser can take, which the later stages are tested
+/// against. The rest use syntax it doesn't take yet, so the core library
+/// can't be checked as a whole until it does. `resolve`'s `core_library`
+/// test pins down what stops them.
+pub(crate) fn core_dreams() -> impl Iterator<Item = (&'static str, &'static str)> {
+    const SYNTAX_ONLY: &[&str] = &["scope.son", "data/never.son", "data/unit.son"];
+    DREAMS
+        .iter()
+        .copied()
+        .filter(|(name, _)| !SYNTAX_ONLY.contains(name))
+}
+
+/// A map of the files without color, so rendered errors can be compared.
+pub(crate) fn map(files: &[(&str, &str)]) -> SourceMap {
+    let mut map = SourceMap::new().color(false);
+    for (name, source) in files {
+        map.add((*name).to_owned(), (*source).to_owned());
+    }
+    map
+}
+
+/// A map of the core dreams with `main` as `main.son`.
+pub(crate) fn program(main: &str) -> SourceMap {
+    let mut files: Vec<_> = core_dreams().collect();
+    files.push(("main.son", main));
+    map(&files)
+}
+
+/// Parses every file in the map, none of which should have errors.
+pub(crate) fn parse(map: &SourceMap) -> Vec<Unit<'_>> {
+    let (units, errors) = resolve::parse(map);
+    assert!(errors.is_empty(), "{:?}", errors);
+    units
+}
+
+/// The errors as they'd be shown.
+pub(crate) fn render(
+    map: &SourceMap,
+    errors: impl IntoIterator<Item = impl Into<Citation>>,
+) -> String {
+    let errors: Vec<_> = errors.into_iter().map(Into::into).collect();
+    let mut out = Vec::new();
+    map.report(&mut out, &errors).unwrap();
+    String::from_utf8(out).unwrap()
+}
@@ -0,0 +1,598 @@
+//! The canonical layout of `.son` files, printed from the syntax tree so
+//! comments come along. Lists and chains stay on one line when they fit and
+//! break an item or call per line when they don't. Anything the parser had
+//! to skip is kept exactly as it was written.
+
+#[cfg(test)]
+mod test;
+
+use crate::syntax::{
+    self, SyntaxElement,
+    SyntaxKind::{self, *},
+    SyntaxNode,
+};
+use std::string::String;
+use unicode_width::UnicodeWidthStr;
+
+/// How wide a line gets before lists and chains are broken up.
+const WIDTH: usize = 100;
+const INDENT: &str = "    ";
+
+/// Formats a whole file.
+pub fn format(source: &str) -> String {
+    let mut printer = Printer::default();
+    printer.node(&syntax::parse(source));
+
+    let mut out = printer.out;
+    out.truncate(out.trim_end().len());
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    out
+}
+
+/// A comment, and how it sat among the lines around it.
+#[derive(Clone)]
+struct Comment {
+    text: String,
+    /// How many newlines came between it and whatever was before it.
+    newlines: usize,
+    /// Whether a newline came right after it.
+    ends_line: bool,
+}
+
+impl Comment {
+    /// Whether it has to be the last thing on its line.
+    fn ends_line(&self) -> bool {
+        self.ends_line || self.text.starts_with("//")
+    }
+}
+
+/// A child that isn't trivia, with the comments before it.
+struct Child {
+    element: SyntaxElement,
+    comments: Vec<Comment>,
+    newlines: usize,
+}
+
+impl Child {
+    fn kind(&self) -> SyntaxKind {
+        self.element.kind()
+    }
+
+    fn node(&self) -> Option<&SyntaxNode> {
+        match &self.element {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        }
+    }
+}
+
+/// The children of `node` that aren't trivia, and the comments after the
+/// last of them.
+fn children(node: &SyntaxNode) -> (Vec<Child>, Vec<Comment>) {
+    let mut children = Vec::new();
+    let mut comments: Vec<Comment> = Vec::new();
+    let mut newlines = 0;
+    let mut after_comment = false;
+
+    for element in node.children_with_tokens() {
+        let token = match &element {
+            SyntaxElement::Token(token) if token.kind().is_trivia() => token,
+            _ => {
+                children.push(Child {
+                    element,
+                    comments: std::mem::take(&mut comments),
+                    newlines,
+                });
+                newlines = 0;
+                after_comment = false;
+                continue;
+            }
+        };
+
+        if token.kind() == Whitespace {
+            let count = token.text().matches('\n').count();
+            if after_comment && count > 0 {
+                if let Some(comment) = comments.last_mut() {
+                    comment.ends_line = true;
+                }
+            }
+            newlines += count;
+            after_comment = false;
+        } else {
+            comments.push(Comment {
+                text: token.text().trim_end().to_owned(),
+                newlines,
+                ends_line: false,
+            });
+            newlines = 0;
+            after_comment = true;
+        }
+    }
+
+    (children, comments)
+}
+
+/// Whether the parser had to skip anything in `node`. Blocks and members
+/// inside it don't count, since their statements are printed on their own.
+fn broken(node: &SyntaxNode) -> bool {
+    node.kind() == Error
+        || node.children_with_tokens().any(|child| match child {
+            SyntaxElement::Token(token) => token.kind() == Error,
+            SyntaxElement::Node(child) => {
+                !matches!(child.kind(), Block | Members) && broken(&child)
+            }
+        })
+}
+
+/// Whether every block and list in `node` has its closing bracket. Files
+/// that stop partway through leave the rest as it was, since anything added
+/// on the end could end up inside.
+fn closed(node: &SyntaxNode) -> bool {
+    let bracketed = matches!(
+        node.kind(),
+        Block
+            | Members
+            | Parameters
+            | Arguments
+            | Generics
+            | TypeArguments
+            | Variants
+            | Fields
+            | PatternList
+            | ImportList
+            | MatchClauses
+    );
+    let children = node.green().children();
+    let ends = children.len() > 1
+        && matches!(
+            children.last().map(|it| it.kind()),
+            Some(CloseParen) | Some(CloseBrace) | Some(CloseAngle)
+        );
+
+    (!bracketed || ends) && node.children().all(|child| closed(&child))
+}
+
+/// Whether `node` is a call, property or `.match` on something before it,
+/// rather than something a chain starts with.
+fn link(node: &SyntaxNode) -> bool {
+    matches!(node.kind(), Chain | Property | Match)
+        && matches!(
+            node.children_with_tokens().next(),
+            Some(SyntaxElement::Node(_))
+        )
+}
+
+#[derive(Default)]
+struct Printer {
+    out: String,
+    indent: usize,
+    /// Set while measuring, so lists and chains never break.
+    flat: bool,
+}
+
+impl Printer {
+    fn node(&mut self, node: &SyntaxNode) {
+        match node.kind() {
+            File => {
+                let (children, trailing) = children(node);
+                self.sequence(children, trailing, true);
+            }
+            Block => self.block(node),
+            Members => {
+                let (children, trailing) = children(node);
+                self.braces(children, trailing);
+            }
+            Parameters | Arguments | Generics | TypeArguments | Variants | Fields | PatternList
+            | ImportList | MatchClauses => self.list(node),
+            _ if link(node) => self.chain(node),
+            Error => self.text(&node.to_string()),
+            Function | Signature | Enum | Struct | Trait | Module | Extension | Import => {
+                self.inline(node);
+                if node.last_token().map(|it| it.kind()) != Some(Semicolon) {
+                    self.text(";");
+                }
+            }
+            _ => self.inline(node),
+        }
+    }
+
+    fn element(&mut self, element: &SyntaxElement) {
+        match element {
+            SyntaxElement::Node(node) => self.node(node),
+            SyntaxElement::Token(token) => self.text(token.text()),
+        }
+    }
+
+    /// Prints a node's children on one line, apart from what has to break.
+    fn inline(&mut self, node: &SyntaxNode) {
+        let (children, trailing) = children(node);
+        self.inline_children(&children);
+        for comment in &trailing {
+            self.comment(comment);
+        }
+    }
+
+    fn inline_children(&mut self, children: &[Child]) {
+        let mut previous = None;
+        for child in children {
+            for comment in &child.comments {
+                self.comment(comment);
+            }
+            if let Some(previous) = previous {
+                self.separate(previous, child.kind());
+            }
+            self.element(&child.element);
+            previous = Some(child.kind());
+        }
+    }
+
+    /// What goes between two children of a node on the same line.
+    fn separate(&mut self, previous: SyntaxKind, next: SyntaxKind) {
+        let tight = matches!(
+            next,
+            Comma
+                | Semicolon
+                | Colon
+                | DotSymbol
+                | AtSymbol
+                | OpenParen
+                | CloseParen
+                | CloseAngle
+                | Parameters
+                | Arguments
+                | TypeArguments
+                | Generics
+                | Fields
+                | Variants
+                | PatternList
+        ) || matches!(previous, DotSymbol | AtSymbol | OpenParen | OpenAngle);
+        // `& &x` can't be `&&x`, which is a different token
+        let tight = tight
+            || previous == Ampersand && !matches!(next, Ampersand | Reference | ReferenceType);
+
+        if previous == Attribute && !tight {
+            self.newline();
+        } else if !tight {
+            self.space();
+        }
+    }
+
+    /// Items, members or statements, a line each. Blank lines between them
+    /// are kept, but never more than one, and items always have one.
+    fn sequence(&mut self, children: Vec<Child>, trailing: Vec<Comment>, items: bool) {
+        let mut after_item = false;
+
+        for child in children {
+            for comment in &child.comments {
+                self.line_comment(comment, items && after_item);
+                if comment.newlines > 0 {
+                    after_item = false;
+                }
+            }
+
+            self.newline();
+            if child.newlines > 1 || (items && after_item) {
+                self.blank_line();
+            }
+
+            match child.node() {
+                Some(node) if broken(node) || !closed(node) => self.text(&node.to_string()),
+                _ => self.element(&child.element),
+            }
+            after_item = true;
+        }
+
+        for comment in &trailing {
+            self.line_comment(comment, items && after_item);
+            if comment.newlines > 0 {
+                after_item = false;
+            }
+        }
+    }
+
+    /// A comment between lines, with a blank line before it if it had one
+    /// or `blank` is set. One that was on the end of a line stays there.
+    fn line_comment(&mut self, comment: &Comment, blank: bool) {
+        if comment.newlines > 1 || (comment.newlines > 0 && blank) {
+            self.blank_line();
+        }
+        self.comment(comment);
+    }
+
+    fn comment(&mut self, comment: &Comment) {
+        if comment.newlines > 0 {
+            self.newline();
+        } else {
+            self.space();
+        }
+
+        self.text(&comment.text);
+        if comment.ends_line() {
+            self.newline();
+        } else {
+            self.space();
+        }
+    }
+
+    /// A block, which is only kept on one line if it's a block argument
+    /// that's just an expression.
+    fn block(&mut self, node: &SyntaxNode) {
+        let (children, trailing) = children(node);
+
+        if let Some(tail) = single_expression(node, &children, &trailing) {
+            let print = |printer: &mut Printer| {
+                printer.text("{");
+                printer.space();
+                printer.node(tail);
+                printer.space();
+                printer.text("}");
+            };
+
+            let measured = self.measure(print);
+            if !measured.contains('\n') && measured.width() <= WIDTH {
+                return print(self);
+            }
+        }
+
+        self.braces(children, trailing);
+    }
+
+    /// `{`, then the children a line each, then `}`.
+    fn braces(&mut self, mut children: Vec<Child>, mut trailing: Vec<Comment>) {
+        let close = match children.last() {
+            Some(last) if last.kind() == CloseBrace && children.len() > 1 => children.pop(),
+            _ => None,
+        };
+        if let Some(close) = &close {
+            trailing = close.comments.clone();
+        }
+
+        let open = children.remove(0);
+        self.element(&open.element);
+        if children.is_empty() && trailing.is_empty() {
+            if close.is_some() {
+                self.text("}");
+            }
+            return;
+        }
+
+        self.indent += 1;
+        self.sequence(children, trailing, false);
+        self.indent -= 1;
+
+        if close.is_some() {
+            self.newline();
+            self.text("}");
+        }
+    }
+
+    /// A bracketed list, on one line if it fits, or an item per line with
+    /// a comma after each.
+    fn list(&mut self, node: &SyntaxNode) {
+        let (mut children, trailing) = children(node);
+        let open = children.remove(0);
+        let close_kind = match open.kind() {
+            OpenParen => CloseParen,
+            OpenAngle => CloseAngle,
+            _ => CloseBrace,
+        };
+        let close = match children.last() {
+            Some(last) if last.kind() == close_kind => children.pop(),
+            _ => None,
+        };
+
+        // Comments around a comma go after it
+        let mut items: Vec<Item> = Vec::new();
+        let mut pending = Vec::new();
+        for child in children {
+            if child.kind() != Comma {
+                items.push(Item {
+                    element: child.element,
+                    before: std::mem::take(&mut pending)
+                        .into_iter()
+                        .chain(child.comments)
+                        .collect(),
+                    after: Vec::new(),
+                });
+            } else if let Some(item) = items.last_mut() {
+                item.after.extend(child.comments);
+            } else {
+                pending.extend(child.comments);
+            }
+        }
+
+        let mut closing = pending;
+        closing.extend(
+            close
+                .iter()
+                .flat_map(|close| close.comments.iter().cloned()),
+        );
+        closing.extend(trailing);
+
+        let comments = !closing.is_empty()
+            || items
+                .iter()
+                .any(|item| !item.before.is_empty() || !item.after.is_empty());
+        let spaced = node.kind() == ImportList;
+        let close_text = close.as_ref().map(|_| match close_kind {
+            CloseParen => ")",
+            CloseAngle => ">",
+            _ => "}",
+        });
+
+        if items.is_empty() && !comments {
+            self.element(&open.element);
+            return close_text.into_iter().for_each(|text| self.text(text));
+        }
+
+        let inline = |printer: &mut Printer| {
+            printer.element(&open.element);
+            if spaced {
+                printer.space();
+            }
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    printer.text(",");
+                    printer.space();
+                }
+                printer.element(&item.element);
+            }
+            if spaced {
+                printer.space();
+            }
+            if let Some(text) = close_text {
+                printer.text(text);
+            }
+        };
+
+        let forced = comments || node.kind() == MatchClauses;
+        if !forced && (self.flat || self.fits(inline)) {
+            return inline(self);
+        }
+
+        self.element(&open.element);
+        self.indent += 1;
+        for item in &items {
+            for comment in &item.before {
+                self.comment(comment);
+            }
+            self.newline();
+            self.element(&item.element);
+            self.text(",");
+            for comment in &item.after {
+                self.comment(comment);
+            }
+        }
+        for comment in &closing {
+            self.comment(comment);
+        }
+        self.indent -= 1;
+
+        if let Some(text) = close_text {
+            self.newline();
+            self.text(text);
+        }
+    }
+
+    /// Calls, properties and `.match`es on the end of each other, on one
+    /// line if they fit, or a line each after what they start with.
+    fn chain(&mut self, node: &SyntaxNode) {
+        let mut links = Vec::new();
+        let mut base = node.clone();
+        while link(&base) {
+            let receiver = base.children().next().unwrap();
+            links.push(children(&base).0);
+            base = receiver;
+        }
+        links.reverse();
+
+        let inline = |printer: &mut Printer| {
+            printer.node(&base);
+            for link in &links {
+                printer.inline_children(&link[1..]);
+            }
+        };
+
+        let comments = links
+            .iter()
+            .any(|link| link[1..].iter().any(|child| !child.comments.is_empty()));
+        if links.len() < 2 || (!comments && (self.flat || self.fits(inline))) {
+            return inline(self);
+        }
+
+        self.node(&base);
+        self.indent += 1;
+        for link in &links {
+            self.newline();
+            self.inline_children(&link[1..]);
+        }
+        self.indent -= 1;
+    }
+
+    /// What `print` would add to the current line, in flat mode.
+    fn measure(&self, print: impl FnOnce(&mut Printer)) -> String {
+        let start = self.out.rfind('\n').map_or(0, |index| index + 1);
+        let mut printer = Printer {
+            out: self.out[start..].to_owned(),
+            indent: self.indent,
+            flat: true,
+        };
+        print(&mut printer);
+        printer.out
+    }
+
+    /// Whether the current line is still short enough after what `print`
+    /// adds to it, when nothing it prints breaks unless it has to.
+    fn fits(&self, print: impl FnOnce(&mut Printer)) -> bool {
+        let measured = self.measure(print);
+        measured.lines().next().unwrap_or("").width() <= WIDTH
+    }
+
+    fn at_line_start(&self) -> bool {
+        self.out.is_empty() || self.out.ends_with('\n')
+    }
+
+    fn text(&mut self, text: &str) {
+        if self.at_line_start() {
+            for _ in 0..self.indent {
+                self.out.push_str(INDENT);
+            }
+        }
+        self.out.push_str(text);
+    }
+
+    fn space(&mut self) {
+        if !self.at_line_start() && !self.out.ends_with(' ') {
+            self.out.push(' ');
+        }
+    }
+
+    fn newline(&mut self) {
+        self.out.truncate(self.out.trim_end_matches(' ').len());
+        if !self.at_line_start() {
+            self.out.push('\n');
+        }
+    }
+
+    fn blank_line(&mut self) {
+        self.newline();
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+}
+
+/// An item in a list, with the comments before it and around the comma
+/// after it.
+struct Item {
+    element: SyntaxElement,
+    before: Vec<Comment>,
+    after: Vec<Comment>,
+}
+
+/// The expression a block argument is made of, if that's all it has.
+fn single_expression<'a>(
+    node: &SyntaxNode,
+    children: &'a [Child],
+    trailing: &[Comment],
+) -> Option<&'a SyntaxNode> {
+    if node.parent()?.kind() != BlockArgument || !trailing.is_empty() {
+        return None;
+    }
+
+    match children {
+        [_, tail, close]
+            if close.kind() == CloseBrace
+                && tail.kind() == ExpressionStatement
+                && tail.comments.is_empty()
+                && close.comments.is_empty() =>
+        {
+            let tail = tail.node()?;
+            let ended = tail.last_token().map(|it| it.kind()) == Some(Semicolon);
+            Some(tail).filter(|tail| !ended && !broken(tail))
+        }
+        _ => None,
+    }
+}
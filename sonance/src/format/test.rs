@@ -0,0 +1,134 @@
+use super::*;
+use crate::fixture::DREAMS;
+use proptest::prelude::*;
+use std::string::String;
+
+/// Every token but whitespace, with commas and semicolons left out since
+/// the formatter adds and removes them, and spaces trimmed off comments.
+fn tokens(source: &str) -> Vec<String> {
+    syntax::parse(source)
+        .tokens()
+        .iter()
+        .filter(|token| !matches!(token.kind(), Whitespace | Comma | Semicolon))
+        .map(|token| token.text().trim_end().to_owned())
+        .collect()
+}
+
+#[test]
+fn dreams_are_formatted() {
+    for (_, dream) in DREAMS {
+        assert_eq!(format(dream), *dream);
+    }
+}
+
+#[test]
+fn idempotent() {
+    for (_, dream) in DREAMS {
+        let formatted = format(dream);
+        assert_eq!(format(&formatted), formatted);
+    }
+}
+
+#[test]
+fn layout() {
+    let source = "module  main ;import std.io\n\n\n\nfunc main ( ) -> Unit{let x:Int=1;\n\n\nx.add( 2 )}\nenum Boolean(True,False)";
+
+    assert_eq!(
+        format(source),
+        "module main;\n\nimport std.io;\n\nfunc main() -> Unit {\n    let x: Int = 1;\n\n    x.add(2)\n};\n\nenum Boolean(True, False);\n"
+    );
+}
+
+#[test]
+fn trailing_commas() {
+    let source = "func main() { print(a, b,); match(x) { True -> 1, False -> 0 } };";
+
+    assert_eq!(
+        format(source),
+        "func main() {\n    print(a, b);\n    match(x) {\n        True -> 1,\n        False -> 0,\n    }\n};\n"
+    );
+
+    let long = format!(
+        "func main() {{ print({}, {}) }};",
+        "a".repeat(50),
+        "b".repeat(50)
+    );
+    assert_eq!(
+        format(&long),
+        format!(
+            "func main() {{\n    print(\n        {},\n        {},\n    )\n}};\n",
+            "a".repeat(50),
+            "b".repeat(50)
+        )
+    );
+}
+
+#[test]
+fn chains() {
+    let short = "func main() { range(from: 0, to: 10).random(using: rng()).print() };";
+    assert_eq!(
+        format(short),
+        "func main() {\n    range(from: 0, to: 10).random(using: rng()).print()\n};\n"
+    );
+
+    let long = "func main() { let guess = stdin().read_line().expect(reason: \"Failed to read a line from standard input\").trim().parse_int(); };";
+    assert_eq!(
+        format(long),
+        "func main() {\n    let guess = stdin()\n        .read_line()\n        .expect(reason: \"Failed to read a line from standard input\")\n        .trim()\n        .parse_int();\n};\n"
+    );
+}
+
+#[test]
+fn block_arguments() {
+    let source = "func main() { x.then {  y  }; x.each { print(it); it } };";
+
+    assert_eq!(
+        format(source),
+        "func main() {\n    x.then { y };\n    x.each {\n        print(it);\n        it\n    }\n};\n"
+    );
+}
+
+#[test]
+fn comments() {
+    let source = "// Heading\nmodule main; // trailing\n/// Docs\nfunc main() {\n    // Before\n  print(a, // first\n  b);\n /* end */ };\n";
+
+    assert_eq!(
+        format(source),
+        "// Heading\nmodule main; // trailing\n\n/// Docs\nfunc main() {\n    // Before\n    print(\n        a, // first\n        b,\n    );\n    /* end */\n};\n"
+    );
+}
+
+#[test]
+fn errors_kept() {
+    let source = "func  loop(x:Int)  {  x };\n\nfunc main() {\n    let  = $ ;\n  print( 1 )\n};\n";
+
+    assert_eq!(
+        format(source),
+        "func  loop(x:Int)  {  x };\n\nfunc main() {\n    let  = $ ;\n    print(1)\n};\n"
+    );
+}
+
+proptest! {
+    /// Formatting twice changes nothing the first time didn't, and never
+    /// loses a token, even in files broken by random edits.
+    #[test]
+    fn idempotent_after_edits(
+        dream in 0..DREAMS.len(),
+        edits in prop::collection::vec(
+            (any::<prop::sample::Index>(), 0..8usize, "([a-z0-9_ \n{}();.,:<>@&\"*-]|//|/\\*|\\*/){0,6}"),
+            0..4,
+        ),
+    ) {
+        let mut text = DREAMS[dream].1.to_owned();
+        // Everything is ASCII, so any byte is a char boundary
+        for (start, len, insert) in edits {
+            let start = start.index(text.len() + 1);
+            let end = (start + len).min(text.len());
+            text.replace_range(start..end, &insert);
+        }
+
+        let formatted = format(&text);
+        prop_assert_eq!(tokens(&formatted), tokens(&text), "{:?}", text);
+        prop_assert_eq!(format(&formatted), formatted, "{:?}", text);
+    }
+}
@@ -0,0 +1,152 @@
+//! Functions every program can call that aren't written in sonance.
+
+use super::Value;
+use std::{io::Write, rc::Rc};
+
+/// The names of every builtin.
+pub const BUILTINS: &[&str] = &[
+    "print",
+    "print_line",
+    "add",
+    "subtract",
+    "multiply",
+    "divide",
+    "remainder",
+    "negate",
+    "equals",
+    "less_than",
+    "greater_than",
+];
+
+/// What a builtin call came to, if `name` is a builtin.
+pub(super) enum Outcome<'a, 'src> {
+    Value(Value<'a, 'src>),
+    /// The arguments are wrong, with why.
+    Invalid(String),
+    Output(std::io::Error),
+}
+
+pub(super) fn call<'a, 'src>(
+    name: &str,
+    arguments: &[Value<'a, 'src>],
+    out: &mut dyn Write,
+) -> Option<Outcome<'a, 'src>> {
+    use Value::*;
+
+    let outcome = match (name, arguments) {
+        ("print", [value]) => output(write!(out, "{}", value)),
+        ("print_line", [value]) => output(writeln!(out, "{}", value)),
+
+        ("add", [Integer(a), Integer(b)]) => integer(a.checked_add(*b)),
+        ("subtract", [Integer(a), Integer(b)]) => integer(a.checked_sub(*b)),
+        ("multiply", [Integer(a), Integer(b)]) => integer(a.checked_mul(*b)),
+        ("divide", [Integer(a), Integer(b)]) => integer(a.checked_div(*b)),
+        ("remainder", [Integer(a), Integer(b)]) => integer(a.checked_rem(*b)),
+        ("negate", [Integer(a)]) => integer(a.checked_neg()),
+
+        ("add", [Float(a), Float(b)]) => Outcome::Value(Float(a + b)),
+        ("subtract", [Float(a), Float(b)]) => Outcome::Value(Float(a - b)),
+        ("multiply", [Float(a), Float(b)]) => Outcome::Value(Float(a * b)),
+        ("divide", [Float(a), Float(b)]) => Outcome::Value(Float(a / b)),
+        ("remainder", [Float(a), Float(b)]) => Outcome::Value(Float(a % b)),
+        ("negate", [Float(a)]) => Outcome::Value(Float(-a)),
+
+        ("add", [String(a), String(b)]) => Outcome::Value(String(Rc::from(format!("{}{}", a, b)))),
+
+        ("equals", [a, b]) => match equals(a, b) {
+            Some(equal) => Outcome::Value(Value::boolean(equal)),
+            None => mismatch(arguments),
+        },
+        ("less_than", [a, b]) => compare(a, b, |ordering| ordering.is_lt()),
+        ("greater_than", [a, b]) => compare(a, b, |ordering| ordering.is_gt()),
+
+        ("print", _) | ("print_line", _) | ("negate", _) => {
+            Outcome::Invalid(format!("takes 1 argument, found {}", arguments.len()))
+        }
+        ("add", [_, _])
+        | ("subtract", [_, _])
+        | ("multiply", [_, _])
+        | ("divide", [_, _])
+        | ("remainder", [_, _]) => mismatch(arguments),
+        ("add", _)
+        | ("subtract", _)
+        | ("multiply", _)
+        | ("divide", _)
+        | ("remainder", _)
+        | ("equals", _)
+        | ("less_than", _)
+        | ("greater_than", _) => {
+            Outcome::Invalid(format!("takes 2 arguments, found {}", arguments.len()))
+        }
+        _ => return None,
+    };
+
+    Some(outcome)
+}
+
+fn output<'a, 'src>(result: std::io::Result<()>) -> Outcome<'a, 'src> {
+    match result {
+        Ok(()) => Outcome::Value(Value::Unit),
+        Err(error) => Outcome::Output(error),
+    }
+}
+
+/// The result of checked integer arithmetic.
+fn integer<'a, 'src>(value: Option<i64>) -> Outcome<'a, 'src> {
+    match value {
+        Some(value) => Outcome::Value(Value::Integer(value)),
+        None => Outcome::Invalid("overflowed or divided by zero".to_owned()),
+    }
+}
+
+fn mismatch<'a, 'src>(arguments: &[Value<'a, 'src>]) -> Outcome<'a, 'src> {
+    let types: Vec<_> = arguments
+        .iter()
+        .map(|it| it.type_name().unwrap_or("block"))
+        .collect();
+    Outcome::Invalid(format!("can't take `{}`", types.join("`, `")))
+}
+
+/// Whether two values are equal, if they're of a type with equality.
+fn equals(a: &Value, b: &Value) -> Option<bool> {
+    use Value::*;
+
+    Some(match (a, b) {
+        (Integer(a), Integer(b)) => a == b,
+        (Float(a), Float(b)) => a == b,
+        (Character(a), Character(b)) => a == b,
+        (String(a), String(b)) => a == b,
+        (Unit, Unit) => true,
+        (Variant(a), Variant(b)) if a.enumeration == b.enumeration => {
+            if a.name != b.name || a.fields.len() != b.fields.len() {
+                return Some(false);
+            }
+            for (a, b) in a.fields.iter().zip(&b.fields) {
+                if !equals(a, b)? {
+                    return Some(false);
+                }
+            }
+            true
+        }
+        _ => return None,
+    })
+}
+
+fn compare<'a, 'src>(
+    a: &Value<'a, 'src>,
+    b: &Value<'a, 'src>,
+    test: impl Fn(std::cmp::Ordering) -> bool,
+) -> Outcome<'a, 'src> {
+    use Value::*;
+
+    let ordering = match (a, b) {
+        (Integer(a), Integer(b)) => a.partial_cmp(b),
+        (Float(a), Float(b)) => a.partial_cmp(b),
+        (Character(a), Character(b)) => a.partial_cmp(b),
+        (String(a), String(b)) => a.partial_cmp(b),
+        _ => return mismatch(&[a.clone(), b.clone()]),
+    };
+
+    // Comparisons with NaN are all false
+    Outcome::Value(Value::boolean(ordering.is_some_and(test)))
+}
@@ -0,0 +1,118 @@
+use crate::error::{Citation, Span};
+use std::io;
+
+#[derive(Debug)]
+pub enum RuntimeError<'src> {
+    /// None of the files have a `main` function.
+    NoMain,
+    /// A name that isn't a local, function, variant or builtin.
+    Unbound { name: &'src str, span: Span },
+    /// A call of something that isn't a function or block.
+    NotCallable { value: String, span: Span },
+    /// Arguments that don't fit the parameters of what's called.
+    Arguments {
+        name: &'src str,
+        message: String,
+        span: Span,
+    },
+    /// A call that fits several functions equally well.
+    Ambiguous {
+        name: &'src str,
+        candidates: Vec<Span>,
+        span: Span,
+    },
+    /// A call that fits none of the functions with its name.
+    NoOverload {
+        name: &'src str,
+        candidates: Vec<Span>,
+        span: Span,
+    },
+    /// A value that none of a match's clauses or a binding's pattern fit.
+    NoMatch { value: String, span: Span },
+    /// An assignment to a name that wasn't bound with `let mut`.
+    Immutable {
+        name: &'src str,
+        binding: Span,
+        span: Span,
+    },
+    /// A builtin given values it can't work with.
+    Builtin {
+        name: &'src str,
+        message: String,
+        span: Span,
+    },
+    /// A `return` with nothing to return from, because there's no such
+    /// label or the function already returned.
+    NoReturnTarget { span: Span },
+    /// The program's output couldn't be written.
+    Output(io::Error),
+}
+
+impl<'src> From<RuntimeError<'src>> for Citation {
+    fn from(error: RuntimeError<'src>) -> Self {
+        match error {
+            RuntimeError::NoMain => Citation::error("No `main` function to run".to_owned())
+                .help("add `func main() { ... };` to one of the files".to_owned()),
+            RuntimeError::Unbound { name, span } => {
+                Citation::error(format!("Unbound name `{}`", name))
+                    .span(span, Some("not a local, function or variant".to_owned()))
+            }
+            RuntimeError::NotCallable { value, span } => {
+                Citation::error(format!("`{}` is not a function", value))
+                    .span(span, Some("called here".to_owned()))
+            }
+            RuntimeError::Arguments {
+                name,
+                message,
+                span,
+            } => {
+                Citation::error(format!("Wrong arguments for `{}`", name)).span(span, Some(message))
+            }
+            RuntimeError::Ambiguous {
+                name,
+                candidates,
+                span,
+            } => candidates.into_iter().fold(
+                Citation::error(format!("Ambiguous call to `{}`", name))
+                    .span(span, Some("fits more than one function".to_owned())),
+                |citation, candidate| citation.secondary(candidate, Some("candidate".to_owned())),
+            ),
+            RuntimeError::NoOverload {
+                name,
+                candidates,
+                span,
+            } => candidates.into_iter().fold(
+                Citation::error(format!("No `{}` takes these arguments", name)).span(span, None),
+                |citation, candidate| citation.secondary(candidate, Some("candidate".to_owned())),
+            ),
+            RuntimeError::NoMatch { value, span } => {
+                Citation::error(format!("No pattern matches `{}`", value))
+                    .span(span, None)
+                    .help("add a clause with a `_` pattern to catch the rest".to_owned())
+            }
+            RuntimeError::Immutable {
+                name,
+                binding,
+                span,
+            } => Citation::error(format!("Cannot assign twice to `{}`", name))
+                .span(span, Some("assigned here".to_owned()))
+                .secondary(binding, Some("bound here".to_owned()))
+                .help(format!("make it mutable with `let mut {}`", name)),
+            RuntimeError::Builtin {
+                name,
+                message,
+                span,
+            } => {
+                Citation::error(format!("Bad call of builtin `{}`", name)).span(span, Some(message))
+            }
+            RuntimeError::NoReturnTarget { span } => {
+                Citation::error("Nothing to return from".to_owned())
+                    .span(span, None)
+                    .note("blocks can only return from functions that are still running".to_owned())
+            }
+            RuntimeError::Output(error) => {
+                Citation::error(format!("Couldn't write output: {}", error))
+            }
+        }
+    }
+}
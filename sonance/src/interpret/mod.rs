@@ -0,0 +1,943 @@
+//! A tree-walking interpreter for parsed files.
+//!
+//! Every file's items share one namespace, as if the files were a single
+//! prelude, so the interpreter doesn't need names to be resolved first.
+//! Functions of the same name are told apart like the type checker tells
+//! them apart, by labels and number of arguments and then by the types of
+//! the values, except that values only have the type they're an instance
+//! of. Builtins are called when no function has the name, and variant
+//! constructors are called like functions.
+//!
+//! Block arguments are closures over the scope they're written in. A bare
+//! `return` in one returns from the function it's written in, even if that
+//! function is further up the stack.
+
+mod builtin;
+mod error;
+#[cfg(test)]
+mod test;
+mod value;
+
+pub use builtin::BUILTINS;
+pub use error::RuntimeError;
+pub use value::{Closure, Value, Variant};
+
+use crate::{
+    error::Span,
+    parser::ast::*,
+    resolve::{
+        overload::{self, Assignment, Choice, Rank},
+        Unit,
+    },
+    types::PRIMITIVES,
+};
+use builtin::Outcome;
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    io::Write,
+    rc::Rc,
+};
+
+/// Runs the `main` function of a program made of `units`, writing what it
+/// prints to `out`.
+pub fn run<'src>(units: &[Unit<'src>], out: &mut dyn Write) -> Result<(), RuntimeError<'src>> {
+    evaluate(units, out).map(drop)
+}
+
+/// Runs `main` like `run`, giving back the value it returns.
+pub fn evaluate<'a, 'src>(
+    units: &'a [Unit<'src>],
+    out: &mut dyn Write,
+) -> Result<Value<'a, 'src>, RuntimeError<'src>> {
+    evaluate_bindings(units, out).map(|(value, _)| value)
+}
+
+/// Runs `main` like `evaluate`, also giving back the names its body bound,
+/// with the values they had when it returned, in the order they were bound.
+pub fn evaluate_bindings<'a, 'src>(
+    units: &'a [Unit<'src>],
+    out: &mut dyn Write,
+) -> Result<(Value<'a, 'src>, Vec<Local<'a, 'src>>), RuntimeError<'src>> {
+    let mut interpreter = Interpreter::new(units, out);
+
+    let main = interpreter
+        .functions
+        .get("main")
+        .and_then(|methods| methods.iter().find(|method| method.owner.is_none()))
+        .copied()
+        .ok_or(RuntimeError::NoMain)?;
+
+    let env = Scope::new(None);
+    match interpreter.body(main, &env) {
+        Ok(value) => Ok((value, env.locals.take())),
+        Err(Unwind::Error(error)) => Err(error),
+        Err(Unwind::Return { span, .. }) => Err(RuntimeError::NoReturnTarget { span }),
+    }
+}
+
+/// A scope of local bindings.
+#[derive(Debug)]
+pub(crate) struct Scope<'a, 'src> {
+    parent: Option<Env<'a, 'src>>,
+    locals: RefCell<Vec<Local<'a, 'src>>>,
+}
+
+pub(crate) type Env<'a, 'src> = Rc<Scope<'a, 'src>>;
+
+/// A name bound by a parameter, `let` or pattern.
+#[derive(Debug, Clone)]
+pub struct Local<'a, 'src> {
+    pub name: &'src str,
+    pub mutable: bool,
+    pub span: Span,
+    pub value: Value<'a, 'src>,
+}
+
+impl<'a, 'src> Scope<'a, 'src> {
+    fn new(parent: Option<Env<'a, 'src>>) -> Env<'a, 'src> {
+        Rc::new(Scope {
+            parent,
+            locals: RefCell::new(Vec::new()),
+        })
+    }
+
+    fn define(&self, name: Ident<'src>, mutable: bool, value: Value<'a, 'src>) {
+        self.locals.borrow_mut().push(Local {
+            name: name.name,
+            mutable,
+            span: name.span,
+            value,
+        });
+    }
+
+    fn get(&self, name: &str) -> Option<Value<'a, 'src>> {
+        let locals = self.locals.borrow();
+        match locals.iter().rev().find(|local| local.name == name) {
+            Some(local) => Some(local.value.clone()),
+            None => self.parent.as_ref()?.get(name),
+        }
+    }
+
+    fn set(&self, name: Ident<'src>, value: Value<'a, 'src>) -> Result<(), RuntimeError<'src>> {
+        let mut locals = self.locals.borrow_mut();
+        match locals
+            .iter_mut()
+            .rev()
+            .find(|local| local.name == name.name)
+        {
+            Some(local) if local.mutable => {
+                local.value = value;
+                Ok(())
+            }
+            Some(local) => Err(RuntimeError::Immutable {
+                name: name.name,
+                binding: local.span,
+                span: name.span,
+            }),
+            None => match &self.parent {
+                Some(parent) => parent.set(name, value),
+                None => Err(RuntimeError::Unbound {
+                    name: name.name,
+                    span: name.span,
+                }),
+            },
+        }
+    }
+}
+
+/// What a `return` can return from, innermost last, and the type the
+/// function being run was declared in.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Context<'src> {
+    targets: Vec<Target<'src>>,
+    owner: Option<&'src str>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Target<'src> {
+    Function { name: &'src str, frame: usize },
+    Loop { frame: usize },
+}
+
+impl<'src> Context<'src> {
+    fn with(&self, target: Target<'src>) -> Self {
+        let mut targets = self.targets.clone();
+        targets.push(target);
+        Self {
+            targets,
+            owner: self.owner,
+        }
+    }
+
+    /// The frame a `return` with `label` unwinds to.
+    fn frame(&self, label: Option<Ident<'src>>) -> Option<usize> {
+        self.targets
+            .iter()
+            .rev()
+            .find_map(|target| match (target, label) {
+                (Target::Function { frame, .. }, None) => Some(*frame),
+                (Target::Loop { frame }, Some(label)) if label.name == "loop" => Some(*frame),
+                (Target::Function { name, frame }, Some(label)) if *name == label.name => {
+                    Some(*frame)
+                }
+                _ => None,
+            })
+    }
+}
+
+/// Why evaluation stopped early.
+#[derive(Debug)]
+enum Unwind<'a, 'src> {
+    Return {
+        frame: usize,
+        value: Value<'a, 'src>,
+        span: Span,
+    },
+    Error(RuntimeError<'src>),
+}
+
+impl<'src> From<RuntimeError<'src>> for Unwind<'_, 'src> {
+    fn from(error: RuntimeError<'src>) -> Self {
+        Unwind::Error(error)
+    }
+}
+
+type Eval<'a, 'src> = Result<Value<'a, 'src>, Unwind<'a, 'src>>;
+
+/// A function, the type it was declared in if any, and the enum,
+/// extension or module it was declared in.
+#[derive(Debug, Clone, Copy)]
+struct Method<'a, 'src> {
+    owner: Option<&'src str>,
+    namespace: Option<&'src str>,
+    function: &'a Function<'src>,
+}
+
+/// What a call is made through, which decides the functions it prefers.
+#[derive(Debug, Clone, Copy)]
+enum Receiver<'src> {
+    None,
+    /// `value.f()`, where the value is the first argument.
+    Value,
+    /// `module.f()` or `Type.f()`.
+    Namespace(&'src str),
+}
+
+/// An argument before it's matched to a parameter.
+enum Argument<'a, 'src> {
+    Value(Value<'a, 'src>),
+    Block(&'a Block<'src>),
+}
+
+struct Interpreter<'a, 'src, 'out> {
+    functions: HashMap<&'src str, Vec<Method<'a, 'src>>>,
+    /// Each variant's enum and number of fields.
+    variants: HashMap<&'src str, (&'src str, usize)>,
+    /// Enums and primitives, which parameters can be declared as.
+    types: HashSet<&'src str>,
+    /// Names that a path can go through: enums, extensions and modules.
+    namespaces: HashSet<&'src str>,
+    out: &'out mut dyn Write,
+    frames: usize,
+}
+
+impl<'a, 'src, 'out> Interpreter<'a, 'src, 'out> {
+    fn new(units: &'a [Unit<'src>], out: &'out mut dyn Write) -> Self {
+        let mut interpreter = Interpreter {
+            functions: HashMap::new(),
+            variants: HashMap::new(),
+            types: PRIMITIVES.iter().copied().collect(),
+            namespaces: HashSet::new(),
+            out,
+            frames: 0,
+        };
+
+        for unit in units {
+            interpreter
+                .namespaces
+                .extend(unit.directory.iter().copied());
+
+            let module = unit.file.items.iter().find_map(|item| match item {
+                Item::Module(module) => Some(module.name.name),
+                _ => None,
+            });
+
+            for item in &unit.file.items {
+                match item {
+                    Item::Function(function) => interpreter.declare(None, module, function),
+                    Item::Enum(enumeration) => {
+                        let name = enumeration.name.name;
+                        interpreter.namespaces.insert(name);
+                        interpreter.types.insert(name);
+                        for variant in &enumeration.variants {
+                            interpreter
+                                .variants
+                                .insert(variant.name.name, (name, variant.fields.len()));
+                        }
+                        for function in &enumeration.functions {
+                            interpreter.declare(Some(name), Some(name), function);
+                        }
+                    }
+                    Item::Extension(extension) => {
+                        interpreter.namespaces.insert(extension.name.name);
+                        let owner = match &extension.target.kind {
+                            TypeKind::Named { name, .. } => Some(name.name),
+                            TypeKind::Block { .. } => None,
+                        };
+                        for function in &extension.functions {
+                            interpreter.declare(owner, Some(extension.name.name), function);
+                        }
+                    }
+                    Item::Module(module) => {
+                        interpreter.namespaces.insert(module.name.name);
+                    }
+                    Item::Trait(_) | Item::Import(_) => {}
+                }
+            }
+        }
+
+        interpreter
+    }
+
+    fn declare(
+        &mut self,
+        owner: Option<&'src str>,
+        namespace: Option<&'src str>,
+        function: &'a Function<'src>,
+    ) {
+        self.functions
+            .entry(function.name.name)
+            .or_default()
+            .push(Method {
+                owner,
+                namespace,
+                function,
+            });
+    }
+
+    /// Calls a function with arguments already matched to its parameters.
+    fn invoke(
+        &mut self,
+        method: Method<'a, 'src>,
+        arguments: Vec<Value<'a, 'src>>,
+        span: Span,
+    ) -> Eval<'a, 'src> {
+        let env = Scope::new(None);
+        for (parameter, argument) in method.function.parameters.iter().zip(arguments) {
+            self.bind(&env, &parameter.pattern, argument, span)?;
+        }
+
+        self.body(method, &env)
+    }
+
+    /// Runs a function's body in `env`, which holds its parameters.
+    fn body(&mut self, method: Method<'a, 'src>, env: &Env<'a, 'src>) -> Eval<'a, 'src> {
+        let function = method.function;
+        self.frames += 1;
+        let frame = self.frames;
+        let context = Context {
+            targets: vec![Target::Function {
+                name: function.name.name,
+                frame,
+            }],
+            owner: method.owner,
+        };
+
+        match self.statements(&function.body, env, &context) {
+            Err(Unwind::Return {
+                frame: target,
+                value,
+                ..
+            }) if target == frame => Ok(value),
+            result => result,
+        }
+    }
+
+    /// Binds a value to a pattern that must fit it, as in `let`.
+    fn bind(
+        &self,
+        env: &Env<'a, 'src>,
+        pattern: &Pattern<'src>,
+        value: Value<'a, 'src>,
+        span: Span,
+    ) -> Result<(), RuntimeError<'src>> {
+        match pattern {
+            Pattern::Binding { mutable, name } => {
+                env.define(*name, *mutable, value);
+                Ok(())
+            }
+            Pattern::Variant { .. } => {
+                if self.matches(env, pattern, &value) {
+                    Ok(())
+                } else {
+                    Err(RuntimeError::NoMatch {
+                        value: value.to_string(),
+                        span,
+                    })
+                }
+            }
+        }
+    }
+
+    /// Whether a value fits a pattern, binding its names if it does.
+    fn matches(
+        &self,
+        env: &Env<'a, 'src>,
+        pattern: &Pattern<'src>,
+        value: &Value<'a, 'src>,
+    ) -> bool {
+        match pattern {
+            Pattern::Binding { name, .. } if name.name == "_" => true,
+            // A bare name is a variant without fields if there is one
+            Pattern::Binding { name, .. } if self.variants.contains_key(name.name) => {
+                matches!(value, Value::Variant(variant) if variant.name == name.name)
+            }
+            Pattern::Binding { mutable, name } => {
+                env.define(*name, *mutable, value.clone());
+                true
+            }
+            Pattern::Variant { path, fields, .. } => {
+                let variant = match value {
+                    Value::Variant(variant) => variant,
+                    _ => return false,
+                };
+                path.last().is_some_and(|name| name.name == variant.name)
+                    && fields.len() == variant.fields.len()
+                    && fields
+                        .iter()
+                        .zip(&variant.fields)
+                        .all(|(field, value)| self.matches(env, field, value))
+            }
+        }
+    }
+
+    fn block(
+        &mut self,
+        block: &'a Block<'src>,
+        env: &Env<'a, 'src>,
+        context: &Context<'src>,
+    ) -> Eval<'a, 'src> {
+        self.statements(block, &Scope::new(Some(env.clone())), context)
+    }
+
+    /// Runs a block's statements and tail in `env` itself.
+    fn statements(
+        &mut self,
+        block: &'a Block<'src>,
+        env: &Env<'a, 'src>,
+        context: &Context<'src>,
+    ) -> Eval<'a, 'src> {
+        for statement in &block.statements {
+            match statement {
+                Statement::Let(statement) => {
+                    let value = self.expression(&statement.value, env, context)?;
+                    self.bind(env, &statement.pattern, value, statement.value.span)?;
+                }
+                Statement::Assign(assignment) => {
+                    let value = self.expression(&assignment.value, env, context)?;
+                    env.set(assignment.name, value)?;
+                }
+                Statement::Expression(expression) => {
+                    self.expression(expression, env, context)?;
+                }
+            }
+        }
+
+        match &block.tail {
+            Some(tail) => self.expression(tail, env, context),
+            None => Ok(Value::Unit),
+        }
+    }
+
+    fn expression(
+        &mut self,
+        expression: &'a Expression<'src>,
+        env: &Env<'a, 'src>,
+        context: &Context<'src>,
+    ) -> Eval<'a, 'src> {
+        match &expression.kind {
+            ExpressionKind::Literal(literal) => Ok(match literal {
+                Literal::Integer(it) => Value::Integer(*it),
+                Literal::Float(it) => Value::Float(*it),
+                Literal::Character(it) => Value::Character(*it),
+                Literal::String(it) => Value::String(Rc::from(it.as_ref())),
+            }),
+            ExpressionKind::Name(name) => Ok(self.name(*name, env)?),
+            ExpressionKind::Call(call) => {
+                let arguments = self.arguments(None, call, env, context)?;
+                match env.get(call.name.name) {
+                    Some(callee) => self.call_value(callee, call.name, arguments, expression.span),
+                    None => self.call_named(
+                        Receiver::None,
+                        call.name,
+                        arguments,
+                        env,
+                        context,
+                        expression.span,
+                    ),
+                }
+            }
+            ExpressionKind::Property { receiver, name } => {
+                if self.is_namespace(receiver, env) {
+                    return Ok(self.name(*name, env)?);
+                }
+
+                // `x.f` is a chain call without arguments
+                let receiver = self.expression(receiver, env, context)?;
+                let arguments = vec![(None, Argument::Value(receiver))];
+                self.call_named(
+                    Receiver::Value,
+                    *name,
+                    arguments,
+                    env,
+                    context,
+                    expression.span,
+                )
+            }
+            ExpressionKind::Chain { receiver, call } => {
+                let (through, receiver) = match &receiver.kind {
+                    ExpressionKind::Name(name) | ExpressionKind::Property { name, .. }
+                        if self.is_namespace(receiver, env) =>
+                    {
+                        (Receiver::Namespace(name.name), None)
+                    }
+                    _ => {
+                        let value = self.expression(receiver, env, context)?;
+                        (Receiver::Value, Some(value))
+                    }
+                };
+                let arguments = self.arguments(receiver, call, env, context)?;
+                self.call_named(through, call.name, arguments, env, context, expression.span)
+            }
+            ExpressionKind::Match { scrutinee, clauses } => {
+                let value = self.expression(scrutinee, env, context)?;
+
+                for clause in clauses {
+                    let scope = Scope::new(Some(env.clone()));
+                    if self.matches(&scope, &clause.pattern, &value) {
+                        return self.expression(&clause.body, &scope, context);
+                    }
+                }
+
+                Err(RuntimeError::NoMatch {
+                    value: value.to_string(),
+                    span: scrutinee.span,
+                }
+                .into())
+            }
+            ExpressionKind::Loop(body) => {
+                self.frames += 1;
+                let frame = self.frames;
+                let context = context.with(Target::Loop { frame });
+
+                loop {
+                    match self.block(body, env, &context) {
+                        Ok(_) => {}
+                        Err(Unwind::Return {
+                            frame: target,
+                            value,
+                            ..
+                        }) if target == frame => return Ok(value),
+                        Err(unwind) => return Err(unwind),
+                    }
+                }
+            }
+            ExpressionKind::Block(block) => self.block(block, env, context),
+            ExpressionKind::Return { label, value } => {
+                let value = match value {
+                    Some(value) => self.expression(value, env, context)?,
+                    None => Value::Unit,
+                };
+                let frame = context.frame(*label).ok_or(RuntimeError::NoReturnTarget {
+                    span: expression.span,
+                })?;
+
+                Err(Unwind::Return {
+                    frame,
+                    value,
+                    span: expression.span,
+                })
+            }
+        }
+    }
+
+    /// Whether an expression is a path to an enum or module rather than a
+    /// value, like `Optional` in `Optional.from(1)`.
+    fn is_namespace(&self, expression: &Expression<'src>, env: &Env<'a, 'src>) -> bool {
+        match &expression.kind {
+            ExpressionKind::Name(name) => {
+                self.namespaces.contains(name.name) && env.get(name.name).is_none()
+            }
+            ExpressionKind::Property { receiver, name } => {
+                self.namespaces.contains(name.name) && self.is_namespace(receiver, env)
+            }
+            _ => false,
+        }
+    }
+
+    fn name(
+        &self,
+        name: Ident<'src>,
+        env: &Env<'a, 'src>,
+    ) -> Result<Value<'a, 'src>, RuntimeError<'src>> {
+        if let Some(value) = env.get(name.name) {
+            return Ok(value);
+        }
+
+        match self.variants.get(name.name) {
+            Some((enumeration, 0)) => Ok(Value::Variant(Rc::new(Variant {
+                enumeration,
+                name: name.name,
+                fields: Vec::new(),
+            }))),
+            Some(_) => Ok(Value::Function(name.name)),
+            None if self.functions.contains_key(name.name) => Ok(Value::Function(name.name)),
+            // Unit is built in until structs are
+            None if name.name == "Unit" => Ok(Value::Unit),
+            None => Err(RuntimeError::Unbound {
+                name: name.name,
+                span: name.span,
+            }),
+        }
+    }
+
+    /// Evaluates a call's arguments, after the receiver of a chain. Blocks
+    /// become closures once it's known what they're passed to.
+    fn arguments(
+        &mut self,
+        receiver: Option<Value<'a, 'src>>,
+        call: &'a Call<'src>,
+        env: &Env<'a, 'src>,
+        context: &Context<'src>,
+    ) -> Result<Vec<(Option<Ident<'src>>, Argument<'a, 'src>)>, Unwind<'a, 'src>> {
+        let mut arguments: Vec<_> = receiver
+            .map(|receiver| (None, Argument::Value(receiver)))
+            .into_iter()
+            .collect();
+
+        for argument in &call.arguments {
+            let value = self.expression(&argument.value, env, context)?;
+            arguments.push((argument.label, Argument::Value(value)));
+        }
+        for argument in &call.block_arguments {
+            arguments.push((argument.label, Argument::Block(&argument.block)));
+        }
+
+        Ok(arguments)
+    }
+
+    /// Calls a function, variant constructor or builtin by name.
+    fn call_named(
+        &mut self,
+        receiver: Receiver<'src>,
+        name: Ident<'src>,
+        arguments: Vec<(Option<Ident<'src>>, Argument<'a, 'src>)>,
+        env: &Env<'a, 'src>,
+        context: &Context<'src>,
+        span: Span,
+    ) -> Eval<'a, 'src> {
+        if let Some(method) = self.dispatch(receiver, name, &arguments, context, span)? {
+            let arguments = self.match_arguments(method.function, arguments, env, context, span)?;
+            return self.invoke(method, arguments, span);
+        }
+
+        let values = self.values(name, arguments, env, context)?;
+
+        if let Some(&(enumeration, fields)) = self.variants.get(name.name) {
+            if values.len() != fields {
+                return Err(RuntimeError::Arguments {
+                    name: name.name,
+                    message: format!("takes {} fields, found {}", fields, values.len()),
+                    span,
+                }
+                .into());
+            }
+            return Ok(Value::Variant(Rc::new(Variant {
+                enumeration,
+                name: name.name,
+                fields: values,
+            })));
+        }
+
+        match builtin::call(name.name, &values, self.out) {
+            Some(Outcome::Value(value)) => Ok(value),
+            Some(Outcome::Invalid(message)) => Err(RuntimeError::Builtin {
+                name: name.name,
+                message,
+                span,
+            }
+            .into()),
+            Some(Outcome::Output(error)) => Err(RuntimeError::Output(error).into()),
+            None => Err(RuntimeError::Unbound {
+                name: name.name,
+                span: name.span,
+            }
+            .into()),
+        }
+    }
+
+    /// Picks which of the functions called `name` a call goes to, preferring
+    /// those declared in the type of the receiver, or of the function being
+    /// run if there isn't one. Going through a namespace only looks at the
+    /// functions declared in it, if there are any.
+    fn dispatch(
+        &self,
+        receiver: Receiver<'src>,
+        name: Ident<'src>,
+        arguments: &[(Option<Ident<'src>>, Argument<'a, 'src>)],
+        context: &Context<'src>,
+        span: Span,
+    ) -> Result<Option<Method<'a, 'src>>, RuntimeError<'src>> {
+        let declared: &[Method] = match self.functions.get(name.name) {
+            Some(methods) => methods,
+            None => return Ok(None),
+        };
+        let mut methods: Vec<_> = match receiver {
+            Receiver::Namespace(namespace) => declared
+                .iter()
+                .filter(|method| method.namespace == Some(namespace))
+                .copied()
+                .collect(),
+            Receiver::None | Receiver::Value => Vec::new(),
+        };
+        if methods.is_empty() {
+            methods = declared.to_vec();
+        }
+
+        let head = match (receiver, arguments.first()) {
+            (Receiver::Value, Some((None, Argument::Value(value)))) => value.type_name(),
+            (Receiver::Value, _) => None,
+            (Receiver::None, _) | (Receiver::Namespace(_), _) => context.owner,
+        };
+        let labels: Vec<_> = arguments.iter().map(|(label, _)| *label).collect();
+        let fitting = methods.iter().filter_map(|method| {
+            let parameters: Vec<_> = method
+                .function
+                .parameters
+                .iter()
+                .map(Parameter::argument_label)
+                .collect();
+            let assignment = overload::assign(&parameters, &labels);
+            let fits = assignment.fits() && self.accepts(method, &assignment, arguments);
+            fits.then(|| (*method, Rank::of(method.owner, head)))
+        });
+
+        match overload::choose(fitting) {
+            Choice::One(method) => Ok(Some(method)),
+            Choice::Ambiguous(methods) => Err(RuntimeError::Ambiguous {
+                name: name.name,
+                candidates: methods.iter().map(|it| it.function.name.span).collect(),
+                span,
+            }),
+            // The arguments are reported against the only one there is
+            Choice::None if methods.len() == 1 => Ok(Some(methods[0])),
+            Choice::None => Err(RuntimeError::NoOverload {
+                name: name.name,
+                candidates: methods.iter().map(|it| it.function.name.span).collect(),
+                span,
+            }),
+        }
+    }
+
+    /// Whether the values going to a function are instances of the types
+    /// its parameters are declared as. Generics and blocks take anything.
+    fn accepts(
+        &self,
+        method: &Method<'a, 'src>,
+        assignment: &Assignment<'src>,
+        arguments: &[(Option<Ident<'src>>, Argument<'a, 'src>)],
+    ) -> bool {
+        let parameters = &method.function.parameters;
+        assignment
+            .slots
+            .iter()
+            .zip(parameters)
+            .all(|(slot, parameter)| {
+                let value = match slot.map(|it| &arguments[it].1) {
+                    Some(Argument::Value(value)) => value,
+                    _ => return true,
+                };
+                let ty = match &parameter.ty.kind {
+                    TypeKind::Named { name, .. } if name.name == "Self" => method.owner,
+                    TypeKind::Named { name, .. } => Some(name.name),
+                    TypeKind::Block { .. } => None,
+                };
+                match ty {
+                    Some(ty) if self.types.contains(ty) => value.type_name() == Some(ty),
+                    _ => true,
+                }
+            })
+    }
+
+    /// Matches arguments to a function's parameters. Labelled arguments go
+    /// to the parameter with that label, or that name if it has no label,
+    /// and the rest fill the other parameters in order.
+    fn match_arguments(
+        &self,
+        function: &'a Function<'src>,
+        arguments: Vec<(Option<Ident<'src>>, Argument<'a, 'src>)>,
+        env: &Env<'a, 'src>,
+        context: &Context<'src>,
+        span: Span,
+    ) -> Result<Vec<Value<'a, 'src>>, RuntimeError<'src>> {
+        let name = function.name.name;
+        let parameters = &function.parameters;
+        let labels: Vec<_> = parameters.iter().map(Parameter::argument_label).collect();
+        let assignment = overload::assign(
+            &labels,
+            &arguments
+                .iter()
+                .map(|(label, _)| *label)
+                .collect::<Vec<_>>(),
+        );
+
+        if let Some((_, label)) = assignment.unknown.first() {
+            return Err(RuntimeError::Arguments {
+                name,
+                message: format!("no parameter labelled `{}` left", label.name),
+                span: label.span,
+            });
+        }
+        if !assignment.extra.is_empty() {
+            return Err(RuntimeError::Arguments {
+                name,
+                message: format!(
+                    "takes {} arguments, found {}",
+                    parameters.len(),
+                    arguments.len()
+                ),
+                span,
+            });
+        }
+
+        let mut arguments: Vec<_> = arguments
+            .into_iter()
+            .map(|(_, argument)| Some(argument))
+            .collect();
+        parameters
+            .iter()
+            .zip(assignment.slots)
+            .enumerate()
+            .map(
+                |(index, (parameter, slot))| match slot.and_then(|it| arguments[it].take()) {
+                    Some(Argument::Value(value)) => Ok(value),
+                    Some(Argument::Block(block)) => Ok(closure(block, &parameter.ty, env, context)),
+                    None => Err(RuntimeError::Arguments {
+                        name,
+                        message: match parameter.argument_label() {
+                            Some(label) => format!("missing argument `{}`", label),
+                            None => format!("missing argument {}", index + 1),
+                        },
+                        span,
+                    }),
+                },
+            )
+            .collect()
+    }
+
+    /// The values of arguments to something without declared parameters.
+    fn values(
+        &self,
+        name: Ident<'src>,
+        arguments: Vec<(Option<Ident<'src>>, Argument<'a, 'src>)>,
+        env: &Env<'a, 'src>,
+        context: &Context<'src>,
+    ) -> Result<Vec<Value<'a, 'src>>, RuntimeError<'src>> {
+        arguments
+            .into_iter()
+            .map(|(label, argument)| match (label, argument) {
+                (Some(label), _) => Err(RuntimeError::Arguments {
+                    name: name.name,
+                    message: format!("`{}` doesn't take labelled arguments", name.name),
+                    span: label.span,
+                }),
+                (None, Argument::Value(value)) => Ok(value),
+                (None, Argument::Block(block)) => Ok(Value::Closure(Rc::new(Closure {
+                    block,
+                    parameters: vec!["it"],
+                    env: env.clone(),
+                    context: context.clone(),
+                }))),
+            })
+            .collect()
+    }
+
+    /// Calls a local, which is a block or function.
+    fn call_value(
+        &mut self,
+        callee: Value<'a, 'src>,
+        name: Ident<'src>,
+        arguments: Vec<(Option<Ident<'src>>, Argument<'a, 'src>)>,
+        span: Span,
+    ) -> Eval<'a, 'src> {
+        match callee {
+            Value::Closure(closure) => {
+                let env = Scope::new(Some(closure.env.clone()));
+                let values = self.values(name, arguments, &env, &closure.context)?;
+                if values.len() > closure.parameters.len() {
+                    return Err(RuntimeError::Arguments {
+                        name: name.name,
+                        message: format!(
+                            "block takes {} arguments, found {}",
+                            closure.parameters.len(),
+                            values.len()
+                        ),
+                        span,
+                    }
+                    .into());
+                }
+
+                for (parameter, value) in closure.parameters.iter().zip(values) {
+                    let name = Ident {
+                        name: parameter,
+                        span,
+                    };
+                    env.define(name, false, value);
+                }
+                self.block(closure.block, &env, &closure.context)
+            }
+            Value::Function(function) => {
+                let name = Ident {
+                    name: function,
+                    span: name.span,
+                };
+                let env = Scope::new(None);
+                let context = Context::default();
+                self.call_named(Receiver::None, name, arguments, &env, &context, span)
+            }
+            value => Err(RuntimeError::NotCallable {
+                value: value.to_string(),
+                span,
+            }
+            .into()),
+        }
+    }
+}
+
+/// A block passed to a parameter of type `ty`, which names the values it's
+/// called with. A single unnamed value is called `it`.
+fn closure<'a, 'src>(
+    block: &'a Block<'src>,
+    ty: &Type<'src>,
+    env: &Env<'a, 'src>,
+    context: &Context<'src>,
+) -> Value<'a, 'src> {
+    let parameters = match &ty.kind {
+        TypeKind::Block { parameters, .. } if parameters.len() == 1 => {
+            vec![parameters[0].label.map_or("it", |label| label.name)]
+        }
+        TypeKind::Block { parameters, .. } => parameters
+            .iter()
+            .map(|parameter| parameter.label.map_or("_", |label| label.name))
+            .collect(),
+        TypeKind::Named { .. } => vec!["it"],
+    };
+
+    Value::Closure(Rc::new(Closure {
+        block,
+        parameters,
+        env: env.clone(),
+        context: context.clone(),
+    }))
+}
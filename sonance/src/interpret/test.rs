@@ -0,0 +1,152 @@
+use super::*;
+use crate::fixture::{parse, program, render};
+
+/// Runs `main` with the core dreams, returning what it printed or the
+/// rendered error.
+fn run_main(main: &str) -> Result<String, String> {
+    let map = program(main);
+    let units = parse(&map);
+
+    let mut out = Vec::new();
+    match run(&units, &mut out) {
+        Ok(()) => Ok(String::from_utf8(out).unwrap()),
+        Err(error) => Err(render(&map, [error])),
+    }
+}
+
+#[test]
+fn core_data() {
+    let output = run_main(
+        "func main() {
+    print_line(True.is_false);
+    print_line(True.then { 1 }.else { 0 });
+    print_line(False.then { 2 }.else { 0 });
+    print_line(Nothing.okay_or_else { \"none\" });
+    print_line(Optional.from('x').okay_or_else { \"none\" }.okay);
+};",
+    );
+
+    assert_eq!(output.as_deref(), Ok("False\n1\n0\nError(none)\nSome(x)\n"));
+}
+
+#[test]
+fn labels_and_blocks() {
+    let output = run_main(
+        "func count(from mut start: Int, to end: Int, body: block(index: Int) -> Unit) {
+    loop {
+        match(start.less_than(end)) {
+            True -> body(start),
+            False -> return@loop,
+        };
+        start = start.add(1);
+    };
+};
+
+func main() {
+    count(to: 3, from: 1) { print(index) };
+    let mut total = 0;
+    count(from: 0, to: 4) { total = total.add(index) };
+    print_line(total);
+};",
+    );
+
+    assert_eq!(output.as_deref(), Ok("126\n"));
+}
+
+#[test]
+fn returns() {
+    let output = run_main(
+        "func each(body: block(it: Int) -> Unit) {
+    body(1);
+    body(2);
+    body(3);
+};
+
+func find(target: Int) -> Optional<Int> {
+    each { match(it.equals(target)) { True -> return Some(it), _ -> Unit } };
+    Nothing
+};
+
+func main() {
+    print_line(find(2));
+    print_line(find(5));
+    let mut i = 0;
+    while { i.less_than(2) } { i = i.add(1) };
+    print_line(i);
+};",
+    );
+
+    assert_eq!(output.as_deref(), Ok("Some(2)\nNothing\n2\n"));
+}
+
+#[test]
+fn errors() {
+    assert_eq!(
+        run_main("func main() {\n    let x = 1;\n    x = 2;\n};\n"),
+        Err("error: Cannot assign twice to `x`
+ --> main.son:3:5
+  |
+2 |     let x = 1;
+  |         - bound here
+3 |     x = 2;
+  |     ^ assigned here
+  |
+  = help: make it mutable with `let mut x`
+"
+        .to_owned())
+    );
+
+    assert_eq!(
+        run_main("func main() { match(Some(1)) { Nothing -> 0 } };"),
+        Err("error: No pattern matches `Some(1)`
+ --> main.son:1:21
+  |
+1 | func main() { match(Some(1)) { Nothing -> 0 } };
+  |                     ^^^^^^^
+  |
+  = help: add a clause with a `_` pattern to catch the rest
+"
+        .to_owned())
+    );
+
+    assert!(run_main("func helper() {};").is_err());
+}
+
+#[test]
+fn overloads() {
+    let functions = "func scope<T>(body: block() -> T) -> T { body() };
+func scope<Self, T>(self: Self, body: block(it: Self) -> T) -> T { body(self) };
+func describe(self: Int) -> String { \"int\" };
+func describe(self: String) -> String { \"string\" };
+func range(from start: Int, to end: Int) -> Int { end.subtract(start) };
+func range(to end: Int) -> Int { end };
+";
+
+    let output = run_main(&format!(
+        "{}func main() {{
+    print_line(scope {{ 1 }});
+    print_line(2.scope {{ it.add(1) }});
+    print_line(\"a\".describe);
+    print_line(describe(1));
+    print_line(range(to: 5).add(range(from: 1, to: 3)));
+}};",
+        functions
+    ));
+    assert_eq!(output.as_deref(), Ok("1\n3\nstring\nint\n7\n"));
+
+    assert_eq!(
+        run_main(&format!("{}func main() {{ 'c'.describe; }};", functions)),
+        Err("error: No `describe` takes these arguments
+ --> main.son:7:15
+  |
+3 | func describe(self: Int) -> String { \"int\" };
+  |      -------- candidate
+4 | func describe(self: String) -> String { \"string\" };
+  |      -------- candidate
+...
+7 | func main() { 'c'.describe; };
+  |               ^^^^^^^^^^^^
+"
+        .to_owned())
+    );
+}
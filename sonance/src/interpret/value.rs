@@ -0,0 +1,89 @@
+use super::{Context, Env};
+use crate::parser::ast::Block;
+use std::{fmt, rc::Rc};
+
+#[derive(Debug, Clone)]
+pub enum Value<'a, 'src> {
+    Integer(i64),
+    Float(f64),
+    Character(char),
+    String(Rc<str>),
+    Variant(Rc<Variant<'a, 'src>>),
+    /// A function, variant constructor or builtin, by name. Which one is
+    /// called depends on the arguments.
+    Function(&'src str),
+    Closure(Rc<Closure<'a, 'src>>),
+    Unit,
+}
+
+#[derive(Debug, Clone)]
+pub struct Variant<'a, 'src> {
+    pub enumeration: &'src str,
+    pub name: &'src str,
+    pub fields: Vec<Value<'a, 'src>>,
+}
+
+/// A block argument, with the scope it was written in.
+#[derive(Debug, Clone)]
+pub struct Closure<'a, 'src> {
+    pub block: &'a Block<'src>,
+    /// The names of the values it's called with, from the type of the
+    /// parameter it was passed to.
+    pub parameters: Vec<&'src str>,
+    pub(super) env: Env<'a, 'src>,
+    pub(super) context: Context<'src>,
+}
+
+impl<'a, 'src> Value<'a, 'src> {
+    /// A variant of the core `Boolean`, for builtins that answer yes or no.
+    pub fn boolean(value: bool) -> Self {
+        Value::Variant(Rc::new(Variant {
+            enumeration: "Boolean",
+            name: if value { "True" } else { "False" },
+            fields: Vec::new(),
+        }))
+    }
+
+    /// The name of the value's type, for picking between functions of the
+    /// same name. Blocks and functions don't have one.
+    pub fn type_name(&self) -> Option<&'src str> {
+        match self {
+            Value::Integer(_) => Some("Int"),
+            Value::Float(_) => Some("Float"),
+            Value::Character(_) => Some("Char"),
+            Value::String(_) => Some("String"),
+            Value::Variant(variant) => Some(variant.enumeration),
+            Value::Unit => Some("Unit"),
+            Value::Function(_) | Value::Closure(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Value<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Integer(it) => write!(f, "{}", it),
+            Value::Float(it) => write!(f, "{:?}", it),
+            Value::Character(it) => write!(f, "{}", it),
+            Value::String(it) => write!(f, "{}", it),
+            Value::Variant(variant) => {
+                write!(f, "{}", variant.name)?;
+                if variant.fields.is_empty() {
+                    return Ok(());
+                }
+
+                write!(f, "(")?;
+                for (index, field) in variant.fields.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", field)?;
+                }
+                write!(f, ")")
+            }
+            Value::Function(name) => write!(f, "<func {}>", name),
+            Value::Closure(_) => write!(f, "<block>"),
+            Value::Unit => write!(f, "Unit"),
+        }
+    }
+}
@@ -1,24 +1,139 @@
+pub mod bytecode;
 pub mod error;
+#[cfg(test)]
+mod fixture;
+pub mod format;
+pub mod interpret;
+pub mod lsp;
 pub mod parser;
+pub mod repl;
+pub mod resolve;
+pub mod syntax;
 pub mod token;
+pub mod types;
 
-pub fn run(source: &str) {
-    let reporter = error::Reporter::new(source);
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
-    for token in token::scan(source) {
-        match token {
-            Ok(token) => println!("{:?}", token),
-            Err(err) => reporter.report(&[err.into()]),
+/// How errors are written to stderr.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorFormat {
+    /// Rendered snippets of the source.
+    Human,
+    /// One JSON object per line, shaped like an LSP diagnostic.
+    Json,
+}
+
+impl FromStr for ErrorFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(Self::Human),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("unknown error format `{}`", s)),
         }
     }
+}
 
-    // let parsed = parser::parse(tokens.into_iter());
+/// Resolves and type checks every file under `directory`, reporting errors
+/// from any of them. Returns whether there weren't any.
+pub fn check(directory: &Path, format: ErrorFormat) -> io::Result<bool> {
+    let mut map = error::SourceMap::new();
+    resolve::read_dir(&mut map, directory)?;
 
-    // match parsed {
-    //     Ok(parsed) => println!("{:?}", parsed),
-    //     Err(errors) => {
-    //         let errors: Vec<_> = errors.into_iter().map(Into::into).collect();
-    //         error::Reporter::new(source).report(&errors);
-    //     }
-    // }
+    let (units, mut errors) = resolve::parse(&map);
+    let resolution = resolve::resolve(&units);
+    errors.extend(resolution.errors.into_iter().map(error::Citation::from));
+    errors.extend(types::check(&units).into_iter().map(error::Citation::from));
+
+    report(&map, format, &errors);
+    Ok(errors.is_empty())
+}
+
+/// Writes errors from files in the map to stderr.
+fn report(map: &error::SourceMap, format: ErrorFormat, errors: &[error::Citation]) {
+    let stderr = &mut io::stderr();
+    match format {
+        ErrorFormat::Human => map.report(stderr, errors),
+        ErrorFormat::Json => map.report_json(stderr, errors),
+    }
+    .expect("couldn't write errors to stderr");
+}
+
+/// Runs the `main` function of a program made of every file in the map.
+/// Any file failing to parse stops the program from running. Returns
+/// whether it ran without errors.
+pub fn run(map: &error::SourceMap, format: ErrorFormat) -> bool {
+    let (units, errors) = resolve::parse(map);
+    if !errors.is_empty() {
+        report(map, format, &errors);
+        return false;
+    }
+
+    let stdout = io::stdout();
+    match interpret::run(&units, &mut stdout.lock()) {
+        Ok(()) => true,
+        Err(error) => {
+            report(map, format, &[error.into()]);
+            false
+        }
+    }
+}
+
+/// Builds the `main` function of a program made of every file in the map
+/// into a memory image for `sonance-vm`, or reports why it can't be.
+pub fn build(map: &error::SourceMap, format: ErrorFormat) -> Option<Vec<u8>> {
+    let (units, errors) = resolve::parse(map);
+    if !errors.is_empty() {
+        report(map, format, &errors);
+        return None;
+    }
+
+    match bytecode::build(&units) {
+        Ok(image) => Some(image),
+        Err(error) => {
+            report(map, format, &[error.into()]);
+            None
+        }
+    }
+}
+
+/// Formats every `.son` file at or under each of `paths` in place, or with
+/// `check` only lists the ones that would change. Returns whether they were
+/// all formatted already.
+pub fn format(paths: &[PathBuf], check: bool) -> io::Result<bool> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut map = error::SourceMap::new();
+            resolve::read_dir(&mut map, path)?;
+            files.extend(
+                map.files()
+                    .iter()
+                    .map(|file| (path.join(&file.name), file.source.clone())),
+            );
+        } else {
+            files.push((path.clone(), fs::read_to_string(path)?));
+        }
+    }
+
+    let mut formatted = true;
+    for (path, source) in files {
+        let output = format::format(&source);
+        if output == source {
+            continue;
+        }
+
+        formatted = false;
+        if check {
+            println!("{}", path.display());
+        } else {
+            fs::write(&path, output)?;
+        }
+    }
+    Ok(formatted)
 }
@@ -0,0 +1,259 @@
+//! What the server knows about one document, rebuilt from its text on every
+//! request.
+
+use crate::{
+    error::{
+        json::{Diagnostic, Range},
+        Citation, Reporter, Span,
+    },
+    parser::{self, ast::*},
+    resolve::{self, Unit},
+    token::{self, Token, TokenKind},
+};
+use serde::Serialize;
+
+pub struct Analysis<'src> {
+    pub source: &'src str,
+    pub reporter: Reporter<'src>,
+    /// Every token that scanned, including doc comments.
+    pub tokens: Vec<Token<'src>>,
+    /// The items that parsed, even if others didn't, as the only file of
+    /// the root module.
+    pub unit: Unit<'src>,
+    pub citations: Vec<Citation>,
+}
+
+impl<'src> Analysis<'src> {
+    pub fn new(source: &'src str) -> Self {
+        let mut tokens = Vec::new();
+        let mut citations = Vec::new();
+
+        for token in token::scan(source) {
+            match token {
+                Ok(token) => tokens.push(token),
+                Err(error) => citations.push(error.into()),
+            }
+        }
+
+        let (file, errors) = parser::parse_recovering(tokens.iter().cloned());
+
+        // Parsing around tokens that didn't scan mostly cascades, like `run` does
+        if citations.is_empty() {
+            citations.extend(errors.into_iter().map(Into::into));
+        }
+
+        Self {
+            source,
+            reporter: Reporter::new(source),
+            tokens,
+            unit: Unit {
+                directory: Vec::new(),
+                file,
+            },
+            citations,
+        }
+    }
+
+    pub fn diagnostics(&self, uri: &str) -> Vec<Diagnostic> {
+        self.citations
+            .iter()
+            .map(|citation| self.reporter.diagnostic(uri, citation))
+            .collect()
+    }
+
+    /// Semantic tokens in the protocol's relative encoding, five numbers to a
+    /// token. Tokens spanning lines are split, since not every client can
+    /// show them otherwise.
+    pub fn semantic_tokens(&self) -> Vec<usize> {
+        let mut data = Vec::new();
+        let (mut previous_line, mut previous_start) = (0, 0);
+
+        for token in &self.tokens {
+            let kind = match semantic_kind(&token.kind) {
+                Some(kind) => kind,
+                None => continue,
+            };
+
+            let range = self.reporter.range(token.span);
+            let text = &self.source[token.span.start..self.reporter.offset(range.end)];
+            let start = range.start;
+
+            for (index, line) in text.split('\n').enumerate() {
+                let line = line.trim_end_matches('\r');
+                let length = line.encode_utf16().count();
+                if length == 0 {
+                    continue;
+                }
+
+                let (line, character) = match index {
+                    0 => (start.line, start.character),
+                    _ => (start.line + index, 0),
+                };
+
+                let delta_start = if line == previous_line {
+                    character - previous_start
+                } else {
+                    character
+                };
+                data.extend([line - previous_line, delta_start, length, kind as usize, 0]);
+
+                previous_line = line;
+                previous_start = character;
+            }
+        }
+
+        data
+    }
+
+    /// Where the name at `index` was declared, if it's in this file.
+    pub fn definition(&self, index: usize) -> Option<Span> {
+        resolve::resolve(std::slice::from_ref(&self.unit)).definition(index)
+    }
+
+    pub fn symbols(&self) -> Vec<DocumentSymbol> {
+        self.unit
+            .file
+            .items
+            .iter()
+            .filter_map(|item| {
+                Some(match item {
+                    Item::Function(function) => {
+                        self.symbol(function.name, SymbolKind::Function, function.span)
+                    }
+                    Item::Enum(enumeration) => DocumentSymbol {
+                        children: enumeration
+                            .variants
+                            .iter()
+                            .map(|variant| {
+                                let span =
+                                    variant.fields.last().map_or(variant.name.span, |field| {
+                                        variant.name.span.to(field.ty.span)
+                                    });
+                                self.symbol(variant.name, SymbolKind::EnumMember, span)
+                            })
+                            .chain(enumeration.functions.iter().map(|function| {
+                                self.symbol(function.name, SymbolKind::Method, function.span)
+                            }))
+                            .collect(),
+                        ..self.symbol(enumeration.name, SymbolKind::Enum, enumeration.span)
+                    },
+                    Item::Trait(trait_item) => DocumentSymbol {
+                        children: trait_item
+                            .functions
+                            .iter()
+                            .map(|function| {
+                                self.symbol(function.name, SymbolKind::Method, function.span)
+                            })
+                            .collect(),
+                        ..self.symbol(trait_item.name, SymbolKind::Interface, trait_item.span)
+                    },
+                    Item::Module(module) => {
+                        self.symbol(module.name, SymbolKind::Module, module.span)
+                    }
+                    Item::Extension(extension) => DocumentSymbol {
+                        children: extension
+                            .functions
+                            .iter()
+                            .map(|function| {
+                                self.symbol(function.name, SymbolKind::Method, function.span)
+                            })
+                            .collect(),
+                        ..self.symbol(extension.name, SymbolKind::Module, extension.span)
+                    },
+                    Item::Import(_) => return None,
+                })
+            })
+            .collect()
+    }
+
+    fn symbol(&self, name: Ident, kind: SymbolKind, span: Span) -> DocumentSymbol {
+        DocumentSymbol {
+            name: name.name.to_owned(),
+            kind: kind as u8,
+            range: self.reporter.range(span),
+            selection_range: self.reporter.range(name.span),
+            children: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentSymbol {
+    pub name: String,
+    pub kind: u8,
+    pub range: Range,
+    pub selection_range: Range,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<DocumentSymbol>,
+}
+
+/// The subset of the protocol's symbol kinds that items use.
+#[derive(Debug, Clone, Copy)]
+enum SymbolKind {
+    Module = 2,
+    Method = 6,
+    Enum = 10,
+    Interface = 11,
+    Function = 12,
+    EnumMember = 22,
+}
+
+/// Indices into `SEMANTIC_TOKEN_TYPES`.
+#[derive(Debug, Clone, Copy)]
+enum SemanticKind {
+    Keyword,
+    Number,
+    String,
+    Comment,
+    Operator,
+    Variable,
+}
+
+/// The legend sent to the client, in the order of `SemanticKind`.
+pub const SEMANTIC_TOKEN_TYPES: [&str; 6] = [
+    "keyword", "number", "string", "comment", "operator", "variable",
+];
+
+/// Punctuation is left to the editor's own highlighting.
+fn semantic_kind(kind: &TokenKind) -> Option<SemanticKind> {
+    Some(match kind {
+        TokenKind::OpenParen
+        | TokenKind::CloseParen
+        | TokenKind::OpenBrace
+        | TokenKind::CloseBrace
+        | TokenKind::OpenAngle
+        | TokenKind::CloseAngle
+        | TokenKind::Comma
+        | TokenKind::Semicolon
+        | TokenKind::Colon
+        | TokenKind::DotSymbol => return None,
+        TokenKind::Equals
+        | TokenKind::DoubleEquals
+        | TokenKind::Bang
+        | TokenKind::BangEquals
+        | TokenKind::LessEquals
+        | TokenKind::GreaterEquals
+        | TokenKind::Plus
+        | TokenKind::PlusEquals
+        | TokenKind::Minus
+        | TokenKind::MinusEquals
+        | TokenKind::Arrow
+        | TokenKind::Star
+        | TokenKind::StarEquals
+        | TokenKind::Slash
+        | TokenKind::SlashEquals
+        | TokenKind::Percent
+        | TokenKind::PercentEquals
+        | TokenKind::Ampersand
+        | TokenKind::DoubleAmpersand
+        | TokenKind::Pipe
+        | TokenKind::DoublePipe
+        | TokenKind::AtSymbol => SemanticKind::Operator,
+        TokenKind::DocComment(_) => SemanticKind::Comment,
+        TokenKind::Integer(_) | TokenKind::Float(_) => SemanticKind::Number,
+        TokenKind::Character(_) | TokenKind::String(_) => SemanticKind::String,
+        TokenKind::Keyword(_) => SemanticKind::Keyword,
+        TokenKind::Identifier(_) => SemanticKind::Variable,
+    })
+}
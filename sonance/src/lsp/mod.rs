@@ -0,0 +1,262 @@
+//! A language server for `.son` files, spoken over stdio by `sonance lsp`.
+//!
+//! Documents are synced in full and re-analysed from their text on every
+//! request, which is plenty fast for files of this size.
+
+mod analysis;
+mod transport;
+
+pub use transport::{read_body, read_message, write_message};
+
+use crate::error::json::{Location, Position};
+use analysis::{Analysis, SEMANTIC_TOKEN_TYPES};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, Write},
+};
+
+/// Answers messages from `input` until the client asks to exit or the input
+/// ends.
+pub fn serve(mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    let mut server = Server {
+        output: &mut output,
+        documents: HashMap::new(),
+        shutting_down: false,
+    };
+
+    while let Some(body) = read_body(&mut input)? {
+        let message = match serde_json::from_slice(&body) {
+            Ok(message) => message,
+            // There's no telling what it was, so the reply has no id
+            Err(error) => {
+                let message = json!({
+                    "jsonrpc": "2.0",
+                    "id": null,
+                    "error": { "code": code::PARSE_ERROR, "message": error.to_string() },
+                });
+                write_message(server.output, &message)?;
+                continue;
+            }
+        };
+        if !server.handle(message)? {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+struct Server<'out, W: Write> {
+    output: &'out mut W,
+    /// The text of each open document, by uri.
+    documents: HashMap<String, String>,
+    shutting_down: bool,
+}
+
+/// JSON-RPC error codes.
+mod code {
+    pub const PARSE_ERROR: i64 = -32700;
+    pub const INVALID_REQUEST: i64 = -32600;
+    pub const METHOD_NOT_FOUND: i64 = -32601;
+    pub const INVALID_PARAMS: i64 = -32602;
+}
+
+type Response = Result<Value, (i64, String)>;
+
+impl<W: Write> Server<'_, W> {
+    /// Returns false once the client has asked to exit.
+    fn handle(&mut self, message: Value) -> io::Result<bool> {
+        let method = match message.get("method").and_then(Value::as_str) {
+            Some(method) => method,
+            // Responses to requests we never make
+            None => return Ok(true),
+        };
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        let id = match message.get("id") {
+            Some(id) => id.clone(),
+            None => {
+                if method == "exit" {
+                    return Ok(false);
+                }
+                self.notification(method, params)?;
+                return Ok(true);
+            }
+        };
+
+        let response = if self.shutting_down {
+            Err((
+                code::INVALID_REQUEST,
+                "the server is shutting down".to_owned(),
+            ))
+        } else {
+            self.request(method, params)
+        };
+
+        let message = match response {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message },
+            }),
+        };
+        write_message(self.output, &message)?;
+        Ok(true)
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Response {
+        match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "semanticTokensProvider": {
+                        "legend": { "tokenTypes": SEMANTIC_TOKEN_TYPES, "tokenModifiers": [] },
+                        "full": true,
+                    },
+                    "documentSymbolProvider": true,
+                    "definitionProvider": true,
+                },
+                "serverInfo": { "name": "sonance", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "shutdown" => {
+                self.shutting_down = true;
+                Ok(Value::Null)
+            }
+            "textDocument/semanticTokens/full" => {
+                let params: DocumentParams = parse_params(params)?;
+                let data = self.analyse(&params.text_document.uri, |it| it.semantic_tokens());
+                Ok(json!({ "data": data.unwrap_or_default() }))
+            }
+            "textDocument/documentSymbol" => {
+                let params: DocumentParams = parse_params(params)?;
+                let symbols = self.analyse(&params.text_document.uri, |it| it.symbols());
+                Ok(json!(symbols.unwrap_or_default()))
+            }
+            "textDocument/definition" => {
+                let params: PositionParams = parse_params(params)?;
+                let (uri, position) = (params.text_document.uri, params.position);
+
+                let location = self.analyse(&uri, |analysis| {
+                    let offset = analysis.reporter.offset(position);
+                    let span = analysis.definition(offset)?;
+                    Some(Location {
+                        uri: uri.clone(),
+                        range: analysis.reporter.range(span),
+                    })
+                });
+                Ok(json!(location.flatten()))
+            }
+            _ => Err((
+                code::METHOD_NOT_FOUND,
+                format!("unknown method `{}`", method),
+            )),
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: Value) -> io::Result<()> {
+        // Notifications can't be answered, so bad ones are ignored
+        match method {
+            "textDocument/didOpen" => {
+                if let Ok(params) = parse_params::<DidOpenParams>(params) {
+                    let uri = params.text_document.uri;
+                    self.documents
+                        .insert(uri.clone(), params.text_document.text);
+                    self.publish_diagnostics(&uri)?;
+                }
+            }
+            "textDocument/didChange" => {
+                if let Ok(params) = parse_params::<DidChangeParams>(params) {
+                    let uri = params.text_document.uri;
+                    // With full sync the last change holds the whole text
+                    if let Some(change) = params.content_changes.into_iter().last() {
+                        self.documents.insert(uri.clone(), change.text);
+                        self.publish_diagnostics(&uri)?;
+                    }
+                }
+            }
+            "textDocument/didClose" => {
+                if let Ok(params) = parse_params::<DocumentParams>(params) {
+                    let uri = params.text_document.uri;
+                    self.documents.remove(&uri);
+                    self.notify(
+                        "textDocument/publishDiagnostics",
+                        json!({ "uri": uri, "diagnostics": [] }),
+                    )?;
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn publish_diagnostics(&mut self, uri: &str) -> io::Result<()> {
+        let diagnostics = self.analyse(uri, |analysis| analysis.diagnostics(uri));
+        self.notify(
+            "textDocument/publishDiagnostics",
+            json!({ "uri": uri, "diagnostics": diagnostics.unwrap_or_default() }),
+        )
+    }
+
+    fn notify(&mut self, method: &str, params: Value) -> io::Result<()> {
+        write_message(
+            self.output,
+            &json!({ "jsonrpc": "2.0", "method": method, "params": params }),
+        )
+    }
+
+    /// Runs `f` on the open document at `uri`, if there is one.
+    fn analyse<T>(&self, uri: &str, f: impl FnOnce(&Analysis) -> T) -> Option<T> {
+        let source = self.documents.get(uri)?;
+        Some(f(&Analysis::new(source)))
+    }
+}
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, (i64, String)> {
+    serde_json::from_value(params).map_err(|error| (code::INVALID_PARAMS, error.to_string()))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DocumentParams {
+    text_document: DocumentIdentifier,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PositionParams {
+    text_document: DocumentIdentifier,
+    position: Position,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DidOpenParams {
+    text_document: DocumentItem,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DidChangeParams {
+    text_document: DocumentIdentifier,
+    content_changes: Vec<ContentChange>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DocumentIdentifier {
+    uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DocumentItem {
+    uri: String,
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ContentChange {
+    text: String,
+}
@@ -0,0 +1,61 @@
+//! The base protocol: JSON bodies behind `Content-Length` headers.
+
+use serde_json::Value;
+use std::io::{self, BufRead, Write};
+
+/// Reads the next message, or `None` at the end of the input.
+pub fn read_message(input: &mut impl BufRead) -> io::Result<Option<Value>> {
+    match read_body(input)? {
+        Some(body) => serde_json::from_slice(&body)
+            .map(Some)
+            .map_err(|error| invalid(&error.to_string())),
+        None => Ok(None),
+    }
+}
+
+/// Reads the body of the next message without parsing it, or `None` at the
+/// end of the input.
+pub fn read_body(input: &mut impl BufRead) -> io::Result<Option<Vec<u8>>> {
+    let mut length = None;
+
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return match length {
+                None => Ok(None),
+                Some(_) => Err(invalid("input ended inside a header")),
+            };
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+
+        // Other headers, like `Content-Type`, don't change anything
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                let value = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| invalid("bad content length"))?;
+                length = Some(value);
+            }
+        }
+    }
+
+    let length = length.ok_or_else(|| invalid("message has no content length"))?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    Ok(Some(body))
+}
+
+pub fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_owned())
+}
@@ -1,22 +1,45 @@
 use clap::Clap;
-use std::{
-    env, fs,
-    io::{self, Write},
-};
+use rustyline::{error::ReadlineError, DefaultEditor};
+use std::{env, fs, io, path::PathBuf, process};
 
 #[derive(Debug, Clone, Clap)]
 #[clap(version = "0.1.0", author = "jamesBeeProg <jamesBeeProg@gmail.com>")]
 struct Settings {
+    /// How errors are written to stderr.
+    #[clap(long, default_value = "human", possible_values = &["human", "json"])]
+    error_format: compiler::ErrorFormat,
     #[clap(subcommand)]
     sub: SubCommand,
 }
 
 #[derive(Debug, Clone, Clap)]
 enum SubCommand {
+    /// Run a program made of one or more files.
     File {
-        input: String,
+        #[clap(required = true)]
+        inputs: Vec<String>,
     },
+    /// Evaluate items, statements and `:` commands interactively.
     Repl,
+    /// Resolve and type check every `.son` file in a directory.
+    Check { directory: String },
+    /// Serve the language server protocol over stdio.
+    Lsp,
+    /// Build a program into a memory image for `sonance-vm`.
+    Build {
+        input: String,
+        /// Where to write the image.
+        #[clap(short, long, default_value = "out.hex")]
+        output: String,
+    },
+    /// Format `.son` files, or every one in a directory, in place.
+    Fmt {
+        #[clap(required = true)]
+        paths: Vec<String>,
+        /// List files that aren't formatted instead, failing if there are any.
+        #[clap(long)]
+        check: bool,
+    },
     ErrorTest {
         input: String,
         start: usize,
@@ -28,35 +51,110 @@ fn main() {
     let settings = Settings::parse();
 
     match settings.sub {
-        SubCommand::File { input } => run_file(input),
-        SubCommand::Repl => run_repl(),
+        SubCommand::File { inputs } => run_files(inputs, settings.error_format),
+        SubCommand::Repl => run_repl(settings.error_format),
+        SubCommand::Check { directory } => check(directory, settings.error_format),
+        SubCommand::Lsp => {
+            compiler::lsp::serve(io::stdin().lock(), io::stdout()).expect("couldn't talk to client")
+        }
+        SubCommand::Build { input, output } => build(input, output, settings.error_format),
+        SubCommand::Fmt { paths, check } => format(paths, check),
         SubCommand::ErrorTest { input, start, end } => run_error_test(input, start, end),
     }
 }
 
-fn run_file(input: String) {
+fn run_files(paths: Vec<String>, format: compiler::ErrorFormat) {
     let cwd = env::current_dir().expect("couldn't get current dir");
-    let input = fs::read_to_string(cwd.join(input)).expect("couldn't read source file");
-    println!();
-    compiler::run(&input);
+
+    let mut map = compiler::error::SourceMap::new();
+    for path in paths {
+        let input = fs::read_to_string(cwd.join(&path)).expect("couldn't read source file");
+        map.add(path, input);
+    }
+
+    if !compiler::run(&map, format) {
+        process::exit(1);
+    }
+}
+
+fn check(directory: String, format: compiler::ErrorFormat) {
+    let cwd = env::current_dir().expect("couldn't get current dir");
+    let checked =
+        compiler::check(&cwd.join(directory), format).expect("couldn't read source directory");
+    if !checked {
+        process::exit(1);
+    }
 }
 
-fn run_repl() {
+fn build(path: String, output: String, format: compiler::ErrorFormat) {
+    let cwd = env::current_dir().expect("couldn't get current dir");
+    let input = fs::read_to_string(cwd.join(&path)).expect("couldn't read source file");
+
+    let mut map = compiler::error::SourceMap::new();
+    map.add(path, input);
+
+    match compiler::build(&map, format) {
+        Some(image) => fs::write(cwd.join(output), image).expect("couldn't write output file"),
+        None => process::exit(1),
+    }
+}
+
+fn format(paths: Vec<String>, check: bool) {
+    let cwd = env::current_dir().expect("couldn't get current dir");
+    let paths: Vec<_> = paths.iter().map(|path| cwd.join(path)).collect();
+
+    let formatted = compiler::format(&paths, check).expect("couldn't format source files");
+    if check && !formatted {
+        process::exit(1);
+    }
+}
+
+fn run_repl(format: compiler::ErrorFormat) {
+    let mut editor = DefaultEditor::new().expect("couldn't open terminal");
+    let history = env::var_os("HOME").map(|home| PathBuf::from(home).join(".sonance_history"));
+    if let Some(history) = &history {
+        // There's no history the first time
+        let _ = editor.load_history(history);
+    }
+
+    let mut session = compiler::repl::Session::new(format);
+    let mut input = String::new();
     loop {
-        print!("> ");
-        io::stdout().flush().unwrap();
+        let prompt = if input.is_empty() { "> " } else { ". " };
+        match editor.readline(prompt) {
+            Ok(line) => {
+                input.push_str(&line);
+                input.push('\n');
+            }
+            Err(ReadlineError::Interrupted) => {
+                input.clear();
+                continue;
+            }
+            Err(_) => break,
+        }
+
+        if !compiler::repl::complete(&input) {
+            continue;
+        }
 
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).unwrap();
-        let input = input.trim();
+        let entry = input.trim().to_owned();
+        input.clear();
+        if entry.is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(entry.as_str());
 
-        if input == "//exit" {
+        if entry == ":quit" || entry == ":q" {
             break;
         }
 
-        println!();
-        compiler::run(input);
-        println!();
+        session
+            .eval(&entry, &mut io::stdout(), &mut io::stderr())
+            .expect("couldn't write to the terminal");
+    }
+
+    if let Some(history) = &history {
+        let _ = editor.save_history(history);
     }
 }
 
@@ -67,5 +165,9 @@ fn run_error_test(input: String, start: usize, end: usize) {
     use compiler::error::*;
 
     Reporter::new(&input)
-        .report(&[Citation::error("Test error".to_owned()).span(Span { start, end }, None)]);
+        .report(
+            &mut io::stderr(),
+            &[Citation::error("Test error".to_owned()).span(Span { start, end }, None)],
+        )
+        .expect("couldn't write errors to stderr");
 }
@@ -0,0 +1,343 @@
+use crate::error::Span;
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct File<'src> {
+    pub items: Vec<Item<'src>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item<'src> {
+    Function(Function<'src>),
+    Enum(Enum<'src>),
+    Trait(Trait<'src>),
+    Module(ModuleDeclaration<'src>),
+    Extension(Extension<'src>),
+    Import(Import<'src>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ident<'src> {
+    pub name: &'src str,
+    pub span: Span,
+}
+
+/// `func name<generics>(parameters) -> return_type { body }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function<'src> {
+    pub name: Ident<'src>,
+    pub generics: Vec<GenericParameter<'src>>,
+    pub parameters: Vec<Parameter<'src>>,
+    pub return_type: Option<Type<'src>>,
+    pub body: Block<'src>,
+    pub span: Span,
+}
+
+/// `func name<generics>(parameters) -> return_type`, a function without a body.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signature<'src> {
+    pub name: Ident<'src>,
+    pub generics: Vec<GenericParameter<'src>>,
+    pub parameters: Vec<Parameter<'src>>,
+    pub return_type: Option<Type<'src>>,
+    pub span: Span,
+}
+
+/// `enum Name<generics>(variants) -> traits { functions }`, where the traits
+/// and functions are optional.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Enum<'src> {
+    pub name: Ident<'src>,
+    pub generics: Vec<GenericParameter<'src>>,
+    pub variants: Vec<Variant<'src>>,
+    pub traits: Vec<Type<'src>>,
+    pub functions: Vec<Function<'src>>,
+    pub span: Span,
+}
+
+/// `Name` or `Name(fields)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variant<'src> {
+    pub name: Ident<'src>,
+    pub fields: Vec<TypeParameter<'src>>,
+}
+
+/// `trait Name<generics> { signatures }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trait<'src> {
+    pub name: Ident<'src>,
+    pub generics: Vec<GenericParameter<'src>>,
+    pub functions: Vec<Signature<'src>>,
+    pub span: Span,
+}
+
+/// `module name;`, which names the module a file's items belong to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleDeclaration<'src> {
+    pub name: Ident<'src>,
+    pub span: Span,
+}
+
+/// `module name<generics> -> Type with<traits> { functions }`, which adds
+/// functions to a type and implements traits with them. The generics and
+/// traits are optional.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Extension<'src> {
+    pub name: Ident<'src>,
+    pub generics: Vec<GenericParameter<'src>>,
+    pub target: Type<'src>,
+    pub traits: Vec<Type<'src>>,
+    pub functions: Vec<Function<'src>>,
+    pub span: Span,
+}
+
+/// `import tree;`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Import<'src> {
+    pub tree: ImportTree<'src>,
+    pub span: Span,
+}
+
+/// `a.b`, `a.b { trees }` or `{ trees }`, where each tree continues the path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportTree<'src> {
+    pub path: Vec<Ident<'src>>,
+    /// `None` if the path is imported itself.
+    pub children: Option<Vec<ImportTree<'src>>>,
+    pub span: Span,
+}
+
+/// `T` or `T: Bound`
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenericParameter<'src> {
+    pub name: Ident<'src>,
+    pub bound: Option<Type<'src>>,
+}
+
+/// `label pattern: type`, where the label is optional.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter<'src> {
+    pub label: Option<Ident<'src>>,
+    pub pattern: Pattern<'src>,
+    pub ty: Type<'src>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Type<'src> {
+    pub kind: TypeKind<'src>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeKind<'src> {
+    /// `Name<parameters>`
+    Named {
+        name: Ident<'src>,
+        parameters: Vec<TypeParameter<'src>>,
+    },
+    /// `block(parameters) -> return_type`, the type of a block argument. The
+    /// parameter labels name the values the block is called with.
+    Block {
+        parameters: Vec<TypeParameter<'src>>,
+        return_type: Option<Box<Type<'src>>>,
+    },
+}
+
+/// `label: type`, where the label is optional.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeParameter<'src> {
+    pub label: Option<Ident<'src>>,
+    pub ty: Type<'src>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern<'src> {
+    /// `name` or `mut name`. In a match clause this may also name a variant.
+    Binding { mutable: bool, name: Ident<'src> },
+    /// `Some(value)` or `Ordering.Less`
+    Variant {
+        path: Vec<Ident<'src>>,
+        fields: Vec<Pattern<'src>>,
+        span: Span,
+    },
+}
+
+/// `{ statements; tail }`, the block's value is the tail if there is one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block<'src> {
+    pub statements: Vec<Statement<'src>>,
+    pub tail: Option<Box<Expression<'src>>>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement<'src> {
+    Let(LetStatement<'src>),
+    Assign(Assignment<'src>),
+    Expression(Expression<'src>),
+}
+
+/// `let pattern: type = value`
+#[derive(Debug, Clone, PartialEq)]
+pub struct LetStatement<'src> {
+    pub pattern: Pattern<'src>,
+    pub ty: Option<Type<'src>>,
+    pub value: Expression<'src>,
+    pub span: Span,
+}
+
+/// `name = value`, for names bound with `let mut`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assignment<'src> {
+    pub name: Ident<'src>,
+    pub value: Expression<'src>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression<'src> {
+    pub kind: ExpressionKind<'src>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExpressionKind<'src> {
+    Literal(Literal<'src>),
+    /// A bare name, like `value` or `True`.
+    Name(Ident<'src>),
+    Call(Call<'src>),
+    /// `receiver.name`
+    Property {
+        receiver: Box<Expression<'src>>,
+        name: Ident<'src>,
+    },
+    /// `receiver.call(arguments)`, interchangeable with `call(receiver, arguments)`.
+    Chain {
+        receiver: Box<Expression<'src>>,
+        call: Call<'src>,
+    },
+    /// `match(scrutinee) { clauses }` or `scrutinee.match { clauses }`
+    Match {
+        scrutinee: Box<Expression<'src>>,
+        clauses: Vec<MatchClause<'src>>,
+    },
+    /// `loop { body }`, which only ends by returning from it.
+    Loop(Block<'src>),
+    /// `block { body }`, a block used as an expression.
+    Block(Block<'src>),
+    /// `return@label value`, where both parts are optional.
+    Return {
+        label: Option<Ident<'src>>,
+        value: Option<Box<Expression<'src>>>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal<'src> {
+    Integer(i64),
+    Float(f64),
+    Character(char),
+    String(Cow<'src, str>),
+}
+
+/// `name<type_arguments>(arguments) label: { block }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Call<'src> {
+    pub name: Ident<'src>,
+    pub type_arguments: Vec<TypeParameter<'src>>,
+    pub arguments: Vec<Argument<'src>>,
+    pub block_arguments: Vec<BlockArgument<'src>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Argument<'src> {
+    pub label: Option<Ident<'src>>,
+    pub value: Expression<'src>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockArgument<'src> {
+    pub label: Option<Ident<'src>>,
+    pub block: Block<'src>,
+}
+
+/// `pattern -> expression`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchClause<'src> {
+    pub pattern: Pattern<'src>,
+    pub body: Expression<'src>,
+}
+
+impl<'src> Parameter<'src> {
+    /// The label an argument uses to go to this parameter: its label, or its
+    /// name if it doesn't have one.
+    pub fn argument_label(&self) -> Option<&'src str> {
+        match (self.label, &self.pattern) {
+            (Some(label), _) => Some(label.name),
+            (None, Pattern::Binding { name, .. }) => Some(name.name),
+            (None, Pattern::Variant { .. }) => None,
+        }
+    }
+}
+
+impl Type<'_> {
+    /// Whether two types are written the same, wherever they're written.
+    pub fn same(&self, other: &Type) -> bool {
+        let same_parameters = |a: &[TypeParameter], b: &[TypeParameter]| {
+            a.len() == b.len()
+                && a.iter().zip(b).all(|(a, b)| {
+                    a.label.map(|it| it.name) == b.label.map(|it| it.name) && a.ty.same(&b.ty)
+                })
+        };
+
+        match (&self.kind, &other.kind) {
+            (
+                TypeKind::Named { name, parameters },
+                TypeKind::Named {
+                    name: other_name,
+                    parameters: other_parameters,
+                },
+            ) => name.name == other_name.name && same_parameters(parameters, other_parameters),
+            (
+                TypeKind::Block {
+                    parameters,
+                    return_type,
+                },
+                TypeKind::Block {
+                    parameters: other_parameters,
+                    return_type: other_return,
+                },
+            ) => {
+                same_parameters(parameters, other_parameters)
+                    && match (return_type, other_return) {
+                        (Some(a), Some(b)) => a.same(b),
+                        (None, None) => true,
+                        _ => false,
+                    }
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Function<'_> {
+    /// Whether a call can't tell two functions apart, because they take
+    /// the same labels and types. Functions that differ can share a name.
+    pub fn overlaps(&self, other: &Function) -> bool {
+        self.parameters.len() == other.parameters.len()
+            && self
+                .parameters
+                .iter()
+                .zip(&other.parameters)
+                .all(|(a, b)| a.argument_label() == b.argument_label() && a.ty.same(&b.ty))
+    }
+}
+
+impl Pattern<'_> {
+    pub fn span(&self) -> Span {
+        match self {
+            Pattern::Binding { name, .. } => name.span,
+            Pattern::Variant { span, .. } => *span,
+        }
+    }
+}
@@ -1,65 +1,905 @@
-use crate::token::{Token, TokenKind};
+pub mod ast;
+#[cfg(test)]
+mod test;
+
+use crate::{
+    error::Span,
+    token::{Keyword, Token, TokenKind},
+};
+use ast::*;
+
+type ParseResult<'src, T> = Result<T, ParseError<'src>>;
 
 pub fn parse<'src>(
-    mut source: impl Iterator<Item = Token<'src>>,
-) -> Result<Vec<i64>, Vec<ParseError<'src>>> {
-    let mut result = Vec::new();
-    let mut errors = Vec::new();
-
-    while let Some(token) = source.next() {
-        match token.kind {
-            TokenKind::Integer(number) => result.push(number),
-            TokenKind::Semicolon => continue,
-            _ => {
-                errors.push(ParseError::Unimplemented { token });
+    source: impl IntoIterator<Item = Token<'src>>,
+) -> Result<File<'src>, Vec<ParseError<'src>>> {
+    let (file, errors) = parse_recovering(source);
+
+    if errors.is_empty() {
+        Ok(file)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Parses as much as possible, keeping the items that parsed alongside the
+/// errors. This is for tooling that wants to work with broken files.
+pub fn parse_recovering<'src>(
+    source: impl IntoIterator<Item = Token<'src>>,
+) -> (File<'src>, Vec<ParseError<'src>>) {
+    let mut parser = Parser {
+        // Doc comments are only kept for tooling
+        tokens: source
+            .into_iter()
+            .filter(|token| !matches!(token.kind, TokenKind::DocComment(_)))
+            .collect(),
+        position: 0,
+        errors: Vec::new(),
+    };
+
+    let file = parser.file();
+    (file, parser.errors)
+}
+
+struct Parser<'src> {
+    tokens: Vec<Token<'src>>,
+    position: usize,
+    errors: Vec<ParseError<'src>>,
+}
+
+impl<'src> Parser<'src> {
+    fn file(&mut self) -> File<'src> {
+        let mut items = Vec::new();
+
+        while self.peek().is_some() {
+            match self.item() {
+                Ok(item) => items.push(item),
+                Err(error) => {
+                    self.errors.push(error);
+                    self.recover_item();
+                }
+            }
+        }
+
+        File { items }
+    }
+
+    fn item(&mut self) -> ParseResult<'src, Item<'src>> {
+        let item = match self.peek_kind() {
+            Some(TokenKind::Keyword(Keyword::Func)) => Item::Function(self.function()?),
+            Some(TokenKind::Keyword(Keyword::Enum)) => Item::Enum(self.enumeration()?),
+            Some(TokenKind::Keyword(Keyword::Trait)) => Item::Trait(self.trait_item()?),
+            Some(TokenKind::Keyword(Keyword::Module)) => {
+                let start = self.next().map(|token| token.span).unwrap_or_default();
+                let name = self.ident()?;
+
+                if self.at(&TokenKind::Semicolon) {
+                    Item::Module(ModuleDeclaration {
+                        name,
+                        span: start.to(name.span),
+                    })
+                } else {
+                    Item::Extension(self.extension(start, name)?)
+                }
+            }
+            Some(TokenKind::Keyword(Keyword::Import)) => {
+                let start = self.next().map(|token| token.span).unwrap_or_default();
+                let tree = self.import_tree()?;
+                Item::Import(Import {
+                    span: start.to(tree.span),
+                    tree,
+                })
+            }
+            _ => return Err(self.unexpected("item")),
+        };
+
+        self.expect(&TokenKind::Semicolon)?;
+        Ok(item)
+    }
+
+    fn function(&mut self) -> ParseResult<'src, Function<'src>> {
+        let Signature {
+            name,
+            generics,
+            parameters,
+            return_type,
+            span,
+        } = self.signature()?;
+        let body = self.block()?;
+
+        Ok(Function {
+            name,
+            generics,
+            parameters,
+            return_type,
+            span: span.to(body.span),
+            body,
+        })
+    }
+
+    fn signature(&mut self) -> ParseResult<'src, Signature<'src>> {
+        let func = self.expect(&TokenKind::Keyword(Keyword::Func))?;
+        let name = self.ident()?;
+        let generics = self.generics()?;
+
+        let (parameters, _) = self.list(
+            &TokenKind::OpenParen,
+            &TokenKind::CloseParen,
+            Self::parameter,
+        )?;
+
+        let return_type = if self.eat(&TokenKind::Arrow).is_some() {
+            Some(self.ty()?)
+        } else {
+            None
+        };
+
+        Ok(Signature {
+            name,
+            generics,
+            parameters,
+            return_type,
+            span: func.span.to(self.previous_span()),
+        })
+    }
+
+    fn enumeration(&mut self) -> ParseResult<'src, Enum<'src>> {
+        let start = self.expect(&TokenKind::Keyword(Keyword::Enum))?;
+        let name = self.ident()?;
+        let generics = self.generics()?;
+
+        let (variants, mut end) =
+            self.list(&TokenKind::OpenParen, &TokenKind::CloseParen, |parser| {
+                let name = parser.ident()?;
+                let fields = if parser.at(&TokenKind::OpenParen) {
+                    parser
+                        .list(
+                            &TokenKind::OpenParen,
+                            &TokenKind::CloseParen,
+                            Self::type_parameter,
+                        )?
+                        .0
+                } else {
+                    Vec::new()
+                };
+                Ok(Variant { name, fields })
+            })?;
+
+        let mut traits = Vec::new();
+        if self.eat(&TokenKind::Arrow).is_some() {
+            loop {
+                let ty = self.ty()?;
+                end = ty.span;
+                traits.push(ty);
+                if self.eat(&TokenKind::Comma).is_none() {
+                    break;
+                }
+            }
+        }
+
+        let mut functions = Vec::new();
+        if self.at(&TokenKind::OpenBrace) {
+            end = self.members(|parser| {
+                functions.push(parser.function()?);
+                Ok(())
+            })?;
+        }
+
+        Ok(Enum {
+            name,
+            generics,
+            variants,
+            traits,
+            functions,
+            span: start.span.to(end),
+        })
+    }
+
+    /// The rest of an extension, after `module name`.
+    fn extension(&mut self, start: Span, name: Ident<'src>) -> ParseResult<'src, Extension<'src>> {
+        let generics = self.generics()?;
+        self.expect(&TokenKind::Arrow)?;
+        let target = self.ty()?;
+
+        // `with` is only special here, so it isn't a keyword
+        let traits = match self.peek_kind() {
+            Some(TokenKind::Identifier("with")) => {
+                self.next();
+                let (traits, _) = self.type_parameters()?;
+                traits.into_iter().map(|it| it.ty).collect()
+            }
+            _ => Vec::new(),
+        };
+
+        let mut functions = Vec::new();
+        let end = self.members(|parser| {
+            functions.push(parser.function()?);
+            Ok(())
+        })?;
+
+        Ok(Extension {
+            name,
+            generics,
+            target,
+            traits,
+            functions,
+            span: start.to(end),
+        })
+    }
+
+    fn trait_item(&mut self) -> ParseResult<'src, Trait<'src>> {
+        let start = self.expect(&TokenKind::Keyword(Keyword::Trait))?;
+        let name = self.ident()?;
+        let generics = self.generics()?;
+
+        let mut functions = Vec::new();
+        let end = self.members(|parser| {
+            functions.push(parser.signature()?);
+            Ok(())
+        })?;
+
+        Ok(Trait {
+            name,
+            generics,
+            functions,
+            span: start.span.to(end),
+        })
+    }
+
+    fn import_tree(&mut self) -> ParseResult<'src, ImportTree<'src>> {
+        let mut path = Vec::new();
+        if !self.at(&TokenKind::OpenBrace) {
+            path.push(self.ident()?);
+            while self.eat(&TokenKind::DotSymbol).is_some() {
+                path.push(self.ident()?);
+            }
+        }
+
+        let start = path.first().map(|name| name.span);
+        if !self.at(&TokenKind::OpenBrace) {
+            return Ok(ImportTree {
+                span: start.unwrap_or_default().to(self.previous_span()),
+                path,
+                children: None,
+            });
+        }
+
+        let open = self.peek().map(|token| token.span).unwrap_or_default();
+        let (children, end) = self.list(
+            &TokenKind::OpenBrace,
+            &TokenKind::CloseBrace,
+            Self::import_tree,
+        )?;
+
+        Ok(ImportTree {
+            path,
+            children: Some(children),
+            span: start.unwrap_or(open).to(end),
+        })
+    }
+
+    /// `{ member; member; }`, returning the span of the closing brace.
+    fn members(
+        &mut self,
+        mut member: impl FnMut(&mut Self) -> ParseResult<'src, ()>,
+    ) -> ParseResult<'src, Span> {
+        self.expect(&TokenKind::OpenBrace)?;
+
+        loop {
+            if let Some(close) = self.eat(&TokenKind::CloseBrace) {
+                return Ok(close.span);
+            }
+
+            if self.peek().is_none() {
+                return Err(self.unexpected("`}`"));
+            }
+
+            let result = member(self).and_then(|()| self.expect(&TokenKind::Semicolon));
+            if let Err(error) = result {
+                self.errors.push(error);
+                self.recover_statement();
+            }
+        }
+    }
+
+    /// `<T, U: Bound>`, or nothing.
+    fn generics(&mut self) -> ParseResult<'src, Vec<GenericParameter<'src>>> {
+        if !self.at(&TokenKind::OpenAngle) {
+            return Ok(Vec::new());
+        }
+
+        let (generics, _) = self.list(&TokenKind::OpenAngle, &TokenKind::CloseAngle, |parser| {
+            Ok(GenericParameter {
+                name: parser.ident()?,
+                bound: parser.type_annotation()?,
+            })
+        })?;
+        Ok(generics)
+    }
+
+    fn parameter(&mut self) -> ParseResult<'src, Parameter<'src>> {
+        // A label is followed by the pattern, rather than the type annotation
+        let label = match self.peek_nth_kind(1) {
+            Some(TokenKind::Identifier(_)) | Some(TokenKind::Keyword(Keyword::Mut)) => {
+                Some(self.ident()?)
+            }
+            _ => None,
+        };
+
+        let pattern = self.binding()?;
+        self.expect(&TokenKind::Colon)?;
+        let ty = self.ty()?;
+
+        Ok(Parameter { label, pattern, ty })
+    }
+
+    fn ty(&mut self) -> ParseResult<'src, Type<'src>> {
+        if let Some(block) = self.eat(&TokenKind::Keyword(Keyword::Block)) {
+            let (parameters, mut end) = self.list(
+                &TokenKind::OpenParen,
+                &TokenKind::CloseParen,
+                Self::type_parameter,
+            )?;
+
+            let return_type = if self.eat(&TokenKind::Arrow).is_some() {
+                let ty = self.ty()?;
+                end = ty.span;
+                Some(Box::new(ty))
+            } else {
+                None
+            };
+
+            return Ok(Type {
+                kind: TypeKind::Block {
+                    parameters,
+                    return_type,
+                },
+                span: block.span.to(end),
+            });
+        }
+
+        let name = self.ident()?;
+
+        if !self.at(&TokenKind::OpenAngle) {
+            return Ok(Type {
+                kind: TypeKind::Named {
+                    name,
+                    parameters: Vec::new(),
+                },
+                span: name.span,
+            });
+        }
+
+        let (parameters, end) = self.type_parameters()?;
+
+        Ok(Type {
+            kind: TypeKind::Named { name, parameters },
+            span: name.span.to(end),
+        })
+    }
+
+    fn type_annotation(&mut self) -> ParseResult<'src, Option<Type<'src>>> {
+        if self.eat(&TokenKind::Colon).is_some() {
+            self.ty().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn type_parameters(&mut self) -> ParseResult<'src, (Vec<TypeParameter<'src>>, Span)> {
+        self.list(
+            &TokenKind::OpenAngle,
+            &TokenKind::CloseAngle,
+            Self::type_parameter,
+        )
+    }
+
+    fn type_parameter(&mut self) -> ParseResult<'src, TypeParameter<'src>> {
+        Ok(TypeParameter {
+            label: self.label()?,
+            ty: self.ty()?,
+        })
+    }
+
+    /// `mut name` or `name`, as used by parameters and let statements.
+    fn binding(&mut self) -> ParseResult<'src, Pattern<'src>> {
+        let mutable = self.eat(&TokenKind::Keyword(Keyword::Mut)).is_some();
+        let name = self.ident()?;
+        Ok(Pattern::Binding { mutable, name })
+    }
+
+    /// Patterns in match clauses, which may also destructure variants.
+    fn pattern(&mut self) -> ParseResult<'src, Pattern<'src>> {
+        if self.at(&TokenKind::Keyword(Keyword::Mut)) {
+            return self.binding();
+        }
+
+        let name = self.ident()?;
+        let mut path = vec![name];
+        while self.eat(&TokenKind::DotSymbol).is_some() {
+            path.push(self.ident()?);
+        }
+
+        let mut span = name.span.to(path[path.len() - 1].span);
+
+        let fields = if self.at(&TokenKind::OpenParen) {
+            let (fields, end) =
+                self.list(&TokenKind::OpenParen, &TokenKind::CloseParen, Self::pattern)?;
+            span = span.to(end);
+            fields
+        } else if path.len() == 1 {
+            return Ok(Pattern::Binding {
+                mutable: false,
+                name,
+            });
+        } else {
+            Vec::new()
+        };
+
+        Ok(Pattern::Variant { path, fields, span })
+    }
+
+    fn block(&mut self) -> ParseResult<'src, Block<'src>> {
+        let open = self.expect(&TokenKind::OpenBrace)?;
+        let mut statements = Vec::new();
+        let mut tail = None;
+
+        loop {
+            if let Some(close) = self.eat(&TokenKind::CloseBrace) {
+                return Ok(Block {
+                    statements,
+                    tail,
+                    span: open.span.to(close.span),
+                });
+            }
+
+            if self.peek().is_none() {
+                return Err(self.unexpected("`}`"));
+            }
+
+            let statement = match self.statement() {
+                Ok(statement) => statement,
+                Err(error) => {
+                    self.errors.push(error);
+                    self.recover_statement();
+                    continue;
+                }
+            };
+
+            if self.eat(&TokenKind::Semicolon).is_some() {
+                statements.push(statement);
+                continue;
+            }
+
+            // Without a semicolon the block has to end here
+            match statement {
+                Statement::Expression(expression) if self.at(&TokenKind::CloseBrace) => {
+                    tail = Some(Box::new(expression))
+                }
+                _ if self.at(&TokenKind::CloseBrace) => statements.push(statement),
+                // End of file is reported when the loop comes back around
+                _ if self.peek().is_none() => {}
+                _ => {
+                    let error = self.unexpected("`;` or `}`");
+                    self.errors.push(error);
+                    self.recover_statement();
+                }
+            }
+        }
+    }
+
+    fn statement(&mut self) -> ParseResult<'src, Statement<'src>> {
+        let assignment = matches!(self.peek_kind(), Some(TokenKind::Identifier(_)))
+            && matches!(self.peek_nth_kind(1), Some(TokenKind::Equals));
+        if assignment {
+            let name = self.ident()?;
+            self.next();
+            let value = self.expression()?;
+            return Ok(Statement::Assign(Assignment {
+                name,
+                span: name.span.to(value.span),
+                value,
+            }));
+        }
+
+        let start = match self.eat(&TokenKind::Keyword(Keyword::Let)) {
+            Some(start) => start,
+            None => return self.expression().map(Statement::Expression),
+        };
+
+        let pattern = self.binding()?;
+        let ty = self.type_annotation()?;
+        self.expect(&TokenKind::Equals)?;
+        let value = self.expression()?;
+
+        Ok(Statement::Let(LetStatement {
+            pattern,
+            ty,
+            span: start.span.to(value.span),
+            value,
+        }))
+    }
+
+    fn expression(&mut self) -> ParseResult<'src, Expression<'src>> {
+        let mut expression = self.primary()?;
+
+        while self.eat(&TokenKind::DotSymbol).is_some() {
+            if self.eat(&TokenKind::Keyword(Keyword::Match)).is_some() {
+                let (clauses, end) = self.match_clauses()?;
+
+                expression = Expression {
+                    span: expression.span.to(end),
+                    kind: ExpressionKind::Match {
+                        scrutinee: Box::new(expression),
+                        clauses,
+                    },
+                };
+
                 continue;
             }
+
+            let name = self.ident()?;
+            let span = expression.span.to(self.previous_span());
+            let receiver = Box::new(expression);
+
+            expression = match self.call(name)? {
+                Some(call) => Expression {
+                    span: span.to(self.previous_span()),
+                    kind: ExpressionKind::Chain { receiver, call },
+                },
+                None => Expression {
+                    span,
+                    kind: ExpressionKind::Property { receiver, name },
+                },
+            };
         }
 
-        // Take next token or finish if end of file
-        let token = if let Some(token) = source.next() {
-            token
+        Ok(expression)
+    }
+
+    fn primary(&mut self) -> ParseResult<'src, Expression<'src>> {
+        let token = match self.peek() {
+            Some(token) => token.clone(),
+            None => return Err(self.unexpected("expression")),
+        };
+
+        let kind = match token.kind {
+            TokenKind::OpenParen => {
+                self.next();
+                let expression = self.expression()?;
+                self.expect(&TokenKind::CloseParen)?;
+                return Ok(expression);
+            }
+            TokenKind::Integer(it) => {
+                self.next();
+                ExpressionKind::Literal(Literal::Integer(it))
+            }
+            TokenKind::Float(it) => {
+                self.next();
+                ExpressionKind::Literal(Literal::Float(it))
+            }
+            TokenKind::Character(it) => {
+                self.next();
+                ExpressionKind::Literal(Literal::Character(it))
+            }
+            TokenKind::String(it) => {
+                self.next();
+                ExpressionKind::Literal(Literal::String(it))
+            }
+            TokenKind::Keyword(Keyword::Match) => {
+                self.next();
+                self.expect(&TokenKind::OpenParen)?;
+                let scrutinee = Box::new(self.expression()?);
+                self.expect(&TokenKind::CloseParen)?;
+                let (clauses, _) = self.match_clauses()?;
+                ExpressionKind::Match { scrutinee, clauses }
+            }
+            TokenKind::Keyword(Keyword::Loop) => {
+                self.next();
+                ExpressionKind::Loop(self.block()?)
+            }
+            TokenKind::Keyword(Keyword::Block) => {
+                self.next();
+                ExpressionKind::Block(self.block()?)
+            }
+            TokenKind::Keyword(Keyword::Return) => {
+                self.next();
+
+                let label = if self.eat(&TokenKind::AtSymbol).is_some() {
+                    Some(self.return_label()?)
+                } else {
+                    None
+                };
+
+                let value = if self.at_expression() {
+                    Some(Box::new(self.expression()?))
+                } else {
+                    None
+                };
+
+                ExpressionKind::Return { label, value }
+            }
+            TokenKind::Identifier(_) => {
+                let name = self.ident()?;
+
+                match self.call(name)? {
+                    Some(call) => ExpressionKind::Call(call),
+                    None => ExpressionKind::Name(name),
+                }
+            }
+            _ => return Err(self.unexpected("expression")),
+        };
+
+        Ok(Expression {
+            kind,
+            span: token.span.to(self.previous_span()),
+        })
+    }
+
+    /// The rest of a call after its name, or `None` if it is just a name.
+    fn call(&mut self, name: Ident<'src>) -> ParseResult<'src, Option<Call<'src>>> {
+        let type_arguments = if self.at(&TokenKind::OpenAngle) {
+            Some(self.type_parameters()?.0)
         } else {
-            break;
+            None
         };
 
-        // Expect semicolon
-        if let TokenKind::Semicolon = token.kind {
+        let arguments = if self.at(&TokenKind::OpenParen) {
+            let (arguments, _) =
+                self.list(&TokenKind::OpenParen, &TokenKind::CloseParen, |parser| {
+                    Ok(Argument {
+                        label: parser.label()?,
+                        value: parser.expression()?,
+                    })
+                })?;
+            Some(arguments)
         } else {
-            errors.push(ParseError::ExpectedSemicolon { token });
+            None
+        };
 
-            // Consume tokens until a semi colon is found
-            // This is done to avoid cascading errors
-            for token in &mut source {
-                if let TokenKind::Semicolon = token.kind {
-                    break;
+        let mut block_arguments = Vec::new();
+        loop {
+            let labelled = matches!(self.peek_kind(), Some(TokenKind::Identifier(_)))
+                && matches!(self.peek_nth_kind(1), Some(TokenKind::Colon))
+                && matches!(self.peek_nth_kind(2), Some(TokenKind::OpenBrace));
+
+            if !labelled && !self.at(&TokenKind::OpenBrace) {
+                break;
+            }
+
+            block_arguments.push(BlockArgument {
+                label: self.label()?,
+                block: self.block()?,
+            });
+        }
+
+        if type_arguments.is_none() && arguments.is_none() && block_arguments.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(Call {
+            name,
+            type_arguments: type_arguments.unwrap_or_default(),
+            arguments: arguments.unwrap_or_default(),
+            block_arguments,
+        }))
+    }
+
+    fn match_clauses(&mut self) -> ParseResult<'src, (Vec<MatchClause<'src>>, Span)> {
+        self.list(&TokenKind::OpenBrace, &TokenKind::CloseBrace, |parser| {
+            let pattern = parser.pattern()?;
+            parser.expect(&TokenKind::Arrow)?;
+            let body = parser.expression()?;
+            Ok(MatchClause { pattern, body })
+        })
+    }
+
+    /// An optional `label:` in front of a parameter or argument.
+    fn label(&mut self) -> ParseResult<'src, Option<Ident<'src>>> {
+        let labelled = matches!(self.peek_kind(), Some(TokenKind::Identifier(_)))
+            && matches!(self.peek_nth_kind(1), Some(TokenKind::Colon));
+
+        if !labelled {
+            return Ok(None);
+        }
+
+        let label = self.ident()?;
+        self.next();
+        Ok(Some(label))
+    }
+
+    /// `open item, item, ... close`, allowing a trailing separator.
+    fn list<T>(
+        &mut self,
+        open: &TokenKind<'src>,
+        close: &TokenKind<'src>,
+        mut item: impl FnMut(&mut Self) -> ParseResult<'src, T>,
+    ) -> ParseResult<'src, (Vec<T>, Span)> {
+        self.expect(open)?;
+        let mut items = Vec::new();
+
+        loop {
+            if let Some(close) = self.eat(close) {
+                return Ok((items, close.span));
+            }
+
+            items.push(item(self)?);
+
+            if self.eat(&TokenKind::Comma).is_none() {
+                let close = self.expect(close)?;
+                return Ok((items, close.span));
+            }
+        }
+    }
+
+    fn ident(&mut self) -> ParseResult<'src, Ident<'src>> {
+        match self.peek() {
+            Some(Token {
+                kind: TokenKind::Identifier(name),
+                span,
+            }) => {
+                let ident = Ident { name, span: *span };
+                self.next();
+                Ok(ident)
+            }
+            _ => Err(self.unexpected("identifier")),
+        }
+    }
+
+    /// A function name, or `loop` to return from the innermost loop.
+    fn return_label(&mut self) -> ParseResult<'src, Ident<'src>> {
+        match self.peek_kind() {
+            Some(TokenKind::Keyword(Keyword::Loop)) => {
+                let span = self.next().map(|token| token.span).unwrap_or_default();
+                Ok(Ident { name: "loop", span })
+            }
+            _ => self.ident(),
+        }
+    }
+
+    fn at_expression(&self) -> bool {
+        matches!(
+            self.peek_kind(),
+            Some(TokenKind::OpenParen)
+                | Some(TokenKind::Integer(_))
+                | Some(TokenKind::Float(_))
+                | Some(TokenKind::Character(_))
+                | Some(TokenKind::String(_))
+                | Some(TokenKind::Identifier(_))
+                | Some(TokenKind::Keyword(Keyword::Match))
+                | Some(TokenKind::Keyword(Keyword::Loop))
+                | Some(TokenKind::Keyword(Keyword::Block))
+                | Some(TokenKind::Keyword(Keyword::Return))
+        )
+    }
+
+    fn expect(&mut self, kind: &TokenKind<'src>) -> ParseResult<'src, Token<'src>> {
+        match self.eat(kind) {
+            Some(token) => Ok(token),
+            None => Err(self.unexpected(&format!("`{}`", kind))),
+        }
+    }
+
+    fn eat(&mut self, kind: &TokenKind<'src>) -> Option<Token<'src>> {
+        if self.at(kind) {
+            self.next()
+        } else {
+            None
+        }
+    }
+
+    fn at(&self, kind: &TokenKind<'src>) -> bool {
+        self.peek_kind() == Some(kind)
+    }
+
+    fn peek(&self) -> Option<&Token<'src>> {
+        self.tokens.get(self.position)
+    }
+
+    fn peek_kind(&self) -> Option<&TokenKind<'src>> {
+        self.peek_nth_kind(0)
+    }
+
+    fn peek_nth_kind(&self, n: usize) -> Option<&TokenKind<'src>> {
+        self.tokens.get(self.position + n).map(|token| &token.kind)
+    }
+
+    fn next(&mut self) -> Option<Token<'src>> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn previous_span(&self) -> Span {
+        self.tokens[self.position - 1].span
+    }
+
+    fn unexpected(&self, expected: &str) -> ParseError<'src> {
+        match self.peek() {
+            Some(found) => ParseError::Expected {
+                expected: expected.to_owned(),
+                found: found.clone(),
+            },
+            None => ParseError::UnexpectedEof {
+                expected: expected.to_owned(),
+                position: self.tokens.last().map_or(0, |token| token.span.end),
+            },
+        }
+    }
+
+    /// Skip to the end of the current statement, without leaving the block.
+    /// This is done to avoid cascading errors.
+    fn recover_statement(&mut self) {
+        let mut depth = 0usize;
+
+        while let Some(kind) = self.peek_kind() {
+            match kind {
+                TokenKind::Semicolon if depth == 0 => {
+                    self.next();
+                    return;
                 }
+                TokenKind::CloseBrace if depth == 0 => return,
+                TokenKind::OpenBrace | TokenKind::OpenParen => depth += 1,
+                TokenKind::CloseBrace | TokenKind::CloseParen => depth = depth.saturating_sub(1),
+                _ => {}
             }
+
+            self.next();
         }
     }
 
-    if errors.is_empty() {
-        Ok(result)
-    } else {
-        Err(errors)
+    /// Skip to the start of the next item.
+    fn recover_item(&mut self) {
+        let mut depth = 0usize;
+
+        while let Some(kind) = self.peek_kind() {
+            match kind {
+                TokenKind::Semicolon if depth == 0 => {
+                    self.next();
+                    return;
+                }
+                TokenKind::Keyword(Keyword::Func)
+                | TokenKind::Keyword(Keyword::Enum)
+                | TokenKind::Keyword(Keyword::Trait)
+                | TokenKind::Keyword(Keyword::Module)
+                | TokenKind::Keyword(Keyword::Import)
+                    if depth == 0 =>
+                {
+                    return
+                }
+                TokenKind::OpenBrace | TokenKind::OpenParen => depth += 1,
+                TokenKind::CloseBrace | TokenKind::CloseParen => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+
+            self.next();
+        }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ParseError<'src> {
-    ExpectedSemicolon { token: Token<'src> },
-    Unimplemented { token: Token<'src> },
+    Expected {
+        expected: String,
+        found: Token<'src>,
+    },
+    UnexpectedEof {
+        expected: String,
+        position: usize,
+    },
 }
 
 impl<'src> From<ParseError<'src>> for crate::error::Citation {
     fn from(error: ParseError<'src>) -> Self {
         use crate::error::Citation;
         match error {
-            ParseError::ExpectedSemicolon { token } => {
-                Citation::error("Expected semicolon".to_owned()).span(token.span, None)
+            ParseError::Expected { expected, found } => {
+                Citation::error(format!("Expected {}, found `{}`", expected, found.kind))
+                    .span(found.span, Some(format!("expected {}", expected)))
             }
-            ParseError::Unimplemented { token } => {
-                Citation::error("Unimplemented".to_owned()).span(token.span, None)
+            ParseError::UnexpectedEof { expected, position } => {
+                Citation::error(format!("Expected {}, found end of file", expected))
+                    .span(Span::new(position, position), None)
             }
         }
     }
@@ -0,0 +1,452 @@
+use super::{ast::*, *};
+use crate::token::scan;
+
+fn parse_source(source: &str) -> Result<File<'_>, Vec<ParseError<'_>>> {
+    parse(scan(source).map(|token| token.expect("source should scan")))
+}
+
+/// Parses a single function and returns it.
+fn function(source: &str) -> Function<'_> {
+    let mut file = parse_source(source).expect("source should parse");
+    assert_eq!(file.items.len(), 1);
+
+    match file.items.remove(0) {
+        Item::Function(function) => function,
+        item => panic!("expected a function, found {:?}", item),
+    }
+}
+
+/// Parses a single function and returns the tail of its body.
+fn tail(source: &str) -> Expression<'_> {
+    *function(source).body.tail.expect("body should have a tail")
+}
+
+fn name<'src>(expression: &Expression<'src>) -> &'src str {
+    match &expression.kind {
+        ExpressionKind::Name(name) => name.name,
+        ExpressionKind::Call(call) => call.name.name,
+        ExpressionKind::Chain { call, .. } => call.name.name,
+        ExpressionKind::Property { name, .. } => name.name,
+        kind => panic!("expected a named expression, found {:?}", kind),
+    }
+}
+
+fn type_name<'src>(ty: &Type<'src>) -> &'src str {
+    match &ty.kind {
+        TypeKind::Named { name, .. } => name.name,
+        kind => panic!("expected a named type, found {:?}", kind),
+    }
+}
+
+#[test]
+fn function_items() {
+    let function = function("func into<T, R: From<T>>(other: T, from mut start: Int) -> R {};");
+
+    assert_eq!(function.name.name, "into");
+    assert_eq!(function.span, Span::new(0, 62));
+
+    let generics: Vec<_> = function
+        .generics
+        .iter()
+        .map(|it| (it.name.name, it.bound.as_ref().map(type_name)))
+        .collect();
+    assert_eq!(generics, vec![("T", None), ("R", Some("From"))]);
+    assert!(matches!(
+        &function.generics[1].bound.as_ref().unwrap().kind,
+        TypeKind::Named { parameters, .. } if parameters.len() == 1
+    ));
+
+    assert_eq!(function.parameters[0].label, None);
+    assert_eq!(function.parameters[1].label.map(|it| it.name), Some("from"));
+    assert!(matches!(
+        function.parameters[1].pattern,
+        Pattern::Binding {
+            mutable: true,
+            name: Ident { name: "start", .. }
+        }
+    ));
+    assert_eq!(type_name(&function.parameters[1].ty), "Int");
+    assert_eq!(function.return_type.as_ref().map(type_name), Some("R"));
+}
+
+#[test]
+fn blocks() {
+    let body = function("func test() { let mut a: Int = 1; a; b };").body;
+
+    assert_eq!(body.statements.len(), 2);
+    assert!(matches!(
+        &body.statements[0],
+        Statement::Let(LetStatement {
+            pattern: Pattern::Binding { mutable: true, .. },
+            ty: Some(_),
+            ..
+        })
+    ));
+    assert_eq!(
+        body.tail.map(|it| name(&it).to_owned()),
+        Some("b".to_owned())
+    );
+
+    let body = function("func test() { a; let b = 2 };").body;
+    assert_eq!(body.statements.len(), 2);
+    assert!(body.tail.is_none());
+}
+
+#[test]
+fn chains() {
+    let chain = tail("func test() { range(from: 0, to: 100).random(using: rng()).value };");
+
+    let (receiver, name) = match chain.kind {
+        ExpressionKind::Property { receiver, name } => (receiver, name),
+        kind => panic!("expected a property, found {:?}", kind),
+    };
+    assert_eq!(name.name, "value");
+
+    let (receiver, call) = match receiver.kind {
+        ExpressionKind::Chain { receiver, call } => (receiver, call),
+        kind => panic!("expected a chain, found {:?}", kind),
+    };
+    assert_eq!(call.name.name, "random");
+    assert_eq!(call.arguments[0].label.map(|it| it.name), Some("using"));
+
+    match receiver.kind {
+        ExpressionKind::Call(call) => {
+            let labels: Vec<_> = call
+                .arguments
+                .iter()
+                .map(|it| it.label.map(|it| it.name))
+                .collect();
+            assert_eq!(labels, vec![Some("from"), Some("to")]);
+        }
+        kind => panic!("expected a call, found {:?}", kind),
+    }
+}
+
+#[test]
+fn block_arguments() {
+    let call = match tail("func test() { repeat { a(); } then: { b } };").kind {
+        ExpressionKind::Call(call) => call,
+        kind => panic!("expected a call, found {:?}", kind),
+    };
+
+    assert_eq!(call.name.name, "repeat");
+    assert!(call.arguments.is_empty());
+    assert_eq!(call.block_arguments.len(), 2);
+    assert_eq!(call.block_arguments[0].label, None);
+    assert_eq!(
+        call.block_arguments[1].label.map(|it| it.name),
+        Some("then")
+    );
+
+    let chain = tail("func test() { condition.then<T>(x) { self } };");
+    match chain.kind {
+        ExpressionKind::Chain { call, .. } => {
+            assert_eq!(call.type_arguments.len(), 1);
+            assert_eq!(call.arguments.len(), 1);
+            assert_eq!(call.block_arguments.len(), 1);
+        }
+        kind => panic!("expected a chain, found {:?}", kind),
+    }
+}
+
+#[test]
+fn loops() {
+    let body = match tail("func test() { loop { a(); return@loop; } };").kind {
+        ExpressionKind::Loop(body) => body,
+        kind => panic!("expected a loop, found {:?}", kind),
+    };
+
+    assert_eq!(body.statements.len(), 2);
+    assert!(matches!(
+        &body.statements[1],
+        Statement::Expression(Expression {
+            kind: ExpressionKind::Return {
+                label: Some(Ident { name: "loop", .. }),
+                value: None
+            },
+            ..
+        })
+    ));
+
+    assert!(matches!(
+        tail("func test() { block { 1 } };").kind,
+        ExpressionKind::Block(Block { tail: Some(_), .. })
+    ));
+}
+
+#[test]
+fn matches() {
+    let clauses =
+        match tail("func test() { match(self) { True -> False, Some(value) -> value, } };").kind {
+            ExpressionKind::Match { scrutinee, clauses } => {
+                assert_eq!(name(&scrutinee), "self");
+                clauses
+            }
+            kind => panic!("expected a match, found {:?}", kind),
+        };
+
+    assert_eq!(clauses.len(), 2);
+    assert!(matches!(clauses[0].pattern, Pattern::Binding { .. }));
+    assert!(matches!(
+        &clauses[1].pattern,
+        Pattern::Variant { path, fields, .. } if path.len() == 1 && fields.len() == 1
+    ));
+
+    match tail("func test() { a.compare(to: b).match { Ordering.Less -> return@main 1 } };").kind {
+        ExpressionKind::Match { scrutinee, clauses } => {
+            assert_eq!(name(&scrutinee), "compare");
+            assert!(matches!(
+                &clauses[0].pattern,
+                Pattern::Variant { path, fields, .. } if path.len() == 2 && fields.is_empty()
+            ));
+            assert!(matches!(
+                &clauses[0].body.kind,
+                ExpressionKind::Return {
+                    label: Some(Ident { name: "main", .. }),
+                    value: Some(_)
+                }
+            ));
+        }
+        kind => panic!("expected a match, found {:?}", kind),
+    }
+}
+
+#[test]
+fn returns() {
+    let body = function("func test() { return; return@outer; return 1; };").body;
+
+    let returns: Vec<_> = body
+        .statements
+        .iter()
+        .map(|statement| match statement {
+            Statement::Expression(Expression {
+                kind: ExpressionKind::Return { label, value },
+                ..
+            }) => (label.map(|it| it.name), value.is_some()),
+            statement => panic!("expected a return, found {:?}", statement),
+        })
+        .collect();
+
+    assert_eq!(
+        returns,
+        vec![(None, false), (Some("outer"), false), (None, true)]
+    );
+}
+
+#[test]
+fn enums() {
+    let source = "enum Optional<T>(Some(T), Nothing) -> From<T>, Default {
+        func else(self: Self, other: T) -> T { other };
+    };
+    enum Never();";
+    let file = parse_source(source).expect("source should parse");
+
+    let optional = match &file.items[0] {
+        Item::Enum(it) => it,
+        item => panic!("expected an enum, found {:?}", item),
+    };
+    assert_eq!(optional.name.name, "Optional");
+    assert_eq!(optional.generics[0].name.name, "T");
+    let variants: Vec<_> = optional
+        .variants
+        .iter()
+        .map(|it| (it.name.name, it.fields.len()))
+        .collect();
+    assert_eq!(variants, vec![("Some", 1), ("Nothing", 0)]);
+    let traits: Vec<_> = optional.traits.iter().map(type_name).collect();
+    assert_eq!(traits, vec!["From", "Default"]);
+    assert_eq!(optional.functions[0].name.name, "else");
+    assert_eq!(
+        &source[optional.span.start..=optional.span.end],
+        &source[..118]
+    );
+
+    match &file.items[1] {
+        Item::Enum(never) => {
+            assert_eq!(never.name.name, "Never");
+            assert!(never.variants.is_empty());
+            assert!(never.functions.is_empty());
+        }
+        item => panic!("expected an enum, found {:?}", item),
+    }
+}
+
+#[test]
+fn traits() {
+    let source = "trait From<T> { func from(other: T) -> Self; func into(self: Self); };";
+    let file = parse_source(source).expect("source should parse");
+
+    let from = match &file.items[0] {
+        Item::Trait(it) => it,
+        item => panic!("expected a trait, found {:?}", item),
+    };
+    assert_eq!(from.name.name, "From");
+    assert_eq!(from.span, Span::new(0, 68));
+
+    let functions: Vec<_> = from
+        .functions
+        .iter()
+        .map(|it| (it.name.name, it.return_type.as_ref().map(type_name)))
+        .collect();
+    assert_eq!(functions, vec![("from", Some("Self")), ("into", None)]);
+    assert_eq!(from.functions[0].span, Span::new(16, 42));
+}
+
+#[test]
+fn modules_and_imports() {
+    let source = "module guess;
+    import {
+        std { compare.Ordering, io.stdin },
+        random { Random, thread_rng },
+    };
+    import std.io;";
+    let file = parse_source(source).expect("source should parse");
+
+    match &file.items[0] {
+        Item::Module(module) => assert_eq!(module.name.name, "guess"),
+        item => panic!("expected a module, found {:?}", item),
+    }
+
+    /// Every path the tree imports, joined with dots.
+    fn paths(tree: &ImportTree, prefix: &str, out: &mut Vec<String>) {
+        let mut prefix = prefix.to_owned();
+        for name in &tree.path {
+            if !prefix.is_empty() {
+                prefix.push('.');
+            }
+            prefix.push_str(name.name);
+        }
+
+        match &tree.children {
+            Some(children) => {
+                for child in children {
+                    paths(child, &prefix, out);
+                }
+            }
+            None => out.push(prefix),
+        }
+    }
+
+    let mut imported = Vec::new();
+    for item in &file.items[1..] {
+        match item {
+            Item::Import(import) => paths(&import.tree, "", &mut imported),
+            item => panic!("expected an import, found {:?}", item),
+        }
+    }
+    assert_eq!(
+        imported,
+        vec![
+            "std.compare.Ordering",
+            "std.io.stdin",
+            "random.Random",
+            "random.thread_rng",
+            "std.io",
+        ]
+    );
+
+    match &file.items[2] {
+        Item::Import(import) => assert_eq!(
+            &source[import.span.start..=import.span.end],
+            "import std.io"
+        ),
+        item => panic!("expected an import, found {:?}", item),
+    }
+}
+
+#[test]
+fn errors() {
+    let source = "func test() { foo(; let = 3; ok() }; 12; func fine() {};";
+
+    assert_eq!(
+        parse_source(source),
+        Err(vec![
+            ParseError::Expected {
+                expected: "expression".to_owned(),
+                found: Token::new(TokenKind::Semicolon, Span::new(18, 18)),
+            },
+            ParseError::Expected {
+                expected: "identifier".to_owned(),
+                found: Token::new(TokenKind::Equals, Span::new(24, 24)),
+            },
+            ParseError::Expected {
+                expected: "item".to_owned(),
+                found: Token::new(TokenKind::Integer(12), Span::new(37, 38)),
+            },
+        ])
+    );
+
+    assert_eq!(
+        parse_source("func test() { a"),
+        Err(vec![ParseError::UnexpectedEof {
+            expected: "`}`".to_owned(),
+            position: 14,
+        }])
+    );
+}
+
+#[test]
+fn block_types() {
+    let function = function("func while(self: block() -> Self, body: block(value: T)) {};");
+
+    match &function.parameters[0].ty.kind {
+        TypeKind::Block {
+            parameters,
+            return_type,
+        } => {
+            assert!(parameters.is_empty());
+            assert_eq!(return_type.as_deref().map(type_name), Some("Self"));
+        }
+        kind => panic!("expected a block type, found {:?}", kind),
+    }
+    match &function.parameters[1].ty.kind {
+        TypeKind::Block {
+            parameters,
+            return_type: None,
+        } => assert_eq!(parameters[0].label.map(|it| it.name), Some("value")),
+        kind => panic!("expected a block type, found {:?}", kind),
+    }
+    assert_eq!(function.parameters[1].ty.span, Span::new(40, 54));
+}
+
+#[test]
+fn assignments() {
+    let body = function("func test() { let mut x = 1; x = x.add(1); x };").body;
+
+    match &body.statements[1] {
+        Statement::Assign(assignment) => {
+            assert_eq!(assignment.name.name, "x");
+            assert_eq!(name(&assignment.value), "add");
+            assert_eq!(assignment.span, Span::new(29, 40));
+        }
+        statement => panic!("expected an assignment, found {:?}", statement),
+    }
+}
+
+#[test]
+fn extensions() {
+    let source = "module default_some<T: Default> -> Optional<T> with<Default> {
+    func default() { Some(T.default()) };
+};
+module empty -> Int {};";
+    let file = parse_source(source).expect("source should parse");
+
+    match &file.items[0] {
+        Item::Extension(extension) => {
+            assert_eq!(extension.name.name, "default_some");
+            assert_eq!(extension.generics.len(), 1);
+            assert_eq!(type_name(&extension.target), "Optional");
+            let traits: Vec<_> = extension.traits.iter().map(type_name).collect();
+            assert_eq!(traits, vec!["Default"]);
+            assert_eq!(extension.functions[0].name.name, "default");
+        }
+        item => panic!("expected an extension, found {:?}", item),
+    }
+    match &file.items[1] {
+        Item::Extension(extension) => {
+            assert!(extension.traits.is_empty());
+            assert!(extension.functions.is_empty());
+        }
+        item => panic!("expected an extension, found {:?}", item),
+    }
+}
@@ -0,0 +1,398 @@
+#[cfg(test)]
+mod test;
+
+use crate::{
+    error::{Citation, SourceMap},
+    interpret::{self, Local, Value},
+    parser::ast::Item,
+    resolve,
+    token::{self, Keyword, TokenKind},
+    types, ErrorFormat,
+};
+use std::{
+    io::{self, Write},
+    iter,
+};
+
+const HELP: &str = "\
+Items like `func` and `enum` are kept for later inputs, replacing the ones
+they redefine, and anything else is run as the body of `main` with its value
+printed. Names bound by `let` are kept too, unless their values can't be
+written as source that runs, like blocks.
+
+:tokens <source>  print the tokens the source scans to
+:ast <source>     print the syntax tree the source parses to
+:type <source>    print the type of the source as the body of `main`
+:help             print this message
+:quit             leave the REPL, as does Ctrl-D";
+
+/// The state kept between inputs to the REPL: every item defined so far,
+/// and the names bound by `let`.
+pub struct Session {
+    /// Each item's file name and source, one item to a file.
+    items: Vec<(String, String)>,
+    /// How many items have been kept, for naming their files.
+    kept: usize,
+    /// A `let` statement for each name bound, giving it back its value.
+    bindings: Vec<(String, String)>,
+    format: ErrorFormat,
+    color: bool,
+}
+
+impl Session {
+    pub fn new(format: ErrorFormat) -> Self {
+        Self {
+            items: Vec::new(),
+            kept: 0,
+            bindings: Vec::new(),
+            format,
+            color: true,
+        }
+    }
+
+    /// Sets whether human readable errors are colored.
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Defines the items in the input, runs its statements, or runs one of
+    /// the `:` commands. What the program prints goes to `out`, and what went
+    /// wrong to `errors`.
+    pub fn eval(
+        &mut self,
+        input: &str,
+        out: &mut dyn Write,
+        errors: &mut dyn Write,
+    ) -> io::Result<()> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Ok(());
+        }
+
+        let command = match input.strip_prefix(':') {
+            Some(command) => command,
+            None if items(input) => return self.define(input, errors),
+            None => return self.run(input, out, errors),
+        };
+
+        let (command, source) = command
+            .split_once(char::is_whitespace)
+            .map_or((command, ""), |(command, source)| (command, source.trim()));
+        match command {
+            "tokens" => self.tokens(source, out, errors),
+            "ast" => self.ast(source, out, errors),
+            "type" => self.ty(source, out, errors),
+            "help" => writeln!(out, "{}", HELP),
+            _ => writeln!(errors, "unknown command `:{}`, try `:help`", command),
+        }
+    }
+
+    /// Keeps the items, replacing the ones they redefine, if they parse,
+    /// resolve and type check along with the rest.
+    fn define(&mut self, input: &str, errors: &mut dyn Write) -> io::Result<()> {
+        let source = terminated(input);
+        let new = split(&source);
+
+        let mut items = self.items.clone();
+        let old = items.len();
+        for (index, item) in new.iter().enumerate() {
+            let name = format!("<repl {}>", self.kept + index + 1);
+            items.push((name, (*item).to_owned()));
+        }
+
+        let map = self.map(&items);
+        let (units, citations) = resolve::parse(&map);
+        if !citations.is_empty() {
+            return self.report(&map, errors, &citations);
+        }
+
+        let mut kept = units[..old]
+            .iter()
+            .map(|unit| {
+                !units[old..]
+                    .iter()
+                    .any(|new| redefines(&new.file.items, &unit.file.items))
+            })
+            .chain(iter::repeat(true));
+        items.retain(|_| kept.next().unwrap_or(true));
+
+        let map = self.map(&items);
+        let (units, _) = resolve::parse(&map);
+        let resolution = resolve::resolve(&units);
+        let mut citations: Vec<_> = resolution.errors.into_iter().map(Citation::from).collect();
+        citations.extend(types::check(&units).into_iter().map(Citation::from));
+        if !citations.is_empty() {
+            return self.report(&map, errors, &citations);
+        }
+
+        self.items = items;
+        self.kept += new.len();
+        Ok(())
+    }
+
+    /// Runs the statements as the body of `main`, printing its value and
+    /// keeping what it bound.
+    fn run(&mut self, input: &str, out: &mut dyn Write, errors: &mut dyn Write) -> io::Result<()> {
+        let map = self.program(input);
+        let (units, citations) = resolve::parse(&map);
+        if !citations.is_empty() {
+            return self.report(&map, errors, &citations);
+        }
+
+        let (value, locals) = match interpret::evaluate_bindings(&units, out) {
+            Ok(it) => it,
+            Err(error) => return self.report(&map, errors, &[error.into()]),
+        };
+        for local in locals {
+            self.bind(local, errors)?;
+        }
+
+        match value {
+            Value::Unit => Ok(()),
+            value => writeln!(out, "{}", value),
+        }
+    }
+
+    /// Keeps the value a name was left with for later inputs, if it can be
+    /// written as source that later inputs can run.
+    fn bind(&mut self, local: Local, errors: &mut dyn Write) -> io::Result<()> {
+        if local.name == "_" {
+            return Ok(());
+        }
+
+        self.bindings.retain(|(name, _)| name != local.name);
+        let value = match literal(&local.value) {
+            Some(value) => value,
+            None => {
+                return writeln!(
+                    errors,
+                    "note: `{}` isn't kept for later inputs, since `{}` can't be written as source",
+                    local.name, local.value
+                )
+            }
+        };
+
+        let mutable = if local.mutable { "mut " } else { "" };
+        let statement = format!("let {}{} = {};", mutable, local.name, value);
+        if !self.runs(&statement) {
+            // Like `True` from a builtin, without `Boolean` defined
+            return writeln!(
+                errors,
+                "note: `{}` isn't kept for later inputs, since `{}` doesn't run in them",
+                local.name, value
+            );
+        }
+        self.bindings.push((local.name.to_owned(), statement));
+        Ok(())
+    }
+
+    /// Whether the statements run after the names bound so far.
+    fn runs(&self, statements: &str) -> bool {
+        let map = self.program(statements);
+        let (units, citations) = resolve::parse(&map);
+        citations.is_empty() && interpret::run(&units, &mut io::sink()).is_ok()
+    }
+
+    fn tokens(&self, source: &str, out: &mut dyn Write, errors: &mut dyn Write) -> io::Result<()> {
+        let mut citations = Vec::new();
+        for token in token::scan(source) {
+            match token {
+                Ok(token) => writeln!(out, "{}", token)?,
+                Err(error) => citations.push(error.into()),
+            }
+        }
+
+        let mut map = SourceMap::new().color(self.color);
+        map.add("<repl>".to_owned(), source.to_owned());
+        self.report(&map, errors, &citations)
+    }
+
+    /// Prints the parsed items, or the body of `main` for statements.
+    fn ast(&self, source: &str, out: &mut dyn Write, errors: &mut dyn Write) -> io::Result<()> {
+        let mut map = SourceMap::new().color(self.color);
+        let defines = items(source);
+        if defines {
+            map.add("<repl>".to_owned(), terminated(source));
+        } else {
+            map.add("<repl>".to_owned(), wrap("", source));
+        }
+
+        let (units, citations) = resolve::parse(&map);
+        if !citations.is_empty() {
+            return self.report(&map, errors, &citations);
+        }
+
+        let items = &units[0].file.items;
+        match items.as_slice() {
+            [Item::Function(main)] if !defines => writeln!(out, "{:#?}", main.body),
+            _ => writeln!(out, "{:#?}", items),
+        }
+    }
+
+    /// Prints the type of the statements as the body of `main`.
+    fn ty(&self, source: &str, out: &mut dyn Write, errors: &mut dyn Write) -> io::Result<()> {
+        if items(source) {
+            return writeln!(errors, "`:type` takes statements, not items");
+        }
+
+        let map = self.program(source);
+        let (units, citations) = resolve::parse(&map);
+        if !citations.is_empty() {
+            return self.report(&map, errors, &citations);
+        }
+
+        match types::infer(&units, "main") {
+            (Some(ty), type_errors) if type_errors.is_empty() => writeln!(out, "{}", ty),
+            (_, type_errors) => {
+                let citations: Vec<_> = type_errors.into_iter().map(Citation::from).collect();
+                self.report(&map, errors, &citations)
+            }
+        }
+    }
+
+    /// A map with a file for each item.
+    fn map(&self, items: &[(String, String)]) -> SourceMap {
+        let mut map = SourceMap::new().color(self.color);
+        for (name, source) in items {
+            map.add(name.clone(), source.clone());
+        }
+        map
+    }
+
+    /// The items defined so far, with `main` made of the statements after
+    /// the names bound before.
+    fn program(&self, statements: &str) -> SourceMap {
+        let mut map = self.map(&self.items);
+        let bindings: Vec<_> = self.bindings.iter().map(|(_, it)| it.as_str()).collect();
+        map.add("<repl>".to_owned(), wrap(&bindings.join(" "), statements));
+        map
+    }
+
+    fn report(
+        &self,
+        map: &SourceMap,
+        mut errors: &mut dyn Write,
+        citations: &[Citation],
+    ) -> io::Result<()> {
+        match self.format {
+            ErrorFormat::Human => map.report(&mut errors, citations),
+            ErrorFormat::Json => map.report_json(&mut errors, citations),
+        }
+    }
+}
+
+/// Whether the input has as many closing brackets as opening ones, so that
+/// the REPL can stop asking for more lines. Extra closing brackets are left
+/// for the parser to complain about.
+pub fn complete(input: &str) -> bool {
+    let mut depth = 0;
+    for token in token::scan(input).flatten() {
+        match token.kind {
+            TokenKind::OpenParen | TokenKind::OpenBrace => depth += 1,
+            TokenKind::CloseParen | TokenKind::CloseBrace => depth -= 1,
+            _ => {}
+        }
+    }
+    depth <= 0
+}
+
+/// Whether the input starts with an item rather than a statement.
+fn items(input: &str) -> bool {
+    matches!(
+        token::scan(input).flatten().next().map(|token| token.kind),
+        Some(TokenKind::Keyword(
+            Keyword::Func | Keyword::Enum | Keyword::Trait | Keyword::Module | Keyword::Import
+        ))
+    )
+}
+
+/// The items with the `;` after the last one added if it's missing.
+fn terminated(input: &str) -> String {
+    let last = token::scan(input).flatten().last();
+    match last.map(|token| token.kind) {
+        Some(TokenKind::Semicolon) => input.to_owned(),
+        _ => format!("{};", input),
+    }
+}
+
+/// Splits items after the `;` ending each, leaving anything after the last
+/// one on it. Without one, the source is a single item.
+fn split(source: &str) -> Vec<&str> {
+    let mut ends = Vec::new();
+    let mut depth = 0;
+    for token in token::scan(source).flatten() {
+        match token.kind {
+            TokenKind::OpenParen | TokenKind::OpenBrace => depth += 1,
+            TokenKind::CloseParen | TokenKind::CloseBrace => depth -= 1,
+            TokenKind::Semicolon if depth == 0 => ends.push(token.span.end + 1),
+            _ => {}
+        }
+    }
+    match ends.last_mut() {
+        Some(last) => *last = source.len(),
+        None => ends.push(source.len()),
+    }
+
+    let mut start = 0;
+    ends.into_iter()
+        .map(|end| {
+            let item = source[start..end].trim();
+            start = end;
+            item
+        })
+        .collect()
+}
+
+/// Whether any of the new items redefines any of the old: they have the
+/// same name, and aren't functions that calls can tell apart.
+fn redefines(new: &[Item], old: &[Item]) -> bool {
+    fn name<'src>(item: &Item<'src>) -> Option<&'src str> {
+        match item {
+            Item::Function(function) => Some(function.name.name),
+            Item::Enum(enumeration) => Some(enumeration.name.name),
+            Item::Trait(trait_item) => Some(trait_item.name.name),
+            Item::Extension(extension) => Some(extension.name.name),
+            Item::Module(_) | Item::Import(_) => None,
+        }
+    }
+
+    new.iter().any(|new| {
+        old.iter().any(|old| match (new, old) {
+            (Item::Function(new), Item::Function(old)) => {
+                new.name.name == old.name.name && new.overlaps(old)
+            }
+            _ => name(new).is_some() && name(new) == name(old),
+        })
+    })
+}
+
+/// Source that evaluates to the value, if there is any. Blocks, functions
+/// and `Unit` don't have literals.
+fn literal(value: &Value) -> Option<String> {
+    Some(match value {
+        Value::Integer(it) if *it < 0 => format!("negate({})", it.checked_neg()?),
+        Value::Integer(it) => it.to_string(),
+        Value::Float(it) if !it.is_finite() => return None,
+        Value::Float(it) if *it < 0.0 => format!("negate({})", literal(&Value::Float(-it))?),
+        // Floats need a `.` to scan as floats
+        Value::Float(it) => match it.abs().to_string() {
+            text if text.contains('.') => text,
+            text => format!("{}.0", text),
+        },
+        Value::Character(it) => format!("'{}'", it.escape_debug()),
+        Value::String(it) => format!("\"{}\"", it.escape_debug()),
+        Value::Variant(variant) if variant.fields.is_empty() => variant.name.to_owned(),
+        Value::Variant(variant) => {
+            let fields: Option<Vec<_>> = variant.fields.iter().map(literal).collect();
+            format!("{}({})", variant.name, fields?.join(", "))
+        }
+        Value::Function(_) | Value::Closure(_) | Value::Unit => return None,
+    })
+}
+
+/// The statements as the body of `main` after the bindings, each statement
+/// on the line it was on.
+fn wrap(bindings: &str, statements: &str) -> String {
+    format!("func main() {{ {}\n{}\n}};", bindings, statements)
+}
@@ -0,0 +1,166 @@
+use super::*;
+
+/// Evaluates each input in turn in one session, returning what was printed
+/// and the rendered errors.
+fn session(inputs: &[&str]) -> (String, String) {
+    let mut session = Session::new(ErrorFormat::Human).color(false);
+    let (mut out, mut errors) = (Vec::new(), Vec::new());
+    for input in inputs {
+        session.eval(input, &mut out, &mut errors).unwrap();
+    }
+    (
+        String::from_utf8(out).unwrap(),
+        String::from_utf8(errors).unwrap(),
+    )
+}
+
+#[test]
+fn values() {
+    let (out, errors) = session(&["1.add(2)", "print_line(\"hi\");", "let x = 4; x.add(x)"]);
+
+    assert_eq!(errors, "");
+    assert_eq!(out, "3\nhi\n8\n");
+}
+
+#[test]
+fn definitions_kept() {
+    let (out, errors) = session(&[
+        "enum Light(Red, Green)",
+        "func twice(x: Int) -> Int { x.add(x) };",
+        "twice(x: 21)",
+        "Green",
+    ]);
+
+    assert_eq!(errors, "");
+    assert_eq!(out, "42\nGreen\n");
+}
+
+#[test]
+fn broken_definitions_dropped() {
+    let (out, errors) = session(&["func broken( {", "func fine() -> Int { 1 }", "fine()"]);
+
+    assert!(errors.contains("<repl 1>"), "{}", errors);
+    assert_eq!(out, "1\n");
+}
+
+#[test]
+fn redefinitions_replace() {
+    let (out, errors) = session(&[
+        "func f() -> Int { 1 }",
+        "func f() -> Int { 2 }; func f(x: Int) -> Int { x }",
+        "f()",
+        "f(x: 3)",
+        "enum Light(Red, Green)",
+        "enum Light(Red, Amber, Green)",
+        "Amber",
+    ]);
+
+    assert_eq!(errors, "");
+    assert_eq!(out, "2\n3\nAmber\n");
+}
+
+#[test]
+fn ill_typed_definitions_dropped() {
+    let (out, errors) = session(&[
+        "func g() -> Int { 'c' }",
+        "func h() -> Int { missing() }",
+        ":type 1",
+    ]);
+
+    assert!(errors.contains("<repl 1>"), "{}", errors);
+    assert!(errors.contains("Unresolved name `missing`"), "{}", errors);
+    assert_eq!(out, "Int\n");
+}
+
+#[test]
+fn bindings_kept() {
+    let (out, errors) = session(&[
+        "enum Optional<T>(Some(T), Nothing)",
+        "let x = 4; let mut text = \"a\\\"b\"; print_line(x)",
+        "text = text.add(\"c\"); let n = 3.subtract(5);",
+        "let pair = Some('\\n'); let f = 2.0;",
+        "x.add(1)",
+        "text",
+        "n",
+        "pair",
+        "f",
+        ":type pair",
+    ]);
+
+    assert_eq!(errors, "");
+    assert_eq!(out, "4\n5\na\"bc\n-2\nSome(\n)\n2.0\nOptional<Char>\n");
+}
+
+#[test]
+fn unwritable_bindings_noted() {
+    let (out, errors) = session(&["func one() -> Int { 1 }", "let p = one;", "p"]);
+
+    assert_eq!(out, "");
+    assert!(errors.starts_with("note: `p` isn't kept"), "{}", errors);
+    assert!(errors.contains("Unbound name `p`"), "{}", errors);
+}
+
+#[test]
+fn unrunnable_bindings_noted() {
+    // `Boolean` isn't defined in the session, so `True` can't be given back
+    let (out, errors) = session(&["let c = 1.equals(1); c", "1.add(1)", "c"]);
+
+    assert_eq!(out, "True\n2\n");
+    assert!(
+        errors.starts_with("note: `c` isn't kept for later inputs, since `True` doesn't run"),
+        "{}",
+        errors
+    );
+    assert!(errors.contains("Unbound name `c`"), "{}", errors);
+}
+
+#[test]
+fn runtime_errors() {
+    let (out, errors) = session(&["missing(1)", "2"]);
+
+    assert!(errors.contains("<repl>"), "{}", errors);
+    assert_eq!(out, "2\n");
+}
+
+#[test]
+fn tokens() {
+    let (out, errors) = session(&[":tokens let x"]);
+
+    assert_eq!(errors, "");
+    assert_eq!(out, "let [0..2]\nx [4..4]\n");
+}
+
+#[test]
+fn ast() {
+    let (out, errors) = session(&[":ast enum Light(Red, Green)", ":ast 1"]);
+
+    assert_eq!(errors, "");
+    assert!(out.starts_with("[\n    Enum(\n"), "{}", out);
+    assert!(out.contains("Block {\n"), "{}", out);
+}
+
+#[test]
+fn types() {
+    let (out, errors) = session(&["enum Light(Red, Green)", ":type Red", ":type 'c'"]);
+
+    assert_eq!(errors, "");
+    assert_eq!(out, "Light\nChar\n");
+}
+
+#[test]
+fn unknown_command() {
+    let (out, errors) = session(&[":frobnicate"]);
+
+    assert_eq!(out, "");
+    assert_eq!(errors, "unknown command `:frobnicate`, try `:help`\n");
+}
+
+#[test]
+fn completeness() {
+    assert!(complete("1.add(2)"));
+    assert!(!complete("func main() {"));
+    assert!(!complete("print(\n  1,"));
+    assert!(complete("func main() {\n  print(1)\n}"));
+    assert!(complete("\"{\""));
+    assert!(complete("}"));
+}
@@ -0,0 +1,74 @@
+use crate::error::{Citation, Span};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResolveError<'src> {
+    /// A name that isn't in scope.
+    Unresolved { name: &'src str, span: Span },
+    /// A path whose `name` isn't a member of the path before it.
+    UnresolvedMember {
+        path: String,
+        name: &'src str,
+        span: Span,
+    },
+    /// Two declarations of the same name in one module.
+    Duplicate {
+        name: &'src str,
+        first: Option<Span>,
+        second: Span,
+    },
+    /// A name that could refer to several things: imported into a module
+    /// that already has it from elsewhere, or only found in the prelude,
+    /// where several modules declare it.
+    Ambiguous {
+        name: &'src str,
+        /// Where each thing it could refer to is declared or imported.
+        candidates: Vec<Span>,
+        span: Span,
+    },
+    /// `module name;` anywhere but the start of a file.
+    ModuleNotFirst { span: Span },
+}
+
+impl<'src> From<ResolveError<'src>> for Citation {
+    fn from(error: ResolveError<'src>) -> Self {
+        match error {
+            ResolveError::Unresolved { name, span } => {
+                Citation::error(format!("Unresolved name `{}`", name))
+                    .span(span, Some("not found in this scope".to_owned()))
+            }
+            ResolveError::UnresolvedMember { path, name, span } => {
+                Citation::error(format!("Unresolved path `{}.{}`", path, name))
+                    .span(span, Some(format!("no `{}` in `{}`", name, path)))
+            }
+            ResolveError::Duplicate {
+                name,
+                first,
+                second,
+            } => {
+                let citation = Citation::error(format!("`{}` is defined multiple times", name))
+                    .span(second, Some("redefined here".to_owned()));
+                match first {
+                    Some(first) => citation.secondary(first, Some("first defined here".to_owned())),
+                    None => citation,
+                }
+            }
+            ResolveError::Ambiguous {
+                name,
+                candidates,
+                span,
+            } => candidates.into_iter().fold(
+                Citation::error(format!("`{}` is ambiguous", name))
+                    .span(span, Some("could refer to more than one thing".to_owned()))
+                    .help(format!("import only the `{}` meant", name)),
+                |citation, candidate| {
+                    citation.secondary(candidate, Some("it could be this".to_owned()))
+                },
+            ),
+            ResolveError::ModuleNotFirst { span } => {
+                Citation::error("Module declarations must come first in a file".to_owned())
+                    .span(span, None)
+                    .help("move this to the top of the file".to_owned())
+            }
+        }
+    }
+}
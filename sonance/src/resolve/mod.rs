@@ -0,0 +1,1001 @@
+//! Name resolution across a directory of `.son` files.
+//!
+//! Every directory is a module, and so is every file that starts with
+//! `module name;`, nested inside its directory's module. Files without a
+//! declaration add their items to their directory's module. Enum variants
+//! are declared alongside their enum.
+//!
+//! Import paths start at the root module. Other names are looked up in the
+//! enclosing scopes, then the module, then each module it's nested in, so
+//! everything declared at the root is visible everywhere. A block passed to
+//! a function also sees the names its parameter's `block(name: T)` type
+//! gives it.
+//!
+//! Names that aren't found that way fall back to the prelude, which holds the
+//! items of every module. The interpreter and type checker see every file as
+//! one namespace, and the prelude matches them, so the files of the core
+//! library can use each other's items without imports. Where several modules
+//! declare the same name, other than as overloads, using it without an
+//! import is ambiguous.
+
+mod error;
+pub mod overload;
+#[cfg(test)]
+mod test;
+
+pub use error::ResolveError;
+
+use crate::{
+    error::{Citation, SourceMap, Span},
+    interpret::BUILTINS,
+    parser::{self, ast::*},
+    token,
+    types::PRIMITIVES,
+};
+use overload::Assignment;
+use std::{collections::HashMap, fs, io, iter, path::Path};
+
+/// Adds every `.son` file under `root` to the map, named by its path from
+/// `root`. Files are added in name order so the spans are stable.
+pub fn read_dir(map: &mut SourceMap, root: &Path) -> io::Result<()> {
+    fn visit(map: &mut SourceMap, root: &Path, directory: &Path) -> io::Result<()> {
+        let mut entries = fs::read_dir(directory)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<io::Result<Vec<_>>>()?;
+        entries.sort();
+
+        for path in entries {
+            if path.is_dir() {
+                visit(map, root, &path)?;
+            } else if path.extension().is_some_and(|it| it == "son") {
+                let relative = path.strip_prefix(root).unwrap_or(&path);
+                let name = relative
+                    .iter()
+                    .map(|part| part.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                map.add(name, fs::read_to_string(&path)?);
+            }
+        }
+
+        Ok(())
+    }
+
+    visit(map, root, root)
+}
+
+/// A parsed file and where it sits in the directory tree.
+#[derive(Debug, Clone)]
+pub struct Unit<'src> {
+    /// The directories between the root and the file.
+    pub directory: Vec<&'src str>,
+    pub file: File<'src>,
+}
+
+/// Scans and parses every file in the map, with spans in the map's space.
+/// Files that fail to parse keep the items that did.
+pub fn parse(map: &SourceMap) -> (Vec<Unit<'_>>, Vec<Citation>) {
+    let mut units = Vec::new();
+    let mut citations = Vec::new();
+
+    for file in map.files() {
+        let mut tokens = Vec::new();
+        let mut scan_errors = Vec::new();
+
+        for token in token::scan(&file.source) {
+            match token {
+                Ok(mut token) => {
+                    token.span = file.span(token.span);
+                    tokens.push(token);
+                }
+                Err(error) => scan_errors.push(file.citation(error.into())),
+            }
+        }
+
+        let (parsed, errors) = parser::parse_recovering(tokens);
+
+        // Parsing around tokens that didn't scan mostly cascades
+        if scan_errors.is_empty() {
+            citations.extend(errors.into_iter().map(Citation::from));
+        } else {
+            citations.append(&mut scan_errors);
+        }
+
+        let mut directory: Vec<_> = file.name.split('/').collect();
+        directory.pop();
+        units.push(Unit {
+            directory,
+            file: parsed,
+        });
+    }
+
+    (units, citations)
+}
+
+pub type ModuleId = usize;
+
+/// Something a name can refer to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Definition {
+    Module(ModuleId),
+    /// An item, by its unit and its index among the unit's items.
+    Item(usize, usize),
+    /// A variant of an enum item, by its index among the enum's variants.
+    Variant(usize, usize, usize),
+}
+
+/// A name in a module.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Binding {
+    pub definition: Definition,
+    /// Where the name was declared or imported. Modules that are only a
+    /// directory don't have one.
+    pub span: Option<Span>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Module<'src> {
+    /// Empty for the root.
+    pub name: &'src str,
+    pub parent: Option<ModuleId>,
+    /// Names declared in the module: child modules, items and variants.
+    pub names: HashMap<&'src str, Binding>,
+    /// Names brought in by imports.
+    pub imports: HashMap<&'src str, Binding>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Resolution<'src> {
+    /// The module tree, with the root first.
+    pub modules: Vec<Module<'src>>,
+    /// The module each unit's items belong to.
+    pub unit_modules: Vec<ModuleId>,
+    /// The span of each name in the source, with the span of what it refers
+    /// to. Declarations refer to themselves.
+    pub references: Vec<(Span, Span)>,
+    pub errors: Vec<ResolveError<'src>>,
+}
+
+impl Resolution<'_> {
+    /// Where the name at `index` was declared.
+    pub fn definition(&self, index: usize) -> Option<Span> {
+        self.references
+            .iter()
+            .find(|(name, _)| name.start <= index && index <= name.end)
+            .map(|(_, target)| *target)
+    }
+}
+
+pub fn resolve<'src>(units: &[Unit<'src>]) -> Resolution<'src> {
+    let mut resolver = Resolver {
+        units,
+        resolution: Resolution {
+            modules: vec![Module {
+                name: "",
+                parent: None,
+                names: HashMap::new(),
+                imports: HashMap::new(),
+            }],
+            unit_modules: Vec::new(),
+            references: Vec::new(),
+            errors: Vec::new(),
+        },
+        module: 0,
+        prelude: HashMap::new(),
+        locals: Vec::new(),
+        deferred: Vec::new(),
+        functions: Vec::new(),
+    };
+
+    for unit in units {
+        let module = resolver.unit_module(unit);
+        resolver.resolution.unit_modules.push(module);
+    }
+    for index in 0..units.len() {
+        resolver.declare_items(index);
+    }
+    resolver.prelude();
+    for index in 0..units.len() {
+        resolver.imports(index);
+    }
+    for index in 0..units.len() {
+        resolver.bodies(index);
+    }
+
+    resolver.resolution
+}
+
+struct Resolver<'a, 'src> {
+    units: &'a [Unit<'src>],
+    resolution: Resolution<'src>,
+    /// The module whose items are being walked.
+    module: ModuleId,
+    /// Items of every module, for names not found in scope, with every
+    /// module's item for names declared in several.
+    prelude: HashMap<&'src str, Vec<Binding>>,
+    /// Names in scope at the current point of the walk, innermost last.
+    locals: Vec<Ident<'src>>,
+    /// Names a block is given by some of the functions it may be passed to
+    /// but not others, with how many locals were in scope before them.
+    /// Which one it is is left to the type checker.
+    deferred: Vec<(&'src str, usize)>,
+    /// The functions being walked, for `return@name`.
+    functions: Vec<Ident<'src>>,
+}
+
+/// What a name was found to be.
+#[derive(Debug, Clone, Copy)]
+enum Lookup {
+    Local(Span),
+    /// A block parameter whose declaration depends on the overload chosen.
+    Deferred,
+    Global(Binding),
+    /// A name only in the prelude, from several modules.
+    Ambiguous,
+}
+
+impl<'a, 'src> Resolver<'a, 'src> {
+    /// Finds or makes the module a unit's items belong to.
+    fn unit_module(&mut self, unit: &Unit<'src>) -> ModuleId {
+        let mut module = 0;
+        for name in &unit.directory {
+            module = self.child_module(module, name, None);
+        }
+
+        for (index, item) in unit.file.items.iter().enumerate() {
+            if let Item::Module(declaration) = item {
+                if index == 0 {
+                    module =
+                        self.child_module(module, declaration.name.name, Some(declaration.name));
+                } else {
+                    self.resolution.errors.push(ResolveError::ModuleNotFirst {
+                        span: declaration.span,
+                    });
+                }
+            }
+        }
+
+        module
+    }
+
+    /// The child of `parent` called `name`, made if it doesn't exist yet.
+    /// Declaring a module twice is an error, but both files still share it.
+    fn child_module(
+        &mut self,
+        parent: ModuleId,
+        name: &'src str,
+        declaration: Option<Ident<'src>>,
+    ) -> ModuleId {
+        let span = declaration.map(|it| it.span);
+        if let Some(span) = span {
+            self.resolution.references.push((span, span));
+        }
+
+        // Items are declared after every module, so only modules are here yet
+        let existing = self.resolution.modules[parent].names.get(name).copied();
+        if let Some(Binding {
+            definition: Definition::Module(module),
+            span: first,
+        }) = existing
+        {
+            match (first, span) {
+                (Some(first), Some(second)) => {
+                    self.resolution.errors.push(ResolveError::Duplicate {
+                        name,
+                        first: Some(first),
+                        second,
+                    })
+                }
+                // A directory gets its declaration from the file naming it
+                (None, Some(_)) => {
+                    self.resolution.modules[parent].names.insert(
+                        name,
+                        Binding {
+                            definition: Definition::Module(module),
+                            span,
+                        },
+                    );
+                }
+                _ => {}
+            }
+            return module;
+        }
+
+        let module = self.resolution.modules.len();
+        self.resolution.modules.push(Module {
+            name,
+            parent: Some(parent),
+            names: HashMap::new(),
+            imports: HashMap::new(),
+        });
+        self.resolution.modules[parent].names.insert(
+            name,
+            Binding {
+                definition: Definition::Module(module),
+                span,
+            },
+        );
+        module
+    }
+
+    fn declare_items(&mut self, unit: usize) {
+        let module = self.resolution.unit_modules[unit];
+
+        for (index, item) in self.units[unit].file.items.iter().enumerate() {
+            let name = match item {
+                Item::Function(function) => function.name,
+                Item::Enum(enumeration) => {
+                    for (variant, declaration) in enumeration.variants.iter().enumerate() {
+                        let definition = Definition::Variant(unit, index, variant);
+                        self.declare(module, declaration.name, definition);
+                    }
+                    enumeration.name
+                }
+                Item::Trait(trait_item) => trait_item.name,
+                Item::Extension(extension) => extension.name,
+                Item::Module(_) | Item::Import(_) => continue,
+            };
+            self.declare(module, name, Definition::Item(unit, index));
+        }
+    }
+
+    fn declare(&mut self, module: ModuleId, name: Ident<'src>, definition: Definition) {
+        self.resolution.references.push((name.span, name.span));
+
+        let binding = Binding {
+            definition,
+            span: Some(name.span),
+        };
+        match self.resolution.modules[module]
+            .names
+            .get(name.name)
+            .copied()
+        {
+            // Overloads keep the first as what the name refers to
+            Some(first) if self.overloads(first.definition, definition) => {}
+            Some(first) => self.resolution.errors.push(ResolveError::Duplicate {
+                name: name.name,
+                first: first.span,
+                second: name.span,
+            }),
+            None => {
+                self.resolution.modules[module]
+                    .names
+                    .insert(name.name, binding);
+            }
+        }
+    }
+
+    /// Whether two definitions are functions that calls can tell apart.
+    fn overloads(&self, first: Definition, second: Definition) -> bool {
+        let function = |definition| match definition {
+            Definition::Item(unit, index) => match &self.units[unit].file.items[index] {
+                Item::Function(function) => Some(function),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        match (function(first), function(second)) {
+            (Some(first), Some(second)) => !first.overlaps(second),
+            _ => false,
+        }
+    }
+
+    /// Fills the prelude from every module's items and variants.
+    fn prelude(&mut self) {
+        for module in &self.resolution.modules {
+            for (name, binding) in &module.names {
+                if !matches!(binding.definition, Definition::Module(_)) {
+                    self.prelude.entry(name).or_default().push(*binding);
+                }
+            }
+        }
+    }
+
+    fn imports(&mut self, unit: usize) {
+        let module = self.resolution.unit_modules[unit];
+
+        for item in &self.units[unit].file.items {
+            if let Item::Import(import) = item {
+                self.import(module, &import.tree, None, &mut Vec::new());
+            }
+        }
+    }
+
+    /// Imports what a tree names into `module`. Each name in the tree is
+    /// looked up once, in what the path before it names, or in the root
+    /// module for the first. A name that isn't found is reported, and
+    /// nothing after it is imported.
+    fn import(
+        &mut self,
+        module: ModuleId,
+        tree: &ImportTree<'src>,
+        mut binding: Option<Binding>,
+        path: &mut Vec<Ident<'src>>,
+    ) {
+        let length = path.len();
+
+        for name in &tree.path {
+            let found = match binding {
+                Some(binding) => self.member(binding.definition, name.name),
+                None => self.resolution.modules[0].names.get(name.name).copied(),
+            };
+            match found {
+                Some(found) => {
+                    self.reference(*name, found);
+                    binding = Some(found);
+                    path.push(*name);
+                }
+                None => {
+                    self.resolution.errors.push(match binding {
+                        Some(_) => ResolveError::UnresolvedMember {
+                            path: join(path),
+                            name: name.name,
+                            span: name.span,
+                        },
+                        None => ResolveError::Unresolved {
+                            name: name.name,
+                            span: name.span,
+                        },
+                    });
+                    path.truncate(length);
+                    return;
+                }
+            }
+        }
+
+        match (&tree.children, binding, path.last()) {
+            (Some(children), _, _) => {
+                for child in children {
+                    self.import(module, child, binding, path);
+                }
+            }
+            (None, Some(binding), Some(&name)) => self.bring(module, name, binding),
+            (None, _, _) => {}
+        }
+
+        path.truncate(length);
+    }
+
+    /// Adds an imported name to a module, unless it has the name already.
+    fn bring(&mut self, module: ModuleId, name: Ident<'src>, binding: Binding) {
+        let scope = &self.resolution.modules[module];
+        let existing = scope
+            .names
+            .get(name.name)
+            .or_else(|| scope.imports.get(name.name));
+
+        match existing {
+            Some(existing) if existing.definition == binding.definition => {}
+            Some(existing) => self.resolution.errors.push(ResolveError::Ambiguous {
+                name: name.name,
+                candidates: existing.span.into_iter().chain(binding.span).collect(),
+                span: name.span,
+            }),
+            None => {
+                self.resolution.modules[module]
+                    .imports
+                    .insert(name.name, binding);
+            }
+        }
+    }
+
+    /// A member of a module or enum.
+    fn member(&self, definition: Definition, name: &str) -> Option<Binding> {
+        match definition {
+            Definition::Module(module) => self.resolution.modules[module].names.get(name).copied(),
+            Definition::Item(unit, index) => match &self.units[unit].file.items[index] {
+                Item::Enum(enumeration) => enumeration
+                    .variants
+                    .iter()
+                    .position(|variant| variant.name.name == name)
+                    .map(|variant| Binding {
+                        definition: Definition::Variant(unit, index, variant),
+                        span: Some(enumeration.variants[variant].name.span),
+                    }),
+                _ => None,
+            },
+            Definition::Variant(..) => None,
+        }
+    }
+
+    fn reference(&mut self, name: Ident<'src>, binding: Binding) {
+        if let Some(span) = binding.span {
+            self.resolution.references.push((name.span, span));
+        }
+    }
+
+    /// Looks a name up in the enclosing scopes, then the modules, then the
+    /// prelude.
+    fn lookup(&self, name: &str) -> Option<Lookup> {
+        let local = self.locals.iter().rposition(|local| local.name == name);
+        let deferred = self.deferred.iter().rev().find(|(it, _)| *it == name);
+        match (local, deferred) {
+            (Some(local), Some((_, before))) if local < *before => return Some(Lookup::Deferred),
+            (Some(local), _) => return Some(Lookup::Local(self.locals[local].span)),
+            (None, Some(_)) => return Some(Lookup::Deferred),
+            (None, None) => {}
+        }
+
+        let mut module = Some(self.module);
+        while let Some(id) = module {
+            let scope = &self.resolution.modules[id];
+            if let Some(binding) = scope.names.get(name).or_else(|| scope.imports.get(name)) {
+                return Some(Lookup::Global(*binding));
+            }
+            module = scope.parent;
+        }
+
+        let candidates = self.prelude.get(name)?;
+        let first = candidates[0];
+        let overloads = candidates[1..]
+            .iter()
+            .all(|it| self.overloads(first.definition, it.definition));
+        Some(if overloads {
+            Lookup::Global(first)
+        } else {
+            Lookup::Ambiguous
+        })
+    }
+
+    /// Resolves a use of a name, reporting it if it isn't in scope.
+    fn resolve_name(&mut self, name: Ident<'src>) -> Option<Definition> {
+        match self.lookup(name.name) {
+            Some(Lookup::Local(span)) => {
+                self.resolution.references.push((name.span, span));
+                None
+            }
+            Some(Lookup::Deferred) => None,
+            Some(Lookup::Ambiguous) => {
+                let candidates = &self.prelude[name.name];
+                self.resolution.errors.push(ResolveError::Ambiguous {
+                    name: name.name,
+                    candidates: candidates.iter().filter_map(|it| it.span).collect(),
+                    span: name.span,
+                });
+                None
+            }
+            Some(Lookup::Global(binding)) => {
+                self.reference(name, binding);
+                Some(binding.definition)
+            }
+            // Built in names can be shadowed, but are always there
+            None if PRIMITIVES.contains(&name.name) || BUILTINS.contains(&name.name) => None,
+            None => {
+                self.resolution.errors.push(ResolveError::Unresolved {
+                    name: name.name,
+                    span: name.span,
+                });
+                None
+            }
+        }
+    }
+
+    /// Resolves `member` within what `path` resolved to, if that has members.
+    fn resolve_member(
+        &mut self,
+        path: &Expression<'src>,
+        definition: Option<Definition>,
+        member: Ident<'src>,
+    ) -> Option<Definition> {
+        let definition = definition.filter(|definition| match definition {
+            Definition::Module(_) => true,
+            Definition::Item(unit, index) => {
+                matches!(self.units[*unit].file.items[*index], Item::Enum(_))
+            }
+            Definition::Variant(..) => false,
+        })?;
+
+        match self.member(definition, member.name) {
+            Some(binding) => {
+                self.reference(member, binding);
+                Some(binding.definition)
+            }
+            None => {
+                self.resolution.errors.push(ResolveError::UnresolvedMember {
+                    path: path_text(path),
+                    name: member.name,
+                    span: member.span,
+                });
+                None
+            }
+        }
+    }
+
+    /// Brings a new name into the innermost scope.
+    fn bind(&mut self, name: Ident<'src>) {
+        self.resolution.references.push((name.span, name.span));
+        self.locals.push(name);
+    }
+
+    fn bodies(&mut self, unit: usize) {
+        self.module = self.resolution.unit_modules[unit];
+        let units = self.units;
+
+        for item in &units[unit].file.items {
+            match item {
+                Item::Function(function) => self.function(function),
+                Item::Enum(enumeration) => {
+                    self.locals.push(Ident {
+                        name: "Self",
+                        span: enumeration.name.span,
+                    });
+                    self.generics(&enumeration.generics);
+
+                    for variant in &enumeration.variants {
+                        for field in &variant.fields {
+                            self.ty(&field.ty);
+                        }
+                    }
+                    for ty in &enumeration.traits {
+                        self.ty(ty);
+                    }
+                    self.methods(&enumeration.functions);
+                }
+                Item::Extension(extension) => {
+                    self.generics(&extension.generics);
+                    self.ty(&extension.target);
+                    self.locals.push(Ident {
+                        name: "Self",
+                        span: extension.target.span,
+                    });
+
+                    for ty in &extension.traits {
+                        self.ty(ty);
+                    }
+                    self.methods(&extension.functions);
+                }
+                Item::Trait(trait_item) => {
+                    self.locals.push(Ident {
+                        name: "Self",
+                        span: trait_item.name.span,
+                    });
+                    self.generics(&trait_item.generics);
+
+                    for signature in &trait_item.functions {
+                        let scope = self.locals.len();
+                        self.resolution
+                            .references
+                            .push((signature.name.span, signature.name.span));
+                        self.generics(&signature.generics);
+                        self.parameters(&signature.parameters);
+                        if let Some(ty) = &signature.return_type {
+                            self.ty(ty);
+                        }
+                        self.locals.truncate(scope);
+                    }
+                }
+                Item::Module(_) | Item::Import(_) => {}
+            }
+
+            self.locals.clear();
+        }
+    }
+
+    fn methods(&mut self, functions: &[Function<'src>]) {
+        for function in functions {
+            // Methods aren't declared in the module like items are
+            let name = function.name.span;
+            self.resolution.references.push((name, name));
+            self.function(function);
+        }
+    }
+
+    fn function(&mut self, function: &Function<'src>) {
+        let scope = self.locals.len();
+
+        self.generics(&function.generics);
+        self.parameters(&function.parameters);
+        if let Some(ty) = &function.return_type {
+            self.ty(ty);
+        }
+
+        self.functions.push(function.name);
+        self.block(&function.body);
+        self.functions.pop();
+
+        self.locals.truncate(scope);
+    }
+
+    fn generics(&mut self, generics: &[GenericParameter<'src>]) {
+        for generic in generics {
+            self.bind(generic.name);
+        }
+        for generic in generics {
+            if let Some(bound) = &generic.bound {
+                self.ty(bound);
+            }
+        }
+    }
+
+    fn parameters(&mut self, parameters: &[Parameter<'src>]) {
+        for parameter in parameters {
+            self.ty(&parameter.ty);
+            if let Pattern::Binding { name, .. } = parameter.pattern {
+                self.bind(name);
+            }
+        }
+    }
+
+    fn ty(&mut self, ty: &Type<'src>) {
+        match &ty.kind {
+            TypeKind::Named { name, parameters } => {
+                self.resolve_name(*name);
+                for parameter in parameters {
+                    self.ty(&parameter.ty);
+                }
+            }
+            TypeKind::Block {
+                parameters,
+                return_type,
+            } => {
+                for parameter in parameters {
+                    self.ty(&parameter.ty);
+                }
+                if let Some(ty) = return_type {
+                    self.ty(ty);
+                }
+            }
+        }
+    }
+
+    fn block(&mut self, block: &Block<'src>) {
+        let scope = self.locals.len();
+
+        for statement in &block.statements {
+            match statement {
+                Statement::Let(statement) => {
+                    // The value can't see the name it's being bound to
+                    self.expression(&statement.value);
+                    if let Some(ty) = &statement.ty {
+                        self.ty(ty);
+                    }
+                    if let Pattern::Binding { name, .. } = statement.pattern {
+                        self.bind(name);
+                    }
+                }
+                Statement::Assign(assignment) => {
+                    self.resolve_name(assignment.name);
+                    self.expression(&assignment.value);
+                }
+                Statement::Expression(expression) => self.expression(expression),
+            }
+        }
+        if let Some(tail) = &block.tail {
+            self.expression(tail);
+        }
+
+        self.locals.truncate(scope);
+    }
+
+    fn expression(&mut self, expression: &Expression<'src>) {
+        self.path(expression);
+    }
+
+    /// Walks an expression, returning what it names if it's a path to a
+    /// module, item or variant.
+    fn path(&mut self, expression: &Expression<'src>) -> Option<Definition> {
+        match &expression.kind {
+            ExpressionKind::Literal(_) => None,
+            ExpressionKind::Name(name) => self.resolve_name(*name),
+            ExpressionKind::Call(call) => {
+                self.resolve_name(call.name);
+                self.arguments(call, false);
+                None
+            }
+            ExpressionKind::Property { receiver, name } => {
+                let definition = self.path(receiver);
+                self.resolve_member(receiver, definition, *name)
+            }
+            ExpressionKind::Chain { receiver, call } => {
+                // `module.function()` is a path, otherwise the call is
+                // resolved by the receiver's type
+                let definition = self.path(receiver);
+                let member = self.resolve_member(receiver, definition, call.name);
+                self.arguments(call, member.is_none());
+                None
+            }
+            ExpressionKind::Match { scrutinee, clauses } => {
+                self.expression(scrutinee);
+
+                for clause in clauses {
+                    let scope = self.locals.len();
+                    self.pattern(&clause.pattern);
+                    self.expression(&clause.body);
+                    self.locals.truncate(scope);
+                }
+                None
+            }
+            ExpressionKind::Loop(block) | ExpressionKind::Block(block) => {
+                self.block(block);
+                None
+            }
+            ExpressionKind::Return { label, value } => {
+                let function = label.and_then(|label| {
+                    let function = self
+                        .functions
+                        .iter()
+                        .rev()
+                        .find(|it| it.name == label.name)?;
+                    Some((label.span, function.span))
+                });
+                if let Some(reference) = function {
+                    self.resolution.references.push(reference);
+                }
+                if let Some(value) = value {
+                    self.expression(value);
+                }
+                None
+            }
+        }
+    }
+
+    /// Walks a call's arguments, with a value passed before them if it has
+    /// a `receiver`. Blocks see the names the functions they're passed to
+    /// give them.
+    fn arguments(&mut self, call: &Call<'src>, receiver: bool) {
+        for argument in &call.type_arguments {
+            self.ty(&argument.ty);
+        }
+        for argument in &call.arguments {
+            self.expression(&argument.value);
+        }
+
+        let before = usize::from(receiver) + call.arguments.len();
+        for (index, argument) in call.block_arguments.iter().enumerate() {
+            let scope = (self.locals.len(), self.deferred.len());
+
+            let names = self.block_parameters(call, receiver, before + index);
+            let same = |a: &Vec<Ident>, b: &Vec<Ident>| {
+                a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.name == b.name)
+            };
+            match names.split_first() {
+                Some((first, rest)) if rest.iter().all(|it| same(first, it)) => {
+                    self.locals.extend(first)
+                }
+                _ => {
+                    let before = self.locals.len();
+                    let names = names.iter().flatten().map(|name| (name.name, before));
+                    self.deferred.extend(names)
+                }
+            }
+
+            self.block(&argument.block);
+            self.locals.truncate(scope.0);
+            self.deferred.truncate(scope.1);
+        }
+    }
+
+    /// The names the block argument at `index` is given by each function
+    /// called like `call` that its arguments fit, or by each function of
+    /// its name if none fit. A chain's receiver is the first argument.
+    fn block_parameters(
+        &self,
+        call: &Call<'src>,
+        receiver: bool,
+        index: usize,
+    ) -> Vec<Vec<Ident<'src>>> {
+        let methods = |functions: &'a [Function<'src>]| {
+            let signatures = functions.iter();
+            signatures.map(|it| (it.name, &it.parameters[..])).collect()
+        };
+        let mut candidates = Vec::new();
+        for unit in self.units {
+            for item in &unit.file.items {
+                let signatures: Vec<_> = match item {
+                    Item::Function(function) => vec![(function.name, &function.parameters[..])],
+                    Item::Enum(enumeration) => methods(&enumeration.functions),
+                    Item::Extension(extension) => methods(&extension.functions),
+                    Item::Trait(trait_item) => trait_item
+                        .functions
+                        .iter()
+                        .map(|it| (it.name, &it.parameters[..]))
+                        .collect(),
+                    Item::Module(_) | Item::Import(_) => Vec::new(),
+                };
+                let named = signatures
+                    .into_iter()
+                    .filter(|(it, _)| it.name == call.name.name);
+                candidates.extend(named.map(|(_, parameters)| parameters));
+            }
+        }
+
+        let labels: Vec<_> = iter::repeat_n(None, usize::from(receiver))
+            .chain(call.arguments.iter().map(|it| it.label))
+            .chain(call.block_arguments.iter().map(|it| it.label))
+            .collect();
+        let assignments: Vec<_> = candidates
+            .iter()
+            .map(|parameters| {
+                let parameters: Vec<_> = parameters.iter().map(Parameter::argument_label).collect();
+                overload::assign(&parameters, &labels)
+            })
+            .collect();
+        let fitting = assignments.iter().any(Assignment::fits);
+
+        candidates
+            .iter()
+            .zip(&assignments)
+            .filter(|(_, assignment)| !fitting || assignment.fits())
+            .map(|(parameters, assignment)| {
+                let slot = assignment.slots.iter().position(|it| *it == Some(index));
+                slot.map_or_else(Vec::new, |slot| block_names(&parameters[slot].ty))
+            })
+            .collect()
+    }
+
+    /// A pattern in a match clause, where a bare name may be a variant.
+    fn pattern(&mut self, pattern: &Pattern<'src>) {
+        match pattern {
+            Pattern::Binding { name, .. } => match self.lookup(name.name) {
+                Some(Lookup::Global(
+                    binding @ Binding {
+                        definition: Definition::Variant(..),
+                        ..
+                    },
+                )) => self.reference(*name, binding),
+                Some(Lookup::Ambiguous) => {
+                    self.resolve_name(*name);
+                }
+                _ => self.bind(*name),
+            },
+            Pattern::Variant { path, fields, .. } => {
+                if let Some((first, rest)) = path.split_first() {
+                    let mut definition = self.resolve_name(*first);
+                    for (index, name) in rest.iter().enumerate() {
+                        let binding = definition.and_then(|it| self.member(it, name.name));
+                        definition = binding.map(|it| it.definition);
+                        match binding {
+                            Some(binding) => self.reference(*name, binding),
+                            None => self.resolution.errors.push(ResolveError::UnresolvedMember {
+                                path: join(&path[..=index]),
+                                name: name.name,
+                                span: name.span,
+                            }),
+                        }
+                    }
+                }
+                for field in fields {
+                    self.pattern(field);
+                }
+            }
+        }
+    }
+}
+
+/// The names a block passed where `ty` goes is given. A single unnamed
+/// value is called `it`.
+fn block_names<'src>(ty: &Type<'src>) -> Vec<Ident<'src>> {
+    match &ty.kind {
+        TypeKind::Block { parameters, .. } if parameters.len() == 1 => {
+            vec![parameters[0].label.unwrap_or(Ident {
+                name: "it",
+                span: parameters[0].ty.span,
+            })]
+        }
+        TypeKind::Block { parameters, .. } => parameters
+            .iter()
+            .filter_map(|parameter| parameter.label)
+            .collect(),
+        TypeKind::Named { .. } => Vec::new(),
+    }
+}
+
+fn join(path: &[Ident]) -> String {
+    path.iter()
+        .map(|name| name.name)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// The source form of a path expression, for messages.
+fn path_text(expression: &Expression) -> String {
+    match &expression.kind {
+        ExpressionKind::Name(name) => name.name.to_owned(),
+        ExpressionKind::Property { receiver, name } => {
+            format!("{}.{}", path_text(receiver), name.name)
+        }
+        _ => "expression".to_owned(),
+    }
+}
@@ -0,0 +1,143 @@
+//! Picking which of the functions with the same name a call goes to. The
+//! type checker and interpreter both use this, and differ only in how much
+//! they know about the receiver.
+
+use crate::{error::Span, parser::ast::Ident};
+
+/// Where a call's arguments go among a function's parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assignment<'src> {
+    /// The index of the argument each parameter is given, if any.
+    pub slots: Vec<Option<usize>>,
+    /// Unlabelled arguments left over once every parameter has one.
+    pub extra: Vec<usize>,
+    /// Labelled arguments with no parameter of that label left.
+    pub unknown: Vec<(usize, Ident<'src>)>,
+}
+
+impl<'src> Assignment<'src> {
+    /// Whether every argument has a parameter and every parameter has an
+    /// argument.
+    pub fn fits(&self) -> bool {
+        self.extra.is_empty() && self.unknown.is_empty() && self.slots.iter().all(Option::is_some)
+    }
+
+    /// What's wrong with the arguments, and where if not the whole call.
+    pub fn problem(&self) -> Option<(String, Option<Span>)> {
+        if let Some((_, label)) = self.unknown.first() {
+            return Some((
+                format!("no parameter labelled `{}` left", label.name),
+                Some(label.span),
+            ));
+        }
+
+        let missing = self.slots.iter().filter(|it| it.is_none()).count();
+        if missing > 0 || !self.extra.is_empty() {
+            let expected = self.slots.len();
+            let found = expected - missing + self.extra.len();
+            return Some((
+                format!("takes {} arguments, found {}", expected, found),
+                None,
+            ));
+        }
+        None
+    }
+}
+
+/// Matches arguments to parameters, given the label each parameter takes
+/// and each argument has. Labelled arguments go to the parameter with that
+/// label, and the rest fill the other parameters in order.
+pub fn assign<'src>(
+    parameters: &[Option<&str>],
+    arguments: &[Option<Ident<'src>>],
+) -> Assignment<'src> {
+    let mut assignment = Assignment {
+        slots: vec![None; parameters.len()],
+        extra: Vec::new(),
+        unknown: Vec::new(),
+    };
+    let mut unlabelled = Vec::new();
+
+    for (index, label) in arguments.iter().enumerate() {
+        let label = match label {
+            Some(label) => label,
+            None => {
+                unlabelled.push(index);
+                continue;
+            }
+        };
+
+        let slot = parameters
+            .iter()
+            .zip(&assignment.slots)
+            .position(|(parameter, slot)| *parameter == Some(label.name) && slot.is_none());
+        match slot {
+            Some(slot) => assignment.slots[slot] = Some(index),
+            None => assignment.unknown.push((index, *label)),
+        }
+    }
+
+    let mut unlabelled = unlabelled.into_iter();
+    for slot in assignment.slots.iter_mut().filter(|slot| slot.is_none()) {
+        *slot = unlabelled.next();
+    }
+    assignment.extra = unlabelled.collect();
+    assignment
+}
+
+/// How closely a function that fits a call is tied to it. Calls go to the
+/// closest function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Rank {
+    /// Declared in the receiver's type, or for calls without a receiver,
+    /// in the type the call is made in.
+    Owner,
+    /// Not declared in a type, like free functions and constructors.
+    Free,
+    /// Declared in some other type.
+    Other,
+}
+
+impl Rank {
+    pub fn of(owner: Option<&str>, receiver: Option<&str>) -> Self {
+        match owner {
+            None => Rank::Free,
+            Some(_) if owner == receiver => Rank::Owner,
+            Some(_) => Rank::Other,
+        }
+    }
+}
+
+/// Which function a call goes to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Choice<T> {
+    One(T),
+    /// Several functions fit equally well.
+    Ambiguous(Vec<T>),
+    None,
+}
+
+/// Picks the closest of the functions that fit a call, given with their
+/// ranks.
+pub fn choose<T>(fitting: impl IntoIterator<Item = (T, Rank)>) -> Choice<T> {
+    let mut best: Option<Rank> = None;
+    let mut chosen = Vec::new();
+
+    for (candidate, rank) in fitting {
+        match best {
+            Some(best) if rank > best => continue,
+            Some(best) if rank == best => {}
+            _ => {
+                best = Some(rank);
+                chosen.clear();
+            }
+        }
+        chosen.push(candidate);
+    }
+
+    match chosen.len() {
+        0 => Choice::None,
+        1 => Choice::One(chosen.remove(0)),
+        _ => Choice::Ambiguous(chosen),
+    }
+}
@@ -0,0 +1,419 @@
+use super::*;
+use crate::fixture::{self, core_dreams, map, render};
+
+/// Parses and resolves every file in the map, returning what went wrong.
+fn check(map: &SourceMap) -> Vec<Citation> {
+    let (units, mut citations) = parse(map);
+    let resolution = resolve(&units);
+    citations.extend(resolution.errors.into_iter().map(Citation::from));
+    citations
+}
+
+/// Renders everything `check` finds, as it would be shown.
+fn report(files: &[(&str, &str)]) -> String {
+    let map = map(files);
+    render(&map, check(&map))
+}
+
+/// The span of the `nth` occurrence of `text` in the named file, in the
+/// map's span space.
+fn find(map: &SourceMap, file: &str, text: &str, nth: usize) -> Span {
+    let file = map.files().iter().find(|it| it.name == file).unwrap();
+    let start = file
+        .source
+        .match_indices(text)
+        .nth(nth)
+        .expect("text should be in source")
+        .0;
+    file.span(Span::new(start, start + text.len() - 1))
+}
+
+#[test]
+fn module_tree() {
+    let map = map(&[
+        (
+            "main.son",
+            "import { data { optional { Optional, Some }, result } };
+func main() -> Optional<Int> { result.Okay(Some(1)).okay() };
+enum Int();",
+        ),
+        (
+            "data/optional.son",
+            "module optional;\nenum Optional<T>(Some(T), Nothing) {};",
+        ),
+        (
+            "data/result.son",
+            "module result;\nenum Result<T, E>(Okay(T), Error(E)) {};",
+        ),
+        ("data/shared.son", "func shared() {};"),
+    ]);
+    let (units, errors) = parse(&map);
+    assert!(errors.is_empty());
+
+    let resolution = resolve(&units);
+    assert_eq!(resolution.errors, vec![]);
+
+    let names: Vec<_> = resolution
+        .modules
+        .iter()
+        .map(|module| (module.name, module.parent))
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            ("", None),
+            ("data", Some(0)),
+            ("optional", Some(1)),
+            ("result", Some(1))
+        ]
+    );
+    assert_eq!(resolution.unit_modules, vec![0, 2, 3, 1]);
+    assert!(resolution.modules[1].names.contains_key("shared"));
+
+    // Imports and paths go to their declarations in other files
+    let definition = |text, nth| {
+        let span = find(&map, "main.son", text, nth);
+        resolution.definition(span.start)
+    };
+    let declaration = |file, text| Some(find(&map, file, text, 0));
+    assert_eq!(
+        definition("Optional", 0),
+        declaration("data/optional.son", "Optional")
+    );
+    assert_eq!(
+        definition("Optional", 1),
+        declaration("data/optional.son", "Optional")
+    );
+    assert_eq!(
+        definition("result", 0),
+        declaration("data/result.son", "result")
+    );
+    assert_eq!(
+        definition("Okay", 0),
+        declaration("data/result.son", "Okay")
+    );
+    assert_eq!(
+        definition("Some", 1),
+        declaration("data/optional.son", "Some")
+    );
+    // A directory has nothing to go to
+    assert_eq!(definition("data", 0), None);
+}
+
+#[test]
+fn scopes() {
+    let map = map(&[(
+        "main.son",
+        "enum Boolean(True, False) {};
+func not<V>(value: V) -> Boolean {
+    let value = match(value) { True -> False, other -> other };
+    block { let inner = value; inner };
+    inner
+};",
+    )]);
+    let (units, _) = parse(&map);
+    let resolution = resolve(&units);
+
+    let at = |text, nth| find(&map, "main.son", text, nth);
+    let definition = |text, nth| resolution.definition(at(text, nth).start);
+
+    assert_eq!(definition("V", 1), Some(at("V", 0)));
+    assert_eq!(definition("value", 2), Some(at("value", 0)));
+    assert_eq!(definition("value", 3), Some(at("value", 1)));
+    assert_eq!(definition("True", 1), Some(at("True", 0)));
+    assert_eq!(definition("other", 1), Some(at("other", 0)));
+
+    // `inner` went out of scope with its block
+    assert_eq!(
+        resolution.errors,
+        vec![ResolveError::Unresolved {
+            name: "inner",
+            span: at("inner", 2),
+        }]
+    );
+}
+
+#[test]
+fn prelude() {
+    let map = map(&[
+        (
+            "data/optional.son",
+            "module optional;\nenum Optional<T>(Some(T), Nothing) {};",
+        ),
+        (
+            "data/boolean.son",
+            "module boolean;\nfunc some() -> Optional<Int> { Some(1) };",
+        ),
+        ("main.son", "func main() { Nothing };"),
+    ]);
+    let (units, _) = parse(&map);
+    let resolution = resolve(&units);
+    assert_eq!(resolution.errors, vec![]);
+
+    let at = |text, nth| find(&map, "data/optional.son", text, nth);
+    let definition = |file, text| resolution.definition(find(&map, file, text, 0).start);
+    assert_eq!(
+        definition("data/boolean.son", "Optional"),
+        Some(at("Optional", 0))
+    );
+    assert_eq!(definition("data/boolean.son", "Some"), Some(at("Some", 0)));
+    assert_eq!(definition("main.son", "Nothing"), Some(at("Nothing", 0)));
+}
+
+#[test]
+fn block_parameters() {
+    let map = map(&[(
+        "main.son",
+        "func count(from start: Int, to end: Int, body: block(index: Int) -> Int) {};
+func take_if<T>(self: T, condition: block(it: T) -> Int) -> T { self };
+func scope<T>(body: block() -> T) -> T { body() };
+func scope<Self, T>(self: Self, body: block(it: Self) -> T) -> T { body(self) };
+func pair(body: block(left: Int, right: Int) -> Int) {};
+func pair(first: Int, body: block(right: Int, left: Int) -> Int) {};
+func pair(second: Int, body: block(other: Int) -> Int) {};
+func main() {
+    count(from: 0, to: 3) { print(index) };
+    5.take_if { it };
+    5.scope { it };
+    scope { it };
+    pair(1) { left };
+    index
+};",
+    )]);
+    let (units, _) = parse(&map);
+    let resolution = resolve(&units);
+
+    let at = |text, nth| find(&map, "main.son", text, nth);
+    let definition = |text, nth| resolution.definition(at(text, nth).start);
+    assert_eq!(definition("index", 1), Some(at("index", 0)));
+    assert_eq!(definition("it", 3), Some(at("it", 1)));
+    assert_eq!(definition("it", 4), Some(at("it", 2)));
+
+    // `scope` without a receiver doesn't name its block's value, and which
+    // `pair` the block goes to is left to the type checker
+    assert_eq!(definition("left", 2), None);
+    assert_eq!(
+        resolution.errors,
+        vec![
+            ResolveError::Unresolved {
+                name: "it",
+                span: at("it", 5),
+            },
+            ResolveError::Unresolved {
+                name: "index",
+                span: at("index", 2),
+            },
+        ]
+    );
+}
+
+/// The core library uses syntax the AST parser doesn't take yet: an
+/// attribute, a keyword as a function name, a `struct` and a `&mut` type.
+/// Until it does, the files with them can't be checked, so their errors are
+/// pinned here. The other files parse and resolve cleanly.
+#[test]
+fn core_library() {
+    let mut library = SourceMap::new().color(false);
+    let core = Path::new(env!("CARGO_MANIFEST_DIR")).join("../sonance-dreams/core");
+    read_dir(&mut library, &core).unwrap();
+
+    let (units, errors) = parse(&library);
+    let rendered = render(&library, errors);
+    let unparsed: Vec<_> = rendered
+        .lines()
+        .filter_map(|line| line.strip_prefix(" --> "))
+        .collect();
+    assert_eq!(
+        unparsed,
+        vec![
+            "data/never.son:5:1",
+            "data/never.son:6:6",
+            "data/unit.son:3:1",
+            "scope.son:9:45"
+        ],
+        "{}",
+        rendered
+    );
+    assert_eq!(resolve(&units).errors, vec![]);
+
+    let map = map(&core_dreams().collect::<Vec<_>>());
+    assert_eq!(resolve(&fixture::parse(&map)).errors, vec![]);
+}
+
+#[test]
+fn unresolved() {
+    assert_eq!(
+        report(&[
+            ("a.son", "import b.missing;\n"),
+            (
+                "b.son",
+                "module b;\nenum E(One) {};\nfunc f() -> E { E.Two };\n"
+            ),
+        ]),
+        "error: Unresolved path `b.missing`
+ --> a.son:1:10
+  |
+1 | import b.missing;
+  |          ^^^^^^^ no `missing` in `b`
+
+error: Unresolved path `E.Two`
+ --> b.son:3:19
+  |
+3 | func f() -> E { E.Two };
+  |                   ^^^ no `Two` in `E`
+"
+    );
+}
+
+#[test]
+fn nested_imports() {
+    // A missing prefix is reported once, not once for each path through it
+    assert_eq!(
+        report(&[
+            (
+                "main.son",
+                "import { std { compare.Ordering, io.stdin } };\n"
+            ),
+            ("io.son", "module io;\nfunc stdin() {};\n"),
+        ]),
+        "error: Unresolved name `std`
+ --> main.son:1:10
+  |
+1 | import { std { compare.Ordering, io.stdin } };
+  |          ^^^ not found in this scope
+"
+    );
+    assert_eq!(
+        report(&[
+            (
+                "main.son",
+                "import { io { missing.Ordering, stdin, missing.other } };\n"
+            ),
+            ("io.son", "module io;\nfunc stdin() {};\n"),
+        ]),
+        "error: Unresolved path `io.missing`
+ --> main.son:1:15
+  |
+1 | import { io { missing.Ordering, stdin, missing.other } };
+  |               ^^^^^^^ no `missing` in `io`
+
+error: Unresolved path `io.missing`
+ --> main.son:1:40
+  |
+1 | import { io { missing.Ordering, stdin, missing.other } };
+  |                                        ^^^^^^^ no `missing` in `io`
+"
+    );
+}
+
+#[test]
+fn duplicates_across_files() {
+    assert_eq!(
+        report(&[
+            ("one.son", "func shared() {};\n"),
+            ("two.son", "\nfunc shared() {};\n"),
+        ]),
+        "error: `shared` is defined multiple times
+ --> two.son:2:6
+  |
+2 | func shared() {};
+  |      ^^^^^^ redefined here
+  |
+  = note: first defined here at one.son:1:6
+"
+    );
+}
+
+#[test]
+fn ambiguous_imports() {
+    assert_eq!(
+        report(&[
+            ("a.son", "module a;\nfunc thing() {};\n"),
+            ("b.son", "module b;\nfunc thing() {};\n"),
+            ("main.son", "import { a.thing, b.thing };\n"),
+        ]),
+        "error: `thing` is ambiguous
+ --> main.son:1:21
+  |
+1 | import { a.thing, b.thing };
+  |                     ^^^^^ could refer to more than one thing
+  |
+  = note: it could be this at a.son:2:6
+  = note: it could be this at b.son:2:6
+  = help: import only the `thing` meant
+"
+    );
+}
+
+#[test]
+fn ambiguous_prelude() {
+    assert_eq!(
+        report(&[
+            ("a.son", "module a;\nenum Color(Red, Blue);\n"),
+            ("b.son", "module b;\nenum Shade(Red, Dark);\n"),
+            (
+                "c.son",
+                "module c;\nimport b.Red;\nfunc f() -> Shade { Red };\n"
+            ),
+            ("main.son", "func main() { Blue; Red };\n"),
+        ]),
+        "error: `Red` is ambiguous
+ --> main.son:1:21
+  |
+1 | func main() { Blue; Red };
+  |                     ^^^ could refer to more than one thing
+  |
+  = note: it could be this at a.son:2:12
+  = note: it could be this at b.son:2:12
+  = help: import only the `Red` meant
+"
+    );
+}
+
+#[test]
+fn module_declarations() {
+    assert_eq!(
+        report(&[
+            ("x.son", "module x;\n"),
+            ("y.son", "module x;\nmodule y;\n"),
+        ]),
+        "error: `x` is defined multiple times
+ --> y.son:1:8
+  |
+1 | module x;
+  |        ^ redefined here
+  |
+  = note: first defined here at x.son:1:8
+
+error: Module declarations must come first in a file
+ --> y.son:2:1
+  |
+2 | module y;
+  | ^^^^^^^^
+  |
+  = help: move this to the top of the file
+"
+    );
+}
+
+#[test]
+fn overloads() {
+    assert_eq!(
+        report(&[(
+            "main.son",
+            "func range(to end: Int) {};
+func range(from start: Int, to end: Int) {};
+func range(to other: Int) {};
+",
+        )]),
+        "error: `range` is defined multiple times
+ --> main.son:3:6
+  |
+1 | func range(to end: Int) {};
+  |      ----- first defined here
+...
+3 | func range(to other: Int) {};
+  |      ^^^^^ redefined here
+"
+    );
+}
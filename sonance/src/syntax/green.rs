@@ -0,0 +1,141 @@
+use super::SyntaxKind;
+use std::rc::Rc;
+
+/// An immutable node, knowing only its kind, children and length. Nothing
+/// in it depends on where it is, so a subtree an edit didn't touch is shared
+/// between the old and new trees.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GreenNode {
+    kind: SyntaxKind,
+    len: usize,
+    children: Vec<GreenElement>,
+}
+
+impl GreenNode {
+    pub fn new(kind: SyntaxKind, children: Vec<GreenElement>) -> Self {
+        let len = children.iter().map(GreenElement::len).sum();
+        Self {
+            kind,
+            len,
+            children,
+        }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    /// The length of its text in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn children(&self) -> &[GreenElement] {
+        &self.children
+    }
+
+    /// A copy with the child at `index` replaced, sharing the others.
+    pub fn replace_child(&self, index: usize, child: GreenElement) -> Self {
+        let mut children = self.children.clone();
+        children[index] = child;
+        Self::new(self.kind, children)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GreenToken {
+    kind: SyntaxKind,
+    text: String,
+}
+
+impl GreenToken {
+    pub fn new(kind: SyntaxKind, text: String) -> Self {
+        Self { kind, text }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GreenElement {
+    Node(Rc<GreenNode>),
+    Token(Rc<GreenToken>),
+}
+
+impl GreenElement {
+    pub fn kind(&self) -> SyntaxKind {
+        match self {
+            Self::Node(node) => node.kind(),
+            Self::Token(token) => token.kind(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Node(node) => node.len(),
+            Self::Token(token) => token.text().len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Builds a tree from the top down, as the parser goes.
+#[derive(Default)]
+pub(super) struct Builder {
+    /// The kind of each node still open, and where its children start.
+    parents: Vec<(SyntaxKind, usize)>,
+    /// The children of every open node, one after the other.
+    children: Vec<GreenElement>,
+}
+
+impl Builder {
+    pub fn start_node(&mut self, kind: SyntaxKind) {
+        self.parents.push((kind, self.children.len()));
+    }
+
+    /// Starts a node holding what was added since `checkpoint`, for nodes
+    /// like chains that are only known once their first child is parsed.
+    pub fn start_node_at(&mut self, checkpoint: usize, kind: SyntaxKind) {
+        self.parents.push((kind, checkpoint));
+    }
+
+    pub fn checkpoint(&self) -> usize {
+        self.children.len()
+    }
+
+    pub fn token(&mut self, kind: SyntaxKind, text: &str) {
+        let token = GreenToken::new(kind, text.to_owned());
+        self.children.push(GreenElement::Token(Rc::new(token)));
+    }
+
+    pub fn finish_node(&mut self) {
+        let (kind, first) = self.parents.pop().expect("a node should be open");
+        let children = self.children.split_off(first);
+        let node = GreenNode::new(kind, children);
+        self.children.push(GreenElement::Node(Rc::new(node)));
+    }
+
+    /// The one node everything was built in.
+    pub fn finish(mut self) -> GreenNode {
+        assert!(self.parents.is_empty(), "every node should be finished");
+        match self.children.pop() {
+            Some(GreenElement::Node(node)) if self.children.is_empty() => {
+                Rc::try_unwrap(node).unwrap_or_else(|node| (*node).clone())
+            }
+            _ => panic!("everything should be built in one node"),
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use crate::token::{Keyword, TokenKind};
+
+/// What a node or token in the syntax tree is. Tokens have the kinds of the
+/// scanner's tokens without their values, plus the trivia it skips.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyntaxKind {
+    // Trivia
+    Whitespace,
+    /// A `//` or `/* */` comment.
+    Comment,
+    /// A `///` or `/** */` comment.
+    DocComment,
+
+    // Tokens
+    OpenParen,
+    CloseParen,
+    OpenBrace,
+    CloseBrace,
+    OpenAngle,
+    CloseAngle,
+    Comma,
+    Semicolon,
+    Colon,
+    Equals,
+    DoubleEquals,
+    Bang,
+    BangEquals,
+    LessEquals,
+    GreaterEquals,
+    Plus,
+    PlusEquals,
+    Minus,
+    MinusEquals,
+    Arrow,
+    Star,
+    StarEquals,
+    Slash,
+    SlashEquals,
+    Percent,
+    PercentEquals,
+    Ampersand,
+    DoubleAmpersand,
+    Pipe,
+    DoublePipe,
+    DotSymbol,
+    AtSymbol,
+    Integer,
+    Float,
+    Character,
+    String,
+    Identifier,
+    StructKeyword,
+    FuncKeyword,
+    LetKeyword,
+    MutKeyword,
+    ReturnKeyword,
+    MatchKeyword,
+    LoopKeyword,
+    ImportKeyword,
+    ModuleKeyword,
+    EnumKeyword,
+    TraitKeyword,
+    BlockKeyword,
+
+    // Items
+    File,
+    Function,
+    /// A function without a body, in a trait.
+    Signature,
+    Enum,
+    Struct,
+    Trait,
+    /// `module name;`
+    Module,
+    /// `module name -> Type { ... }`
+    Extension,
+    Import,
+    ImportTree,
+    ImportList,
+    /// `@name` in front of an item.
+    Attribute,
+    Generics,
+    GenericParameter,
+    Parameters,
+    Parameter,
+    Variants,
+    Variant,
+    /// The fields of a variant or struct.
+    Fields,
+    /// The traits an enum, struct or extension implements.
+    Traits,
+    /// The functions in an enum, struct, trait or extension.
+    Members,
+
+    // Types
+    Type,
+    BlockType,
+    ReferenceType,
+    TypeArguments,
+    TypeArgument,
+
+    // Statements
+    Block,
+    LetStatement,
+    Assignment,
+    /// An expression with its `;`, or the tail of a block without one.
+    ExpressionStatement,
+
+    // Expressions
+    Literal,
+    Name,
+    Paren,
+    Call,
+    Chain,
+    Property,
+    Match,
+    MatchClauses,
+    MatchClause,
+    Loop,
+    BlockExpression,
+    Return,
+    Reference,
+    Arguments,
+    Argument,
+    BlockArgument,
+
+    // Patterns
+    BindingPattern,
+    VariantPattern,
+    PatternList,
+
+    /// Text the scanner couldn't make a token of, or tokens the parser
+    /// couldn't make sense of.
+    Error,
+}
+
+impl SyntaxKind {
+    /// Whether the parser skips tokens of this kind.
+    pub fn is_trivia(self) -> bool {
+        matches!(self, Self::Whitespace | Self::Comment | Self::DocComment)
+    }
+}
+
+impl From<&TokenKind<'_>> for SyntaxKind {
+    fn from(kind: &TokenKind) -> Self {
+        match kind {
+            TokenKind::OpenParen => Self::OpenParen,
+            TokenKind::CloseParen => Self::CloseParen,
+            TokenKind::OpenBrace => Self::OpenBrace,
+            TokenKind::CloseBrace => Self::CloseBrace,
+            TokenKind::OpenAngle => Self::OpenAngle,
+            TokenKind::CloseAngle => Self::CloseAngle,
+            TokenKind::Comma => Self::Comma,
+            TokenKind::Semicolon => Self::Semicolon,
+            TokenKind::Colon => Self::Colon,
+            TokenKind::Equals => Self::Equals,
+            TokenKind::DoubleEquals => Self::DoubleEquals,
+            TokenKind::Bang => Self::Bang,
+            TokenKind::BangEquals => Self::BangEquals,
+            TokenKind::LessEquals => Self::LessEquals,
+            TokenKind::GreaterEquals => Self::GreaterEquals,
+            TokenKind::Plus => Self::Plus,
+            TokenKind::PlusEquals => Self::PlusEquals,
+            TokenKind::Minus => Self::Minus,
+            TokenKind::MinusEquals => Self::MinusEquals,
+            TokenKind::Arrow => Self::Arrow,
+            TokenKind::Star => Self::Star,
+            TokenKind::StarEquals => Self::StarEquals,
+            TokenKind::Slash => Self::Slash,
+            TokenKind::SlashEquals => Self::SlashEquals,
+            TokenKind::Percent => Self::Percent,
+            TokenKind::PercentEquals => Self::PercentEquals,
+            TokenKind::Ampersand => Self::Ampersand,
+            TokenKind::DoubleAmpersand => Self::DoubleAmpersand,
+            TokenKind::Pipe => Self::Pipe,
+            TokenKind::DoublePipe => Self::DoublePipe,
+            TokenKind::DotSymbol => Self::DotSymbol,
+            TokenKind::AtSymbol => Self::AtSymbol,
+            TokenKind::DocComment(_) => Self::DocComment,
+            TokenKind::Integer(_) => Self::Integer,
+            TokenKind::Float(_) => Self::Float,
+            TokenKind::Character(_) => Self::Character,
+            TokenKind::String(_) => Self::String,
+            TokenKind::Identifier(_) => Self::Identifier,
+            TokenKind::Keyword(keyword) => match keyword {
+                Keyword::Struct => Self::StructKeyword,
+                Keyword::Func => Self::FuncKeyword,
+                Keyword::Let => Self::LetKeyword,
+                Keyword::Mut => Self::MutKeyword,
+                Keyword::Return => Self::ReturnKeyword,
+                Keyword::Match => Self::MatchKeyword,
+                Keyword::Loop => Self::LoopKeyword,
+                Keyword::Import => Self::ImportKeyword,
+                Keyword::Module => Self::ModuleKeyword,
+                Keyword::Enum => Self::EnumKeyword,
+                Keyword::Trait => Self::TraitKeyword,
+                Keyword::Block => Self::BlockKeyword,
+            },
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use super::SyntaxKind;
+use crate::token;
+
+/// A token's kind and text.
+pub(super) type Lexeme<'src> = (SyntaxKind, &'src str);
+
+/// Splits all of `source` into lexemes, trivia included. The tokens come
+/// from the scanner, so the two can't disagree, and whatever it skipped
+/// between them is whitespace, comments or errors.
+pub(super) fn lex(source: &str) -> Vec<Lexeme<'_>> {
+    let mut lexemes = Vec::new();
+    let mut position = 0;
+
+    for token in token::scan(source).filter_map(Result::ok) {
+        // Spans end at the start of their last char
+        let span = token.span;
+        let end = span.end + source[span.end..].chars().next().map_or(0, char::len_utf8);
+
+        gap(&source[position..span.start], &mut lexemes);
+        lexemes.push((SyntaxKind::from(&token.kind), &source[span.start..end]));
+        position = end;
+    }
+
+    gap(&source[position..], &mut lexemes);
+    lexemes
+}
+
+/// Splits text between tokens into whitespace, plain comments, and errors
+/// for anything the scanner reported instead of making a token.
+fn gap<'src>(mut text: &'src str, lexemes: &mut Vec<Lexeme<'src>>) {
+    while let Some(char) = text.chars().next() {
+        let (kind, len) = if char.is_whitespace() {
+            (
+                SyntaxKind::Whitespace,
+                prefix_len(text, char::is_whitespace),
+            )
+        } else if text.starts_with("//") {
+            (SyntaxKind::Comment, text.find('\n').unwrap_or(text.len()))
+        } else if text.starts_with("/*") {
+            match block_comment_len(text) {
+                Some(len) => (SyntaxKind::Comment, len),
+                None => (SyntaxKind::Error, text.len()),
+            }
+        } else {
+            let len = prefix_len(text, |it| !it.is_whitespace());
+            let len = text[..len].find("//").unwrap_or(len);
+            let len = text[..len].find("/*").unwrap_or(len);
+            // A comment can't start the error, since that's checked above
+            (SyntaxKind::Error, len.max(char.len_utf8()))
+        };
+
+        lexemes.push((kind, &text[..len]));
+        text = &text[len..];
+    }
+}
+
+fn prefix_len(text: &str, predicate: impl Fn(char) -> bool) -> usize {
+    text.find(|it| !predicate(it)).unwrap_or(text.len())
+}
+
+/// The length of the nested block comment `text` starts with, or `None` if
+/// it isn't closed.
+fn block_comment_len(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        if rest.starts_with("/*") {
+            depth += 1;
+            rest = &rest[2..];
+        } else if rest.starts_with("*/") {
+            depth -= 1;
+            rest = &rest[2..];
+            if depth == 0 {
+                return Some(text.len() - rest.len());
+            }
+        } else {
+            let char = rest.chars().next()?;
+            rest = &rest[char.len_utf8()..];
+        }
+    }
+    None
+}
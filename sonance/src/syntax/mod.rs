@@ -0,0 +1,28 @@
+//! A lossless syntax tree, which keeps the whitespace and comments the
+//! parser skips so tools like the formatter can give back exactly what they
+//! were given.
+//!
+//! It's built like rowan's. Green nodes are immutable and only know their
+//! kind, length and children, so versions of a file share whatever an edit
+//! didn't touch. Red nodes are made over them as the tree is walked, adding
+//! parents and offsets.
+
+mod green;
+mod kind;
+mod lexer;
+mod parser;
+mod red;
+mod reparse;
+#[cfg(test)]
+mod test;
+
+pub use green::{GreenElement, GreenNode, GreenToken};
+pub use kind::SyntaxKind;
+pub use red::{SyntaxElement, SyntaxNode, SyntaxToken};
+pub use reparse::{reparse, Edit};
+
+/// Parses a file into a tree whose text is exactly `source`, errors and
+/// all.
+pub fn parse(source: &str) -> SyntaxNode {
+    SyntaxNode::new_root(parser::file(&lexer::lex(source)))
+}
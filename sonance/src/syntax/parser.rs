@@ -0,0 +1,779 @@
+//! The grammar of the syntax tree. It follows `crate::parser`, but never
+//! gives up: whatever doesn't fit goes in an error node, so every lexeme
+//! ends up in the tree.
+
+use super::{
+    green::{Builder, GreenNode},
+    lexer::Lexeme,
+    SyntaxKind::{self, *},
+};
+
+/// Parses a whole file.
+pub(super) fn file(lexemes: &[Lexeme]) -> GreenNode {
+    let mut parser = Parser::new(lexemes);
+    parser.builder.start_node(File);
+
+    while parser.peek().is_some() {
+        if parser.at_item() {
+            parser.item();
+        } else {
+            parser.recover_item();
+        }
+    }
+
+    parser.flush(lexemes.len());
+    parser.builder.finish_node();
+    parser.builder.finish()
+}
+
+/// Parses a block on its own, or `None` if the lexemes aren't exactly one
+/// closed block.
+pub(super) fn block(lexemes: &[Lexeme]) -> Option<GreenNode> {
+    if lexemes.first().map(|it| it.0) != Some(OpenBrace) {
+        return None;
+    }
+
+    let mut parser = Parser::new(lexemes);
+    parser.block();
+    if parser.peek().is_some() || parser.emitted != lexemes.len() {
+        return None;
+    }
+
+    let block = parser.builder.finish();
+    let closed = matches!(block.children().last(), Some(last) if last.kind() == CloseBrace);
+    Some(block).filter(|_| closed)
+}
+
+struct Parser<'a> {
+    lexemes: &'a [Lexeme<'a>],
+    /// The index of each lexeme that isn't trivia.
+    tokens: Vec<usize>,
+    /// The next of `tokens` to parse.
+    position: usize,
+    /// The next lexeme to add to the tree, which is behind `position` by
+    /// the trivia before it.
+    emitted: usize,
+    builder: Builder,
+}
+
+impl<'a> Parser<'a> {
+    fn new(lexemes: &'a [Lexeme<'a>]) -> Self {
+        Self {
+            lexemes,
+            tokens: (0..lexemes.len())
+                .filter(|&index| !lexemes[index].0.is_trivia())
+                .collect(),
+            position: 0,
+            emitted: 0,
+            builder: Builder::default(),
+        }
+    }
+
+    fn item(&mut self) {
+        // Look past any attributes to see what they're on
+        let mut ahead = 0;
+        while self.peek_nth(ahead) == Some(AtSymbol) {
+            ahead += if self.peek_nth(ahead + 1) == Some(Identifier) {
+                2
+            } else {
+                1
+            };
+        }
+
+        let kind = match self.peek_nth(ahead) {
+            Some(FuncKeyword) => Function,
+            Some(EnumKeyword) => Enum,
+            Some(StructKeyword) => Struct,
+            Some(TraitKeyword) => Trait,
+            Some(ImportKeyword) => Import,
+            Some(ModuleKeyword) if self.peek_nth(ahead + 2) == Some(Semicolon) => Module,
+            Some(ModuleKeyword) => Extension,
+            _ => Error,
+        };
+
+        self.start(kind);
+        self.attributes();
+
+        match kind {
+            Function => self.function(true),
+            Enum | Struct => self.enumeration(),
+            Trait => {
+                self.bump();
+                self.expect(Identifier);
+                self.generics();
+                self.members(Signature);
+            }
+            Import => {
+                self.bump();
+                self.import_tree();
+            }
+            Module => {
+                self.bump();
+                self.expect(Identifier);
+            }
+            Extension => self.extension(),
+            _ => {}
+        }
+
+        self.end_member(Self::at_item);
+        self.finish();
+    }
+
+    /// `;` after an item or member, skipping anything left of it first.
+    fn end_member(&mut self, next: fn(&Self) -> bool) {
+        if !self.eat(Semicolon) {
+            self.recover(|parser| parser.at(Semicolon) || next(parser));
+            self.eat(Semicolon);
+        }
+    }
+
+    fn attributes(&mut self) {
+        while self.at(AtSymbol) {
+            self.start(Attribute);
+            self.bump();
+            self.expect(Identifier);
+            self.finish();
+        }
+    }
+
+    /// A function after its attributes, with its body unless it's in a
+    /// trait.
+    fn function(&mut self, body: bool) {
+        self.expect(FuncKeyword);
+        self.expect(Identifier);
+        self.generics();
+
+        if self.at(OpenParen) {
+            self.list(Parameters, CloseParen, Self::parameter);
+        }
+        if self.eat(Arrow) {
+            self.ty();
+        }
+        if body && self.at(OpenBrace) {
+            self.block();
+        }
+    }
+
+    /// Enums and structs, which only differ in the keyword and what's in
+    /// the parentheses.
+    fn enumeration(&mut self) {
+        let fields = self.at(StructKeyword);
+        self.bump();
+        self.expect(Identifier);
+        self.generics();
+
+        if self.at(OpenParen) && fields {
+            self.list(Fields, CloseParen, Self::type_argument);
+        } else if self.at(OpenParen) {
+            self.list(Variants, CloseParen, Self::variant);
+        }
+
+        if self.at(Arrow) {
+            self.start(Traits);
+            self.bump();
+            self.ty();
+            while self.eat(Comma) {
+                self.ty();
+            }
+            self.finish();
+        }
+
+        self.members(Function);
+    }
+
+    fn variant(&mut self) {
+        if !self.at(Identifier) {
+            return;
+        }
+
+        self.start(Variant);
+        self.bump();
+        if self.at(OpenParen) {
+            self.list(Fields, CloseParen, Self::type_argument);
+        }
+        self.finish();
+    }
+
+    /// The rest of `module name -> Type with<Trait> { ... }`.
+    fn extension(&mut self) {
+        self.bump();
+        self.expect(Identifier);
+        self.generics();
+        self.expect(Arrow);
+        self.ty();
+
+        // `with` is only special here, so it isn't a keyword
+        if self.at(Identifier) && self.peek_text() == Some("with") {
+            self.start(Traits);
+            self.bump();
+            if self.at(OpenAngle) {
+                self.list(TypeArguments, CloseAngle, Self::type_argument);
+            }
+            self.finish();
+        }
+
+        self.members(Function);
+    }
+
+    /// `{ member; member; }`, with members of `kind`.
+    fn members(&mut self, kind: SyntaxKind) {
+        if !self.at(OpenBrace) {
+            return;
+        }
+
+        self.start(Members);
+        self.bump();
+
+        loop {
+            if self.eat(CloseBrace) || self.peek().is_none() {
+                break;
+            }
+
+            let at_member = |parser: &Self| parser.at(FuncKeyword) || parser.at(AtSymbol);
+            if !at_member(self) {
+                self.recover_statement();
+                continue;
+            }
+
+            self.start(kind);
+            self.attributes();
+            self.function(kind == Function);
+            self.end_member(at_member);
+            self.finish();
+        }
+
+        self.finish();
+    }
+
+    fn import_tree(&mut self) {
+        if !self.at(Identifier) && !self.at(OpenBrace) {
+            return;
+        }
+
+        self.start(ImportTree);
+        if self.eat(Identifier) {
+            while self.eat(DotSymbol) {
+                self.expect(Identifier);
+            }
+        }
+        if self.at(OpenBrace) {
+            self.list(ImportList, CloseBrace, Self::import_tree);
+        }
+        self.finish();
+    }
+
+    /// `<T, U: Bound>`, or nothing.
+    fn generics(&mut self) {
+        if self.at(OpenAngle) {
+            self.list(Generics, CloseAngle, |parser| {
+                if !parser.at(Identifier) {
+                    return;
+                }
+
+                parser.start(GenericParameter);
+                parser.bump();
+                if parser.eat(Colon) {
+                    parser.ty();
+                }
+                parser.finish();
+            });
+        }
+    }
+
+    fn parameter(&mut self) {
+        if !self.at(Identifier) && !self.at(MutKeyword) {
+            return;
+        }
+
+        self.start(Parameter);
+        // A label is followed by the pattern, rather than the type annotation
+        if self.at(Identifier) && matches!(self.peek_nth(1), Some(Identifier) | Some(MutKeyword)) {
+            self.bump();
+        }
+        self.binding();
+        self.expect(Colon);
+        self.ty();
+        self.finish();
+    }
+
+    fn ty(&mut self) {
+        match self.peek() {
+            Some(BlockKeyword) => {
+                self.start(BlockType);
+                self.bump();
+                if self.at(OpenParen) {
+                    self.list(TypeArguments, CloseParen, Self::type_argument);
+                }
+                if self.eat(Arrow) {
+                    self.ty();
+                }
+                self.finish();
+            }
+            Some(Ampersand) => {
+                self.start(ReferenceType);
+                self.bump();
+                self.eat(MutKeyword);
+                self.ty();
+                self.finish();
+            }
+            Some(Identifier) => {
+                self.start(Type);
+                self.bump();
+                if self.at(OpenAngle) {
+                    self.list(TypeArguments, CloseAngle, Self::type_argument);
+                }
+                self.finish();
+            }
+            _ => {}
+        }
+    }
+
+    fn type_argument(&mut self) {
+        if !matches!(
+            self.peek(),
+            Some(Identifier) | Some(BlockKeyword) | Some(Ampersand)
+        ) {
+            return;
+        }
+
+        self.start(TypeArgument);
+        self.label();
+        self.ty();
+        self.finish();
+    }
+
+    /// `mut name` or `name`, as used by parameters and let statements.
+    fn binding(&mut self) {
+        self.start(BindingPattern);
+        self.eat(MutKeyword);
+        self.expect(Identifier);
+        self.finish();
+    }
+
+    /// Patterns in match clauses, which may also destructure variants.
+    fn pattern(&mut self) {
+        if self.at(MutKeyword) {
+            return self.binding();
+        }
+        if !self.at(Identifier) {
+            return;
+        }
+        if !matches!(self.peek_nth(1), Some(DotSymbol) | Some(OpenParen)) {
+            return self.binding();
+        }
+
+        self.start(VariantPattern);
+        self.bump();
+        while self.eat(DotSymbol) {
+            self.expect(Identifier);
+        }
+        if self.at(OpenParen) {
+            self.list(PatternList, CloseParen, Self::pattern);
+        }
+        self.finish();
+    }
+
+    fn block(&mut self) {
+        self.start(Block);
+        self.bump();
+
+        loop {
+            if self.eat(CloseBrace) || self.peek().is_none() {
+                break;
+            }
+
+            if self.at_expression() || self.at(LetKeyword) {
+                self.statement();
+            } else {
+                self.recover_statement();
+            }
+        }
+
+        self.finish();
+    }
+
+    fn statement(&mut self) {
+        let kind = match self.peek() {
+            Some(LetKeyword) => LetStatement,
+            Some(Identifier) if self.peek_nth(1) == Some(Equals) => Assignment,
+            _ => ExpressionStatement,
+        };
+
+        self.start(kind);
+        match kind {
+            LetStatement => {
+                self.bump();
+                if self.at(Identifier) || self.at(MutKeyword) {
+                    self.binding();
+                }
+                if self.eat(Colon) {
+                    self.ty();
+                }
+                self.expect(Equals);
+                self.expression();
+            }
+            Assignment => {
+                self.bump();
+                self.bump();
+                self.expression();
+            }
+            _ => self.expression(),
+        }
+
+        // Without a semicolon the block has to end here
+        if !self.eat(Semicolon) && !self.at(CloseBrace) && self.peek().is_some() {
+            self.recover_statement();
+        }
+        self.finish();
+    }
+
+    fn expression(&mut self) {
+        if !self.at_expression() {
+            return;
+        }
+
+        if self.at(Ampersand) {
+            self.start(Reference);
+            self.bump();
+            self.eat(MutKeyword);
+            self.expression();
+            self.finish();
+            return;
+        }
+
+        let start = self.checkpoint();
+        self.primary();
+
+        while self.at(DotSymbol) {
+            let kind = match self.peek_nth(1) {
+                Some(MatchKeyword) => Match,
+                Some(Identifier) if self.at_call(2) => Chain,
+                _ => Property,
+            };
+
+            self.builder.start_node_at(start, kind);
+            self.bump();
+            match kind {
+                Match => {
+                    self.bump();
+                    self.match_clauses();
+                }
+                Chain => {
+                    self.bump();
+                    self.call();
+                }
+                _ => {
+                    self.expect(Identifier);
+                }
+            }
+            self.finish();
+        }
+    }
+
+    fn primary(&mut self) {
+        let kind = match self.peek() {
+            Some(OpenParen) => Paren,
+            Some(Integer) | Some(Float) | Some(Character) | Some(String) => Literal,
+            Some(MatchKeyword) => Match,
+            Some(LoopKeyword) => Loop,
+            Some(BlockKeyword) => BlockExpression,
+            Some(ReturnKeyword) => Return,
+            Some(Identifier) if self.at_call(1) => Call,
+            _ => Name,
+        };
+
+        self.start(kind);
+        self.bump();
+        match kind {
+            Paren => {
+                self.expression();
+                self.expect(CloseParen);
+            }
+            Match => {
+                if self.eat(OpenParen) {
+                    self.expression();
+                    self.expect(CloseParen);
+                }
+                self.match_clauses();
+            }
+            Loop | BlockExpression if self.at(OpenBrace) => self.block(),
+            Return => {
+                if self.eat(AtSymbol) && !self.eat(LoopKeyword) {
+                    self.expect(Identifier);
+                }
+                self.expression();
+            }
+            Call => self.call(),
+            _ => {}
+        }
+        self.finish();
+    }
+
+    /// The rest of a call after its name.
+    fn call(&mut self) {
+        if self.at(OpenAngle) {
+            self.list(TypeArguments, CloseAngle, Self::type_argument);
+        }
+
+        if self.at(OpenParen) {
+            self.list(Arguments, CloseParen, |parser| {
+                if !parser.at_expression() {
+                    return;
+                }
+
+                parser.start(Argument);
+                parser.label();
+                parser.expression();
+                parser.finish();
+            });
+        }
+
+        while self.at_block_argument(0) {
+            self.start(BlockArgument);
+            self.label();
+            self.block();
+            self.finish();
+        }
+    }
+
+    fn match_clauses(&mut self) {
+        if !self.at(OpenBrace) {
+            return;
+        }
+
+        self.list(MatchClauses, CloseBrace, |parser| {
+            if !parser.at(Identifier) && !parser.at(MutKeyword) {
+                return;
+            }
+
+            parser.start(MatchClause);
+            parser.pattern();
+            parser.expect(Arrow);
+            parser.expression();
+            parser.finish();
+        });
+    }
+
+    /// An optional `label:` in front of a parameter or argument.
+    fn label(&mut self) {
+        if self.at(Identifier) && self.peek_nth(1) == Some(Colon) {
+            self.bump();
+            self.bump();
+        }
+    }
+
+    /// `open item, item, ... close` in a node of `kind`, allowing a trailing
+    /// comma. Items that don't start with a token they expect add nothing.
+    fn list(&mut self, kind: SyntaxKind, close: SyntaxKind, mut item: impl FnMut(&mut Self)) {
+        self.start(kind);
+        self.bump();
+
+        loop {
+            if self.eat(close) || self.peek().is_none() {
+                break;
+            }
+
+            item(self);
+            if self.eat(Comma) || self.at(close) {
+                continue;
+            }
+
+            // Skip whatever is left of a broken item
+            self.recover(|parser| parser.at(Comma) || parser.at(close));
+            if !self.eat(Comma) && !self.at(close) {
+                break;
+            }
+        }
+
+        self.finish();
+    }
+
+    /// Wraps tokens in an error node until `stop` holds outside of any
+    /// brackets, or until a bracket that closes one the error didn't open.
+    fn recover(&mut self, stop: impl Fn(&Self) -> bool) {
+        let mut depth = 0usize;
+        let mut started = false;
+
+        while let Some(kind) = self.peek() {
+            match kind {
+                _ if depth == 0 && stop(self) => break,
+                CloseParen | CloseBrace if depth == 0 => break,
+                OpenParen | OpenBrace => depth += 1,
+                CloseParen | CloseBrace => depth -= 1,
+                _ => {}
+            }
+
+            if !started {
+                self.start(Error);
+                started = true;
+            }
+            self.bump();
+        }
+
+        if started {
+            self.finish();
+        }
+    }
+
+    /// Wraps tokens in an error node up to the end of the statement, without
+    /// leaving the block. Always takes at least one token.
+    fn recover_statement(&mut self) {
+        let mut depth = 0usize;
+        self.start(Error);
+
+        while let Some(kind) = self.peek() {
+            match kind {
+                Semicolon if depth == 0 => {
+                    self.bump();
+                    break;
+                }
+                CloseBrace if depth == 0 => break,
+                OpenBrace | OpenParen => depth += 1,
+                CloseBrace | CloseParen => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+
+            self.bump();
+        }
+
+        self.finish();
+    }
+
+    /// Wraps tokens in an error node up to the start of the next item.
+    fn recover_item(&mut self) {
+        let mut depth = 0usize;
+        self.start(Error);
+
+        while let Some(kind) = self.peek() {
+            match kind {
+                Semicolon if depth == 0 => {
+                    self.bump();
+                    break;
+                }
+                _ if depth == 0 && self.at_item() => break,
+                OpenBrace | OpenParen => depth += 1,
+                CloseBrace | CloseParen => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+
+            self.bump();
+        }
+
+        self.finish();
+    }
+
+    fn at_item(&self) -> bool {
+        matches!(
+            self.peek(),
+            Some(FuncKeyword)
+                | Some(EnumKeyword)
+                | Some(StructKeyword)
+                | Some(TraitKeyword)
+                | Some(ModuleKeyword)
+                | Some(ImportKeyword)
+                | Some(AtSymbol)
+        )
+    }
+
+    fn at_expression(&self) -> bool {
+        matches!(
+            self.peek(),
+            Some(OpenParen)
+                | Some(Integer)
+                | Some(Float)
+                | Some(Character)
+                | Some(String)
+                | Some(Identifier)
+                | Some(Ampersand)
+                | Some(MatchKeyword)
+                | Some(LoopKeyword)
+                | Some(BlockKeyword)
+                | Some(ReturnKeyword)
+        )
+    }
+
+    /// Whether a call's arguments start `n` tokens ahead, rather than it
+    /// being just a name.
+    fn at_call(&self, n: usize) -> bool {
+        matches!(
+            self.peek_nth(n),
+            Some(OpenAngle) | Some(OpenParen) | Some(OpenBrace)
+        ) || self.at_block_argument(n)
+    }
+
+    fn at_block_argument(&self, n: usize) -> bool {
+        let labelled = self.peek_nth(n) == Some(Identifier)
+            && self.peek_nth(n + 1) == Some(Colon)
+            && self.peek_nth(n + 2) == Some(OpenBrace);
+        labelled || self.peek_nth(n) == Some(OpenBrace)
+    }
+
+    fn expect(&mut self, kind: SyntaxKind) -> bool {
+        self.eat(kind)
+    }
+
+    fn eat(&mut self, kind: SyntaxKind) -> bool {
+        let at = self.at(kind);
+        if at {
+            self.bump();
+        }
+        at
+    }
+
+    fn at(&self, kind: SyntaxKind) -> bool {
+        self.peek() == Some(kind)
+    }
+
+    fn peek(&self) -> Option<SyntaxKind> {
+        self.peek_nth(0)
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<SyntaxKind> {
+        let index = *self.tokens.get(self.position + n)?;
+        Some(self.lexemes[index].0)
+    }
+
+    fn peek_text(&self) -> Option<&str> {
+        let index = *self.tokens.get(self.position)?;
+        Some(self.lexemes[index].1)
+    }
+
+    /// Adds the next token to the tree, along with the trivia before it.
+    fn bump(&mut self) {
+        let index = self.tokens[self.position];
+        self.flush(index + 1);
+        self.position += 1;
+    }
+
+    /// Adds lexemes up to `end` to the tree.
+    fn flush(&mut self, end: usize) {
+        for &(kind, text) in &self.lexemes[self.emitted..end] {
+            self.builder.token(kind, text);
+        }
+        self.emitted = end;
+    }
+
+    /// Adds the trivia before the next token, so nodes start at a token.
+    fn flush_trivia(&mut self) {
+        let end = self
+            .tokens
+            .get(self.position)
+            .copied()
+            .unwrap_or(self.lexemes.len());
+        self.flush(end);
+    }
+
+    fn start(&mut self, kind: SyntaxKind) {
+        self.flush_trivia();
+        self.builder.start_node(kind);
+    }
+
+    fn checkpoint(&mut self) -> usize {
+        self.flush_trivia();
+        self.builder.checkpoint()
+    }
+
+    fn finish(&mut self) {
+        self.builder.finish_node();
+    }
+}
@@ -0,0 +1,285 @@
+use super::{GreenElement, GreenNode, GreenToken, SyntaxKind};
+use std::{
+    fmt::{self, Debug, Display, Formatter},
+    ops::Range,
+    rc::Rc,
+};
+
+/// A green node along with where it is, made as the tree is walked.
+#[derive(Clone)]
+pub struct SyntaxNode(Rc<NodeData>);
+
+struct NodeData {
+    green: Rc<GreenNode>,
+    parent: Option<SyntaxNode>,
+    /// Where it is among its parent's children.
+    index: usize,
+    offset: usize,
+}
+
+#[derive(Clone)]
+pub struct SyntaxToken {
+    green: Rc<GreenToken>,
+    parent: SyntaxNode,
+    index: usize,
+    offset: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxNode {
+    pub fn new_root(green: GreenNode) -> Self {
+        Self(Rc::new(NodeData {
+            green: Rc::new(green),
+            parent: None,
+            index: 0,
+            offset: 0,
+        }))
+    }
+
+    pub fn green(&self) -> &Rc<GreenNode> {
+        &self.0.green
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.0.green.kind()
+    }
+
+    /// Where its text is in the whole file, in bytes.
+    pub fn range(&self) -> Range<usize> {
+        self.0.offset..self.0.offset + self.0.green.len()
+    }
+
+    pub fn parent(&self) -> Option<SyntaxNode> {
+        self.0.parent.clone()
+    }
+
+    /// Its parent, their parent and so on up to the root.
+    pub fn ancestors(&self) -> impl Iterator<Item = SyntaxNode> {
+        std::iter::successors(self.parent(), SyntaxNode::parent)
+    }
+
+    pub fn children_with_tokens(&self) -> impl Iterator<Item = SyntaxElement> + '_ {
+        let mut offset = self.0.offset;
+        self.0
+            .green
+            .children()
+            .iter()
+            .enumerate()
+            .map(move |(index, green)| {
+                let element = SyntaxElement::new(green, self, index, offset);
+                offset += green.len();
+                element
+            })
+    }
+
+    pub fn children(&self) -> impl Iterator<Item = SyntaxNode> + '_ {
+        self.children_with_tokens().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// Every token under it, in order.
+    pub fn tokens(&self) -> Vec<SyntaxToken> {
+        let mut tokens = Vec::new();
+        for child in self.children_with_tokens() {
+            match child {
+                SyntaxElement::Node(node) => tokens.extend(node.tokens()),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+        tokens
+    }
+
+    pub fn first_token(&self) -> Option<SyntaxToken> {
+        self.children_with_tokens().find_map(|child| match child {
+            SyntaxElement::Node(node) => node.first_token(),
+            SyntaxElement::Token(token) => Some(token),
+        })
+    }
+
+    pub fn last_token(&self) -> Option<SyntaxToken> {
+        let children: Vec<_> = self.children_with_tokens().collect();
+        children.into_iter().rev().find_map(|child| match child {
+            SyntaxElement::Node(node) => node.last_token(),
+            SyntaxElement::Token(token) => Some(token),
+        })
+    }
+
+    /// The smallest element whose range holds all of `range`. An empty range
+    /// between two tokens is held by the first.
+    pub fn covering_element(&self, range: Range<usize>) -> SyntaxElement {
+        let mut node = self.clone();
+        loop {
+            let child = node.children_with_tokens().find(|child| {
+                let covered = child.range();
+                covered.start <= range.start && range.end <= covered.end
+            });
+
+            match child {
+                Some(SyntaxElement::Node(child)) => node = child,
+                Some(token) => return token,
+                None => return SyntaxElement::Node(node),
+            }
+        }
+    }
+
+    /// The root of a copy of the tree with this node replaced.
+    pub fn replace_with(&self, green: GreenNode) -> GreenNode {
+        match &self.0.parent {
+            Some(parent) => parent.replace_child(self.0.index, GreenElement::Node(Rc::new(green))),
+            None => green,
+        }
+    }
+
+    fn replace_child(&self, index: usize, child: GreenElement) -> GreenNode {
+        self.replace_with(self.0.green.replace_child(index, child))
+    }
+
+    /// The element after this node or token in its parent, or after its
+    /// parent if it's the last.
+    fn next_sibling_or_uncle(&self, index: usize) -> Option<SyntaxElement> {
+        match self.children_with_tokens().nth(index + 1) {
+            Some(sibling) => Some(sibling),
+            None => self.parent()?.next_sibling_or_uncle(self.0.index),
+        }
+    }
+
+    fn previous_sibling_or_uncle(&self, index: usize) -> Option<SyntaxElement> {
+        match index.checked_sub(1) {
+            Some(index) => self.children_with_tokens().nth(index),
+            None => self.parent()?.previous_sibling_or_uncle(self.0.index),
+        }
+    }
+}
+
+impl Display for SyntaxNode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.tokens()
+            .iter()
+            .try_for_each(|token| f.write_str(token.text()))
+    }
+}
+
+/// Writes the tree out a node or token per line, like `Name@4..8`.
+impl Debug for SyntaxNode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let depth = self.ancestors().count();
+        writeln!(
+            f,
+            "{:indent$}{:?}@{:?}",
+            "",
+            self.kind(),
+            self.range(),
+            indent = depth * 2
+        )?;
+
+        for child in self.children_with_tokens() {
+            match child {
+                SyntaxElement::Node(node) => write!(f, "{:?}", node)?,
+                SyntaxElement::Token(token) => {
+                    writeln!(f, "{:indent$}{:?}", "", token, indent = depth * 2 + 2)?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SyntaxToken {
+    pub fn green(&self) -> &Rc<GreenToken> {
+        &self.green
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.green.kind()
+    }
+
+    pub fn text(&self) -> &str {
+        self.green.text()
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.offset + self.text().len()
+    }
+
+    pub fn parent(&self) -> SyntaxNode {
+        self.parent.clone()
+    }
+
+    pub fn next_token(&self) -> Option<SyntaxToken> {
+        let mut next = self.parent.next_sibling_or_uncle(self.index)?;
+        loop {
+            match next {
+                SyntaxElement::Token(token) => return Some(token),
+                SyntaxElement::Node(node) => match node.first_token() {
+                    Some(token) => return Some(token),
+                    None => next = node.parent()?.next_sibling_or_uncle(node.0.index)?,
+                },
+            }
+        }
+    }
+
+    pub fn previous_token(&self) -> Option<SyntaxToken> {
+        let mut previous = self.parent.previous_sibling_or_uncle(self.index)?;
+        loop {
+            match previous {
+                SyntaxElement::Token(token) => return Some(token),
+                SyntaxElement::Node(node) => match node.last_token() {
+                    Some(token) => return Some(token),
+                    None => previous = node.parent()?.previous_sibling_or_uncle(node.0.index)?,
+                },
+            }
+        }
+    }
+
+    /// The root of a copy of the tree with this token replaced.
+    pub fn replace_with(&self, green: GreenToken) -> GreenNode {
+        self.parent
+            .replace_child(self.index, GreenElement::Token(Rc::new(green)))
+    }
+}
+
+impl Debug for SyntaxToken {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}@{:?} {:?}", self.kind(), self.range(), self.text())
+    }
+}
+
+impl SyntaxElement {
+    fn new(green: &GreenElement, parent: &SyntaxNode, index: usize, offset: usize) -> Self {
+        match green {
+            GreenElement::Node(green) => Self::Node(SyntaxNode(Rc::new(NodeData {
+                green: green.clone(),
+                parent: Some(parent.clone()),
+                index,
+                offset,
+            }))),
+            GreenElement::Token(green) => Self::Token(SyntaxToken {
+                green: green.clone(),
+                parent: parent.clone(),
+                index,
+                offset,
+            }),
+        }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        match self {
+            Self::Node(node) => node.kind(),
+            Self::Token(token) => token.kind(),
+        }
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        match self {
+            Self::Node(node) => node.range(),
+            Self::Token(token) => token.range(),
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! Updating a tree after an edit by only reparsing around it. A token is
+//! relexed on its own when the edit stays inside it, and a block is
+//! reparsed on its own when the edit stays inside its braces. Anything else
+//! parses the whole file again.
+
+use super::{
+    lexer, parser, GreenElement, GreenNode, GreenToken, SyntaxElement, SyntaxKind, SyntaxNode,
+};
+use std::ops::Range;
+
+/// Replaces the text in `range` with `insert`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    pub range: Range<usize>,
+    pub insert: String,
+}
+
+impl Edit {
+    pub fn apply(&self, text: &mut String) {
+        text.replace_range(self.range.clone(), &self.insert);
+    }
+
+    /// The same edit to text starting at `offset`.
+    fn relative_to(&self, offset: usize) -> Self {
+        Self {
+            range: self.range.start - offset..self.range.end - offset,
+            insert: self.insert.clone(),
+        }
+    }
+}
+
+/// The tree of `root`'s text after `edit`, sharing what it didn't change.
+/// It's always the same as parsing the edited text from scratch.
+pub fn reparse(root: &SyntaxNode, edit: &Edit) -> SyntaxNode {
+    // A broken string or comment can change how everything after it is
+    // scanned, so an edit anywhere could matter
+    let green = if lexical_errors(root.green()) {
+        None
+    } else {
+        reparse_token(root, edit).or_else(|| reparse_block(root, edit))
+    };
+
+    match green {
+        Some(green) => SyntaxNode::new_root(green),
+        None => {
+            let mut text = root.to_string();
+            edit.apply(&mut text);
+            super::parse(&text)
+        }
+    }
+}
+
+/// Whether any of the text couldn't be scanned.
+fn lexical_errors(node: &GreenNode) -> bool {
+    node.children().iter().any(|child| match child {
+        GreenElement::Node(node) => lexical_errors(node),
+        GreenElement::Token(token) => token.kind() == SyntaxKind::Error,
+    })
+}
+
+/// Tokens that can be edited without reparsing, since the parser only
+/// looks at their kind.
+const RELEXABLE: &[SyntaxKind] = &[
+    SyntaxKind::Whitespace,
+    SyntaxKind::Comment,
+    SyntaxKind::DocComment,
+    SyntaxKind::Identifier,
+    SyntaxKind::Integer,
+    SyntaxKind::Float,
+    SyntaxKind::Character,
+    SyntaxKind::String,
+];
+
+/// Relexes the one token an edit is inside, if it stays one token of the
+/// same kind.
+fn reparse_token(root: &SyntaxNode, edit: &Edit) -> Option<GreenNode> {
+    let token = match root.covering_element(edit.range.clone()) {
+        SyntaxElement::Token(token) => token,
+        SyntaxElement::Node(_) => return None,
+    };
+
+    let kind = token.kind();
+    if !RELEXABLE.contains(&kind) {
+        return None;
+    }
+
+    let mut text = token.text().to_owned();
+    edit.relative_to(token.range().start).apply(&mut text);
+
+    // `with` is the one identifier the parser treats differently
+    if kind == SyntaxKind::Identifier && (token.text() == "with" || text == "with") {
+        return None;
+    }
+
+    // The tokens either side are lexed too, in case the edit joins them
+    let previous = token.previous_token();
+    let next = token.next_token();
+    let context: String = previous
+        .iter()
+        .map(|it| it.text())
+        .chain(Some(text.as_str()))
+        .chain(next.iter().map(|it| it.text()))
+        .collect();
+
+    let expected: Vec<_> = previous
+        .iter()
+        .map(|it| (it.kind(), it.text()))
+        .chain(Some((kind, text.as_str())))
+        .chain(next.iter().map(|it| (it.kind(), it.text())))
+        .collect();
+    if lexer::lex(&context) != expected {
+        return None;
+    }
+
+    Some(token.replace_with(GreenToken::new(kind, text)))
+}
+
+/// Reparses the innermost block an edit is inside, leaving its braces.
+fn reparse_block(root: &SyntaxNode, edit: &Edit) -> Option<GreenNode> {
+    let covering = match root.covering_element(edit.range.clone()) {
+        SyntaxElement::Node(node) => node,
+        SyntaxElement::Token(token) => token.parent(),
+    };
+
+    let block = std::iter::once(covering.clone())
+        .chain(covering.ancestors())
+        .find(|node| {
+            let range = node.range();
+            node.kind() == SyntaxKind::Block
+                && range.start < edit.range.start
+                && edit.range.end < range.end
+        })?;
+
+    let mut text = block.to_string();
+    edit.relative_to(block.range().start).apply(&mut text);
+
+    let lexemes = lexer::lex(&text);
+    if lexemes.iter().any(|(kind, _)| *kind == SyntaxKind::Error) {
+        return None;
+    }
+
+    let green = parser::block(&lexemes)?;
+    Some(block.replace_with(green))
+}
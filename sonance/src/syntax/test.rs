@@ -0,0 +1,179 @@
+use super::*;
+use crate::fixture::DREAMS;
+use proptest::{prelude::*, sample::Index};
+use std::rc::Rc;
+
+/// Whether the tree has a node or token of `kind` anywhere.
+fn contains(node: &SyntaxNode, kind: SyntaxKind) -> bool {
+    node.kind() == kind
+        || node.tokens().iter().any(|token| token.kind() == kind)
+        || node.children().any(|child| contains(&child, kind))
+}
+
+/// The edit replacing `len` bytes from roughly `start`, moved out to the
+/// nearest chars.
+fn edit(text: &str, start: Index, len: usize, insert: &str) -> Edit {
+    let mut start = start.index(text.len() + 1);
+    while !text.is_char_boundary(start) {
+        start -= 1;
+    }
+    let mut end = (start + len).min(text.len());
+    while !text.is_char_boundary(end) {
+        end += 1;
+    }
+
+    Edit {
+        range: start..end,
+        insert: insert.to_owned(),
+    }
+}
+
+#[test]
+fn dreams_round_trip() {
+    for (_, dream) in DREAMS {
+        assert_eq!(parse(dream).to_string(), *dream);
+    }
+}
+
+#[test]
+fn tree() {
+    let tree = parse("/// Entry\nfunc main() { let x = 1; // one\n x };\n");
+
+    assert_eq!(
+        format!("{:?}", tree),
+        r#"File@0..48
+  DocComment@0..9 "/// Entry"
+  Whitespace@9..10 "\n"
+  Function@10..47
+    FuncKeyword@10..14 "func"
+    Whitespace@14..15 " "
+    Identifier@15..19 "main"
+    Parameters@19..21
+      OpenParen@19..20 "("
+      CloseParen@20..21 ")"
+    Whitespace@21..22 " "
+    Block@22..46
+      OpenBrace@22..23 "{"
+      Whitespace@23..24 " "
+      LetStatement@24..34
+        LetKeyword@24..27 "let"
+        Whitespace@27..28 " "
+        BindingPattern@28..29
+          Identifier@28..29 "x"
+        Whitespace@29..30 " "
+        Equals@30..31 "="
+        Whitespace@31..32 " "
+        Literal@32..33
+          Integer@32..33 "1"
+        Semicolon@33..34 ";"
+      Whitespace@34..35 " "
+      Comment@35..41 "// one"
+      Whitespace@41..43 "\n "
+      ExpressionStatement@43..44
+        Name@43..44
+          Identifier@43..44 "x"
+      Whitespace@44..45 " "
+      CloseBrace@45..46 "}"
+    Semicolon@46..47 ";"
+  Whitespace@47..48 "\n"
+"#
+    );
+}
+
+#[test]
+fn errors() {
+    let source = "func main( { $ \"open\n};\nenum 1 /* never closed";
+    let tree = parse(source);
+
+    assert_eq!(tree.to_string(), source);
+    assert!(contains(&tree, SyntaxKind::Error));
+}
+
+#[test]
+fn reparse_shares_untouched_items() {
+    let source = "func one() { 1 };\nfunc two() { 2.add(3) };\n";
+    let tree = parse(source);
+
+    // Inside `two`'s block, so only it is reparsed
+    let start = source.find("3").unwrap();
+    let edit = Edit {
+        range: start..start + 1,
+        insert: "4.negate".to_owned(),
+    };
+    let reparsed = reparse(&tree, &edit);
+
+    assert_eq!(
+        reparsed.to_string(),
+        "func one() { 1 };\nfunc two() { 2.add(4.negate) };\n"
+    );
+    match (&tree.green().children()[0], &reparsed.green().children()[0]) {
+        (GreenElement::Node(old), GreenElement::Node(new)) => assert!(Rc::ptr_eq(old, new)),
+        children => panic!("expected functions, found {:?}", children),
+    }
+}
+
+#[test]
+fn reparse_relexes_tokens() {
+    let tree = parse("func main() { value };");
+    let edit = Edit {
+        range: 14..19,
+        insert: "other".to_owned(),
+    };
+
+    let reparsed = reparse(&tree, &edit);
+    assert_eq!(reparsed.to_string(), "func main() { other };");
+    assert_eq!(reparsed.green(), parse("func main() { other };").green());
+
+    // Joining tokens needs more than relexing one
+    let edit = Edit {
+        range: 4..5,
+        insert: String::new(),
+    };
+    let reparsed = reparse(&tree, &edit);
+    assert_eq!(reparsed.green(), parse("funcmain() { value };").green());
+}
+
+proptest! {
+    #[test]
+    fn round_trip(source in "\\PC*") {
+        prop_assert_eq!(parse(&source).to_string(), source);
+    }
+
+    #[test]
+    fn round_trip_tokens(source in "([a-z_]+|[0-9.]+|[ \n{}()<>;:,.@&=-]|//[a-z ]*\n|\"[a-z]*\"?|/\\*|\\*/)*") {
+        prop_assert_eq!(parse(&source).to_string(), source);
+    }
+
+    /// Reparsing after each edit gives the same tree as parsing from
+    /// scratch.
+    #[test]
+    fn reparse_matches_parse(
+        dream in 0..DREAMS.len(),
+        edits in prop::collection::vec(
+            (
+                any::<Index>(),
+                0..12usize,
+                // Mostly edits that scan, so the tree can be reparsed in
+                // pieces for longer
+                prop_oneof![
+                    3 => "[a-z0-9_ \n]{0,4}",
+                    1 => "[a-z0-9_ \n{}();.,:<>@&\"'/*-]{0,6}",
+                ],
+            ),
+            1..6,
+        ),
+    ) {
+        let mut text = DREAMS[dream].1.to_owned();
+        let mut tree = parse(&text);
+
+        for (start, len, insert) in edits {
+            let edit = edit(&text, start, len, &insert);
+            edit.apply(&mut text);
+            tree = reparse(&tree, &edit);
+
+            let parsed = parse(&text);
+            prop_assert_eq!(tree.to_string(), text.clone());
+            prop_assert_eq!(tree.green(), parsed.green());
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use crate::error::Span;
 use std::{
+    borrow::Cow,
     fmt::{Debug, Display, Formatter, Result as FmtResult},
     str::FromStr,
 };
@@ -39,11 +40,37 @@ pub enum TokenKind<'src> {
     CloseAngle,
     Comma,
     Semicolon,
+    Colon,
+    Equals,
+    DoubleEquals,
+    Bang,
+    BangEquals,
+    LessEquals,
+    GreaterEquals,
+    Plus,
+    PlusEquals,
+    Minus,
+    MinusEquals,
+    Arrow,
+    Star,
+    StarEquals,
+    Slash,
+    SlashEquals,
+    Percent,
+    PercentEquals,
+    Ampersand,
+    DoubleAmpersand,
+    Pipe,
+    DoublePipe,
     DotSymbol,
     AtSymbol,
+    /// The text of a `///` or `/** */` comment, without its delimiters.
+    DocComment(&'src str),
     Integer(i64),
+    Float(f64),
     Character(char),
-    String(&'src str),
+    /// Borrowed from the source unless it had escapes.
+    String(Cow<'src, str>),
     Keyword(Keyword),
     Identifier(&'src str),
 }
@@ -62,9 +89,33 @@ impl Display for TokenKind<'_> {
                 Self::CloseAngle => ">",
                 Self::Comma => ",",
                 Self::Semicolon => ";",
+                Self::Colon => ":",
+                Self::Equals => "=",
+                Self::DoubleEquals => "==",
+                Self::Bang => "!",
+                Self::BangEquals => "!=",
+                Self::LessEquals => "<=",
+                Self::GreaterEquals => ">=",
+                Self::Plus => "+",
+                Self::PlusEquals => "+=",
+                Self::Minus => "-",
+                Self::MinusEquals => "-=",
+                Self::Arrow => "->",
+                Self::Star => "*",
+                Self::StarEquals => "*=",
+                Self::Slash => "/",
+                Self::SlashEquals => "/=",
+                Self::Percent => "%",
+                Self::PercentEquals => "%=",
+                Self::Ampersand => "&",
+                Self::DoubleAmpersand => "&&",
+                Self::Pipe => "|",
+                Self::DoublePipe => "||",
                 Self::DotSymbol => ".",
                 Self::AtSymbol => "@",
+                Self::DocComment(it) => return write!(f, "///{}", it),
                 Self::Integer(it) => return write!(f, "{}", it),
+                Self::Float(it) => return write!(f, "{:?}", it),
                 Self::Character(it) => return write!(f, "'{}'", it),
                 Self::String(it) => return write!(f, r#""{}""#, it),
                 Self::Keyword(it) => return write!(f, "{}", it),
@@ -77,6 +128,17 @@ impl Display for TokenKind<'_> {
 #[derive(Debug, Clone, PartialEq)]
 pub enum Keyword {
     Struct,
+    Func,
+    Let,
+    Mut,
+    Return,
+    Match,
+    Loop,
+    Import,
+    Module,
+    Enum,
+    Trait,
+    Block,
 }
 
 impl Display for Keyword {
@@ -86,6 +148,17 @@ impl Display for Keyword {
             "{}",
             match self {
                 Self::Struct => "struct",
+                Self::Func => "func",
+                Self::Let => "let",
+                Self::Mut => "mut",
+                Self::Return => "return",
+                Self::Match => "match",
+                Self::Loop => "loop",
+                Self::Import => "import",
+                Self::Module => "module",
+                Self::Enum => "enum",
+                Self::Trait => "trait",
+                Self::Block => "block",
             }
         )
     }
@@ -97,6 +170,17 @@ impl FromStr for Keyword {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         Ok(match s {
             "struct" => Self::Struct,
+            "func" => Self::Func,
+            "let" => Self::Let,
+            "mut" => Self::Mut,
+            "return" => Self::Return,
+            "match" => Self::Match,
+            "loop" => Self::Loop,
+            "import" => Self::Import,
+            "module" => Self::Module,
+            "enum" => Self::Enum,
+            "trait" => Self::Trait,
+            "block" => Self::Block,
             _ => return Err(()),
         })
     }
@@ -1,22 +1,78 @@
-use std::num::ParseIntError;
 use crate::error::Span;
+use std::num::ParseIntError;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum ScanError {
-    InvalidToken { position: usize },
+    InvalidToken {
+        span: Span,
+    },
+    UnterminatedBlockComment {
+        start: usize,
+    },
     // TODO: Rust doesn't allow you to inspect the cause yet
-    InvalidInteger { source: ParseIntError,span: Span },
-    UnterminatedCharacterEof { start: usize },
-    EmptyCharacter { span: Span },
-    CharacterExpectedClosing { actual: char, span: Span },
-    UnterminatedString { start: usize },
+    InvalidInteger {
+        source: ParseIntError,
+        span: Span,
+    },
+    InvalidDigit {
+        digit: char,
+        radix: u32,
+        position: usize,
+    },
+    MissingDigits {
+        span: Span,
+    },
+    UnterminatedCharacterEof {
+        start: usize,
+    },
+    EmptyCharacter {
+        span: Span,
+    },
+    CharacterExpectedClosing {
+        actual: char,
+        span: Span,
+    },
+    UnterminatedString {
+        span: Span,
+    },
+    InvalidEscape {
+        escape: char,
+        span: Span,
+    },
+    MalformedUnicodeEscape {
+        span: Span,
+    },
+    InvalidCodePoint {
+        value: u32,
+        span: Span,
+    },
 }
 
 impl From<ScanError> for crate::error::Citation {
     fn from(error: ScanError) -> Self {
         use crate::error::Citation;
         match error {
-            ScanError::InvalidToken { position } => Citation::error("Invalid token".to_owned())
+            ScanError::InvalidToken { span } => {
+                Citation::error("Invalid token".to_owned()).span(span, None)
+            }
+            ScanError::UnterminatedBlockComment { start } => {
+                Citation::error("Unterminated block comment".to_owned()).span(
+                    Span {
+                        start,
+                        end: start + 1,
+                    },
+                    Some("comment starts here".to_owned()),
+                )
+            }
+            ScanError::InvalidInteger { source, span } => {
+                Citation::error("Invalid integer literal".to_owned())
+                    .span(span, Some(source.to_string()))
+            }
+            ScanError::InvalidDigit {
+                digit,
+                radix,
+                position,
+            } => Citation::error(format!("Invalid digit {} in base {} literal", digit, radix))
                 .span(
                     Span {
                         start: position,
@@ -24,8 +80,9 @@ impl From<ScanError> for crate::error::Citation {
                     },
                     None,
                 ),
-            ScanError::InvalidInteger { source, span } => {
-                Citation::error("Invalid integer literal".to_owned()).span(span, Some(source.to_string()))
+            ScanError::MissingDigits { span } => {
+                Citation::error("Integer literal has no digits after its prefix".to_owned())
+                    .span(span, None)
             }
             ScanError::UnterminatedCharacterEof { start } => {
                 Citation::error("Rest of character literal expected, found end of file".to_owned())
@@ -46,12 +103,25 @@ impl From<ScanError> for crate::error::Citation {
                 actual
             ))
             .span(position, None),
-            ScanError::UnterminatedString { start } => {
-                Citation::error("Unterminated string literal".to_owned()).span(
-                    Span { start, end: start },
-                    Some("string starts here".to_owned()),
+            ScanError::UnterminatedString { span } => {
+                Citation::error("Unterminated string literal".to_owned())
+                    .span(span, Some("string starts here".to_owned()))
+                    .help("add a closing `\"` at the end of the string".to_owned())
+            }
+            ScanError::InvalidEscape { escape, span } => {
+                Citation::error(format!("Unknown escape sequence \\{}", escape)).span(span, None)
+            }
+            ScanError::MalformedUnicodeEscape { span } => {
+                Citation::error("Malformed unicode escape".to_owned()).span(
+                    span,
+                    Some("expected 1 to 6 hex digits in braces, like \\u{1F600}".to_owned()),
                 )
             }
+            ScanError::InvalidCodePoint { value, span } => Citation::error(format!(
+                "Invalid unicode escape, {:X} is not a character",
+                value
+            ))
+            .span(span, None),
         }
     }
 }
@@ -8,6 +8,7 @@ pub use data::{Keyword, Token, TokenKind};
 pub use error::ScanError;
 use itertools::Itertools;
 use std::{
+    borrow::Cow,
     iter::Peekable,
     str::{CharIndices, FromStr},
 };
@@ -24,18 +25,35 @@ struct Scanner<'src> {
     chars: Peekable<CharIndices<'src>>,
 }
 
+/// What a comment scans to, since only doc comments are kept.
+enum Comment<'src> {
+    Plain,
+    Doc(Token<'src>),
+}
+
 impl<'src> Iterator for Scanner<'src> {
     type Item = ScanResult<'src>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let head = self.trim()?;
+        // Note the condition is in the middle of the loop
+        let head = loop {
+            let head = self.trim()?;
+
+            match self.comment(head) {
+                // Loop again for potentially more whitespace or comments
+                Some(Ok(Comment::Plain)) => continue,
+                Some(Ok(Comment::Doc(token))) => return Some(Ok(token)),
+                Some(Err(error)) => return Some(Err(error)),
+                None => break head,
+            }
+        };
 
         self.simple_tokens(head)
             .or_else(|| self.number_literals(head))
             .or_else(|| self.character_literal(head))
             .or_else(|| self.string_literal(head))
             .or_else(|| self.identifier_or_keyword(head))
-            .or(Some(Err(ScanError::InvalidToken { position: head.0 })))
+            .or_else(|| Some(Err(self.invalid_token(head))))
     }
 }
 
@@ -47,49 +65,173 @@ impl<'src> Scanner<'src> {
         }
     }
 
-    /// Ignore whitespace and comments in between token boundaries
+    /// Ignore whitespace in between token boundaries
     fn trim(&mut self) -> Option<Head> {
-        // Note the condition is in the middle of the loop
-        loop {
-            // Trim any whitespace
-            self.chars
-                .peeking_take_while(|it| it.1.is_whitespace())
-                .for_each(drop);
+        self.chars
+            .peeking_take_while(|it| it.1.is_whitespace())
+            .for_each(drop);
 
-            let head = self.chars.next()?;
+        self.chars.next()
+    }
 
-            // Check for "//" comment starter
-            if head.1 == '/' && self.chars.next_if(|it| it.1 == '/').is_some() {
-                // Keep consuming chars until a new line
-                self.chars
-                    .peeking_take_while(|it| it.1 != '\n')
-                    .for_each(drop);
+    /// Scans `//` and `/* */` comments, or `///` and `/** */` doc comments.
+    fn comment(&mut self, head: Head) -> Option<Result<Comment<'src>, ScanError>> {
+        if head.1 != '/' {
+            return None;
+        }
 
-                // Loop again for potentially more whitespace or comments
-                continue;
+        match self.chars.peek()?.1 {
+            '/' => {
+                self.chars.next();
+                Some(Ok(self.line_comment(head)))
+            }
+            '*' => {
+                self.chars.next();
+                Some(self.block_comment(head))
             }
+            _ => None,
+        }
+    }
+
+    fn line_comment(&mut self, head: Head) -> Comment<'src> {
+        // Exactly three slashes make a doc comment, more are just decoration
+        let mut ahead = self.chars.clone();
+        let doc = matches!(
+            (ahead.next(), ahead.next()),
+            (Some((_, '/')), next) if next.is_none_or(|it| it.1 != '/')
+        );
+
+        // The second slash is already consumed
+        let slashes = if doc { 3 } else { 2 };
+        let text = &self.source[head.0 + slashes..];
+        let text = &text[..text.find('\n').unwrap_or(text.len())];
+
+        // Keep consuming chars until a new line
+        let last = self
+            .chars
+            .peeking_take_while(|it| it.1 != '\n')
+            .last()
+            .map_or(head.0 + 1, |it| it.0);
 
-            // No whitespace, comments, or EOF, assume a valid token is next
-            return Some(head);
+        if !doc {
+            return Comment::Plain;
+        }
+
+        Comment::Doc(Token::new(
+            TokenKind::DocComment(text),
+            Span::new(head.0, last),
+        ))
+    }
+
+    /// Block comments nest, so each `/*` needs its own `*/`.
+    fn block_comment(&mut self, head: Head) -> Result<Comment<'src>, ScanError> {
+        // `/**/` is an empty plain comment rather than an open doc comment
+        let mut ahead = self.chars.clone();
+        let doc = matches!(
+            (ahead.next(), ahead.next()),
+            (Some((_, '*')), Some((_, next))) if next != '/'
+        );
+        if doc {
+            self.chars.next();
+        }
+
+        let mut depth = 1;
+        let (star, closing) = loop {
+            let char = self
+                .chars
+                .next()
+                .ok_or(ScanError::UnterminatedBlockComment { start: head.0 })?;
+
+            match char.1 {
+                '/' if self.chars.next_if(|it| it.1 == '*').is_some() => depth += 1,
+                '*' => {
+                    if let Some(closing) = self.chars.next_if(|it| it.1 == '/') {
+                        depth -= 1;
+                        if depth == 0 {
+                            break (char.0, closing.0);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        };
+
+        if !doc {
+            return Ok(Comment::Plain);
+        }
+
+        Ok(Comment::Doc(Token::new(
+            TokenKind::DocComment(&self.source[head.0 + 3..star]),
+            Span::new(head.0, closing),
+        )))
+    }
+
+    /// Skips to the end of the line or statement after an invalid char, so
+    /// the broken statement doesn't cause more errors. Every invalid char
+    /// skipped is merged into one error.
+    fn invalid_token(&mut self, head: Head) -> ScanError {
+        let last = self
+            .chars
+            .peeking_take_while(|it| it.1 != '\n' && it.1 != ';')
+            .filter(|it| !can_start_token(it.1))
+            .last()
+            .unwrap_or(head);
+
+        ScanError::InvalidToken {
+            span: Span::new(head.0, last.0),
         }
     }
 
     fn simple_tokens(&mut self, head: Head) -> Option<ScanResult<'src>> {
-        let kind = match head.1 {
-            '(' => TokenKind::OpenParen,
-            ')' => TokenKind::CloseParen,
-            '{' => TokenKind::OpenBrace,
-            '}' => TokenKind::CloseBrace,
-            '<' => TokenKind::OpenAngle,
-            '>' => TokenKind::CloseAngle,
-            ',' => TokenKind::Comma,
-            ';' => TokenKind::Semicolon,
-            '.' => TokenKind::DotSymbol,
-            '@' => TokenKind::AtSymbol,
+        use TokenKind::*;
+
+        let (kind, end) = match head.1 {
+            '(' => (OpenParen, head.0),
+            ')' => (CloseParen, head.0),
+            '{' => (OpenBrace, head.0),
+            '}' => (CloseBrace, head.0),
+            '<' => self.operator(head, LessEquals, OpenAngle),
+            '>' => self.operator(head, GreaterEquals, CloseAngle),
+            ',' => (Comma, head.0),
+            ';' => (Semicolon, head.0),
+            ':' => (Colon, head.0),
+            '=' => self.operator(head, DoubleEquals, Equals),
+            '!' => self.operator(head, BangEquals, Bang),
+            '+' => self.operator(head, PlusEquals, Plus),
+            '-' => match self.chars.next_if(|it| it.1 == '>') {
+                Some(arrow) => (Arrow, arrow.0),
+                None => self.operator(head, MinusEquals, Minus),
+            },
+            '*' => self.operator(head, StarEquals, Star),
+            '/' => self.operator(head, SlashEquals, Slash),
+            '%' => self.operator(head, PercentEquals, Percent),
+            '&' => match self.chars.next_if(|it| it.1 == '&') {
+                Some(second) => (DoubleAmpersand, second.0),
+                None => (Ampersand, head.0),
+            },
+            '|' => match self.chars.next_if(|it| it.1 == '|') {
+                Some(second) => (DoublePipe, second.0),
+                None => (Pipe, head.0),
+            },
+            '.' => (DotSymbol, head.0),
+            '@' => (AtSymbol, head.0),
             _ => return None,
         };
 
-        Some(Ok(Token::new(kind, Span::new(head.0, head.0))))
+        Some(Ok(Token::new(kind, Span::new(head.0, end))))
+    }
+
+    /// Picks `with_equals` when `head` is followed by a `=`, like `+=`.
+    fn operator(
+        &mut self,
+        head: Head,
+        with_equals: TokenKind<'src>,
+        single: TokenKind<'src>,
+    ) -> (TokenKind<'src>, usize) {
+        match self.chars.next_if(|it| it.1 == '=') {
+            Some(equals) => (with_equals, equals.0),
+            None => (single, head.0),
+        }
     }
 
     fn number_literals(&mut self, head: Head) -> Option<ScanResult<'src>> {
@@ -97,28 +239,90 @@ impl<'src> Scanner<'src> {
             return None;
         }
 
+        // A base prefix can only follow a leading zero
+        let radix = match self.chars.peek() {
+            Some((_, 'x')) if head.1 == '0' => 16,
+            Some((_, 'b')) if head.1 == '0' => 2,
+            _ => return Some(self.decimal_literal(head)),
+        };
+
+        let prefix = self.chars.next()?;
+        Some(self.prefixed_literal(head, prefix, radix))
+    }
+
+    fn decimal_literal(&mut self, head: Head) -> ScanResult<'src> {
+        let digits = |it: &Head| it.1.is_ascii_digit() || it.1 == '_';
+
         // Keep consuming digit chars, last digit needed for slice
+        let mut last = self.chars.peeking_take_while(digits).last().unwrap_or(head);
+
+        // A dot only starts a fraction when a digit follows it, otherwise it
+        // is a chain like `1.max(2)`, so look two chars ahead
+        let mut ahead = self.chars.clone();
+        let float = matches!(
+            (ahead.next(), ahead.next()),
+            (Some((_, '.')), Some((_, digit))) if digit.is_ascii_digit()
+        );
+
+        if float {
+            self.chars.next();
+            last = self
+                .chars
+                .peeking_take_while(digits)
+                .last()
+                .expect("a digit should follow the dot");
+        }
+
+        let span = Span::new(head.0, last.0);
+        let literal: String = self.source[head.0..=last.0]
+            .chars()
+            .filter(|it| *it != '_')
+            .collect();
+
+        if float {
+            // Digits either side of a dot are always a valid float
+            let float = literal.parse().expect("float literal should parse");
+            return Ok(Token::new(TokenKind::Float(float), span));
+        }
+
+        // Parse the digits into a number
+        literal
+            .parse()
+            .map(|integer| Token::new(TokenKind::Integer(integer), span))
+            .map_err(|source| ScanError::InvalidInteger { source, span })
+    }
+
+    fn prefixed_literal(&mut self, head: Head, prefix: Head, radix: u32) -> ScanResult<'src> {
+        // Consume anything alphanumeric, so a bad digit is reported here rather
+        // than starting an identifier
         let last = self
             .chars
-            .peeking_take_while(|it| it.1.is_ascii_digit())
+            .peeking_take_while(|it| it.1.is_ascii_alphanumeric() || it.1 == '_')
             .last()
-            .unwrap_or(head);
-
-        // TODO: Implement float literals. Delayed due to needing double look ahead.
+            .unwrap_or(prefix);
+
+        let span = Span::new(head.0, last.0);
+        let digits = &self.source[prefix.0 + 1..=last.0];
+
+        if let Some((offset, digit)) = digits
+            .char_indices()
+            .find(|it| it.1 != '_' && !it.1.is_digit(radix))
+        {
+            return Err(ScanError::InvalidDigit {
+                digit,
+                radix,
+                position: prefix.0 + 1 + offset,
+            });
+        }
 
-        let span = Span {
-            start: head.0,
-            end: last.0,
-        };
+        let digits: String = digits.chars().filter(|it| *it != '_').collect();
+        if digits.is_empty() {
+            return Err(ScanError::MissingDigits { span });
+        }
 
-        // Parse the digits into a number
-        Some(
-            self.source[head.0..=last.0]
-                .parse()
-                .map(TokenKind::Integer)
-                .map(|kind| Token::new(kind, span))
-                .map_err(|source| ScanError::InvalidInteger { source, span }),
-        )
+        i64::from_str_radix(&digits, radix)
+            .map(|integer| Token::new(TokenKind::Integer(integer), span))
+            .map_err(|source| ScanError::InvalidInteger { source, span })
     }
 
     fn character_literal(&mut self, head: Head) -> Option<ScanResult<'src>> {
@@ -137,7 +341,16 @@ impl<'src> Scanner<'src> {
                     span: Span::new(head.0, char.0),
                 }))
             }
-            Some(char) => char,
+            Some((backslash, '\\')) => match self.escape(backslash) {
+                None => return Some(Err(ScanError::UnterminatedCharacterEof { start: head.0 })),
+                Some(Ok(char)) => char,
+                Some(Err(error)) => {
+                    // Skip the closing quote so it doesn't start another literal
+                    self.chars.next_if(|it| it.1 == QUOTE);
+                    return Some(Err(error));
+                }
+            },
+            Some(char) => char.1,
         };
 
         let closing = match self.chars.next() {
@@ -154,7 +367,7 @@ impl<'src> Scanner<'src> {
         };
 
         Some(Ok(Token::new(
-            TokenKind::Character(char.1),
+            TokenKind::Character(char),
             Span::new(head.0, closing.0),
         )))
     }
@@ -164,38 +377,141 @@ impl<'src> Scanner<'src> {
             return None;
         }
 
-        // Keep consuming chars until a quote
-        self.chars
-            .peeking_take_while(|it| it.1 != '"')
-            .for_each(drop);
+        let source = self.source;
+        // Only allocated once there is an escape, until then the string can be
+        // borrowed from the source
+        let mut owned: Option<String> = None;
+        let mut error = None;
+
+        // Where to carry on scanning if the string is never closed
+        let mut next_line = None;
+
+        // Keep consuming chars until a quote, even after a bad escape, so the
+        // rest of the string isn't scanned as tokens
+        let closing = loop {
+            match self.chars.next() {
+                None => return Some(Err(self.unterminated_string(head, next_line))),
+                Some((closing, '"')) => break closing,
+                Some((backslash, '\\')) => match self.escape(backslash) {
+                    // Reported as unterminated on the next loop
+                    None => {}
+                    Some(Ok(char)) => owned
+                        .get_or_insert_with(|| source[head.0 + 1..backslash].to_owned())
+                        .push(char),
+                    Some(Err(escape)) => {
+                        error.get_or_insert(escape);
+                    }
+                },
+                Some((_, char)) => {
+                    if char == '\n' && next_line.is_none() {
+                        next_line = Some(self.chars.clone());
+                    }
+                    if let Some(owned) = &mut owned {
+                        owned.push(char);
+                    }
+                }
+            }
+        };
 
-        // Expect a closing quote
-        let closing = if let Some(closing) = self.chars.next_if(|it| it.1 == '"') {
-            closing
-        } else {
-            return Some(Err(ScanError::UnterminatedString { start: head.0 }));
+        if let Some(error) = error {
+            return Some(Err(error));
+        }
+
+        let kind = TokenKind::String(match owned {
+            Some(owned) => Cow::Owned(owned),
+            // Slice the leading and trailing quote
+            None => Cow::Borrowed(&source[head.0 + 1..closing]),
+        });
+
+        Some(Ok(Token::new(kind, Span::new(head.0, closing))))
+    }
+
+    /// Reports a string that reached the end of the file. Only its first line
+    /// is treated as part of the string, and scanning carries on from the line
+    /// after, rather than losing the rest of the file to the string.
+    fn unterminated_string(
+        &mut self,
+        head: Head,
+        next_line: Option<Peekable<CharIndices<'src>>>,
+    ) -> ScanError {
+        if let Some(next_line) = next_line {
+            self.chars = next_line;
+        }
+
+        let line = self.source[head.0..].lines().next().unwrap_or_default();
+        let last = line.char_indices().last().map_or(0, |it| it.0);
+
+        ScanError::UnterminatedString {
+            span: Span::new(head.0, head.0 + last),
+        }
+    }
+
+    /// Reads the rest of an escape sequence after its backslash, or `None` at
+    /// the end of the file.
+    fn escape(&mut self, backslash: usize) -> Option<Result<char, ScanError>> {
+        let (position, char) = self.chars.next()?;
+
+        Some(match char {
+            'n' => Ok('\n'),
+            'r' => Ok('\r'),
+            't' => Ok('\t'),
+            '0' => Ok('\0'),
+            '\\' | '\'' | '"' => Ok(char),
+            'u' => self.unicode_escape(backslash, position),
+            _ => Err(ScanError::InvalidEscape {
+                escape: char,
+                span: Span::new(backslash, position),
+            }),
+        })
+    }
+
+    /// Reads the `{...}` of a `\u{...}` escape, with up to 6 hex digits.
+    fn unicode_escape(&mut self, backslash: usize, u: usize) -> Result<char, ScanError> {
+        let malformed = |end| ScanError::MalformedUnicodeEscape {
+            span: Span::new(backslash, end),
         };
 
-        // Slice the leading and trailing quote
-        let kind = TokenKind::String(&self.source[head.0 + 1..closing.0]);
+        let open = self
+            .chars
+            .next_if(|it| it.1 == '{')
+            .ok_or_else(|| malformed(u))?;
+        let last = self
+            .chars
+            .peeking_take_while(|it| it.1.is_ascii_hexdigit())
+            .last();
+        let digits = &self.source[open.0 + 1..=last.unwrap_or(open).0];
 
-        Some(Ok(Token::new(kind, Span::new(head.0, closing.0))))
+        let closing = self
+            .chars
+            .next_if(|it| it.1 == '}')
+            .ok_or_else(|| malformed(last.unwrap_or(open).0))?;
+
+        if digits.is_empty() || digits.len() > 6 {
+            return Err(malformed(closing.0));
+        }
+
+        // At most 6 hex digits always fit
+        let value = u32::from_str_radix(digits, 16).expect("hex digits should parse");
+        char::from_u32(value).ok_or(ScanError::InvalidCodePoint {
+            value,
+            span: Span::new(backslash, closing.0),
+        })
     }
 
     fn identifier_or_keyword(&mut self, head: Head) -> Option<ScanResult<'src>> {
-        if !head.1.is_alphabetic() {
+        if !head.1.is_alphabetic() && head.1 != '_' {
             return None;
         }
 
         // Keep consuming chars, last char needed for slice
         let last = self
             .chars
-            .peeking_take_while(|it| it.1.is_alphanumeric())
+            .peeking_take_while(|it| it.1.is_alphanumeric() || it.1 == '_')
             .last()
             .unwrap_or(head);
 
         // Check if reserved word
-        let kind = &self.source[head.0..=last.0];
+        let kind = &self.source[head.0..last.0 + last.1.len_utf8()];
         let kind = Keyword::from_str(kind)
             .map(TokenKind::Keyword)
             .unwrap_or_else(|()| TokenKind::Identifier(kind));
@@ -203,3 +519,9 @@ impl<'src> Scanner<'src> {
         Some(Ok(Token::new(kind, Span::new(head.0, last.0))))
     }
 }
+
+/// Whether `char` is whitespace or could start some token. Should be kept in
+/// sync with the scanners in `next()`.
+fn can_start_token(char: char) -> bool {
+    char.is_whitespace() || char.is_alphanumeric() || "_'\"(){}<>,;:=!+-*/%&|.@".contains(char)
+}
@@ -0,0 +1,208 @@
+use crate::error::{Citation, Span};
+
+/// Types in errors are already written out, since variables are only
+/// meaningful to the checker that made them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeError<'src> {
+    /// A value of one type where another was expected.
+    Mismatch {
+        expected: String,
+        found: String,
+        span: Span,
+    },
+    /// A type name that isn't a primitive, enum or generic parameter.
+    UnknownType { name: &'src str, span: Span },
+    /// A bound or implementation of something that isn't a trait.
+    NotATrait { name: &'src str, span: Span },
+    /// A type or trait given the wrong number of type arguments.
+    TypeArguments {
+        name: &'src str,
+        expected: usize,
+        found: usize,
+        span: Span,
+    },
+    /// A type that doesn't implement a trait it's required to.
+    Unsatisfied {
+        ty: String,
+        bound: String,
+        span: Span,
+        /// Where the bound was required.
+        required: Span,
+    },
+    /// An implementation missing one of its trait's functions.
+    MissingFunction {
+        bound: String,
+        function: &'src str,
+        span: Span,
+        declared: Span,
+    },
+    /// An implementation whose function doesn't fit the trait's signature.
+    SignatureMismatch {
+        bound: String,
+        expected: String,
+        found: String,
+        span: Span,
+    },
+    /// A chain call to a name with no function for the receiver's type.
+    NoMethod {
+        name: &'src str,
+        ty: String,
+        span: Span,
+    },
+    /// Arguments that don't fit the parameters of what's called.
+    Arguments {
+        name: &'src str,
+        message: String,
+        span: Span,
+    },
+    /// A call of a local that isn't a block.
+    NotCallable { ty: String, span: Span },
+    /// A call that fits several functions equally well.
+    Ambiguous {
+        name: &'src str,
+        candidates: Vec<Candidate<'src>>,
+        span: Span,
+    },
+    /// A call that fits none of the functions with its name.
+    NoOverload {
+        name: &'src str,
+        /// The types of the arguments, written out.
+        arguments: String,
+        candidates: Vec<Candidate<'src>>,
+        span: Span,
+    },
+}
+
+/// One of the functions a call could go to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Candidate<'src> {
+    pub signature: String,
+    /// The enum, extension or module it's declared in.
+    pub namespace: Option<&'src str>,
+    pub span: Span,
+}
+
+impl Candidate<'_> {
+    fn label(&self) -> String {
+        match self.namespace {
+            Some(namespace) => format!("`{}` in `{}`", self.signature, namespace),
+            None => format!("`{}`", self.signature),
+        }
+    }
+}
+
+/// Points at each candidate where it's declared.
+fn candidates(mut citation: Citation, candidates: &[Candidate]) -> Citation {
+    for candidate in candidates {
+        citation = citation.secondary(candidate.span, Some(candidate.label()));
+    }
+    citation
+}
+
+impl<'src> From<TypeError<'src>> for Citation {
+    fn from(error: TypeError<'src>) -> Self {
+        match error {
+            TypeError::Mismatch {
+                expected,
+                found,
+                span,
+            } => Citation::error("Mismatched types".to_owned()).span(
+                span,
+                Some(format!("expected `{}`, found `{}`", expected, found)),
+            ),
+            TypeError::UnknownType { name, span } => {
+                Citation::error(format!("Unknown type `{}`", name))
+                    .span(span, Some("not a type or generic parameter".to_owned()))
+            }
+            TypeError::NotATrait { name, span } => {
+                Citation::error(format!("`{}` is not a trait", name)).span(span, None)
+            }
+            TypeError::TypeArguments {
+                name,
+                expected,
+                found,
+                span,
+            } => Citation::error(format!("Wrong number of type arguments for `{}`", name)).span(
+                span,
+                Some(format!("expected {}, found {}", expected, found)),
+            ),
+            TypeError::Unsatisfied {
+                ty,
+                bound,
+                span,
+                required,
+            } => Citation::error(format!("`{}` doesn't implement `{}`", ty, bound))
+                .span(
+                    span,
+                    Some(format!("the trait `{}` is not implemented", bound)),
+                )
+                .secondary(required, Some("required by this bound".to_owned())),
+            TypeError::MissingFunction {
+                bound,
+                function,
+                span,
+                declared,
+            } => Citation::error(format!(
+                "Missing `{}` in implementation of `{}`",
+                function, bound
+            ))
+            .span(span, Some(format!("`{}` is not implemented", function)))
+            .secondary(declared, Some("declared here".to_owned())),
+            TypeError::SignatureMismatch {
+                bound,
+                expected,
+                found,
+                span,
+            } => Citation::error(format!("Function doesn't match its trait `{}`", bound))
+                .span(span, Some(format!("found `{}`", found)))
+                .note(format!("the trait expects `{}`", expected)),
+            TypeError::NoMethod { name, ty, span } => {
+                Citation::error(format!("No function `{}` for `{}`", name, ty)).span(span, None)
+            }
+            TypeError::Arguments {
+                name,
+                message,
+                span,
+            } => {
+                Citation::error(format!("Wrong arguments for `{}`", name)).span(span, Some(message))
+            }
+            TypeError::NotCallable { ty, span } => {
+                Citation::error(format!("`{}` is not a block", ty))
+                    .span(span, Some("called here".to_owned()))
+            }
+            TypeError::Ambiguous {
+                name,
+                candidates: list,
+                span,
+            } => {
+                let citation = candidates(
+                    Citation::error(format!("Ambiguous call to `{}`", name))
+                        .span(span, Some("fits more than one function".to_owned())),
+                    &list,
+                );
+
+                // Calls through a module only see the functions in it
+                let mut namespaces: Vec<_> = list.iter().map(|it| it.namespace).collect();
+                namespaces.sort_unstable();
+                namespaces.dedup();
+                match namespaces[..] {
+                    [Some(first), ..] if namespaces.len() == list.len() => citation.help(format!(
+                        "call one through where it's declared, as in `{}.{}`",
+                        first, name
+                    )),
+                    _ => citation,
+                }
+            }
+            TypeError::NoOverload {
+                name,
+                arguments,
+                candidates: list,
+                span,
+            } => candidates(
+                Citation::error(format!("No `{}` takes these arguments", name))
+                    .span(span, Some(format!("called with `{}`", arguments))),
+                &list,
+            ),
+        }
+    }
+}
@@ -0,0 +1,1627 @@
+//! Type checking, with inference inside function bodies.
+//!
+//! Like the interpreter, the checker sees every file's items in one
+//! namespace, leaving scoping errors to name resolution. Names it can't find
+//! get an error type that fits anywhere, so they're only reported once.
+//!
+//! Generic parameters of the function being checked are opaque types that
+//! only have what their bounds give them. Calling a generic function gives
+//! each of its parameters a fresh type variable, and the bounds on them are
+//! checked once the body around the call has been inferred. Traits are
+//! implemented by listing them after an enum's variants, or with an
+//! extension, and `Self` in a trait stands for the implementing type.
+//!
+//! Functions can share a name if calls can tell them apart. A call goes to
+//! the one its labels and number of arguments fit, then the one that takes
+//! the types of its values, preferring functions declared in the receiver's
+//! type. Calls that still fit several are reported with every candidate.
+
+mod error;
+#[cfg(test)]
+mod test;
+mod ty;
+
+pub use error::{Candidate, TypeError};
+pub use ty::{TraitRef, Ty, PRIMITIVES};
+
+use crate::{
+    error::Span,
+    parser::ast::*,
+    resolve::{
+        overload::{self, Assignment, Choice, Rank},
+        Unit,
+    },
+};
+use std::{
+    collections::{HashMap, HashSet},
+    mem,
+};
+
+/// Checks every function body in the program, returning what went wrong.
+pub fn check<'src>(units: &[Unit<'src>]) -> Vec<TypeError<'src>> {
+    let mut checker = Checker::default();
+    checker.declare(units);
+    checker.implementations();
+
+    for signature in mem::take(&mut checker.bodies) {
+        checker.body(&signature, true);
+    }
+
+    checker.errors
+}
+
+/// Checks the program like `check`, except that the tail of the free
+/// function `name` isn't checked against its return type. Its type is given
+/// back instead, if there's such a function.
+pub fn infer<'src>(units: &[Unit<'src>], name: &str) -> (Option<Ty<'src>>, Vec<TypeError<'src>>) {
+    let mut checker = Checker::default();
+    checker.declare(units);
+    checker.implementations();
+
+    let mut inferred = None;
+    for signature in mem::take(&mut checker.bodies) {
+        if signature.name.name == name && signature.owner.is_none() {
+            inferred = Some(checker.body(&signature, false));
+        } else {
+            checker.body(&signature, true);
+        }
+    }
+
+    (inferred, checker.errors)
+}
+
+/// Parameter types, each with the label arguments use for it.
+type Parameters<'src> = Vec<(Option<&'src str>, Ty<'src>)>;
+
+/// A generic parameter and the traits it's bound by.
+#[derive(Debug, Clone)]
+struct Generic<'src> {
+    name: &'src str,
+    bounds: Vec<(TraitRef<'src>, Span)>,
+}
+
+/// A function's type, for any kind of function: free functions, functions
+/// in enums, extensions and traits, variant constructors and builtins.
+#[derive(Debug, Clone)]
+struct Signature<'a, 'src> {
+    name: Ident<'src>,
+    /// The name of the type the function was declared in.
+    owner: Option<&'src str>,
+    /// The enum, extension or module the function was declared in, which
+    /// calls can go through to pick it.
+    namespace: Option<&'src str>,
+    /// The generics of what it was declared in, then its own.
+    generics: Vec<Generic<'src>>,
+    parameters: Parameters<'src>,
+    return_type: Ty<'src>,
+    self_ty: Option<Ty<'src>>,
+    /// The declaration, if the function has a body.
+    function: Option<&'a Function<'src>>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct EnumInfo<'src> {
+    generics: Vec<Generic<'src>>,
+    variants: Vec<(&'src str, Vec<Ty<'src>>)>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct TraitInfo<'a, 'src> {
+    generics: Vec<&'src str>,
+    /// Signatures with `Self` as a generic parameter.
+    functions: Vec<Signature<'a, 'src>>,
+}
+
+/// `target: bound`, given by an enum or extension.
+#[derive(Debug, Clone)]
+struct Implementation<'a, 'src> {
+    generics: Vec<Generic<'src>>,
+    target: Ty<'src>,
+    bound: TraitRef<'src>,
+    span: Span,
+    functions: Vec<Signature<'a, 'src>>,
+}
+
+/// A bound that a type has to meet, checked after the body it's in.
+#[derive(Debug, Clone)]
+struct Obligation<'src> {
+    ty: Ty<'src>,
+    bound: TraitRef<'src>,
+    span: Span,
+    required: Span,
+}
+
+/// What a call is made on.
+#[derive(Debug, Clone)]
+enum Receiver<'src> {
+    None,
+    /// `value.f()`, where the value is the first argument.
+    Value(Ty<'src>, Span),
+    /// `Type.f()`, which calls a function declared in the type.
+    Static(Ty<'src>),
+    /// `module.f()`, which prefers the functions declared in the module.
+    Namespace(&'src str),
+}
+
+#[derive(Debug, Clone)]
+struct Argument<'a, 'src> {
+    label: Option<Ident<'src>>,
+    kind: ArgumentKind<'a, 'src>,
+    span: Span,
+}
+
+#[derive(Debug, Clone)]
+enum ArgumentKind<'a, 'src> {
+    Value(Ty<'src>),
+    /// Blocks are checked once it's known what they're passed to.
+    Block(&'a Block<'src>),
+}
+
+#[derive(Debug, Default)]
+struct Checker<'a, 'src> {
+    enums: HashMap<&'src str, EnumInfo<'src>>,
+    traits: HashMap<&'src str, TraitInfo<'a, 'src>>,
+    implementations: Vec<Implementation<'a, 'src>>,
+    functions: HashMap<&'src str, Vec<Signature<'a, 'src>>>,
+    builtins: HashMap<&'src str, Signature<'a, 'src>>,
+    /// The enum each variant is in.
+    variants: HashMap<&'src str, &'src str>,
+    /// Modules and extensions, which paths can go through.
+    namespaces: HashSet<&'src str>,
+    /// Functions with bodies, in the order they were declared.
+    bodies: Vec<Signature<'a, 'src>>,
+
+    /// What each type variable has been inferred to be.
+    variables: Vec<Option<Ty<'src>>>,
+    /// The generic parameters in scope.
+    generics: Vec<Generic<'src>>,
+    self_ty: Option<Ty<'src>>,
+    /// The enum, extension or module being declared in.
+    namespace: Option<&'src str>,
+    locals: Vec<(&'src str, Ty<'src>)>,
+    /// What a `return` can return from, with the type it returns. Loops
+    /// are called `loop`, which no function can be.
+    returns: Vec<(&'src str, Ty<'src>)>,
+    obligations: Vec<Obligation<'src>>,
+    errors: Vec<TypeError<'src>>,
+}
+
+impl<'a, 'src> Checker<'a, 'src> {
+    /// Collects the types of every item, before any bodies are checked.
+    fn declare(&mut self, units: &'a [Unit<'src>]) {
+        // Names first, so declarations can refer to items after them
+        for unit in units {
+            self.namespaces.extend(unit.directory.iter().copied());
+        }
+        for item in units.iter().flat_map(|unit| &unit.file.items) {
+            match item {
+                Item::Enum(enumeration) => {
+                    let info = EnumInfo {
+                        generics: enumeration
+                            .generics
+                            .iter()
+                            .map(|generic| Generic {
+                                name: generic.name.name,
+                                bounds: Vec::new(),
+                            })
+                            .collect(),
+                        variants: Vec::new(),
+                    };
+                    self.enums.insert(enumeration.name.name, info);
+                }
+                Item::Trait(trait_item) => {
+                    let info = TraitInfo {
+                        generics: trait_item.generics.iter().map(|it| it.name.name).collect(),
+                        functions: Vec::new(),
+                    };
+                    self.traits.insert(trait_item.name.name, info);
+                }
+                Item::Module(module) => {
+                    self.namespaces.insert(module.name.name);
+                }
+                Item::Extension(extension) => {
+                    self.namespaces.insert(extension.name.name);
+                }
+                Item::Function(_) | Item::Import(_) => {}
+            }
+        }
+
+        for unit in units {
+            let module = unit.file.items.iter().find_map(|item| match item {
+                Item::Module(module) => Some(module.name.name),
+                _ => None,
+            });
+
+            for item in &unit.file.items {
+                self.namespace = module;
+                match item {
+                    Item::Function(function) => {
+                        let signature = self.function(function, None);
+                        self.add_function(signature);
+                    }
+                    Item::Enum(enumeration) => self.declare_enum(enumeration),
+                    Item::Trait(trait_item) => self.declare_trait(trait_item),
+                    Item::Extension(extension) => self.declare_extension(extension),
+                    Item::Module(_) | Item::Import(_) => {}
+                }
+                self.generics.clear();
+                self.self_ty = None;
+            }
+        }
+        self.namespace = None;
+
+        self.declare_builtins();
+    }
+
+    fn add_function(&mut self, signature: Signature<'a, 'src>) {
+        if signature.function.is_some() {
+            self.bodies.push(signature.clone());
+        }
+        self.functions
+            .entry(signature.name.name)
+            .or_default()
+            .push(signature);
+    }
+
+    fn declare_enum(&mut self, enumeration: &'a Enum<'src>) {
+        let name = enumeration.name.name;
+        self.namespace = Some(name);
+        let generics = self.generics(&enumeration.generics);
+        let self_ty = Ty::Named {
+            name,
+            arguments: generics.iter().map(|it| Ty::Parameter(it.name)).collect(),
+        };
+        self.self_ty = Some(self_ty.clone());
+
+        let mut variants = Vec::new();
+        for variant in &enumeration.variants {
+            let fields: Vec<_> = variant.fields.iter().map(|it| self.lower(&it.ty)).collect();
+            self.variants.insert(variant.name.name, name);
+
+            // Constructors are functions, so they can be chained into
+            self.add_function(Signature {
+                name: variant.name,
+                owner: None,
+                namespace: self.namespace,
+                generics: generics.clone(),
+                parameters: fields.iter().map(|it| (None, it.clone())).collect(),
+                return_type: self_ty.clone(),
+                self_ty: None,
+                function: None,
+            });
+            variants.push((variant.name.name, fields));
+        }
+        self.enums.insert(
+            name,
+            EnumInfo {
+                generics: generics.clone(),
+                variants,
+            },
+        );
+
+        let functions: Vec<_> = enumeration
+            .functions
+            .iter()
+            .map(|function| self.function(function, Some(name)))
+            .collect();
+        for ty in &enumeration.traits {
+            self.implement(ty, &generics, &self_ty, &functions);
+        }
+        for function in functions {
+            self.add_function(function);
+        }
+    }
+
+    fn declare_trait(&mut self, trait_item: &'a Trait<'src>) {
+        self.generics(&trait_item.generics);
+        self.generics.push(Generic {
+            name: "Self",
+            bounds: Vec::new(),
+        });
+        self.self_ty = Some(Ty::Parameter("Self"));
+
+        let functions = trait_item
+            .functions
+            .iter()
+            .map(|signature| {
+                self.signature(
+                    signature.name,
+                    &signature.generics,
+                    &signature.parameters,
+                    signature.return_type.as_ref(),
+                    None,
+                )
+            })
+            .collect();
+
+        if let Some(info) = self.traits.get_mut(trait_item.name.name) {
+            info.functions = functions;
+        }
+    }
+
+    fn declare_extension(&mut self, extension: &'a Extension<'src>) {
+        self.namespace = Some(extension.name.name);
+        let generics = self.generics(&extension.generics);
+        let target = self.lower(&extension.target);
+        self.self_ty = Some(target.clone());
+
+        let functions: Vec<_> = extension
+            .functions
+            .iter()
+            .map(|function| self.function(function, target.head()))
+            .collect();
+        for ty in &extension.traits {
+            self.implement(ty, &generics, &target, &functions);
+        }
+        for function in functions {
+            self.add_function(function);
+        }
+    }
+
+    fn implement(
+        &mut self,
+        bound: &Type<'src>,
+        generics: &[Generic<'src>],
+        target: &Ty<'src>,
+        functions: &[Signature<'a, 'src>],
+    ) {
+        if let Some(bound_ref) = self.lower_bound(bound) {
+            self.implementations.push(Implementation {
+                generics: generics.to_vec(),
+                target: target.clone(),
+                bound: bound_ref,
+                span: bound.span,
+                functions: functions.to_vec(),
+            });
+        }
+    }
+
+    fn declare_builtins(&mut self) {
+        let t = || Ty::Parameter("T");
+        let generic = || {
+            vec![Generic {
+                name: "T",
+                bounds: Vec::new(),
+            }]
+        };
+        let mut builtin = |name, parameters: Vec<Ty<'src>>, return_type| {
+            let signature = Signature {
+                name: Ident {
+                    name,
+                    span: Span::default(),
+                },
+                owner: None,
+                namespace: None,
+                generics: generic(),
+                parameters: parameters.into_iter().map(|it| (None, it)).collect(),
+                return_type,
+                self_ty: None,
+                function: None,
+            };
+            self.builtins.insert(name, signature);
+        };
+
+        for name in &["print", "print_line"] {
+            builtin(name, vec![t()], Ty::named("Unit"));
+        }
+        builtin("negate", vec![t()], t());
+        for name in &["add", "subtract", "multiply", "divide", "remainder"] {
+            builtin(name, vec![t(), t()], t());
+        }
+        for name in &["equals", "less_than", "greater_than"] {
+            builtin(name, vec![t(), t()], Ty::named("Boolean"));
+        }
+    }
+
+    /// Brings generic parameters into scope, returning them with their
+    /// bounds. Bounds can refer to any of the parameters.
+    fn generics(&mut self, generics: &[GenericParameter<'src>]) -> Vec<Generic<'src>> {
+        let start = self.generics.len();
+        for generic in generics {
+            self.generics.push(Generic {
+                name: generic.name.name,
+                bounds: Vec::new(),
+            });
+        }
+
+        for (index, generic) in generics.iter().enumerate() {
+            if let Some(bound) = &generic.bound {
+                if let Some(trait_ref) = self.lower_bound(bound) {
+                    self.generics[start + index]
+                        .bounds
+                        .push((trait_ref, bound.span));
+                }
+            }
+        }
+
+        self.generics[start..].to_vec()
+    }
+
+    fn function(
+        &mut self,
+        function: &'a Function<'src>,
+        owner: Option<&'src str>,
+    ) -> Signature<'a, 'src> {
+        let mut signature = self.signature(
+            function.name,
+            &function.generics,
+            &function.parameters,
+            function.return_type.as_ref(),
+            owner,
+        );
+        // Without a return type, it's whatever the body gives
+        if function.return_type.is_none() {
+            signature.return_type = self.fresh();
+        }
+        signature.function = Some(function);
+        signature
+    }
+
+    fn signature(
+        &mut self,
+        name: Ident<'src>,
+        generics: &[GenericParameter<'src>],
+        parameters: &[Parameter<'src>],
+        return_type: Option<&Type<'src>>,
+        owner: Option<&'src str>,
+    ) -> Signature<'a, 'src> {
+        let scope = self.generics.len();
+        self.generics(generics);
+
+        let parameters = parameters
+            .iter()
+            .map(|parameter| (parameter.argument_label(), self.lower(&parameter.ty)))
+            .collect();
+        let return_type = match return_type {
+            Some(ty) => self.lower(ty),
+            None => Ty::named("Unit"),
+        };
+
+        let signature = Signature {
+            name,
+            owner,
+            namespace: self.namespace,
+            generics: self.generics.clone(),
+            parameters,
+            return_type,
+            self_ty: self.self_ty.clone(),
+            function: None,
+        };
+        self.generics.truncate(scope);
+        signature
+    }
+
+    /// The type a type annotation means in the current scope.
+    fn lower(&mut self, ty: &Type<'src>) -> Ty<'src> {
+        let (name, parameters) = match &ty.kind {
+            TypeKind::Named { name, parameters } => (*name, parameters),
+            TypeKind::Block {
+                parameters,
+                return_type,
+            } => {
+                return Ty::Block {
+                    parameters: parameters
+                        .iter()
+                        .map(|it| (it.label.map(|label| label.name), self.lower(&it.ty)))
+                        .collect(),
+                    return_type: Box::new(match return_type {
+                        Some(ty) => self.lower(ty),
+                        None => Ty::named("Unit"),
+                    }),
+                }
+            }
+        };
+
+        let arguments: Vec<_> = parameters.iter().map(|it| self.lower(&it.ty)).collect();
+
+        if let (Some(self_ty), "Self") = (&self.self_ty, name.name) {
+            return self.type_arguments(name, ty.span, 0, arguments.len(), self_ty.clone());
+        }
+        if self.generics.iter().any(|it| it.name == name.name) {
+            let parameter = Ty::Parameter(name.name);
+            return self.type_arguments(name, ty.span, 0, arguments.len(), parameter);
+        }
+
+        let expected = match self.enums.get(name.name) {
+            Some(info) => info.generics.len(),
+            None if PRIMITIVES.contains(&name.name) => 0,
+            None => {
+                self.errors.push(TypeError::UnknownType {
+                    name: name.name,
+                    span: name.span,
+                });
+                return Ty::Error;
+            }
+        };
+
+        let count = arguments.len();
+        let named = Ty::Named {
+            name: name.name,
+            arguments,
+        };
+        self.type_arguments(name, ty.span, expected, count, named)
+    }
+
+    /// `ty` if it was given the right number of type arguments.
+    fn type_arguments(
+        &mut self,
+        name: Ident<'src>,
+        span: Span,
+        expected: usize,
+        found: usize,
+        ty: Ty<'src>,
+    ) -> Ty<'src> {
+        if expected == found {
+            return ty;
+        }
+
+        self.errors.push(TypeError::TypeArguments {
+            name: name.name,
+            expected,
+            found,
+            span,
+        });
+        Ty::Error
+    }
+
+    /// The trait a bound names, if it's a trait.
+    fn lower_bound(&mut self, ty: &Type<'src>) -> Option<TraitRef<'src>> {
+        let (name, parameters) = match &ty.kind {
+            TypeKind::Named { name, parameters } => (*name, parameters),
+            TypeKind::Block { .. } => {
+                self.errors.push(TypeError::NotATrait {
+                    name: "block",
+                    span: ty.span,
+                });
+                return None;
+            }
+        };
+
+        let expected = match self.traits.get(name.name) {
+            Some(info) => info.generics.len(),
+            None => {
+                self.errors.push(TypeError::NotATrait {
+                    name: name.name,
+                    span: name.span,
+                });
+                return None;
+            }
+        };
+
+        let arguments: Vec<_> = parameters.iter().map(|it| self.lower(&it.ty)).collect();
+        if arguments.len() != expected {
+            self.errors.push(TypeError::TypeArguments {
+                name: name.name,
+                expected,
+                found: arguments.len(),
+                span: ty.span,
+            });
+            return None;
+        }
+
+        Some(TraitRef {
+            name: name.name,
+            arguments,
+        })
+    }
+
+    /// Checks that every implementation has its trait's functions.
+    fn implementations(&mut self) {
+        for implementation in self.implementations.clone() {
+            let bound = &implementation.bound;
+            let info = match self.traits.get(bound.name) {
+                Some(info) => info.clone(),
+                None => continue,
+            };
+
+            let mut map: HashMap<_, _> = info
+                .generics
+                .iter()
+                .copied()
+                .zip(bound.arguments.iter().cloned())
+                .collect();
+            map.insert("Self", implementation.target.clone());
+
+            for expected in &info.functions {
+                let found = implementation
+                    .functions
+                    .iter()
+                    .find(|function| function.name.name == expected.name.name);
+                let found = match found {
+                    Some(found) => found,
+                    None => {
+                        self.errors.push(TypeError::MissingFunction {
+                            bound: bound.to_string(),
+                            function: expected.name.name,
+                            span: implementation.span,
+                            declared: expected.name.span,
+                        });
+                        continue;
+                    }
+                };
+
+                let parameters: Vec<_> = expected
+                    .parameters
+                    .iter()
+                    .map(|(label, ty)| (*label, ty.substitute(&map)))
+                    .collect();
+                let return_type = expected.return_type.substitute(&map);
+
+                let fits = parameters.len() == found.parameters.len()
+                    && parameters
+                        .iter()
+                        .zip(&found.parameters)
+                        .all(|((_, expected), (_, found))| self.unify(expected, found))
+                    && self.unify(&return_type, &found.return_type);
+
+                if !fits {
+                    self.errors.push(TypeError::SignatureMismatch {
+                        bound: bound.to_string(),
+                        expected: self.show_signature(found.name.name, &parameters, &return_type),
+                        found: self.show_signature(
+                            found.name.name,
+                            &found.parameters,
+                            &found.return_type,
+                        ),
+                        span: found.name.span,
+                    });
+                }
+            }
+        }
+    }
+
+    fn show_signature(
+        &self,
+        name: &str,
+        parameters: &[(Option<&'src str>, Ty<'src>)],
+        return_type: &Ty<'src>,
+    ) -> String {
+        let parameters: Vec<_> = parameters
+            .iter()
+            .map(|(label, ty)| match label {
+                Some(label) => format!("{}: {}", label, self.resolve(ty)),
+                None => self.resolve(ty).to_string(),
+            })
+            .collect();
+        format!(
+            "func {}({}) -> {}",
+            name,
+            parameters.join(", "),
+            self.resolve(return_type)
+        )
+    }
+
+    /// Checks a function's body, and whether its tail fits the return type
+    /// if `expect` is set, giving back the tail's type.
+    fn body(&mut self, signature: &Signature<'a, 'src>, expect: bool) -> Ty<'src> {
+        let function = match signature.function {
+            Some(function) => function,
+            None => return Ty::Error,
+        };
+
+        self.generics = signature.generics.clone();
+        self.self_ty = signature.self_ty.clone();
+        for (parameter, (_, ty)) in function.parameters.iter().zip(&signature.parameters) {
+            self.pattern(&parameter.pattern, ty);
+        }
+
+        self.returns
+            .push((function.name.name, signature.return_type.clone()));
+        let ty = self.block(&function.body);
+        if expect {
+            self.expect(&ty, &signature.return_type, tail_span(&function.body));
+        }
+        self.returns.clear();
+        self.locals.clear();
+
+        self.solve();
+        self.resolve(&ty)
+    }
+
+    /// Checks every bound that calls in the last body required.
+    fn solve(&mut self) {
+        for obligation in mem::take(&mut self.obligations) {
+            if !self.implements(&obligation.ty, &obligation.bound, 0) {
+                self.errors.push(TypeError::Unsatisfied {
+                    ty: self.resolve(&obligation.ty).to_string(),
+                    bound: self.resolve_bound(&obligation.bound).to_string(),
+                    span: obligation.span,
+                    required: obligation.required,
+                });
+            }
+        }
+    }
+
+    /// Whether a type meets a bound. Types that aren't known yet are given
+    /// the benefit of the doubt.
+    fn implements(&mut self, ty: &Ty<'src>, bound: &TraitRef<'src>, depth: usize) -> bool {
+        // Implementations that need themselves can go on forever
+        if depth > 16 {
+            return true;
+        }
+
+        match self.shallow(ty) {
+            Ty::Variable(_) | Ty::Error => true,
+            Ty::Parameter(name) => {
+                let bounds: Vec<_> = self
+                    .generics
+                    .iter()
+                    .filter(|it| it.name == name)
+                    .flat_map(|it| it.bounds.iter().map(|(bound, _)| bound.clone()))
+                    .collect();
+                bounds.iter().any(|it| self.same_bound(it, bound))
+            }
+            ty => {
+                for implementation in self.implementations.clone() {
+                    if implementation.bound.name != bound.name {
+                        continue;
+                    }
+
+                    let snapshot = self.variables.clone();
+                    let map = self.instantiate(&implementation.generics);
+                    let target = implementation.target.substitute(&map);
+                    let fits = self.unify(&target, &ty)
+                        && self.same_bound(&implementation.bound.substitute(&map), bound)
+                        && implementation.generics.iter().all(|generic| {
+                            generic.bounds.iter().all(|(inner, _)| {
+                                let inner = inner.substitute(&map);
+                                self.implements(&map[generic.name], &inner, depth + 1)
+                            })
+                        });
+
+                    if fits {
+                        return true;
+                    }
+                    self.variables = snapshot;
+                }
+                false
+            }
+        }
+    }
+
+    fn same_bound(&mut self, a: &TraitRef<'src>, b: &TraitRef<'src>) -> bool {
+        a.name == b.name
+            && a.arguments.len() == b.arguments.len()
+            && a.arguments
+                .iter()
+                .zip(&b.arguments)
+                .all(|(a, b)| self.unify(a, b))
+    }
+
+    fn fresh(&mut self) -> Ty<'src> {
+        self.variables.push(None);
+        Ty::Variable(self.variables.len() - 1)
+    }
+
+    /// Maps each generic parameter to a fresh variable.
+    fn instantiate(&mut self, generics: &[Generic<'src>]) -> HashMap<&'src str, Ty<'src>> {
+        generics
+            .iter()
+            .map(|generic| (generic.name, self.fresh()))
+            .collect()
+    }
+
+    /// Follows inferred variables until reaching a type that isn't one.
+    fn shallow(&self, ty: &Ty<'src>) -> Ty<'src> {
+        let mut ty = ty.clone();
+        while let Ty::Variable(variable) = ty {
+            match &self.variables[variable] {
+                Some(inferred) => ty = inferred.clone(),
+                None => break,
+            }
+        }
+        ty
+    }
+
+    /// Replaces every inferred variable in a type.
+    fn resolve(&self, ty: &Ty<'src>) -> Ty<'src> {
+        match self.shallow(ty) {
+            Ty::Named { name, arguments } => Ty::Named {
+                name,
+                arguments: arguments.iter().map(|it| self.resolve(it)).collect(),
+            },
+            Ty::Block {
+                parameters,
+                return_type,
+            } => Ty::Block {
+                parameters: parameters
+                    .iter()
+                    .map(|(label, ty)| (*label, self.resolve(ty)))
+                    .collect(),
+                return_type: Box::new(self.resolve(&return_type)),
+            },
+            ty => ty,
+        }
+    }
+
+    fn resolve_bound(&self, bound: &TraitRef<'src>) -> TraitRef<'src> {
+        TraitRef {
+            name: bound.name,
+            arguments: bound.arguments.iter().map(|it| self.resolve(it)).collect(),
+        }
+    }
+
+    /// Makes two types the same by inferring variables. Nothing is inferred
+    /// if they can't be.
+    fn unify(&mut self, a: &Ty<'src>, b: &Ty<'src>) -> bool {
+        let snapshot = self.variables.clone();
+        let unified = self.unify_inner(a, b);
+        if !unified {
+            self.variables = snapshot;
+        }
+        unified
+    }
+
+    fn unify_inner(&mut self, a: &Ty<'src>, b: &Ty<'src>) -> bool {
+        match (self.shallow(a), self.shallow(b)) {
+            (Ty::Error, _) | (_, Ty::Error) => true,
+            (Ty::Variable(a), Ty::Variable(b)) if a == b => true,
+            (Ty::Variable(variable), ty) | (ty, Ty::Variable(variable)) => {
+                if self.occurs(variable, &ty) {
+                    return false;
+                }
+                self.variables[variable] = Some(ty);
+                true
+            }
+            (
+                Ty::Named {
+                    name: a,
+                    arguments: a_arguments,
+                },
+                Ty::Named {
+                    name: b,
+                    arguments: b_arguments,
+                },
+            ) => {
+                a == b
+                    && a_arguments.len() == b_arguments.len()
+                    && a_arguments
+                        .iter()
+                        .zip(&b_arguments)
+                        .all(|(a, b)| self.unify_inner(a, b))
+            }
+            (Ty::Parameter(a), Ty::Parameter(b)) => a == b,
+            (
+                Ty::Block {
+                    parameters: a_parameters,
+                    return_type: a_return,
+                },
+                Ty::Block {
+                    parameters: b_parameters,
+                    return_type: b_return,
+                },
+            ) => {
+                a_parameters.len() == b_parameters.len()
+                    && a_parameters
+                        .iter()
+                        .zip(&b_parameters)
+                        .all(|((_, a), (_, b))| self.unify_inner(a, b))
+                    && self.unify_inner(&a_return, &b_return)
+            }
+            _ => false,
+        }
+    }
+
+    fn occurs(&self, variable: usize, ty: &Ty<'src>) -> bool {
+        match self.shallow(ty) {
+            Ty::Variable(other) => other == variable,
+            Ty::Named { arguments, .. } => arguments.iter().any(|it| self.occurs(variable, it)),
+            Ty::Block {
+                parameters,
+                return_type,
+            } => {
+                parameters.iter().any(|(_, it)| self.occurs(variable, it))
+                    || self.occurs(variable, &return_type)
+            }
+            Ty::Parameter(_) | Ty::Error => false,
+        }
+    }
+
+    /// Reports a mismatch if `found` can't be made into `expected`.
+    fn expect(&mut self, found: &Ty<'src>, expected: &Ty<'src>, span: Span) {
+        if !self.unify(found, expected) {
+            self.errors.push(TypeError::Mismatch {
+                expected: self.resolve(expected).to_string(),
+                found: self.resolve(found).to_string(),
+                span,
+            });
+        }
+    }
+
+    fn block(&mut self, block: &'a Block<'src>) -> Ty<'src> {
+        let scope = self.locals.len();
+
+        for statement in &block.statements {
+            match statement {
+                Statement::Let(statement) => {
+                    let mut ty = self.expression(&statement.value);
+                    if let Some(annotation) = &statement.ty {
+                        let annotation = self.lower(annotation);
+                        self.expect(&ty, &annotation, statement.value.span);
+                        ty = annotation;
+                    }
+                    self.pattern(&statement.pattern, &ty);
+                }
+                Statement::Assign(assignment) => {
+                    let ty = self.expression(&assignment.value);
+                    let local = self
+                        .locals
+                        .iter()
+                        .rev()
+                        .find(|(name, _)| *name == assignment.name.name)
+                        .map(|(_, ty)| ty.clone());
+                    if let Some(local) = local {
+                        self.expect(&ty, &local, assignment.value.span);
+                    }
+                }
+                Statement::Expression(expression) => {
+                    self.expression(expression);
+                }
+            }
+        }
+
+        let ty = match &block.tail {
+            Some(tail) => self.expression(tail),
+            None => Ty::named("Unit"),
+        };
+        self.locals.truncate(scope);
+        ty
+    }
+
+    fn expression(&mut self, expression: &'a Expression<'src>) -> Ty<'src> {
+        match &expression.kind {
+            ExpressionKind::Literal(literal) => Ty::named(match literal {
+                Literal::Integer(_) => "Int",
+                Literal::Float(_) => "Float",
+                Literal::Character(_) => "Char",
+                Literal::String(_) => "String",
+            }),
+            ExpressionKind::Name(name) => self.name(*name),
+            ExpressionKind::Call(call) => {
+                let local = self.local(call.name.name);
+                let arguments = self.arguments(call);
+                match local {
+                    Some(ty) => self.call_local(call.name, ty, arguments, expression.span),
+                    None => self.call(
+                        call.name,
+                        Receiver::None,
+                        &call.type_arguments,
+                        arguments,
+                        expression.span,
+                    ),
+                }
+            }
+            ExpressionKind::Property { receiver, name } => match self.receiver(receiver) {
+                Receiver::None | Receiver::Namespace(_) => self.name(*name),
+                receiver => self.call(*name, receiver, &[], Vec::new(), expression.span),
+            },
+            ExpressionKind::Chain { receiver, call } => {
+                let receiver = self.receiver(receiver);
+                let arguments = self.arguments(call);
+                self.call(
+                    call.name,
+                    receiver,
+                    &call.type_arguments,
+                    arguments,
+                    expression.span,
+                )
+            }
+            ExpressionKind::Match { scrutinee, clauses } => {
+                let scrutinee = self.expression(scrutinee);
+                let ty = self.fresh();
+
+                for clause in clauses {
+                    let scope = self.locals.len();
+                    self.pattern(&clause.pattern, &scrutinee);
+                    let body = self.expression(&clause.body);
+                    self.expect(&body, &ty, clause.body.span);
+                    self.locals.truncate(scope);
+                }
+                ty
+            }
+            ExpressionKind::Loop(body) => {
+                let ty = self.fresh();
+                self.returns.push(("loop", ty.clone()));
+                self.block(body);
+                self.returns.pop();
+                ty
+            }
+            ExpressionKind::Block(block) => self.block(block),
+            ExpressionKind::Return { label, value } => {
+                let (ty, span) = match value {
+                    Some(value) => (self.expression(value), value.span),
+                    None => (Ty::named("Unit"), expression.span),
+                };
+
+                let target = self.returns.iter().rev().find(|(name, _)| match label {
+                    Some(label) => *name == label.name,
+                    None => *name != "loop",
+                });
+                if let Some((_, target)) = target.cloned() {
+                    self.expect(&ty, &target, span);
+                }
+
+                // Nothing comes after a return, so it fits anywhere
+                self.fresh()
+            }
+        }
+    }
+
+    fn local(&self, name: &str) -> Option<Ty<'src>> {
+        self.locals
+            .iter()
+            .rev()
+            .find(|(local, _)| *local == name)
+            .map(|(_, ty)| ty.clone())
+    }
+
+    fn name(&mut self, name: Ident<'src>) -> Ty<'src> {
+        if let Some(ty) = self.local(name.name) {
+            return ty;
+        }
+        if name.name == "Unit" {
+            return Ty::named("Unit");
+        }
+
+        let signature = match self.functions.get(name.name) {
+            Some(signatures) => signatures[0].clone(),
+            None => return Ty::Error,
+        };
+        let (parameters, return_type, _) = self.instantiate_signature(&signature, &[], name.span);
+
+        // A variant without fields is a value, the rest are functions
+        if parameters.is_empty() && self.variants.contains_key(name.name) {
+            return return_type;
+        }
+        Ty::Block {
+            parameters,
+            return_type: Box::new(return_type),
+        }
+    }
+
+    /// What a chain or property is on.
+    fn receiver(&mut self, receiver: &'a Expression<'src>) -> Receiver<'src> {
+        let name = match &receiver.kind {
+            ExpressionKind::Name(name) if self.local(name.name).is_none() => Some(name.name),
+            ExpressionKind::Property { name, .. } if self.is_module_path(receiver) => {
+                return Receiver::Namespace(name.name)
+            }
+            _ => None,
+        };
+
+        match name {
+            Some("Self") if self.self_ty.is_some() => {
+                return Receiver::Static(self.self_ty.clone().unwrap_or(Ty::Error))
+            }
+            Some(name) if self.generics.iter().any(|it| it.name == name) => {
+                return Receiver::Static(Ty::Parameter(name))
+            }
+            Some(name) if self.enums.contains_key(name) => {
+                let generics = self.enums[name].generics.clone();
+                let map = self.instantiate(&generics);
+                let arguments = generics.iter().map(|it| map[it.name].clone()).collect();
+                return Receiver::Static(Ty::Named { name, arguments });
+            }
+            Some(name) if self.namespaces.contains(name) => return Receiver::Namespace(name),
+            _ => {}
+        }
+
+        Receiver::Value(self.expression(receiver), receiver.span)
+    }
+
+    fn is_module_path(&self, expression: &Expression<'src>) -> bool {
+        match &expression.kind {
+            ExpressionKind::Name(name) => {
+                self.namespaces.contains(name.name) && self.local(name.name).is_none()
+            }
+            ExpressionKind::Property { receiver, name } => {
+                self.namespaces.contains(name.name) && self.is_module_path(receiver)
+            }
+            _ => false,
+        }
+    }
+
+    fn arguments(&mut self, call: &'a Call<'src>) -> Vec<Argument<'a, 'src>> {
+        let mut arguments: Vec<_> = call
+            .arguments
+            .iter()
+            .map(|argument| Argument {
+                label: argument.label,
+                kind: ArgumentKind::Value(self.expression(&argument.value)),
+                span: argument.value.span,
+            })
+            .collect();
+
+        for argument in &call.block_arguments {
+            arguments.push(Argument {
+                label: argument.label,
+                kind: ArgumentKind::Block(&argument.block),
+                span: argument.block.span,
+            });
+        }
+        arguments
+    }
+
+    /// Calls a local, which has to be a block.
+    fn call_local(
+        &mut self,
+        name: Ident<'src>,
+        ty: Ty<'src>,
+        arguments: Vec<Argument<'a, 'src>>,
+        span: Span,
+    ) -> Ty<'src> {
+        match self.shallow(&ty) {
+            Ty::Block {
+                parameters,
+                return_type,
+            } => {
+                self.apply(name, &parameters, arguments, span);
+                *return_type
+            }
+            Ty::Error => Ty::Error,
+            ty => {
+                self.errors.push(TypeError::NotCallable {
+                    ty: self.resolve(&ty).to_string(),
+                    span: name.span,
+                });
+                Ty::Error
+            }
+        }
+    }
+
+    /// Checks a call of the function called `name`, picking which one if
+    /// there are several.
+    fn call(
+        &mut self,
+        name: Ident<'src>,
+        receiver: Receiver<'src>,
+        type_arguments: &[TypeParameter<'src>],
+        mut arguments: Vec<Argument<'a, 'src>>,
+        span: Span,
+    ) -> Ty<'src> {
+        if let Receiver::Value(ty, span) = &receiver {
+            arguments.insert(
+                0,
+                Argument {
+                    label: None,
+                    kind: ArgumentKind::Value(ty.clone()),
+                    span: *span,
+                },
+            );
+        }
+
+        let candidates = self.candidates(name.name, &receiver);
+        let signature = match self.choose(name, &candidates, &receiver, &arguments, span) {
+            Some(signature) => signature,
+            None => {
+                self.apply(name, &[], Vec::new(), span);
+                return Ty::Error;
+            }
+        };
+
+        let (parameters, return_type, self_ty) =
+            self.instantiate_signature(&signature, type_arguments, span);
+        if let (Receiver::Static(ty), Some(self_ty)) = (&receiver, self_ty) {
+            self.unify(ty, &self_ty);
+        }
+
+        self.apply(name, &parameters, arguments, span);
+        return_type
+    }
+
+    /// Picks which candidate a call goes to, from its labels and number of
+    /// arguments, then from the types of the receiver and other values.
+    /// Blocks aren't looked at, since they're checked against what they're
+    /// passed to. Calls that fit no candidate or several are reported.
+    fn choose(
+        &mut self,
+        name: Ident<'src>,
+        candidates: &[Signature<'a, 'src>],
+        receiver: &Receiver<'src>,
+        arguments: &[Argument<'a, 'src>],
+        span: Span,
+    ) -> Option<Signature<'a, 'src>> {
+        let labels: Vec<_> = arguments.iter().map(|it| it.label).collect();
+        let fitting: Vec<_> = candidates
+            .iter()
+            .map(|candidate| {
+                let parameters: Vec<_> = candidate.parameters.iter().map(|it| it.0).collect();
+                (candidate, overload::assign(&parameters, &labels))
+            })
+            .filter(|(_, assignment)| assignment.fits())
+            .collect();
+
+        match (&fitting[..], candidates) {
+            // Without a choice to make, the arguments are checked against it
+            ([(only, _)], _) => return Some((*only).clone()),
+            ([], [only]) => return Some(only.clone()),
+            ([], []) => {
+                if let Receiver::Value(ty, _) = receiver {
+                    if self.shallow(ty) != Ty::Error {
+                        self.errors.push(TypeError::NoMethod {
+                            name: name.name,
+                            ty: self.resolve(ty).to_string(),
+                            span: name.span,
+                        });
+                    }
+                }
+                return None;
+            }
+            _ => {}
+        }
+
+        let head = match receiver {
+            Receiver::Value(ty, _) | Receiver::Static(ty) => self.shallow(ty).head(),
+            Receiver::None | Receiver::Namespace(_) => self.self_ty.as_ref().and_then(Ty::head),
+        };
+        let mut ranked = Vec::new();
+        for (candidate, assignment) in &fitting {
+            if self.accepts(candidate, assignment, receiver, arguments, span) {
+                ranked.push((*candidate, Rank::of(candidate.owner, head)));
+            }
+        }
+
+        match overload::choose(ranked) {
+            Choice::One(signature) => Some(signature.clone()),
+            Choice::Ambiguous(signatures) => {
+                self.errors.push(TypeError::Ambiguous {
+                    name: name.name,
+                    candidates: signatures.iter().map(|it| self.candidate(it)).collect(),
+                    span: name.span,
+                });
+                Some(signatures[0].clone())
+            }
+            Choice::None => {
+                let types: Vec<_> = arguments
+                    .iter()
+                    .map(|argument| match &argument.kind {
+                        ArgumentKind::Value(ty) => self.resolve(ty).to_string(),
+                        ArgumentKind::Block(_) => "block".to_owned(),
+                    })
+                    .collect();
+                self.errors.push(TypeError::NoOverload {
+                    name: name.name,
+                    arguments: types.join(", "),
+                    candidates: candidates.iter().map(|it| self.candidate(it)).collect(),
+                    span: name.span,
+                });
+                None
+            }
+        }
+    }
+
+    /// Whether a candidate takes the types of the receiver and the values
+    /// going to it, without inferring anything from them.
+    fn accepts(
+        &mut self,
+        signature: &Signature<'a, 'src>,
+        assignment: &Assignment<'src>,
+        receiver: &Receiver<'src>,
+        arguments: &[Argument<'a, 'src>],
+        span: Span,
+    ) -> bool {
+        let variables = self.variables.clone();
+        let obligations = self.obligations.len();
+
+        let (parameters, _, self_ty) = self.instantiate_signature(signature, &[], span);
+        let mut fits = match (receiver, self_ty) {
+            (Receiver::Static(ty), Some(self_ty)) => self.unify(ty, &self_ty),
+            _ => true,
+        };
+        for (slot, (_, ty)) in assignment.slots.iter().zip(&parameters) {
+            if let Some(ArgumentKind::Value(argument)) = slot.map(|it| &arguments[it].kind) {
+                fits = fits && self.unify(argument, ty);
+            }
+        }
+
+        self.variables = variables;
+        self.obligations.truncate(obligations);
+        fits
+    }
+
+    /// How a function a call could go to is shown in errors.
+    fn candidate(&self, signature: &Signature<'a, 'src>) -> Candidate<'src> {
+        Candidate {
+            signature: self.show_signature(
+                signature.name.name,
+                &signature.parameters,
+                &signature.return_type,
+            ),
+            namespace: signature.namespace,
+            span: signature.name.span,
+        }
+    }
+
+    /// Every function a call could be to. Going through a type limits them
+    /// to the functions declared in it, and going through a module prefers
+    /// the functions declared in it. Builtins are only called when nothing
+    /// else has their name.
+    fn candidates(&self, name: &str, receiver: &Receiver<'src>) -> Vec<Signature<'a, 'src>> {
+        let declared: &[Signature] = self.functions.get(name).map_or(&[], |it| it);
+        let mut candidates = Vec::new();
+
+        match receiver {
+            Receiver::Static(ty) => {
+                let head = self.shallow(ty).head();
+                candidates.extend(
+                    declared
+                        .iter()
+                        .filter(|it| it.owner.is_some() && it.owner == head)
+                        .cloned(),
+                );
+                if let Some(parameter) = head {
+                    candidates.extend(self.bound_functions(parameter, name));
+                }
+                return candidates;
+            }
+            Receiver::Value(ty, _) => {
+                if let Some(parameter) = self.shallow(ty).head() {
+                    candidates.extend(self.bound_functions(parameter, name));
+                }
+            }
+            Receiver::Namespace(namespace) => {
+                candidates.extend(
+                    declared
+                        .iter()
+                        .filter(|it| it.namespace == Some(namespace))
+                        .cloned(),
+                );
+                if !candidates.is_empty() {
+                    return candidates;
+                }
+            }
+            Receiver::None => {}
+        }
+
+        candidates.extend(declared.iter().cloned());
+        if candidates.is_empty() {
+            candidates.extend(self.builtins.get(name).cloned());
+        }
+        candidates
+    }
+
+    /// The functions a generic parameter has from the traits it's bound by,
+    /// with `Self` as the parameter.
+    fn bound_functions(&self, parameter: &'src str, name: &str) -> Vec<Signature<'a, 'src>> {
+        let mut functions = Vec::new();
+
+        for generic in self.generics.iter().filter(|it| it.name == parameter) {
+            for (bound, _) in &generic.bounds {
+                let info = match self.traits.get(bound.name) {
+                    Some(info) => info,
+                    None => continue,
+                };
+                let mut map: HashMap<_, _> = info
+                    .generics
+                    .iter()
+                    .copied()
+                    .zip(bound.arguments.iter().cloned())
+                    .collect();
+                map.insert("Self", Ty::Parameter(parameter));
+
+                for function in info.functions.iter().filter(|it| it.name.name == name) {
+                    functions.push(Signature {
+                        owner: Some(parameter),
+                        // The trait's generics are already mapped
+                        generics: function
+                            .generics
+                            .iter()
+                            .filter(|it| it.name != "Self" && !info.generics.contains(&it.name))
+                            .cloned()
+                            .collect(),
+                        parameters: function
+                            .parameters
+                            .iter()
+                            .map(|(label, ty)| (*label, ty.substitute(&map)))
+                            .collect(),
+                        return_type: function.return_type.substitute(&map),
+                        self_ty: Some(Ty::Parameter(parameter)),
+                        ..function.clone()
+                    });
+                }
+            }
+        }
+
+        functions
+    }
+
+    /// Gives a signature's generics fresh variables, returning the types of
+    /// its parameters, what it returns and its `Self`. The bounds on the
+    /// generics are checked later.
+    fn instantiate_signature(
+        &mut self,
+        signature: &Signature<'a, 'src>,
+        type_arguments: &[TypeParameter<'src>],
+        span: Span,
+    ) -> (Parameters<'src>, Ty<'src>, Option<Ty<'src>>) {
+        let map = self.instantiate(&signature.generics);
+
+        // Type arguments are for the function's own generics, which are last
+        let generics = &signature.generics;
+        if type_arguments.len() > generics.len() {
+            self.errors.push(TypeError::TypeArguments {
+                name: signature.name.name,
+                expected: generics.len(),
+                found: type_arguments.len(),
+                span,
+            });
+        } else {
+            let own = &generics[generics.len() - type_arguments.len()..];
+            for (generic, argument) in own.iter().zip(type_arguments) {
+                let ty = self.lower(&argument.ty);
+                self.expect(&ty, &map[generic.name], argument.ty.span);
+            }
+        }
+
+        for generic in generics {
+            for (bound, required) in &generic.bounds {
+                self.obligations.push(Obligation {
+                    ty: map[generic.name].clone(),
+                    bound: bound.substitute(&map),
+                    span,
+                    required: *required,
+                });
+            }
+        }
+
+        let parameters = signature
+            .parameters
+            .iter()
+            .map(|(label, ty)| (*label, self.resolve(ty).substitute(&map)))
+            .collect();
+        // Return types inferred from the body can mention the generics too
+        (
+            parameters,
+            self.resolve(&signature.return_type).substitute(&map),
+            signature.self_ty.as_ref().map(|it| it.substitute(&map)),
+        )
+    }
+
+    /// Checks arguments against the parameters they go to. Labelled
+    /// arguments go to the parameter with that label, and the rest fill the
+    /// others in order.
+    fn apply(
+        &mut self,
+        name: Ident<'src>,
+        parameters: &[(Option<&'src str>, Ty<'src>)],
+        arguments: Vec<Argument<'a, 'src>>,
+        span: Span,
+    ) {
+        let labels: Vec<_> = parameters.iter().map(|(label, _)| *label).collect();
+        let assignment = overload::assign(
+            &labels,
+            &arguments.iter().map(|it| it.label).collect::<Vec<_>>(),
+        );
+
+        // Arguments without a parameter are still checked, for errors inside
+        for (index, _) in &assignment.unknown {
+            self.argument(&arguments[*index], &Ty::Error);
+        }
+        for index in &assignment.extra {
+            self.argument(&arguments[*index], &Ty::Error);
+        }
+        for (slot, (_, ty)) in assignment.slots.iter().zip(parameters) {
+            if let Some(index) = slot {
+                self.argument(&arguments[*index], ty);
+            }
+        }
+
+        if let Some((message, at)) = assignment.problem() {
+            self.errors.push(TypeError::Arguments {
+                name: name.name,
+                message,
+                span: at.unwrap_or(span),
+            });
+        }
+    }
+
+    fn argument(&mut self, argument: &Argument<'a, 'src>, expected: &Ty<'src>) {
+        match argument.kind {
+            ArgumentKind::Value(ref ty) => self.expect(ty, expected, argument.span),
+            ArgumentKind::Block(block) => self.block_argument(block, expected),
+        }
+    }
+
+    /// Checks a block passed where a block of type `expected` goes.
+    fn block_argument(&mut self, block: &'a Block<'src>, expected: &Ty<'src>) {
+        let (parameters, return_type) = match self.shallow(expected) {
+            Ty::Block {
+                parameters,
+                return_type,
+            } => (parameters, *return_type),
+            Ty::Error => (Vec::new(), Ty::Error),
+            ty => {
+                let return_type = self.fresh();
+                let block_ty = Ty::Block {
+                    parameters: Vec::new(),
+                    return_type: Box::new(return_type.clone()),
+                };
+                self.expect(&block_ty, &ty, block.span);
+                (Vec::new(), return_type)
+            }
+        };
+
+        let scope = self.locals.len();
+        let single = parameters.len() == 1;
+        for (label, ty) in parameters {
+            match label {
+                Some(label) => self.locals.push((label, ty)),
+                None if single => self.locals.push(("it", ty)),
+                None => {}
+            }
+        }
+
+        let ty = self.block(block);
+        // A block that should give `Unit` can end with anything
+        if self.shallow(&return_type) != Ty::named("Unit") {
+            self.expect(&ty, &return_type, tail_span(block));
+        }
+        self.locals.truncate(scope);
+    }
+
+    /// Checks a pattern against the type of the value it's matching,
+    /// binding its names.
+    fn pattern(&mut self, pattern: &Pattern<'src>, expected: &Ty<'src>) {
+        match pattern {
+            Pattern::Binding { name, .. } if name.name == "_" => {}
+            Pattern::Binding { name, .. } if self.variants.contains_key(name.name) => {
+                self.variant_pattern(*name, &[], expected, name.span)
+            }
+            Pattern::Binding { name, .. } => self.locals.push((name.name, expected.clone())),
+            Pattern::Variant { path, fields, span } => match path.last() {
+                Some(name) if self.variants.contains_key(name.name) => {
+                    self.variant_pattern(*name, fields, expected, *span)
+                }
+                _ => {
+                    for field in fields {
+                        self.pattern(field, &Ty::Error);
+                    }
+                }
+            },
+        }
+    }
+
+    fn variant_pattern(
+        &mut self,
+        name: Ident<'src>,
+        fields: &[Pattern<'src>],
+        expected: &Ty<'src>,
+        span: Span,
+    ) {
+        let enumeration = self.variants[name.name];
+        let info = self.enums[enumeration].clone();
+        let map = self.instantiate(&info.generics);
+        let ty = Ty::Named {
+            name: enumeration,
+            arguments: info
+                .generics
+                .iter()
+                .map(|it| map[it.name].clone())
+                .collect(),
+        };
+        self.expect(&ty, expected, span);
+
+        let types = info
+            .variants
+            .iter()
+            .find(|(variant, _)| *variant == name.name)
+            .map_or(&[][..], |(_, types)| types);
+        if types.len() != fields.len() {
+            self.errors.push(TypeError::Arguments {
+                name: name.name,
+                message: format!("has {} fields, found {}", types.len(), fields.len()),
+                span,
+            });
+        }
+
+        for (index, field) in fields.iter().enumerate() {
+            let ty = types.get(index).map_or(Ty::Error, |it| it.substitute(&map));
+            self.pattern(field, &ty);
+        }
+    }
+}
+
+/// Where the value of a block comes from, for mismatches with it.
+fn tail_span(block: &Block) -> Span {
+    block.tail.as_ref().map_or(block.span, |tail| tail.span)
+}
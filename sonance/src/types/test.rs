@@ -0,0 +1,257 @@
+use super::*;
+use crate::fixture::{parse, program, render};
+
+/// What the checker finds wrong with the core dreams themselves. `take_if`
+/// calls `then` on its condition rather than on what the condition gives,
+/// and `while_some` matches on its block rather than on what calling it
+/// gives. Both are for the dreams' owner to settle.
+const CORE: &[&str] = &[
+    "error: Mismatched types
+  --> data/boolean.son:30:5
+   |
+30 |     condition.then { self }
+   |     ^^^^^^^^^ expected `Boolean`, found `block(it: T) -> Boolean`",
+    "error: Mismatched types
+  --> data/optional.son:15:17
+   |
+15 |                 Some(value) -> body(value),
+   |                 ^^^^^^^^^^^ expected `block() -> Optional<T>`, found `Optional<_>`",
+    "error: Mismatched types
+  --> data/optional.son:16:17
+   |
+16 |                 Nothing -> return,
+   |                 ^^^^^^^ expected `block() -> Optional<T>`, found `Optional<_>`",
+];
+
+/// Type checks `main` alongside the core dreams, returning the rendered
+/// errors other than the core's own.
+fn report(main: &str) -> String {
+    let map = program(main);
+    let rendered = render(&map, check(&parse(&map)));
+    let errors: Vec<_> = rendered.trim_end().split("\n\n").collect();
+    for core in CORE {
+        assert!(
+            errors.contains(core),
+            "the core's errors changed:\n{}",
+            rendered
+        );
+    }
+
+    let rest: Vec<_> = errors
+        .into_iter()
+        .filter(|error| !error.is_empty() && !CORE.contains(error))
+        .collect();
+    if rest.is_empty() {
+        String::new()
+    } else {
+        format!("{}\n", rest.join("\n\n"))
+    }
+}
+
+#[test]
+fn core() {
+    assert_eq!(report(""), "");
+}
+
+#[test]
+fn inference() {
+    assert_eq!(
+        report(
+            "func main() {
+    let number = Some(1).else { 2 };
+    let text: String = number;
+    let flag = True.then { 'c' }.okay_or_else { 1.5 }.error;
+    match(flag) { Some(it) -> it.add(1.0), Nothing -> 0 };
+};"
+        ),
+        "error: Mismatched types
+ --> main.son:3:24
+  |
+3 |     let text: String = number;
+  |                        ^^^^^^ expected `String`, found `Int`
+
+error: Mismatched types
+ --> main.son:5:55
+  |
+5 |     match(flag) { Some(it) -> it.add(1.0), Nothing -> 0 };
+  |                                                       ^ expected `Float`, found `Int`
+"
+    );
+}
+
+#[test]
+fn bounds() {
+    assert_eq!(
+        report(
+            "enum Count(Zero, More(Count)) -> Default {
+    func default() -> Self { Zero };
+};
+func pick<T: Default>(other: T) -> T { T.default() };
+func main() {
+    let count: Optional<Count> = into(Zero);
+    let some = pick(Some(More(Zero)));
+    let none = pick(Nothing);
+    let int = pick(1);
+    let wrong: Count = into(Zero);
+};"
+        ),
+        "error: `Int` doesn't implement `Default`
+ --> main.son:9:15
+  |
+4 | func pick<T: Default>(other: T) -> T { T.default() };
+  |              ------- required by this bound
+...
+9 |     let int = pick(1);
+  |               ^^^^^^^ the trait `Default` is not implemented
+
+error: `Count` doesn't implement `From<Count>`
+  --> main.son:10:24
+   |
+10 |     let wrong: Count = into(Zero);
+   |                        ^^^^^^^^^^ the trait `From<Count>` is not implemented
+   |
+   = note: required by this bound at traits/from.son:7:17
+"
+    );
+}
+
+#[test]
+fn implementations() {
+    assert_eq!(
+        report(
+            "enum Empty() -> Default, From<Int> {
+    func from(other: String) -> Self { Self.from(other) };
+};
+module wrap<T> -> Optional<T> with<Missing, Optional<T>> {};"
+        ),
+        "error: `Missing` is not a trait
+ --> main.son:4:36
+  |
+4 | module wrap<T> -> Optional<T> with<Missing, Optional<T>> {};
+  |                                    ^^^^^^^
+
+error: `Optional` is not a trait
+ --> main.son:4:45
+  |
+4 | module wrap<T> -> Optional<T> with<Missing, Optional<T>> {};
+  |                                             ^^^^^^^^
+
+error: Missing `default` in implementation of `Default`
+ --> main.son:1:17
+  |
+1 | enum Empty() -> Default, From<Int> {
+  |                 ^^^^^^^ `default` is not implemented
+  |
+  = note: declared here at traits/default.son:4:10
+
+error: Function doesn't match its trait `From<Int>`
+ --> main.son:2:10
+  |
+2 |     func from(other: String) -> Self { Self.from(other) };
+  |          ^^^^ found `func from(other: String) -> Empty`
+  |
+  = note: the trait expects `func from(other: Int) -> Empty`
+"
+    );
+}
+
+#[test]
+fn calls() {
+    assert_eq!(
+        report(
+            "func count(from start: Int, to end: Int) -> Int { end.subtract(start) };
+func main() {
+    count(to: 3, from: 1);
+    count(1, 2, 3);
+    count(from: 1, end: 2);
+    1.missing();
+    let text = \"a\";
+    text();
+    Some(1).else { 'c' };
+};"
+        ),
+        "error: Wrong arguments for `count`
+ --> main.son:4:5
+  |
+4 |     count(1, 2, 3);
+  |     ^^^^^^^^^^^^^^ takes 2 arguments, found 3
+
+error: Wrong arguments for `count`
+ --> main.son:5:20
+  |
+5 |     count(from: 1, end: 2);
+  |                    ^^^ no parameter labelled `end` left
+
+error: No function `missing` for `Int`
+ --> main.son:6:7
+  |
+6 |     1.missing();
+  |       ^^^^^^^
+
+error: `String` is not a block
+ --> main.son:8:5
+  |
+8 |     text();
+  |     ^^^^ called here
+
+error: Mismatched types
+ --> main.son:9:20
+  |
+9 |     Some(1).else { 'c' };
+  |                    ^^^ expected `Int`, found `Char`
+"
+    );
+}
+
+#[test]
+fn overloads() {
+    assert_eq!(
+        report(
+            "func scope<T>(body: block() -> T) -> T { body() };
+func scope<Self, T>(self: Self, body: block(it: Self) -> T) -> T { body(self) };
+func describe(self: Int) -> String { \"int\" };
+func describe(self: String) -> String { \"string\" };
+func range(from start: Int, to end: Int) -> Int { end.subtract(start) };
+func range(to end: Int) -> Int { end };
+func main() {
+    let a: Int = scope { 1 };
+    let b: String = 1.scope { it.describe() };
+    let c: Int = range(to: 3).add(range(from: 1, to: 3));
+    'c'.describe();
+    let d: Optional<Int> = Optional.default();
+    let e: Optional<Int> = default_none.default();
+};"
+        ),
+        "error: No `describe` takes these arguments
+  --> main.son:11:9
+   |
+ 3 | func describe(self: Int) -> String { \"int\" };
+   |      -------- `func describe(self: Int) -> String`
+ 4 | func describe(self: String) -> String { \"string\" };
+   |      -------- `func describe(self: String) -> String`
+...
+11 |     'c'.describe();
+   |         ^^^^^^^^ called with `Char`
+
+error: Ambiguous call to `default`
+  --> main.son:12:37
+   |
+12 |     let d: Optional<Int> = Optional.default();
+   |                                     ^^^^^^^ fits more than one function
+   |
+   = note: `func default() -> Optional<T>` in `default_none` at data/optional.son:34:10
+   = note: `func default() -> Optional<T>` in `default_some` at data/optional.son:40:10
+   = help: call one through where it's declared, as in `default_none.default`
+"
+    );
+}
+
+#[test]
+fn infer_tail() {
+    let map = program("func main() { Some(1).okay_or_else { 'c' } };");
+
+    let (ty, errors) = infer(&parse(&map), "main");
+    // Only the core's own errors
+    assert_eq!(render(&map, errors), format!("{}\n", CORE.join("\n\n")));
+    assert_eq!(ty.unwrap().to_string(), "Result<Int, Char>");
+}
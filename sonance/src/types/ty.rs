@@ -0,0 +1,136 @@
+use std::{collections::HashMap, fmt};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ty<'src> {
+    /// An enum or built in type with its type arguments.
+    Named {
+        name: &'src str,
+        arguments: Vec<Ty<'src>>,
+    },
+    /// A generic parameter of the function being checked, which stands for
+    /// any type meeting its bounds.
+    Parameter(&'src str),
+    /// The type of a block argument. Labels name the values it's called with.
+    Block {
+        parameters: Vec<(Option<&'src str>, Ty<'src>)>,
+        return_type: Box<Ty<'src>>,
+    },
+    /// A type still being inferred.
+    Variable(usize),
+    /// The type of something that already failed to check, which fits
+    /// anywhere so the failure isn't reported again.
+    Error,
+}
+
+/// A trait with its type arguments, as in the bound `R: From<T>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraitRef<'src> {
+    pub name: &'src str,
+    pub arguments: Vec<Ty<'src>>,
+}
+
+/// The types with no declaration.
+pub const PRIMITIVES: &[&str] = &["Int", "Float", "Char", "String", "Unit"];
+
+impl<'src> Ty<'src> {
+    pub fn named(name: &'src str) -> Self {
+        Ty::Named {
+            name,
+            arguments: Vec::new(),
+        }
+    }
+
+    /// The name of the type a value of this type is an instance of.
+    pub fn head(&self) -> Option<&'src str> {
+        match self {
+            Ty::Named { name, .. } | Ty::Parameter(name) => Some(name),
+            Ty::Block { .. } | Ty::Variable(_) | Ty::Error => None,
+        }
+    }
+
+    /// Replaces generic parameters with the types they're mapped to.
+    pub fn substitute(&self, map: &HashMap<&'src str, Ty<'src>>) -> Self {
+        match self {
+            Ty::Named { name, arguments } => Ty::Named {
+                name,
+                arguments: arguments.iter().map(|it| it.substitute(map)).collect(),
+            },
+            Ty::Parameter(name) => map.get(name).cloned().unwrap_or_else(|| self.clone()),
+            Ty::Block {
+                parameters,
+                return_type,
+            } => Ty::Block {
+                parameters: parameters
+                    .iter()
+                    .map(|(label, ty)| (*label, ty.substitute(map)))
+                    .collect(),
+                return_type: Box::new(return_type.substitute(map)),
+            },
+            Ty::Variable(_) | Ty::Error => self.clone(),
+        }
+    }
+}
+
+impl<'src> TraitRef<'src> {
+    pub fn substitute(&self, map: &HashMap<&'src str, Ty<'src>>) -> Self {
+        TraitRef {
+            name: self.name,
+            arguments: self.arguments.iter().map(|it| it.substitute(map)).collect(),
+        }
+    }
+}
+
+/// Writes `<a, b>`, or nothing if there are no arguments.
+fn arguments(f: &mut fmt::Formatter<'_>, arguments: &[Ty]) -> fmt::Result {
+    if arguments.is_empty() {
+        return Ok(());
+    }
+
+    write!(f, "<")?;
+    for (index, argument) in arguments.iter().enumerate() {
+        if index > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", argument)?;
+    }
+    write!(f, ">")
+}
+
+impl fmt::Display for Ty<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ty::Named {
+                name,
+                arguments: it,
+            } => {
+                write!(f, "{}", name)?;
+                arguments(f, it)
+            }
+            Ty::Parameter(name) => write!(f, "{}", name),
+            Ty::Block {
+                parameters,
+                return_type,
+            } => {
+                write!(f, "block(")?;
+                for (index, (label, ty)) in parameters.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    if let Some(label) = label {
+                        write!(f, "{}: ", label)?;
+                    }
+                    write!(f, "{}", ty)?;
+                }
+                write!(f, ") -> {}", return_type)
+            }
+            Ty::Variable(_) | Ty::Error => write!(f, "_"),
+        }
+    }
+}
+
+impl fmt::Display for TraitRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        arguments(f, &self.arguments)
+    }
+}
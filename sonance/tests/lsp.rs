@@ -0,0 +1,258 @@
+//! Drives the language server with scripted JSON-RPC sessions.
+
+use compiler::lsp::{read_message, serve, write_message};
+use serde_json::{json, Value};
+
+const URI: &str = "file:///main.son";
+
+/// Runs a session of `messages`, returning everything the server wrote.
+fn session(messages: &[Value]) -> Vec<Value> {
+    let mut input = Vec::new();
+    for message in messages {
+        write_message(&mut input, message).unwrap();
+    }
+    serve_all(&input)
+}
+
+/// Serves raw `input`, returning everything the server wrote.
+fn serve_all(input: &[u8]) -> Vec<Value> {
+    let mut output = Vec::new();
+    serve(input, &mut output).unwrap();
+
+    let mut output = &output[..];
+    let mut replies = Vec::new();
+    while let Some(reply) = read_message(&mut output).unwrap() {
+        replies.push(reply);
+    }
+    replies
+}
+
+fn request(id: u64, method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+fn open(text: &str) -> Value {
+    notification(
+        "textDocument/didOpen",
+        json!({
+            "textDocument": { "uri": URI, "languageId": "sonance", "version": 1, "text": text },
+        }),
+    )
+}
+
+fn document() -> Value {
+    json!({ "textDocument": { "uri": URI } })
+}
+
+fn at(line: usize, character: usize) -> Value {
+    json!({
+        "textDocument": { "uri": URI },
+        "position": { "line": line, "character": character },
+    })
+}
+
+/// The result of the response to request `id`.
+fn result(replies: &[Value], id: u64) -> &Value {
+    let reply = replies
+        .iter()
+        .find(|reply| reply["id"] == json!(id))
+        .expect("request should be answered");
+    &reply["result"]
+}
+
+#[test]
+fn lifecycle() {
+    let replies = session(&[
+        request(1, "initialize", json!({ "capabilities": {} })),
+        notification("initialized", json!({})),
+        request(2, "textDocument/hover", at(0, 0)),
+        request(3, "shutdown", Value::Null),
+        request(4, "initialize", json!({ "capabilities": {} })),
+        notification("exit", Value::Null),
+        request(5, "shutdown", Value::Null),
+    ]);
+
+    assert_eq!(replies.len(), 4);
+
+    let capabilities = &result(&replies, 1)["capabilities"];
+    assert_eq!(capabilities["textDocumentSync"], json!(1));
+    assert_eq!(capabilities["definitionProvider"], json!(true));
+    assert_eq!(
+        capabilities["semanticTokensProvider"]["legend"]["tokenTypes"][0],
+        json!("keyword")
+    );
+
+    assert_eq!(replies[1]["error"]["code"], json!(-32601));
+    assert_eq!(
+        replies[2],
+        json!({ "jsonrpc": "2.0", "id": 3, "result": null })
+    );
+    assert_eq!(replies[3]["error"]["code"], json!(-32600));
+}
+
+#[test]
+fn diagnostics() {
+    let replies = session(&[
+        open("func main() {\n    let x = ;\n};\n"),
+        notification(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": URI, "version": 2 },
+                "contentChanges": [{ "text": "func main() {};\n" }],
+            }),
+        ),
+        notification("textDocument/didClose", document()),
+    ]);
+
+    assert_eq!(replies.len(), 3);
+    assert!(replies
+        .iter()
+        .all(|reply| reply["method"] == json!("textDocument/publishDiagnostics")));
+
+    let diagnostics = &replies[0]["params"]["diagnostics"];
+    assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+    assert_eq!(
+        diagnostics[0]["message"],
+        json!("Expected expression, found `;`")
+    );
+    assert_eq!(
+        diagnostics[0]["range"],
+        json!({ "start": { "line": 1, "character": 12 }, "end": { "line": 1, "character": 13 } })
+    );
+    assert_eq!(diagnostics[0]["severity"], json!(1));
+
+    assert_eq!(replies[1]["params"]["diagnostics"], json!([]));
+    assert_eq!(replies[2]["params"]["diagnostics"], json!([]));
+}
+
+#[test]
+fn semantic_tokens() {
+    let replies = session(&[
+        open("/// Doc\nfunc main() {\n    let s = \"a\nb\";\n};"),
+        request(1, "textDocument/semanticTokens/full", document()),
+    ]);
+
+    // keyword 0, number 1, string 2, comment 3, operator 4, variable 5
+    #[rustfmt::skip]
+    let expected = json!([
+        0, 0, 7, 3, 0, // `/// Doc`
+        1, 0, 4, 0, 0, // `func`
+        0, 5, 4, 5, 0, // `main`
+        1, 4, 3, 0, 0, // `let`
+        0, 4, 1, 5, 0, // `s`
+        0, 2, 1, 4, 0, // `=`
+        0, 2, 2, 2, 0, // `"a`
+        1, 0, 2, 2, 0, // `b"`
+    ]);
+    assert_eq!(result(&replies, 1)["data"], expected);
+}
+
+#[test]
+fn document_symbols() {
+    let source = "func main() {};
+enum Optional<T>(Some(T), Nothing) {
+    func else(self: Self, other: T) -> T { other };
+};
+trait Default { func default() -> Self; };";
+    let replies = session(&[
+        open(source),
+        request(1, "textDocument/documentSymbol", document()),
+    ]);
+
+    let symbols = result(&replies, 1);
+    let summary: Vec<_> = symbols
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|symbol| {
+            let children: Vec<_> = symbol["children"]
+                .as_array()
+                .map(|children| children.iter().map(|child| child["name"].clone()).collect())
+                .unwrap_or_default();
+            (symbol["name"].clone(), symbol["kind"].clone(), children)
+        })
+        .collect();
+
+    assert_eq!(
+        summary,
+        vec![
+            (json!("main"), json!(12), vec![]),
+            (
+                json!("Optional"),
+                json!(10),
+                vec![json!("Some"), json!("Nothing"), json!("else")]
+            ),
+            (json!("Default"), json!(11), vec![json!("default")]),
+        ]
+    );
+    assert_eq!(
+        symbols[1]["selectionRange"],
+        json!({ "start": { "line": 1, "character": 5 }, "end": { "line": 1, "character": 13 } })
+    );
+    assert_eq!(
+        symbols[1]["range"]["end"],
+        json!({ "line": 3, "character": 1 })
+    );
+}
+
+#[test]
+fn definitions() {
+    let source = "enum Boolean(True, False) {};
+func not(value: Boolean) -> Boolean {
+    let value = match(value) { True -> False, other -> True };
+    value
+};
+func main() { not(True) };";
+    let replies = session(&[
+        open(source),
+        // The parameter's type
+        request(1, "textDocument/definition", at(1, 18)),
+        // `value` in the match refers to the parameter
+        request(2, "textDocument/definition", at(2, 22)),
+        // The tail refers to the let binding, which shadows it
+        request(3, "textDocument/definition", at(3, 4)),
+        // A variant used as a pattern
+        request(4, "textDocument/definition", at(2, 32)),
+        // A new binding in a match clause is its own definition
+        request(5, "textDocument/definition", at(2, 46)),
+        // A call to another function
+        request(6, "textDocument/definition", at(5, 15)),
+        // Nothing to go to
+        request(7, "textDocument/definition", at(5, 12)),
+    ]);
+
+    let range = |id| result(&replies, id)["range"].clone();
+    let span = |line, start, end| {
+        json!({
+            "start": { "line": line, "character": start },
+            "end": { "line": line, "character": end },
+        })
+    };
+
+    assert_eq!(result(&replies, 1)["uri"], json!(URI));
+    assert_eq!(range(1), span(0, 5, 12));
+    assert_eq!(range(2), span(1, 9, 14));
+    assert_eq!(range(3), span(2, 8, 13));
+    assert_eq!(range(4), span(0, 13, 17));
+    assert_eq!(range(5), span(2, 46, 51));
+    assert_eq!(range(6), span(1, 5, 8));
+    assert_eq!(*result(&replies, 7), Value::Null);
+}
+
+#[test]
+fn malformed_messages() {
+    let mut input = Vec::new();
+    write_message(&mut input, &request(1, "initialize", json!({}))).unwrap();
+    input.extend(b"Content-Length: 9\r\n\r\n{\"id\": 2,");
+    write_message(&mut input, &request(3, "shutdown", Value::Null)).unwrap();
+
+    let replies = serve_all(&input);
+    assert_eq!(replies.len(), 3);
+    assert_eq!(replies[1]["id"], Value::Null);
+    assert_eq!(replies[1]["error"]["code"], json!(-32700));
+    assert_eq!(*result(&replies, 3), Value::Null);
+}
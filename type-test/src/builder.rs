@@ -1,26 +1,69 @@
 use crate::{
+    class::{Class, ClassEnv, Instance},
     data::Context,
     error::{Error, Result},
-    expr::{CallExpr, Expr, FuncExpr, IfExpr, LetExpr},
-    ty::{FuncTy, Ty},
+    expr::{CallExpr, Expr, FuncExpr, IfExpr, LetExpr, LetRecExpr},
+    program::Program,
+    ty::{FuncTy, Predicate, Qualified, Scheme, Ty},
+    typed::Typed,
 };
 
 pub fn infer(expr: Expr) -> Result<Ty> {
-    let env = [
-        (
-            "even?".to_owned(),
-            ty_func(ty_name("Number"), ty_name("Bool")),
-        ),
-        (
-            "inc".to_owned(),
-            ty_func(ty_name("Number"), ty_name("Number")),
-        ),
-        ("any".to_owned(), ty_var("any")),
-    ];
+    infer_qualified(expr).map(|it| it.ty)
+}
 
-    let mut context = Context::new(IntoIterator::into_iter(env).collect());
+pub fn infer_qualified(expr: Expr) -> Result<Qualified> {
+    let (preds, typed) = elaborate(expr)?;
+    Ok(Qualified {
+        preds,
+        ty: typed.ty,
+    })
+}
 
-    expr.infer(&mut context).map(|it| it.0)
+/// Infers `expr`, keeping the final type of every sub-expression.
+pub fn infer_annotated(expr: Expr) -> Result<Typed> {
+    elaborate(expr).map(|it| it.1)
+}
+
+fn elaborate(expr: Expr) -> Result<(Vec<Predicate>, Typed)> {
+    let mut context = prelude()?;
+
+    let typed = expr.infer(&mut context)?.resolve(&mut context);
+
+    let mut vars = Vec::new();
+    typed.ty.free_variables(&mut vars);
+    let preds = context.solve(&vars)?;
+
+    Ok((preds, typed))
+}
+
+pub fn infer_program(program: Program) -> Result<Vec<(String, Scheme)>> {
+    let mut context = prelude()?;
+
+    let schemes = program.infer(&mut context)?;
+    context.solve(&[])?;
+
+    Ok(schemes)
+}
+
+fn prelude() -> Result<Context> {
+    let mut classes = ClassEnv::default();
+    classes.declare(class(
+        "Num",
+        "a",
+        [
+            ("even?", ty_func(ty_var("a"), ty_name("Bool"))),
+            ("inc", ty_func(ty_var("a"), ty_var("a"))),
+        ],
+    ))?;
+    classes.implement(instance("Num", "Number"))?;
+
+    let env = classes
+        .methods()
+        .chain([("any".to_owned(), Scheme::mono(ty_var("any")))])
+        .collect();
+
+    Ok(Context::new(env, classes))
 }
 
 pub fn ty_var(name: &str) -> Ty {
@@ -35,6 +78,30 @@ pub fn ty_func(from: Ty, to: Ty) -> Ty {
     Ty::Func(Box::new(FuncTy { from, to }))
 }
 
+pub fn pred(class: &str, ty: Ty) -> Predicate {
+    Predicate {
+        class: class.to_owned(),
+        ty,
+    }
+}
+
+pub fn class<const N: usize>(name: &str, param: &str, methods: [(&str, Ty); N]) -> Class {
+    Class {
+        name: name.to_owned(),
+        param: param.to_owned(),
+        methods: IntoIterator::into_iter(methods)
+            .map(|(name, ty)| (name.to_owned(), ty))
+            .collect(),
+    }
+}
+
+pub fn instance(class: &str, ty: &str) -> Instance {
+    Instance {
+        class: class.to_owned(),
+        ty: ty.to_owned(),
+    }
+}
+
 pub fn number(number: i32) -> Expr {
     Expr::Number(number)
 }
@@ -70,6 +137,36 @@ pub fn let_in(name: &str, expr: Expr, body: Expr) -> Expr {
     }))
 }
 
+pub fn let_rec<const N: usize>(bindings: [(&str, Expr); N], body: Expr) -> Expr {
+    Expr::LetRec(Box::new(LetRecExpr {
+        bindings: IntoIterator::into_iter(bindings)
+            .map(|(name, expr)| (name.to_owned(), expr))
+            .collect(),
+        body,
+    }))
+}
+
+pub fn program<const N: usize>(defs: [(&str, Expr); N]) -> Program {
+    Program {
+        defs: IntoIterator::into_iter(defs)
+            .map(|(name, expr)| (name.to_owned(), expr))
+            .collect(),
+    }
+}
+
 pub fn err_mismatch(expected: Ty, found: Ty) -> Error {
     Error::TypeMismatch { expected, found }
 }
+
+pub fn err_missing_instance(class: &str, ty: Ty) -> Error {
+    Error::MissingInstance {
+        class: class.to_owned(),
+        ty,
+    }
+}
+
+pub fn err_ambiguous(class: &str, ty: Ty) -> Error {
+    Error::AmbiguousConstraint {
+        pred: pred(class, ty),
+    }
+}
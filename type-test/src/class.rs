@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+
+use crate::{
+    error::{Error, Result},
+    ty::{Predicate, Scheme, Ty},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Class {
+    pub name: String,
+    pub param: String,
+    pub methods: Vec<(String, Ty)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instance {
+    pub class: String,
+    pub ty: String,
+}
+
+/// Evidence that a predicate holds, as a dictionary-passing elaboration
+/// would see it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Dictionary {
+    /// The dictionary of a declared instance.
+    Instance(Instance),
+    /// A dictionary the caller has to pass in, kept in the qualified type.
+    Param(Predicate),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ClassEnv {
+    classes: HashMap<String, Class>,
+    instances: Vec<Instance>,
+}
+
+impl ClassEnv {
+    pub fn declare(&mut self, class: Class) -> Result<()> {
+        if self.classes.contains_key(&class.name) {
+            return Err(Error::DuplicateClass { name: class.name });
+        }
+
+        self.classes.insert(class.name.clone(), class);
+        Ok(())
+    }
+
+    pub fn implement(&mut self, instance: Instance) -> Result<()> {
+        if !self.classes.contains_key(&instance.class) {
+            return Err(Error::UnknownClass {
+                name: instance.class,
+            });
+        }
+
+        if self.instances.contains(&instance) {
+            return Err(Error::DuplicateInstance {
+                class: instance.class,
+                ty: Ty::Named(instance.ty),
+            });
+        }
+
+        self.instances.push(instance);
+        Ok(())
+    }
+
+    /// The type schemes of every class method, e.g. `Num a => a -> a`.
+    pub fn methods(&self) -> impl Iterator<Item = (String, Scheme)> + '_ {
+        self.classes.values().flat_map(|class| {
+            class.methods.iter().map(move |(name, ty)| {
+                let scheme = Scheme {
+                    vars: vec![class.param.clone()],
+                    preds: vec![Predicate {
+                        class: class.name.clone(),
+                        ty: Ty::Variable(class.param.clone()),
+                    }],
+                    ty: ty.clone(),
+                };
+
+                (name.clone(), scheme)
+            })
+        })
+    }
+
+    pub fn entail(&self, pred: &Predicate) -> Result<Dictionary> {
+        if !self.classes.contains_key(&pred.class) {
+            return Err(Error::UnknownClass {
+                name: pred.class.clone(),
+            });
+        }
+
+        let missing = || Error::MissingInstance {
+            class: pred.class.clone(),
+            ty: pred.ty.clone(),
+        };
+
+        let name = match &pred.ty {
+            Ty::Variable(_) => return Ok(Dictionary::Param(pred.clone())),
+            Ty::Named(name) => name,
+            Ty::Func(_) => return Err(missing()),
+        };
+
+        self.instances
+            .iter()
+            .find(|instance| instance.class == pred.class && instance.ty == *name)
+            .cloned()
+            .map(Dictionary::Instance)
+            .ok_or_else(missing)
+    }
+}
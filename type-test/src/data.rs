@@ -1,52 +1,200 @@
-use std::{collections::HashMap, ops::AddAssign};
+use std::collections::HashMap;
 
 use crate::{
+    class::{ClassEnv, Dictionary},
     error::{Error, Result},
-    ty::Ty,
+    store::Store,
+    ty::{Predicate, Scheme, Ty},
 };
 
 #[derive(Debug, Clone, Default)]
 pub struct Context {
-    env: HashMap<String, Ty>,
+    env: HashMap<String, Scheme>,
+    classes: ClassEnv,
+    constraints: Vec<Predicate>,
+    store: Store,
+    level: usize,
     next: usize,
 }
 
 impl Context {
-    pub fn new(env: HashMap<String, Ty>) -> Self {
-        Self { env, next: 0 }
+    pub fn new(env: HashMap<String, Scheme>, classes: ClassEnv) -> Self {
+        Self {
+            env,
+            classes,
+            constraints: Vec::new(),
+            store: Store::default(),
+            level: 0,
+            next: 0,
+        }
     }
 
-    pub fn get(&self, name: &str) -> Result<&Ty> {
-        self.env
+    /// Looks up `name`, instantiating its scheme and recording the
+    /// constraints it brings with it.
+    pub fn get(&mut self, name: &str) -> Result<Ty> {
+        let scheme = self
+            .env
             .get(name)
+            .cloned()
             .ok_or_else(|| Error::UnboundTypeVariable {
                 name: name.to_owned(),
-            })
+            })?;
+
+        Ok(self.instantiate(scheme))
     }
 
     pub fn new_ty_variable(&mut self) -> Ty {
-        let ty = Ty::Variable(format!("T{}", self.next));
+        let name = format!("T{}", self.next);
         self.next += 1;
-        ty
+        self.store.fresh(name, self.level)
+    }
+
+    pub fn unify(&mut self, found: &Ty, expected: &Ty) -> Result<()> {
+        self.store.unify(found, expected)
+    }
+
+    pub fn resolve(&mut self, ty: &Ty) -> Ty {
+        self.store.resolve(ty)
+    }
+
+    fn instantiate(&mut self, scheme: Scheme) -> Ty {
+        if scheme.vars.is_empty() {
+            return scheme.ty;
+        }
+
+        let mut subs = Substitutions::default();
+        for var in scheme.vars {
+            let ty = self.new_ty_variable();
+            subs.insert(var, ty);
+        }
+
+        self.constraints
+            .extend(scheme.preds.into_iter().map(|pred| pred.substitute(&subs)));
+        scheme.ty.substitute(&subs)
+    }
+
+    /// Runs `f` one level deeper, so the variables it creates can be
+    /// generalized once it returns.
+    pub fn nested<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        self.level += 1;
+        let result = f(self);
+        self.level -= 1;
+        result
+    }
+
+    /// Runs `f` with `name` bound to `ty`, restoring any shadowed binding after.
+    pub fn with<R>(&mut self, name: String, ty: Ty, f: impl FnOnce(&mut Self) -> R) -> R {
+        self.with_all(vec![(name, Scheme::mono(ty))], f)
+    }
+
+    /// Runs `f` with every binding in scope, restoring any shadowed bindings after.
+    pub fn with_all<R>(
+        &mut self,
+        bindings: Vec<(String, Scheme)>,
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> R {
+        let shadowed: Vec<_> = bindings
+            .into_iter()
+            .map(|(name, scheme)| {
+                let shadowed = self.env.insert(name.clone(), scheme);
+                (name, shadowed)
+            })
+            .collect();
+
+        let result = f(self);
+
+        for (name, shadowed) in shadowed.into_iter().rev() {
+            match shadowed {
+                Some(scheme) => self.env.insert(name, scheme),
+                None => self.env.remove(&name),
+            };
+        }
+
+        result
     }
 
-    pub fn with(&self, name: String, ty: Ty) -> Self {
-        let mut context = self.clone();
-        context.env.insert(name, ty);
-        context
+    /// Binds `name` for the rest of inference, as top-level definitions are.
+    pub fn define(&mut self, name: String, scheme: Scheme) {
+        self.env.insert(name, scheme);
     }
 
-    pub fn substitute(&self, subs: &Substitutions) -> Self {
-        Self::new(
-            self.env
-                .clone()
-                .into_iter()
-                .map(|(name, ty)| (name, ty.substitute(subs)))
-                .collect(),
-        )
+    /// Quantifies each type over its variables that were created deeper
+    /// than the current level, moving the constraints on those variables
+    /// into the schemes.
+    pub fn generalize(&mut self, tys: Vec<(String, Ty)>) -> Result<Vec<(String, Scheme)>> {
+        self.reduce()?;
+
+        let schemes: Vec<_> = tys
+            .into_iter()
+            .map(|(name, ty)| {
+                let ty = self.store.resolve(&ty);
+
+                let mut vars = Vec::new();
+                ty.free_variables(&mut vars);
+                vars.retain(
+                    |var| matches!(self.store.level(var), Some(level) if level > self.level),
+                );
+
+                let mut preds = Vec::new();
+                for pred in &self.constraints {
+                    if pred.is_over(&vars) && !preds.contains(pred) {
+                        preds.push(pred.clone());
+                    }
+                }
+
+                (name, Scheme { vars, preds, ty })
+            })
+            .collect();
+
+        self.constraints
+            .retain(|pred| !schemes.iter().any(|(_, scheme)| pred.is_over(&scheme.vars)));
+
+        Ok(schemes)
+    }
+
+    /// Resolves every constraint, discharging the ones on concrete types
+    /// so only constraints on variables are carried along.
+    fn reduce(&mut self) -> Result<()> {
+        let mut constraints = Vec::with_capacity(self.constraints.len());
+
+        for pred in std::mem::take(&mut self.constraints) {
+            let pred = Predicate {
+                class: pred.class,
+                ty: self.store.resolve(&pred.ty),
+            };
+
+            if let Dictionary::Param(pred) = self.classes.entail(&pred)? {
+                constraints.push(pred);
+            }
+        }
+
+        self.constraints = constraints;
+        Ok(())
+    }
+
+    /// Resolves the constraints collected so far against the instances in
+    /// scope. Constraints on the type variables in `keep` are kept as
+    /// dictionary parameters, any other variable is ambiguous.
+    pub fn solve(&mut self, keep: &[String]) -> Result<Vec<Predicate>> {
+        self.reduce()?;
+
+        let mut preds = Vec::new();
+
+        for pred in std::mem::take(&mut self.constraints) {
+            match &pred.ty {
+                Ty::Variable(name) if !keep.contains(name) => {
+                    return Err(Error::AmbiguousConstraint { pred })
+                }
+                _ if preds.contains(&pred) => {}
+                _ => preds.push(pred),
+            }
+        }
+
+        Ok(preds)
     }
 }
 
+/// A mapping from quantified variables to the types they are instantiated with.
 #[derive(Debug, Clone, Default)]
 pub struct Substitutions(HashMap<String, Ty>);
 
@@ -55,15 +203,7 @@ impl Substitutions {
         self.0.get(name)
     }
 
-    pub fn of(name: String, ty: Ty) -> Substitutions {
-        let mut map = HashMap::with_capacity(1);
-        map.insert(name, ty);
-        Substitutions(map)
-    }
-}
-
-impl AddAssign for Substitutions {
-    fn add_assign(&mut self, rhs: Self) {
-        self.0.extend(rhs.0.into_iter())
+    pub fn insert(&mut self, name: String, ty: Ty) {
+        self.0.insert(name, ty);
     }
 }
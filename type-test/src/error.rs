@@ -1,6 +1,6 @@
 use thiserror::Error;
 
-use crate::ty::Ty;
+use crate::ty::{Predicate, Ty};
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
@@ -12,4 +12,14 @@ pub enum Error {
     SelfReference { ty: Ty },
     #[error("Unbound type variable")]
     UnboundTypeVariable { name: String },
+    #[error("No instance for constraint")]
+    MissingInstance { class: String, ty: Ty },
+    #[error("Ambiguous constraint")]
+    AmbiguousConstraint { pred: Predicate },
+    #[error("Unknown class")]
+    UnknownClass { name: String },
+    #[error("Class declared twice")]
+    DuplicateClass { name: String },
+    #[error("Instance declared twice")]
+    DuplicateInstance { class: String, ty: Ty },
 }
@@ -1,10 +1,13 @@
 use crate::{
     builder,
-    data::{Context, Substitutions},
+    data::Context,
     error::Result,
-    ty::Ty,
+    ty::{Scheme, Ty},
+    typed::{Typed, TypedExpr},
 };
 
+type Bindings<T> = Vec<(String, T)>;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expr {
     Number(i32),
@@ -13,17 +16,66 @@ pub enum Expr {
     Call(Box<CallExpr>),
     If(Box<IfExpr>),
     Let(Box<LetExpr>),
+    LetRec(Box<LetRecExpr>),
 }
 
 impl Expr {
-    pub fn infer(self, ctx: &mut Context) -> Result<(Ty, Substitutions)> {
+    pub fn infer(self, ctx: &mut Context) -> Result<Typed> {
         match self {
-            Expr::Number(_) => Ok((Ty::Named("Number".to_string()), Substitutions::default())),
-            Expr::Variable(name) => Ok((ctx.get(&name)?.clone(), Substitutions::default())),
+            Expr::Number(number) => Ok(Typed::new(
+                TypedExpr::Number(number),
+                Ty::Named("Number".to_string()),
+            )),
+            Expr::Variable(name) => {
+                let ty = ctx.get(&name)?;
+                Ok(Typed::new(TypedExpr::Variable(name), ty))
+            }
             Expr::Func(it) => it.infer(ctx),
             Expr::Call(it) => it.infer(ctx),
             Expr::If(it) => it.infer(ctx),
             Expr::Let(it) => it.infer(ctx),
+            Expr::LetRec(it) => it.infer(ctx),
+        }
+    }
+
+    /// Collects the variables this expression uses that are not in `bound`.
+    pub fn references(&self, bound: &mut Vec<String>, refs: &mut Vec<String>) {
+        match self {
+            Expr::Number(_) => {}
+            Expr::Variable(name) => {
+                if !bound.contains(name) && !refs.contains(name) {
+                    refs.push(name.clone());
+                }
+            }
+            Expr::Func(it) => {
+                bound.push(it.param.clone());
+                it.body.references(bound, refs);
+                bound.pop();
+            }
+            Expr::Call(it) => {
+                it.func.references(bound, refs);
+                it.arg.references(bound, refs);
+            }
+            Expr::If(it) => {
+                it.condition.references(bound, refs);
+                it.true_branch.references(bound, refs);
+                it.false_branch.references(bound, refs);
+            }
+            Expr::Let(it) => {
+                it.expr.references(bound, refs);
+                bound.push(it.name.clone());
+                it.body.references(bound, refs);
+                bound.pop();
+            }
+            Expr::LetRec(it) => {
+                let len = bound.len();
+                bound.extend(it.bindings.iter().map(|(name, _)| name.clone()));
+                for (_, expr) in &it.bindings {
+                    expr.references(bound, refs);
+                }
+                it.body.references(bound, refs);
+                bound.truncate(len);
+            }
         }
     }
 }
@@ -35,13 +87,18 @@ pub struct FuncExpr {
 }
 
 impl FuncExpr {
-    fn infer(self, ctx: &mut Context) -> Result<(Ty, Substitutions)> {
+    fn infer(self, ctx: &mut Context) -> Result<Typed> {
         let param_ty = ctx.new_ty_variable();
-        let mut ctx = ctx.with(self.param, param_ty.clone());
-        let (body_ty, subs) = self.body.infer(&mut ctx)?;
-        let param_ty = param_ty.substitute(&subs);
+        let body = self.body;
+        let body = ctx.with(self.param.clone(), param_ty.clone(), |ctx| body.infer(ctx))?;
+        let ty = builder::ty_func(param_ty, body.ty.clone());
+
+        let expr = TypedExpr::Func {
+            param: self.param,
+            body: Box::new(body),
+        };
 
-        Ok((builder::ty_func(param_ty, body_ty), subs))
+        Ok(Typed::new(expr, ty))
     }
 }
 
@@ -52,23 +109,19 @@ pub struct CallExpr {
 }
 
 impl CallExpr {
-    fn infer(self, ctx: &mut Context) -> Result<(Ty, Substitutions)> {
-        let (func_ty, mut subs) = self.func.infer(ctx)?;
-        let (arg_ty, new_subs) = self.arg.infer(&mut ctx.substitute(&subs))?;
+    fn infer(self, ctx: &mut Context) -> Result<Typed> {
+        let func = self.func.infer(ctx)?;
+        let arg = self.arg.infer(ctx)?;
 
-        let new_var = ctx.new_ty_variable();
-        subs += new_subs;
+        let ty = ctx.new_ty_variable();
+        ctx.unify(&builder::ty_func(arg.ty.clone(), ty.clone()), &func.ty)?;
 
-        let new_subs = builder::ty_func(arg_ty.clone(), new_var).unify(func_ty.clone())?;
-        let func_ty = func_ty
-            .substitute(&new_subs)
-            .try_into_func()
-            .expect("Should still be a func type here");
+        let expr = TypedExpr::Call {
+            func: Box::new(func),
+            arg: Box::new(arg),
+        };
 
-        subs += new_subs;
-        subs += func_ty.from.substitute(&subs).unify(arg_ty)?;
-
-        Ok((func_ty.to.substitute(&subs), subs))
+        Ok(Typed::new(expr, ty))
     }
 }
 
@@ -80,26 +133,22 @@ pub struct IfExpr {
 }
 
 impl IfExpr {
-    fn infer(self, ctx: &mut Context) -> Result<(Ty, Substitutions)> {
-        let (condition_ty, mut condition_subs) = self.condition.infer(ctx)?;
-        let mut subs = condition_ty.unify(Ty::Named("Bool".to_string()))?;
-        condition_subs += subs.clone();
-
-        let mut ctx = ctx.substitute(&condition_subs);
-        let (true_branch_ty, new_subs) = self.true_branch.infer(&mut ctx)?;
-        subs += new_subs.clone();
-
-        let mut ctx = ctx.substitute(&new_subs);
-        let (false_branch_ty, new_subs) = self.false_branch.infer(&mut ctx)?;
-        subs += new_subs;
-
-        let true_branch_ty = true_branch_ty.substitute(&subs);
-        let false_branch_ty = false_branch_ty.substitute(&subs);
-
-        let new_subs = true_branch_ty.clone().unify(false_branch_ty)?;
-        subs += new_subs.clone();
-
-        Ok((true_branch_ty.substitute(&new_subs), subs))
+    fn infer(self, ctx: &mut Context) -> Result<Typed> {
+        let condition = self.condition.infer(ctx)?;
+        ctx.unify(&condition.ty, &Ty::Named("Bool".to_string()))?;
+
+        let true_branch = self.true_branch.infer(ctx)?;
+        let false_branch = self.false_branch.infer(ctx)?;
+        ctx.unify(&true_branch.ty, &false_branch.ty)?;
+
+        let ty = true_branch.ty.clone();
+        let expr = TypedExpr::If {
+            condition: Box::new(condition),
+            true_branch: Box::new(true_branch),
+            false_branch: Box::new(false_branch),
+        };
+
+        Ok(Typed::new(expr, ty))
     }
 }
 
@@ -111,11 +160,77 @@ pub struct LetExpr {
 }
 
 impl LetExpr {
-    fn infer(self, ctx: &mut Context) -> Result<(Ty, Substitutions)> {
-        let (expr_ty, mut subs) = self.expr.infer(ctx)?;
-        let mut ctx = ctx.substitute(&subs).with(self.name, expr_ty);
-        let (body_ty, new_subs) = self.body.infer(&mut ctx)?;
-        subs += new_subs;
-        Ok((body_ty, subs))
+    fn infer(self, ctx: &mut Context) -> Result<Typed> {
+        let expr = self.expr;
+        let expr = ctx.nested(|ctx| expr.infer(ctx))?;
+        let schemes = ctx.generalize(vec![(self.name.clone(), expr.ty.clone())])?;
+        let body = self.body;
+        let body = ctx.with_all(schemes, |ctx| body.infer(ctx))?;
+
+        let ty = body.ty.clone();
+        let expr = TypedExpr::Let {
+            name: self.name,
+            expr: Box::new(expr),
+            body: Box::new(body),
+        };
+
+        Ok(Typed::new(expr, ty))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LetRecExpr {
+    pub bindings: Vec<(String, Expr)>,
+    pub body: Expr,
+}
+
+impl LetRecExpr {
+    fn infer(self, ctx: &mut Context) -> Result<Typed> {
+        let (schemes, bindings) = Self::infer_group(self.bindings, ctx)?;
+        let body = self.body;
+        let body = ctx.with_all(schemes, |ctx| body.infer(ctx))?;
+
+        let ty = body.ty.clone();
+        let expr = TypedExpr::LetRec {
+            bindings,
+            body: Box::new(body),
+        };
+
+        Ok(Typed::new(expr, ty))
+    }
+
+    /// Infers bindings that may refer to each other. Uses inside the group
+    /// are monomorphic, and the group is only generalized once solved.
+    pub(crate) fn infer_group(
+        bindings: Vec<(String, Expr)>,
+        ctx: &mut Context,
+    ) -> Result<(Bindings<Scheme>, Bindings<Typed>)> {
+        let (vars, typed) = ctx.nested(|ctx| {
+            let vars: Vec<_> = bindings
+                .iter()
+                .map(|(name, _)| (name.clone(), ctx.new_ty_variable()))
+                .collect();
+
+            let mono = vars
+                .iter()
+                .map(|(name, ty)| (name.clone(), Scheme::mono(ty.clone())))
+                .collect();
+
+            let typed = ctx.with_all(mono, |ctx| {
+                let mut typed = Vec::new();
+
+                for ((name, expr), (_, var)) in bindings.into_iter().zip(&vars) {
+                    let expr = expr.infer(ctx)?;
+                    ctx.unify(&expr.ty, var)?;
+                    typed.push((name, expr));
+                }
+
+                Ok(typed)
+            })?;
+
+            Ok((vars, typed))
+        })?;
+
+        Ok((ctx.generalize(vars)?, typed))
     }
 }
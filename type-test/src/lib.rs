@@ -1,7 +1,11 @@
 pub mod builder;
+pub mod class;
 pub mod data;
 pub mod error;
 pub mod expr;
+pub mod program;
+pub mod store;
 #[cfg(test)]
 pub mod test;
 pub mod ty;
+pub mod typed;
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use crate::{
+    data::Context,
+    error::Result,
+    expr::{Expr, LetRecExpr},
+    ty::Scheme,
+};
+
+/// Top-level definitions, which may refer to each other in any order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    pub defs: Vec<(String, Expr)>,
+}
+
+impl Program {
+    /// Splits the definitions into mutually recursive groups, given as
+    /// indices into `defs`. Every group comes after the groups it uses.
+    pub fn groups(&self) -> Vec<Vec<usize>> {
+        let indices: HashMap<_, _> = self
+            .defs
+            .iter()
+            .enumerate()
+            .map(|(index, (name, _))| (name.as_str(), index))
+            .collect();
+
+        let edges: Vec<Vec<usize>> = self
+            .defs
+            .iter()
+            .map(|(_, expr)| {
+                let mut refs = Vec::new();
+                expr.references(&mut Vec::new(), &mut refs);
+                refs.iter()
+                    .filter_map(|name| indices.get(name.as_str()).copied())
+                    .collect()
+            })
+            .collect();
+
+        let mut tarjan = Tarjan {
+            edges: &edges,
+            index: vec![None; edges.len()],
+            low: vec![0; edges.len()],
+            stack: Vec::new(),
+            on_stack: vec![false; edges.len()],
+            next: 0,
+            groups: Vec::new(),
+        };
+
+        for def in 0..edges.len() {
+            if tarjan.index[def].is_none() {
+                tarjan.visit(def);
+            }
+        }
+
+        tarjan.groups
+    }
+
+    /// Infers every definition group in dependency order, generalizing each
+    /// before the groups that use it. Schemes are returned in `defs` order.
+    pub fn infer(self, ctx: &mut Context) -> Result<Vec<(String, Scheme)>> {
+        let groups = self.groups();
+        let mut defs: Vec<_> = self.defs.into_iter().map(Some).collect();
+        let mut schemes = vec![None; defs.len()];
+
+        for group in groups {
+            let bindings = group
+                .iter()
+                .map(|&def| defs[def].take().expect("Each def is in one group"))
+                .collect();
+
+            let (group_schemes, _) = LetRecExpr::infer_group(bindings, ctx)?;
+
+            for (&def, (name, scheme)) in group.iter().zip(group_schemes) {
+                ctx.define(name.clone(), scheme.clone());
+                schemes[def] = Some((name, scheme));
+            }
+        }
+
+        Ok(schemes.into_iter().flatten().collect())
+    }
+}
+
+/// Tarjan's strongly connected components, which come out in reverse
+/// topological order, so dependencies first.
+struct Tarjan<'a> {
+    edges: &'a [Vec<usize>],
+    index: Vec<Option<usize>>,
+    low: Vec<usize>,
+    stack: Vec<usize>,
+    on_stack: Vec<bool>,
+    next: usize,
+    groups: Vec<Vec<usize>>,
+}
+
+impl Tarjan<'_> {
+    fn visit(&mut self, def: usize) {
+        self.index[def] = Some(self.next);
+        self.low[def] = self.next;
+        self.next += 1;
+        self.stack.push(def);
+        self.on_stack[def] = true;
+
+        let edges = self.edges;
+        for &dep in &edges[def] {
+            match self.index[dep] {
+                None => {
+                    self.visit(dep);
+                    self.low[def] = self.low[def].min(self.low[dep]);
+                }
+                Some(index) if self.on_stack[dep] => {
+                    self.low[def] = self.low[def].min(index);
+                }
+                Some(_) => {}
+            }
+        }
+
+        if Some(self.low[def]) == self.index[def] {
+            let mut group = Vec::new();
+
+            loop {
+                let member = self.stack.pop().expect("Def should be on the stack");
+                self.on_stack[member] = false;
+                group.push(member);
+
+                if member == def {
+                    break;
+                }
+            }
+
+            group.sort_unstable();
+            self.groups.push(group);
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+
+use crate::{
+    builder,
+    error::{Error, Result},
+    ty::Ty,
+};
+
+/// Union-find over type variables. Each variable either links to another
+/// variable, or is a root that is still unbound or bound to a type.
+#[derive(Debug, Clone, Default)]
+pub struct Store {
+    ids: HashMap<String, usize>,
+    vars: Vec<Var>,
+}
+
+#[derive(Debug, Clone)]
+enum Var {
+    Link(usize),
+    Unbound { name: String, level: usize },
+    Bound(Ty),
+}
+
+impl Store {
+    pub fn fresh(&mut self, name: String, level: usize) -> Ty {
+        self.ids.insert(name.clone(), self.vars.len());
+        self.vars.push(Var::Unbound {
+            name: name.clone(),
+            level,
+        });
+        Ty::Variable(name)
+    }
+
+    /// The level of an unbound variable, which is generalizable when it is
+    /// deeper than the `let` being generalized.
+    pub fn level(&mut self, name: &str) -> Option<usize> {
+        let root = self.root(name);
+        match &self.vars[root] {
+            Var::Unbound { level, .. } => Some(*level),
+            _ => None,
+        }
+    }
+
+    /// Replaces every bound variable in `ty` with what it is bound to.
+    pub fn resolve(&mut self, ty: &Ty) -> Ty {
+        match self.shallow(ty) {
+            Ty::Func(func_ty) => {
+                builder::ty_func(self.resolve(&func_ty.from), self.resolve(&func_ty.to))
+            }
+            ty => ty,
+        }
+    }
+
+    pub fn unify(&mut self, found: &Ty, expected: &Ty) -> Result<()> {
+        match (self.shallow(found), self.shallow(expected)) {
+            (Ty::Variable(x), Ty::Variable(y)) => {
+                self.union(&x, &y);
+                Ok(())
+            }
+            (Ty::Variable(id), ty) | (ty, Ty::Variable(id)) => self.bind(&id, ty),
+            (Ty::Named(x), Ty::Named(y)) if x == y => Ok(()),
+            (Ty::Func(x), Ty::Func(y)) => {
+                self.unify(&x.from, &y.from)?;
+                self.unify(&x.to, &y.to)
+            }
+            (found, expected) => Err(Error::TypeMismatch {
+                expected: self.resolve(&expected),
+                found: self.resolve(&found),
+            }),
+        }
+    }
+
+    /// Variables not created with `fresh`, like ones from the initial
+    /// environment, are treated as unbound at the outermost level.
+    fn id(&mut self, name: &str) -> usize {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+
+        let id = self.vars.len();
+        self.ids.insert(name.to_owned(), id);
+        self.vars.push(Var::Unbound {
+            name: name.to_owned(),
+            level: 0,
+        });
+        id
+    }
+
+    fn find(&mut self, id: usize) -> usize {
+        let mut root = id;
+        while let Var::Link(parent) = self.vars[root] {
+            root = parent;
+        }
+
+        let mut id = id;
+        while let Var::Link(parent) = self.vars[id] {
+            self.vars[id] = Var::Link(root);
+            id = parent;
+        }
+
+        root
+    }
+
+    fn root(&mut self, name: &str) -> usize {
+        let id = self.id(name);
+        self.find(id)
+    }
+
+    /// Resolves only the outermost variable of `ty`, if any.
+    fn shallow(&mut self, ty: &Ty) -> Ty {
+        let name = match ty {
+            Ty::Variable(name) => name,
+            _ => return ty.clone(),
+        };
+
+        let root = self.root(name);
+        match &self.vars[root] {
+            Var::Unbound { name, .. } => Ty::Variable(name.clone()),
+            Var::Bound(ty) => ty.clone(),
+            Var::Link(_) => unreachable!("Roots are never links"),
+        }
+    }
+
+    fn union(&mut self, from: &str, to: &str) {
+        let from = self.root(from);
+        let to = self.root(to);
+        if from == to {
+            return;
+        }
+
+        let from_level = match self.vars[from] {
+            Var::Unbound { level, .. } => level,
+            _ => unreachable!("Only unbound roots are unioned"),
+        };
+
+        if let Var::Unbound { level, .. } = &mut self.vars[to] {
+            *level = (*level).min(from_level);
+        }
+
+        self.vars[from] = Var::Link(to);
+    }
+
+    fn bind(&mut self, name: &str, ty: Ty) -> Result<()> {
+        let root = self.root(name);
+        let level = match self.vars[root] {
+            Var::Unbound { level, .. } => level,
+            _ => unreachable!("Only unbound roots are bound"),
+        };
+
+        if self.occurs(root, level, &ty) {
+            return Err(Error::SelfReference {
+                ty: self.resolve(&ty),
+            });
+        }
+
+        self.vars[root] = Var::Bound(ty);
+        Ok(())
+    }
+
+    /// Checks whether `root` occurs in `ty`, lowering the level of every
+    /// other variable in `ty` to `level` along the way so they are not
+    /// generalized any deeper than `root` would be.
+    fn occurs(&mut self, root: usize, level: usize, ty: &Ty) -> bool {
+        match self.shallow(ty) {
+            Ty::Named(_) => false,
+            Ty::Variable(name) => {
+                let other = self.root(&name);
+                if let Var::Unbound {
+                    level: other_level, ..
+                } = &mut self.vars[other]
+                {
+                    *other_level = (*other_level).min(level);
+                }
+                other == root
+            }
+            Ty::Func(func_ty) => {
+                self.occurs(root, level, &func_ty.from) || self.occurs(root, level, &func_ty.to)
+            }
+        }
+    }
+}
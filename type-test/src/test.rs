@@ -1,4 +1,22 @@
-use crate::builder::*;
+use crate::{
+    builder::*,
+    class::ClassEnv,
+    error::Error,
+    expr::Expr,
+    ty::{Qualified, Scheme},
+    typed::{Typed, TypedExpr},
+};
+
+fn count_up() -> Expr {
+    func(
+        "x",
+        if_else(
+            call(var("even?"), var("x")),
+            var("x"),
+            call(var("count"), call(var("inc"), var("x"))),
+        ),
+    )
+}
 
 #[test]
 fn checks() {
@@ -20,8 +38,12 @@ fn type_mismatch() {
     );
 
     assert_eq!(
-        infer(call(var("inc"), call(var("even?"), number(123)))),
-        Err(err_mismatch(ty_name("Number"), ty_name("Bool")))
+        infer(if_else(
+            call(var("inc"), number(123)),
+            number(456),
+            number(789)
+        )),
+        Err(err_mismatch(ty_name("Bool"), ty_name("Number")))
     );
 }
 
@@ -80,3 +102,274 @@ fn let_mismatch() {
         Err(err_mismatch(ty_name("Bool"), ty_name("Number")))
     );
 }
+
+#[test]
+fn class_checks() {
+    assert_eq!(
+        infer_qualified(var("inc")),
+        Ok(Qualified {
+            preds: vec![pred("Num", ty_var("T0"))],
+            ty: ty_func(ty_var("T0"), ty_var("T0")),
+        })
+    );
+
+    assert_eq!(
+        infer_qualified(func("x", call(var("even?"), call(var("inc"), var("x"))))),
+        Ok(Qualified {
+            preds: vec![pred("Num", ty_var("T1"))],
+            ty: ty_func(ty_var("T1"), ty_name("Bool")),
+        })
+    );
+
+    assert_eq!(
+        infer_qualified(let_in(
+            "foo",
+            func("bar", call(var("inc"), var("bar"))),
+            call(var("foo"), number(123))
+        )),
+        Ok(Qualified {
+            preds: vec![],
+            ty: ty_name("Number"),
+        })
+    );
+}
+
+#[test]
+fn class_mismatch() {
+    assert_eq!(
+        infer(call(var("inc"), call(var("even?"), number(123)))),
+        Err(err_missing_instance("Num", ty_name("Bool")))
+    );
+
+    assert_eq!(
+        infer(call(var("inc"), func("x", var("x")))),
+        Err(err_missing_instance(
+            "Num",
+            ty_func(ty_var("T1"), ty_var("T1"))
+        ))
+    );
+
+    assert_eq!(
+        infer(call(var("even?"), var("any"))),
+        Err(err_ambiguous("Num", ty_var("T0")))
+    );
+}
+
+#[test]
+fn class_declarations() {
+    let mut classes = ClassEnv::default();
+
+    assert_eq!(
+        classes.implement(instance("Num", "Number")),
+        Err(Error::UnknownClass {
+            name: "Num".to_owned()
+        })
+    );
+
+    assert_eq!(classes.declare(class("Num", "a", [])), Ok(()));
+    assert_eq!(classes.implement(instance("Num", "Number")), Ok(()));
+
+    assert_eq!(
+        classes.implement(instance("Num", "Number")),
+        Err(Error::DuplicateInstance {
+            class: "Num".to_owned(),
+            ty: ty_name("Number"),
+        })
+    );
+}
+
+#[test]
+fn let_rec_checks() {
+    assert_eq!(
+        infer(let_rec(
+            [("count", count_up())],
+            call(var("count"), number(1))
+        )),
+        Ok(ty_name("Number"))
+    );
+
+    assert_eq!(
+        infer_qualified(let_rec([("count", count_up())], var("count"))),
+        Ok(Qualified {
+            preds: vec![pred("Num", ty_var("T7"))],
+            ty: ty_func(ty_var("T7"), ty_var("T7")),
+        })
+    );
+
+    assert_eq!(
+        infer(let_rec(
+            [("id", func("x", var("x")))],
+            if_else(
+                call(var("id"), call(var("even?"), number(1))),
+                call(var("id"), number(2)),
+                number(3)
+            )
+        )),
+        Ok(ty_name("Number"))
+    );
+}
+
+#[test]
+fn let_rec_mismatch() {
+    assert_eq!(
+        infer(let_rec(
+            [(
+                "id",
+                func(
+                    "x",
+                    if_else(
+                        call(var("id"), call(var("even?"), number(1))),
+                        var("x"),
+                        var("x")
+                    )
+                )
+            )],
+            call(var("id"), number(2))
+        )),
+        Err(err_mismatch(ty_name("Bool"), ty_name("Number")))
+    );
+
+    assert_eq!(
+        infer(let_rec([("f", call(var("f"), number(1)))], var("f"))),
+        Err(Error::SelfReference {
+            ty: ty_func(ty_name("Number"), ty_var("T1"))
+        })
+    );
+}
+
+#[test]
+fn program_groups() {
+    let is_even = func(
+        "x",
+        if_else(
+            call(var("even?"), var("x")),
+            var("x"),
+            call(var("is_odd"), call(var("inc"), var("x"))),
+        ),
+    );
+    let is_odd = func("x", call(var("is_even"), var("x")));
+    let twice = func("f", func("x", call(var("f"), call(var("f"), var("x")))));
+
+    let parity_program = program([
+        ("main", call(var("is_odd"), number(1))),
+        ("is_odd", is_odd),
+        ("twice", twice),
+        ("is_even", is_even),
+    ]);
+
+    assert_eq!(parity_program.groups(), vec![vec![1, 3], vec![0], vec![2]]);
+
+    let parity = Scheme {
+        vars: vec!["T3".to_owned()],
+        preds: vec![pred("Num", ty_var("T3"))],
+        ty: ty_func(ty_var("T3"), ty_var("T3")),
+    };
+
+    assert_eq!(
+        infer_program(parity_program),
+        Ok(vec![
+            ("main".to_owned(), Scheme::mono(ty_name("Number"))),
+            ("is_odd".to_owned(), parity.clone()),
+            (
+                "twice".to_owned(),
+                Scheme {
+                    vars: vec!["T15".to_owned()],
+                    preds: vec![],
+                    ty: ty_func(
+                        ty_func(ty_var("T15"), ty_var("T15")),
+                        ty_func(ty_var("T15"), ty_var("T15"))
+                    ),
+                }
+            ),
+            ("is_even".to_owned(), parity),
+        ])
+    );
+
+    assert_eq!(
+        infer_program(program([("bad", call(var("even?"), var("any")))])),
+        Err(err_ambiguous("Num", ty_var("T1")))
+    );
+}
+
+#[test]
+fn annotated() {
+    assert_eq!(
+        infer_annotated(call(var("inc"), number(123))),
+        Ok(Typed::new(
+            TypedExpr::Call {
+                func: Box::new(Typed::new(
+                    TypedExpr::Variable("inc".to_owned()),
+                    ty_func(ty_name("Number"), ty_name("Number"))
+                )),
+                arg: Box::new(Typed::new(TypedExpr::Number(123), ty_name("Number"))),
+            },
+            ty_name("Number")
+        ))
+    );
+
+    assert_eq!(
+        infer_annotated(let_in(
+            "foo",
+            func("bar", call(var("even?"), var("bar"))),
+            call(var("foo"), number(123))
+        ))
+        .map(|it| it.to_string()),
+        Ok(
+            "(let foo = (fun bar -> ((even? : T1 -> Bool) (bar : T1) : Bool) : T1 -> Bool) \
+            in ((foo : Number -> Bool) (123 : Number) : Bool) : Bool)"
+                .to_owned()
+        )
+    );
+
+    assert_eq!(
+        infer_annotated(func(
+            "f",
+            func("x", call(var("f"), call(var("f"), var("x"))))
+        ))
+        .map(|it| it.to_string()),
+        Ok(
+            "(fun f -> (fun x -> ((f : T1 -> T1) ((f : T1 -> T1) (x : T1) : T1) : T1) \
+            : T1 -> T1) : (T1 -> T1) -> T1 -> T1)"
+                .to_owned()
+        )
+    );
+}
+
+/// Builds `let x0 = first in let x1 = next(x0) in ... in last(xN)`.
+fn nested_lets(count: usize, first: Expr, next: impl Fn(Expr) -> Expr, last: Expr) -> Expr {
+    let name = |index: usize| format!("x{}", index);
+
+    let mut expr = last;
+    for index in (1..count).rev() {
+        expr = let_in(&name(index), next(var(&name(index - 1))), expr);
+    }
+
+    let_in(&name(0), first, expr)
+}
+
+#[test]
+fn stress() {
+    // Deeply nested trees overflow the default test thread stack.
+    let checks = std::thread::Builder::new()
+        .stack_size(256 << 20)
+        .spawn(|| {
+            let count = 5000;
+            let last = format!("x{}", count - 1);
+
+            let values = nested_lets(count, number(0), |prev| call(var("inc"), prev), var(&last));
+
+            let funcs = nested_lets(
+                count,
+                func("x", var("x")),
+                |prev| func("x", call(prev, var("x"))),
+                call(var(&last), call(var("even?"), number(1))),
+            );
+
+            (infer(values), infer(funcs))
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+
+    assert_eq!(checks, (Ok(ty_name("Number")), Ok(ty_name("Bool"))));
+}
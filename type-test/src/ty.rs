@@ -1,8 +1,6 @@
-use crate::{
-    builder,
-    data::Substitutions,
-    error::{Error, Result},
-};
+use std::fmt;
+
+use crate::{builder, data::Substitutions};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Ty {
@@ -17,6 +15,55 @@ pub struct FuncTy {
     pub to: Ty,
 }
 
+/// A class constraint such as `Num a`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Predicate {
+    pub class: String,
+    pub ty: Ty,
+}
+
+/// A type with the constraints it still needs, such as `Num a => a -> a`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Qualified {
+    pub preds: Vec<Predicate>,
+    pub ty: Ty,
+}
+
+/// A qualified type quantified over `vars`, instantiated on every use.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scheme {
+    pub vars: Vec<String>,
+    pub preds: Vec<Predicate>,
+    pub ty: Ty,
+}
+
+impl Predicate {
+    pub(crate) fn substitute(self, subs: &Substitutions) -> Predicate {
+        Predicate {
+            class: self.class,
+            ty: self.ty.substitute(subs),
+        }
+    }
+
+    /// Whether this constraint only mentions variables in `vars`, and at
+    /// least one of them.
+    pub(crate) fn is_over(&self, vars: &[String]) -> bool {
+        let mut pred_vars = Vec::new();
+        self.ty.free_variables(&mut pred_vars);
+        !pred_vars.is_empty() && pred_vars.iter().all(|var| vars.contains(var))
+    }
+}
+
+impl Scheme {
+    pub fn mono(ty: Ty) -> Scheme {
+        Scheme {
+            vars: Vec::new(),
+            preds: Vec::new(),
+            ty,
+        }
+    }
+}
+
 impl Ty {
     pub(crate) fn substitute(self, subs: &Substitutions) -> Ty {
         match self {
@@ -28,38 +75,14 @@ impl Ty {
         }
     }
 
-    pub(crate) fn unify(self, other: Ty) -> Result<Substitutions> {
-        match (self, other) {
-            (Ty::Named(x), Ty::Named(y)) if x == y => Ok(Substitutions::default()),
-            (Ty::Variable(id), ty) => ty.bind_variable(id),
-            (ty, Ty::Variable(id)) => ty.bind_variable(id),
-            (Ty::Func(x), Ty::Func(y)) => {
-                let mut subs = x.from.unify(y.from)?;
-
-                subs += x.to.substitute(&subs).unify(y.to.substitute(&subs))?;
-
-                Ok(subs)
-            }
-            (found, expected) => Err(Error::TypeMismatch { expected, found }),
-        }
-    }
-
-    fn bind_variable(self, id: String) -> Result<Substitutions> {
-        if matches!(self, Ty::Variable(ref var_id) if *var_id == id) {
-            Ok(Substitutions::default())
-        } else if self.contains_variable(&id) {
-            Err(Error::SelfReference { ty: self })
-        } else {
-            Ok(Substitutions::of(id, self))
-        }
-    }
-
-    fn contains_variable(&self, id: &str) -> bool {
+    pub(crate) fn free_variables(&self, vars: &mut Vec<String>) {
         match self {
-            Ty::Named(_) => false,
-            Ty::Variable(var_id) => var_id == id,
+            Ty::Named(_) => {}
+            Ty::Variable(var) if vars.contains(var) => {}
+            Ty::Variable(var) => vars.push(var.clone()),
             Ty::Func(func_ty) => {
-                func_ty.from.contains_variable(id) || func_ty.to.contains_variable(id)
+                func_ty.from.free_variables(vars);
+                func_ty.to.free_variables(vars);
             }
         }
     }
@@ -72,3 +95,15 @@ impl Ty {
         }
     }
 }
+
+impl fmt::Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ty::Variable(name) | Ty::Named(name) => write!(f, "{}", name),
+            Ty::Func(func_ty) if matches!(func_ty.from, Ty::Func(_)) => {
+                write!(f, "({}) -> {}", func_ty.from, func_ty.to)
+            }
+            Ty::Func(func_ty) => write!(f, "{} -> {}", func_ty.from, func_ty.to),
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use std::fmt;
+
+use crate::{data::Context, ty::Ty};
+
+/// An expression with the type inferred for every node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Typed {
+    pub expr: TypedExpr,
+    pub ty: Ty,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypedExpr {
+    Number(i32),
+    Variable(String),
+    Func {
+        param: String,
+        body: Box<Typed>,
+    },
+    Call {
+        func: Box<Typed>,
+        arg: Box<Typed>,
+    },
+    If {
+        condition: Box<Typed>,
+        true_branch: Box<Typed>,
+        false_branch: Box<Typed>,
+    },
+    Let {
+        name: String,
+        expr: Box<Typed>,
+        body: Box<Typed>,
+    },
+    LetRec {
+        bindings: Vec<(String, Typed)>,
+        body: Box<Typed>,
+    },
+}
+
+impl Typed {
+    pub fn new(expr: TypedExpr, ty: Ty) -> Self {
+        Self { expr, ty }
+    }
+
+    /// Replaces every bound type variable in the tree with its final type.
+    pub(crate) fn resolve(self, ctx: &mut Context) -> Typed {
+        let mut resolve = |typed: Typed| typed.resolve(ctx);
+
+        let expr = match self.expr {
+            TypedExpr::Number(_) | TypedExpr::Variable(_) => self.expr,
+            TypedExpr::Func { param, body } => TypedExpr::Func {
+                param,
+                body: Box::new(resolve(*body)),
+            },
+            TypedExpr::Call { func, arg } => TypedExpr::Call {
+                func: Box::new(resolve(*func)),
+                arg: Box::new(resolve(*arg)),
+            },
+            TypedExpr::If {
+                condition,
+                true_branch,
+                false_branch,
+            } => TypedExpr::If {
+                condition: Box::new(resolve(*condition)),
+                true_branch: Box::new(resolve(*true_branch)),
+                false_branch: Box::new(resolve(*false_branch)),
+            },
+            TypedExpr::Let { name, expr, body } => TypedExpr::Let {
+                name,
+                expr: Box::new(resolve(*expr)),
+                body: Box::new(resolve(*body)),
+            },
+            TypedExpr::LetRec { bindings, body } => TypedExpr::LetRec {
+                bindings: bindings
+                    .into_iter()
+                    .map(|(name, typed)| (name, resolve(typed)))
+                    .collect(),
+                body: Box::new(resolve(*body)),
+            },
+        };
+
+        Typed::new(expr, ctx.resolve(&self.ty))
+    }
+}
+
+/// Prints every node as `(expr : ty)`.
+impl fmt::Display for Typed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(")?;
+
+        match &self.expr {
+            TypedExpr::Number(number) => write!(f, "{}", number)?,
+            TypedExpr::Variable(name) => write!(f, "{}", name)?,
+            TypedExpr::Func { param, body } => write!(f, "fun {} -> {}", param, body)?,
+            TypedExpr::Call { func, arg } => write!(f, "{} {}", func, arg)?,
+            TypedExpr::If {
+                condition,
+                true_branch,
+                false_branch,
+            } => write!(
+                f,
+                "if {} then {} else {}",
+                condition, true_branch, false_branch
+            )?,
+            TypedExpr::Let { name, expr, body } => {
+                write!(f, "let {} = {} in {}", name, expr, body)?
+            }
+            TypedExpr::LetRec { bindings, body } => {
+                write!(f, "let rec ")?;
+                for (index, (name, typed)) in bindings.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, " and ")?;
+                    }
+                    write!(f, "{} = {}", name, typed)?;
+                }
+                write!(f, " in {}", body)?;
+            }
+        }
+
+        write!(f, " : {})", self.ty)
+    }
+}